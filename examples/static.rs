@@ -1,6 +1,18 @@
 extern crate milstian_internet_framework;
+use milstian_internet_framework::check::Report;
 use milstian_internet_framework::{Application, Config};
+use std::env;
+use std::process;
 fn main() {
-    let config = Config::from_env().expect("Failed to get configuration from environment");
+    // With --check the configuration is only tested, like `nginx -t`
+    let mut args: Vec<String> = env::args().collect();
+    let is_check = args.iter().any(|arg| arg == "--check");
+    args.retain(|arg| arg != "--check");
+    let config = Config::from_env_args(args).expect("Failed to get configuration from environment");
+    if is_check {
+        let report = Report::from_config(&config);
+        println!("{}", report);
+        process::exit(if report.is_ok() { 0 } else { 1 });
+    }
     Application::new(config).tcp_http_with_legacy_responders();
 }
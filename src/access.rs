@@ -0,0 +1,361 @@
+//! # Access control
+//! Rules of roles and permissions that routes require, evaluated against the claims of the
+//! authenticated identity so handlers don't have to check access themselves.
+
+use std::fmt;
+
+use application_layer::http::request::Protocol;
+use application_layer::http::response::{Message, Response};
+use application_layer::http::status::HttpStatus;
+use response::tcp::http::admin;
+
+/// # Authenticated identity with it's claims
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identity {
+    pub id: String,
+    pub permissions: Vec<String>,
+    pub roles: Vec<String>,
+}
+
+impl Identity {
+    pub fn new(id: &str) -> Identity {
+        Identity {
+            id: id.to_string(),
+            permissions: Vec::new(),
+            roles: Vec::new(),
+        }
+    }
+
+    pub fn permission(mut self, permission: &str) -> Identity {
+        self.permissions.push(permission.to_string());
+        self
+    }
+
+    pub fn role(mut self, role: &str) -> Identity {
+        self.roles.push(role.to_string());
+        self
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|existing| existing == role)
+    }
+
+    /// Granted permissions ending with `*` include all permissions starting with the rest,
+    /// `users.*` grants `users.read` and `*` grants everything
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| {
+            if granted.ends_with('*') {
+                permission.starts_with(&granted[..granted.len() - 1])
+            } else {
+                granted == permission
+            }
+        })
+    }
+}
+
+/// # Why access was denied
+#[derive(Clone, Debug, PartialEq)]
+pub struct Denial {
+    /// Id of the identity, none when the request was not authenticated
+    pub identity: Option<String>,
+    pub reason: String,
+    /// Expression of the rule that was not fulfilled
+    pub required: String,
+}
+
+impl Denial {
+    /// 403 Forbidden with the reason and the required rule as JSON
+    pub fn to_response(&self, protocol: &Protocol) -> Message {
+        let body = format!(
+            "{{\"status\":403,\"reason\":{},\"required\":{}}}",
+            admin::Responder::get_json_string(&self.reason),
+            admin::Responder::get_json_string(&self.required)
+        );
+        Response::new(HttpStatus::Forbidden)
+            .protocol(protocol)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .build()
+    }
+}
+
+impl fmt::Display for Denial {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match &self.identity {
+            Some(identity) => write!(formatter, "{} for {}", self.reason, identity),
+            None => write!(formatter, "{}", self.reason),
+        }
+    }
+}
+
+/// # Requirement of a route
+/// Expressions combine `role:name`, `permission:name` and `authenticated` with `and`, `or`,
+/// `not` and parentheses, `and` binds stronger than `or`.
+/// ```rust
+/// use milstian_internet_framework::access::{Identity, Rule};
+/// let rule = Rule::from_str("role:admin or (permission:users.read and not role:guest)").unwrap();
+/// let editor = Identity::new("alice").permission("users.*");
+/// assert!(rule.evaluate(Some(&editor)).is_ok());
+/// let guest = Identity::new("bob").role("guest").permission("users.read");
+/// assert_eq!(
+///     rule.evaluate(Some(&guest)).unwrap_err().reason,
+///     "Missing role admin; Role guest is excluded"
+/// );
+/// assert!(rule.evaluate(None).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    Authenticated,
+    Not(Box<Rule>),
+    Permission(String),
+    Role(String),
+}
+
+impl Rule {
+    pub fn from_str(expression: &str) -> Result<Rule, String> {
+        let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        let mut position = 0;
+        let rule = Rule::parse_any(&tokens, &mut position)?;
+        match tokens.get(position) {
+            Some(token) => Err(format!("Unexpected '{}' in rule '{}'", token, expression)),
+            None => Ok(rule),
+        }
+    }
+
+    /// Check the rule for a identity, requests without one are always denied
+    pub fn evaluate(&self, identity: Option<&Identity>) -> Result<(), Denial> {
+        let identity = match identity {
+            Some(identity) => identity,
+            None => {
+                return Err(Denial {
+                    identity: None,
+                    reason: "Authentication required".to_string(),
+                    required: self.to_string(),
+                })
+            }
+        };
+        match self.get_reason(identity) {
+            Some(reason) => Err(Denial {
+                identity: Some(identity.id.clone()),
+                reason,
+                required: self.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Why the identity doesn't fulfill the rule, none when it does
+    fn get_reason(&self, identity: &Identity) -> Option<String> {
+        match self {
+            Rule::All(rules) => rules.iter().find_map(|rule| rule.get_reason(identity)),
+            Rule::Any(rules) => {
+                let mut reasons = Vec::new();
+                for rule in rules {
+                    reasons.push(rule.get_reason(identity)?);
+                }
+                Some(reasons.join("; "))
+            }
+            Rule::Authenticated => None,
+            Rule::Not(rule) => match rule.get_reason(identity) {
+                Some(_) => None,
+                None => Some(match &**rule {
+                    Rule::Permission(permission) => {
+                        format!("Permission {} is excluded", permission)
+                    }
+                    Rule::Role(role) => format!("Role {} is excluded", role),
+                    rule => format!("Excluded by {}", rule),
+                }),
+            },
+            Rule::Permission(permission) => {
+                if identity.has_permission(permission) {
+                    None
+                } else {
+                    Some(format!("Missing permission {}", permission))
+                }
+            }
+            Rule::Role(role) => {
+                if identity.has_role(role) {
+                    None
+                } else {
+                    Some(format!("Missing role {}", role))
+                }
+            }
+        }
+    }
+
+    fn parse_any(tokens: &[&str], position: &mut usize) -> Result<Rule, String> {
+        let mut rules = vec![Rule::parse_all(tokens, position)?];
+        while tokens.get(*position) == Some(&"or") {
+            *position += 1;
+            rules.push(Rule::parse_all(tokens, position)?);
+        }
+        Ok(if rules.len() == 1 {
+            rules.remove(0)
+        } else {
+            Rule::Any(rules)
+        })
+    }
+
+    fn parse_all(tokens: &[&str], position: &mut usize) -> Result<Rule, String> {
+        let mut rules = vec![Rule::parse_not(tokens, position)?];
+        while tokens.get(*position) == Some(&"and") {
+            *position += 1;
+            rules.push(Rule::parse_not(tokens, position)?);
+        }
+        Ok(if rules.len() == 1 {
+            rules.remove(0)
+        } else {
+            Rule::All(rules)
+        })
+    }
+
+    fn parse_not(tokens: &[&str], position: &mut usize) -> Result<Rule, String> {
+        let token = match tokens.get(*position) {
+            Some(token) => *token,
+            None => return Err("Rule ended unexpectedly".to_string()),
+        };
+        *position += 1;
+        if token == "not" {
+            return Ok(Rule::Not(Box::new(Rule::parse_not(tokens, position)?)));
+        }
+        if token == "(" {
+            let rule = Rule::parse_any(tokens, position)?;
+            if tokens.get(*position) != Some(&")") {
+                return Err("Missing ')' in rule".to_string());
+            }
+            *position += 1;
+            return Ok(rule);
+        }
+        if token == "authenticated" {
+            return Ok(Rule::Authenticated);
+        }
+        let mut parts = token.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("role"), Some(role)) if !role.is_empty() => Ok(Rule::Role(role.to_string())),
+            (Some("permission"), Some(permission)) if !permission.is_empty() => {
+                Ok(Rule::Permission(permission.to_string()))
+            }
+            _ => Err(format!("Unknown requirement '{}' in rule", token)),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let nested = |rule: &Rule| match rule {
+            Rule::All(_) | Rule::Any(_) => format!("({})", rule),
+            rule => rule.to_string(),
+        };
+        match self {
+            Rule::All(rules) => {
+                let rules: Vec<String> = rules.iter().map(nested).collect();
+                write!(formatter, "{}", rules.join(" and "))
+            }
+            Rule::Any(rules) => {
+                let rules: Vec<String> = rules.iter().map(nested).collect();
+                write!(formatter, "{}", rules.join(" or "))
+            }
+            Rule::Authenticated => write!(formatter, "authenticated"),
+            Rule::Not(rule) => write!(formatter, "not {}", nested(rule)),
+            Rule::Permission(permission) => write!(formatter, "permission:{}", permission),
+            Rule::Role(role) => write!(formatter, "role:{}", role),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            Rule::from_str("role:admin").unwrap(),
+            Rule::Role("admin".to_string())
+        );
+        let rule = Rule::from_str("role:a or role:b and (permission:c or not role:d)").unwrap();
+        assert_eq!(
+            rule,
+            Rule::Any(vec![
+                Rule::Role("a".to_string()),
+                Rule::All(vec![
+                    Rule::Role("b".to_string()),
+                    Rule::Any(vec![
+                        Rule::Permission("c".to_string()),
+                        Rule::Not(Box::new(Rule::Role("d".to_string()))),
+                    ]),
+                ]),
+            ])
+        );
+        assert_eq!(
+            rule.to_string(),
+            "role:a or (role:b and (permission:c or not role:d))"
+        );
+        assert_eq!(Rule::from_str(&rule.to_string()).unwrap(), rule);
+        assert_eq!(Rule::from_str("authenticated").unwrap(), Rule::Authenticated);
+
+        assert!(Rule::from_str("").is_err());
+        assert!(Rule::from_str("role:").is_err());
+        assert!(Rule::from_str("admin").is_err());
+        assert!(Rule::from_str("role:a or").is_err());
+        assert!(Rule::from_str("(role:a").is_err());
+        assert!(Rule::from_str("role:a)").is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let rule = Rule::from_str("role:admin and permission:users.write").unwrap();
+        let identity = Identity::new("alice").role("admin");
+        assert_eq!(
+            rule.evaluate(Some(&identity)),
+            Err(Denial {
+                identity: Some("alice".to_string()),
+                reason: "Missing permission users.write".to_string(),
+                required: "role:admin and permission:users.write".to_string(),
+            })
+        );
+        assert!(rule.evaluate(Some(&identity.clone().permission("*"))).is_ok());
+        assert!(rule
+            .evaluate(Some(&identity.clone().permission("users.")))
+            .is_err());
+
+        let denial = Rule::Authenticated.evaluate(None).unwrap_err();
+        assert_eq!(denial.reason, "Authentication required");
+        assert_eq!(denial.to_string(), "Authentication required");
+        assert!(Rule::Authenticated
+            .evaluate(Some(&Identity::new("bob")))
+            .is_ok());
+
+        let rule = Rule::from_str("not (role:guest or role:banned)").unwrap();
+        let denial = rule
+            .evaluate(Some(&Identity::new("bob").role("banned")))
+            .unwrap_err();
+        assert_eq!(denial.reason, "Excluded by role:guest or role:banned");
+        assert_eq!(
+            denial.to_string(),
+            "Excluded by role:guest or role:banned for bob"
+        );
+    }
+
+    #[test]
+    fn test_to_response() {
+        let denial = Denial {
+            identity: Some("alice".to_string()),
+            reason: "Missing role \"admin\"".to_string(),
+            required: "role:admin".to_string(),
+        };
+        let response = denial.to_response(&Protocol::V1_1);
+        assert_eq!(response.status, "403 Forbidden");
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"application/json".to_string())
+        );
+        assert_eq!(
+            String::from_utf8(response.body).unwrap(),
+            "{\"status\":403,\"reason\":\"Missing role \\\"admin\\\"\",\"required\":\"role:admin\"}"
+        );
+    }
+}
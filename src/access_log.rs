@@ -0,0 +1,285 @@
+//! # Access log
+//! Buffered access log writer, lines are queued from workers and written by a background thread.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default number of lines that can be queued before writers have to wait
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Default maximum time a written line stays in the buffer
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+enum Entry {
+    Flush(mpsc::Sender<()>),
+    Line(String),
+    Shutdown,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    blocked: AtomicUsize,
+    dropped: AtomicUsize,
+    errors: AtomicUsize,
+    flushes: AtomicUsize,
+    written: AtomicUsize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// Lines that had to wait for the queue to get space
+    pub blocked: usize,
+    /// Lines that arrived after shutdown and were never written
+    pub dropped: usize,
+    /// Failed writes or flushes
+    pub errors: usize,
+    pub flushes: usize,
+    pub written: usize,
+}
+
+struct Inner {
+    counters: Arc<Counters>,
+    sender: SyncSender<Entry>,
+    thread: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl Inner {
+    fn shutdown(&self) {
+        if let Ok(mut thread) = self.thread.lock() {
+            if let Some(thread) = thread.take() {
+                let _ = self.sender.send(Entry::Shutdown);
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Clones share the same queue, the queue is flushed when the last clone is dropped
+#[derive(Clone)]
+pub struct Writer {
+    inner: Arc<Inner>,
+}
+
+impl Writer {
+    /// Start a background thread writing queued lines to `output`
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::access_log::Writer;
+    /// use std::time::Duration;
+    /// let writer = Writer::new(Box::new(Vec::new()), 16, Duration::from_millis(100));
+    /// writer.log("HTTP access".to_string());
+    /// writer.shutdown();
+    /// assert_eq!(writer.get_statistics().written, 1);
+    /// ```
+    pub fn new(output: Box<Write + Send>, capacity: usize, flush_interval: Duration) -> Writer {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let counters = Arc::new(Counters::default());
+        let thread_counters = Arc::clone(&counters);
+        let thread = thread::spawn(move || {
+            Writer::run(receiver, BufWriter::new(output), flush_interval, &thread_counters);
+        });
+        Writer {
+            inner: Arc::new(Inner {
+                counters,
+                sender,
+                thread: Mutex::new(Some(thread)),
+            }),
+        }
+    }
+
+    /// Append lines to a file, creating it if it does not exist
+    pub fn from_file(filename: &str) -> Result<Writer, String> {
+        match OpenOptions::new().append(true).create(true).open(filename) {
+            Ok(file) => Ok(Writer::new(
+                Box::new(file),
+                DEFAULT_CAPACITY,
+                DEFAULT_FLUSH_INTERVAL,
+            )),
+            Err(error) => Err(format!(
+                "Failed to open access log {}, error: {}",
+                filename, error
+            )),
+        }
+    }
+
+    /// Queue a line, waits for space when the queue is full
+    pub fn log(&self, line: String) {
+        let counters = &self.inner.counters;
+        let entry = match self.inner.sender.try_send(Entry::Line(line)) {
+            Ok(()) => return,
+            Err(TrySendError::Full(entry)) => {
+                counters.blocked.fetch_add(1, Ordering::SeqCst);
+                entry
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                counters.dropped.fetch_add(1, Ordering::SeqCst);
+                return;
+            }
+        };
+        if self.inner.sender.send(entry).is_err() {
+            counters.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Write all lines queued so far and wait until they are flushed
+    pub fn flush(&self) {
+        let (sender, receiver) = mpsc::channel();
+        if self.inner.sender.send(Entry::Flush(sender)).is_ok() {
+            let _ = receiver.recv();
+        }
+    }
+
+    /// Flush all queued lines and stop the background thread, later lines are dropped
+    pub fn shutdown(&self) {
+        self.inner.shutdown();
+    }
+
+    pub fn get_statistics(&self) -> Statistics {
+        let counters = &self.inner.counters;
+        Statistics {
+            blocked: counters.blocked.load(Ordering::SeqCst),
+            dropped: counters.dropped.load(Ordering::SeqCst),
+            errors: counters.errors.load(Ordering::SeqCst),
+            flushes: counters.flushes.load(Ordering::SeqCst),
+            written: counters.written.load(Ordering::SeqCst),
+        }
+    }
+
+    fn run(
+        receiver: Receiver<Entry>,
+        mut output: BufWriter<Box<Write + Send>>,
+        flush_interval: Duration,
+        counters: &Counters,
+    ) {
+        let mut pending = false;
+        let mut last_flush = Instant::now();
+        loop {
+            let timeout = flush_interval
+                .checked_sub(last_flush.elapsed())
+                .unwrap_or(Duration::from_millis(0));
+            let is_running = match receiver.recv_timeout(timeout) {
+                Ok(Entry::Line(line)) => {
+                    match writeln!(output, "{}", line) {
+                        Ok(()) => counters.written.fetch_add(1, Ordering::SeqCst),
+                        Err(_) => counters.errors.fetch_add(1, Ordering::SeqCst),
+                    };
+                    pending = true;
+                    true
+                }
+                Ok(Entry::Flush(acknowledge)) => {
+                    Writer::flush_output(&mut output, counters);
+                    pending = false;
+                    last_flush = Instant::now();
+                    let _ = acknowledge.send(());
+                    true
+                }
+                Ok(Entry::Shutdown) | Err(RecvTimeoutError::Disconnected) => false,
+                Err(RecvTimeoutError::Timeout) => true,
+            };
+            if !is_running {
+                Writer::flush_output(&mut output, counters);
+                break;
+            }
+            if last_flush.elapsed() >= flush_interval {
+                if pending {
+                    Writer::flush_output(&mut output, counters);
+                    pending = false;
+                }
+                last_flush = Instant::now();
+            }
+        }
+    }
+
+    fn flush_output(output: &mut BufWriter<Box<Write + Send>>, counters: &Counters) {
+        match output.flush() {
+            Ok(()) => counters.flushes.fetch_add(1, Ordering::SeqCst),
+            Err(_) => counters.errors.fetch_add(1, Ordering::SeqCst),
+        };
+    }
+}
+
+impl fmt::Debug for Writer {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Writer {:?}", self.get_statistics())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buffer: &[u8]) -> ::std::io::Result<usize> {
+            self.0.lock().unwrap().write(buffer)
+        }
+        fn flush(&mut self) -> ::std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_flush() {
+        let shared = Shared(Arc::new(Mutex::new(Vec::new())));
+        let writer = Writer::new(Box::new(shared.clone()), 4, Duration::from_secs(3600));
+        writer.log("first".to_string());
+        writer.log("second".to_string());
+        assert!(shared.0.lock().unwrap().is_empty());
+        writer.flush();
+        assert_eq!(&shared.0.lock().unwrap()[..], b"first\nsecond\n");
+    }
+
+    #[test]
+    fn test_flush_interval() {
+        let shared = Shared(Arc::new(Mutex::new(Vec::new())));
+        let writer = Writer::new(Box::new(shared.clone()), 4, Duration::from_millis(10));
+        writer.log("line".to_string());
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(&shared.0.lock().unwrap()[..], b"line\n");
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let shared = Shared(Arc::new(Mutex::new(Vec::new())));
+        let writer = Writer::new(Box::new(shared.clone()), 1, Duration::from_secs(3600));
+        for index in 0..100 {
+            writer.clone().log(format!("{}", index));
+        }
+        writer.shutdown();
+        writer.log("late".to_string());
+
+        let lines = String::from_utf8(shared.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(lines.lines().count(), 100);
+        assert_eq!(lines.lines().last(), Some("99"));
+        let statistics = writer.get_statistics();
+        assert_eq!(statistics.written, 100);
+        assert_eq!(statistics.dropped, 1);
+        assert!(statistics.flushes >= 1);
+    }
+
+    #[test]
+    fn test_drop() {
+        let shared = Shared(Arc::new(Mutex::new(Vec::new())));
+        {
+            let writer = Writer::new(Box::new(shared.clone()), 4, Duration::from_secs(3600));
+            let clone = writer.clone();
+            drop(writer);
+            clone.log("last".to_string());
+        }
+        assert_eq!(&shared.0.lock().unwrap()[..], b"last\n");
+    }
+}
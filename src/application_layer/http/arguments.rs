@@ -0,0 +1,303 @@
+//! # HTTP arguments
+//! Ordered collection of query and form arguments where a name may occur multiple times.
+//! Argument names are case-sensitive.
+
+use std::slice;
+
+use application_layer::http::request::decode_percent;
+
+/// # Holds arguments in the order they were received
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::arguments::Arguments;
+/// let arguments = Arguments::from_str("tag=a&page=2&tag=b");
+/// assert_eq!(arguments.get_first("tag"), Some(&"a".to_string()));
+/// assert_eq!(arguments.get_all("tag"), vec![&"a".to_string(), &"b".to_string()]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Arguments {
+    pairs: Vec<(String, String)>,
+}
+
+impl Arguments {
+    pub fn new() -> Arguments {
+        Arguments { pairs: Vec::new() }
+    }
+
+    /// Decode arguments in the application/x-www-form-urlencoded format
+    pub fn from_str(value: &str) -> Arguments {
+        let mut arguments = Arguments::new();
+        for pair in value.split('&') {
+            if pair.is_empty() {
+                continue;
+            }
+            let mut parts = pair.splitn(2, '=');
+            if let Some(name) = parts.next() {
+                let value = parts.next().unwrap_or("");
+                arguments.insert(decode_percent(name), decode_percent(value));
+            }
+        }
+        arguments
+    }
+
+    /// Append a argument, previous arguments with the same name are kept
+    pub fn insert(&mut self, name: String, value: String) {
+        self.pairs.push((name, value));
+    }
+
+    /// Same as get_first, kept for compatibility with code written for a HashMap
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.get_first(name)
+    }
+
+    pub fn get_first(&self, name: &str) -> Option<&String> {
+        self.pairs
+            .iter()
+            .find(|(pair_name, _)| pair_name == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<&String> {
+        self.pairs
+            .iter()
+            .filter(|(pair_name, _)| pair_name == name)
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get_first(name).is_some()
+    }
+
+    pub fn iter(&self) -> slice::Iter<(String, String)> {
+        self.pairs.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Decode PHP/Rails-style names like `items[]` and `user[name]` into nested values,
+    /// a later plain value with the same name replaces a earlier one.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::arguments::Arguments;
+    /// let arguments = Arguments::from_str("items[]=1&items[]=2&user[name]=x");
+    /// let nested = arguments.get_nested().unwrap();
+    /// assert_eq!(nested.get("items").unwrap().get_index(1).unwrap().as_str(), Some("2"));
+    /// assert_eq!(nested.get("user").unwrap().get("name").unwrap().as_str(), Some("x"));
+    /// ```
+    pub fn get_nested(&self) -> Result<Nested, String> {
+        let mut root = Nested::Map(Vec::new());
+        for (name, value) in self.pairs.iter() {
+            let keys = Arguments::get_keys(name);
+            if keys.len() > MAX_DEPTH {
+                return Err(format!("Argument {:?} is nested too deep", name));
+            }
+            root.insert(&keys, value.clone())
+                .map_err(|error| format!("Argument {:?} {}", name, error))?;
+        }
+        Ok(root)
+    }
+
+    /// Split `a[b][]` into `a`, `b` and a empty key, names with unbalanced brackets are not split
+    fn get_keys(name: &str) -> Vec<&str> {
+        let start = match name.find('[') {
+            Some(start) if start > 0 => start,
+            _ => return vec![name],
+        };
+        let mut keys = vec![&name[..start]];
+        let mut rest = &name[start..];
+        while !rest.is_empty() {
+            let end = match rest.find(']') {
+                Some(end) if rest.starts_with('[') => end,
+                _ => return vec![name],
+            };
+            keys.push(&rest[1..end]);
+            rest = &rest[end + 1..];
+        }
+        keys
+    }
+}
+
+/// Maximum number of keys in a nested argument name
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Nested {
+    List(Vec<Nested>),
+    /// Entries in the order their names first appeared
+    Map(Vec<(String, Nested)>),
+    Value(String),
+}
+
+impl Nested {
+    /// Value of key in a map
+    pub fn get(&self, key: &str) -> Option<&Nested> {
+        match self {
+            Nested::Map(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Value at index in a list
+    pub fn get_index(&self, index: usize) -> Option<&Nested> {
+        match self {
+            Nested::List(values) => values.get(index),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Nested::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, keys: &[&str], value: String) -> Result<(), String> {
+        let (key, rest) = match keys.split_first() {
+            Some((key, rest)) => (*key, rest),
+            None => return Err("has no name".to_string()),
+        };
+        let entries = match self {
+            Nested::Map(entries) => entries,
+            _ => return Err("mixes nested and plain values".to_string()),
+        };
+        let position = match entries.iter().position(|(entry_key, _)| entry_key == key) {
+            Some(position) => position,
+            None => {
+                let empty = match rest.first() {
+                    None => Nested::Value(String::new()),
+                    Some(&"") => Nested::List(Vec::new()),
+                    Some(_) => Nested::Map(Vec::new()),
+                };
+                entries.push((key.to_string(), empty));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[position].1;
+        match rest.split_first() {
+            None => match entry {
+                Nested::Value(_) => {
+                    *entry = Nested::Value(value);
+                    Ok(())
+                }
+                _ => Err("mixes nested and plain values".to_string()),
+            },
+            Some((&"", list_rest)) => {
+                let values = match entry {
+                    Nested::List(values) => values,
+                    _ => return Err("mixes lists and other values".to_string()),
+                };
+                if list_rest.is_empty() {
+                    values.push(Nested::Value(value));
+                    return Ok(());
+                }
+
+                // Like `items[][name]=a&items[][price]=1`, a new map is started when the
+                // last map already has the key
+                let is_new_map = match values.last() {
+                    Some(last @ Nested::Map(_)) => last.get(list_rest[0]).is_some(),
+                    _ => true,
+                };
+                if is_new_map {
+                    values.push(Nested::Map(Vec::new()));
+                }
+                match values.last_mut() {
+                    Some(last) => last.insert(list_rest, value),
+                    None => Err("has no list".to_string()),
+                }
+            }
+            Some(_) => entry.insert(rest, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let arguments = Arguments::from_str("a=1&&b&c=%C3%A5+%26&a=2&=3");
+        let pairs: Vec<(&str, &str)> = arguments
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![("a", "1"), ("b", ""), ("c", "å &"), ("a", "2"), ("", "3")]
+        );
+        assert!(Arguments::from_str("").is_empty());
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let mut arguments = Arguments::new();
+        assert_eq!(arguments.get("tag"), None);
+        assert!(arguments.get_all("tag").is_empty());
+
+        arguments.insert("tag".to_string(), "a".to_string());
+        arguments.insert("Tag".to_string(), "b".to_string());
+        arguments.insert("tag".to_string(), "c".to_string());
+        assert_eq!(arguments.len(), 3);
+        assert!(arguments.contains_key("Tag"));
+        assert!(!arguments.contains_key("TAG"));
+        assert_eq!(arguments.get("tag"), Some(&"a".to_string()));
+        assert_eq!(
+            arguments.get_all("tag"),
+            vec![&"a".to_string(), &"c".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_get_keys() {
+        assert_eq!(Arguments::get_keys("a"), vec!["a"]);
+        assert_eq!(Arguments::get_keys("a[b][]"), vec!["a", "b", ""]);
+        assert_eq!(Arguments::get_keys("a[b"), vec!["a[b"]);
+        assert_eq!(Arguments::get_keys("a[b]c"), vec!["a[b]c"]);
+        assert_eq!(Arguments::get_keys("[b]"), vec!["[b]"]);
+    }
+
+    #[test]
+    fn test_get_nested() {
+        let nested = Arguments::from_str(
+            "items[]=1&items[]=2&user[name]=x&user[address][city]=y&a=1&a=2\
+             &rows[][id]=1&rows[][name]=b&rows[][id]=2",
+        ).get_nested()
+            .unwrap();
+        let items = nested.get("items").unwrap();
+        assert_eq!(
+            items,
+            &Nested::List(vec![
+                Nested::Value("1".to_string()),
+                Nested::Value("2".to_string()),
+            ])
+        );
+        assert_eq!(items.get_index(2), None);
+        let user = nested.get("user").unwrap();
+        assert_eq!(user.get("name").unwrap().as_str(), Some("x"));
+        assert_eq!(
+            user.get("address").unwrap().get("city").unwrap().as_str(),
+            Some("y")
+        );
+        assert_eq!(nested.get("a").unwrap().as_str(), Some("2"));
+        let rows = nested.get("rows").unwrap();
+        assert_eq!(rows.get_index(0).unwrap().get("name").unwrap().as_str(), Some("b"));
+        assert_eq!(rows.get_index(1).unwrap().get("id").unwrap().as_str(), Some("2"));
+        assert_eq!(rows.get_index(2), None);
+
+        assert!(Arguments::from_str("a=1&a[b]=2").get_nested().is_err());
+        assert!(Arguments::from_str("a[]=1&a[b]=2").get_nested().is_err());
+        assert!(Arguments::from_str("a[b]=1&a=2").get_nested().is_err());
+        let deep = format!("a{}=1", "[b]".repeat(40));
+        assert!(Arguments::from_str(&deep).get_nested().is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! # HTTP authorization
+//! Typed credentials of the `Authorization` request header, see RFC 7235.
+
+use application_layer::http::header::Headers;
+use application_layer::http::request;
+use base64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Credentials {
+    Basic { user: String, password: String },
+    Bearer(String),
+    Other { scheme: String, parameters: String },
+}
+
+impl Credentials {
+    /// Parse credentials from a header value
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::authorization::Credentials;
+    /// assert_eq!(
+    ///     Credentials::from_str("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="),
+    ///     Some(Credentials::Basic {
+    ///         user: "Aladdin".to_string(),
+    ///         password: "open sesame".to_string(),
+    ///     })
+    /// );
+    /// ```
+    pub fn from_str(value: &str) -> Option<Credentials> {
+        let mut parts = value.trim().splitn(2, ' ');
+        let scheme = parts.next()?;
+        let parameters = parts.next().unwrap_or("").trim();
+        if scheme.is_empty() {
+            return None;
+        }
+
+        if scheme.eq_ignore_ascii_case("Basic") {
+            let decoded = base64::decode(parameters)?;
+            let decoded = String::from_utf8(decoded).ok()?;
+            let mut user_password = decoded.splitn(2, ':');
+            let user = user_password.next()?.to_string();
+            let password = user_password.next()?.to_string();
+            return Some(Credentials::Basic { user, password });
+        }
+
+        if scheme.eq_ignore_ascii_case("Bearer") {
+            if parameters.is_empty() {
+                return None;
+            }
+            return Some(Credentials::Bearer(parameters.to_string()));
+        }
+
+        Some(Credentials::Other {
+            scheme: scheme.to_string(),
+            parameters: parameters.to_string(),
+        })
+    }
+
+    pub fn from_headers(headers: &Headers) -> Option<Credentials> {
+        Credentials::from_str(headers.get("Authorization")?)
+    }
+
+    pub fn from_request(request_message: &request::Message) -> Option<Credentials> {
+        Credentials::from_headers(&request_message.headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            Credentials::from_str("basic dXNlcjpwYXNzOndvcmQ="),
+            Some(Credentials::Basic {
+                user: "user".to_string(),
+                password: "pass:word".to_string(),
+            })
+        );
+        assert_eq!(Credentials::from_str("Basic dXNlcg=="), None);
+        assert_eq!(Credentials::from_str("Basic !!!"), None);
+        assert_eq!(
+            Credentials::from_str("Bearer mF_9.B5f-4.1JqM"),
+            Some(Credentials::Bearer("mF_9.B5f-4.1JqM".to_string()))
+        );
+        assert_eq!(Credentials::from_str("Bearer "), None);
+        assert_eq!(
+            Credentials::from_str("Digest username=\"Mufasa\", realm=\"test\""),
+            Some(Credentials::Other {
+                scheme: "Digest".to_string(),
+                parameters: "username=\"Mufasa\", realm=\"test\"".to_string(),
+            })
+        );
+        assert_eq!(Credentials::from_str(""), None);
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = Headers::new();
+        assert_eq!(Credentials::from_headers(&headers), None);
+        headers.insert("Authorization".to_string(), "Bearer abc".to_string());
+        assert_eq!(
+            Credentials::from_headers(&headers),
+            Some(Credentials::Bearer("abc".to_string()))
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! # HTTP request body reader
+//! Reads a request body from the connection as it arrives instead of buffering it, used for
+//! uploads larger than what should be kept in memory.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+
+use application_layer::http::parser::Parser;
+use application_layer::http::request::find;
+
+/// Longest chunk-size or trailer line accepted
+const MAX_LINE: usize = 4096;
+
+/// # How the end of a body is found, see RFC 7230 section 3.3.3
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Framing {
+    Chunked,
+    Length(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stage {
+    ChunkData(usize),
+    ChunkSize,
+    Done,
+    Length(usize),
+    Trailer,
+}
+
+/// # Yields the bytes of a body, chunked framing is decoded and trailers are skipped
+/// ```rust
+/// use std::io::Read;
+/// use milstian_internet_framework::application_layer::http::body::{Framing, Reader};
+/// let stream = &b"3\r\ndef\r\n0\r\n\r\n"[..];
+/// let mut reader = Reader::new(Framing::Chunked, b"3\r\nabc\r\n".to_vec(), Box::new(stream));
+/// let mut body = String::new();
+/// reader.read_to_string(&mut body).unwrap();
+/// assert_eq!(body, "abcdef");
+/// assert!(reader.is_done());
+/// ```
+pub struct Reader {
+    /// Bytes received from the stream but not read yet
+    buffer: Vec<u8>,
+    stage: Stage,
+    stream: Box<Read + Send>,
+}
+
+impl Reader {
+    /// Read a body that starts with bytes already received and continues on the stream
+    pub fn new(framing: Framing, received: Vec<u8>, stream: Box<Read + Send>) -> Reader {
+        let stage = match framing {
+            Framing::Chunked => Stage::ChunkSize,
+            Framing::Length(0) => Stage::Done,
+            Framing::Length(length) => Stage::Length(length),
+        };
+        Reader {
+            buffer: received,
+            stage,
+            stream,
+        }
+    }
+
+    /// Has the whole body been read?
+    pub fn is_done(&self) -> bool {
+        self.stage == Stage::Done
+    }
+
+    /// Copy at most `limit` body bytes, buffered bytes are used before reading the stream
+    fn read_data(&mut self, output: &mut [u8], limit: usize) -> io::Result<usize> {
+        let length = output.len().min(limit);
+        if !self.buffer.is_empty() {
+            let length = length.min(self.buffer.len());
+            output[..length].copy_from_slice(&self.buffer[..length]);
+            self.buffer.drain(..length);
+            return Ok(length);
+        }
+        match self.stream.read(&mut output[..length])? {
+            0 => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed before end of body",
+            )),
+            read_size => Ok(read_size),
+        }
+    }
+
+    /// Next line without it's line break
+    fn read_line(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(line_end) = find(&self.buffer, b"\r\n", 0) {
+                let mut line: Vec<u8> = self.buffer.drain(..line_end + 2).collect();
+                line.truncate(line_end);
+                return Ok(line);
+            }
+            if self.buffer.len() > MAX_LINE {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Chunk line too long"));
+            }
+            let mut temp_buffer = [0; 512];
+            match self.stream.read(&mut temp_buffer)? {
+                0 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection closed before end of body",
+                    ))
+                }
+                read_size => self.buffer.extend_from_slice(&temp_buffer[..read_size]),
+            }
+        }
+    }
+}
+
+impl Read for Reader {
+    fn read(&mut self, output: &mut [u8]) -> io::Result<usize> {
+        if output.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.stage {
+                Stage::Done => return Ok(0),
+                Stage::Length(remaining) => {
+                    let read_size = self.read_data(output, remaining)?;
+                    self.stage = if read_size == remaining {
+                        Stage::Done
+                    } else {
+                        Stage::Length(remaining - read_size)
+                    };
+                    return Ok(read_size);
+                }
+                Stage::ChunkSize => {
+                    let line = self.read_line()?;
+                    self.stage = match Parser::get_chunk_size(&line) {
+                        Some(0) => Stage::Trailer,
+                        Some(size) => Stage::ChunkData(size),
+                        None => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "Invalid chunk size",
+                            ))
+                        }
+                    };
+                }
+                Stage::ChunkData(0) => {
+                    if !self.read_line()?.is_empty() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "Chunk data is longer than it's size",
+                        ));
+                    }
+                    self.stage = Stage::ChunkSize;
+                }
+                Stage::ChunkData(remaining) => {
+                    let read_size = self.read_data(output, remaining)?;
+                    self.stage = Stage::ChunkData(remaining - read_size);
+                    return Ok(read_size);
+                }
+                Stage::Trailer => {
+                    if self.read_line()?.is_empty() {
+                        self.stage = Stage::Done;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Reader {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "Reader {{ buffered: {}, stage: {:?} }}",
+            self.buffer.len(),
+            self.stage
+        )
+    }
+}
+
+/// # A body left on the connection, it can be taken once
+#[derive(Clone, Default)]
+pub struct Stream {
+    reader: Arc<Mutex<Option<Reader>>>,
+}
+
+impl Stream {
+    pub fn new(reader: Reader) -> Stream {
+        Stream {
+            reader: Arc::new(Mutex::new(Some(reader))),
+        }
+    }
+
+    /// Is there no reader left to take?
+    pub fn is_empty(&self) -> bool {
+        match self.reader.lock() {
+            Ok(reader) => reader.is_none(),
+            Err(_) => true,
+        }
+    }
+
+    /// Take the reader, only the first call gets it
+    pub fn take(&self) -> Option<Reader> {
+        match self.reader.lock() {
+            Ok(mut reader) => reader.take(),
+            Err(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for Stream {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.reader.try_lock() {
+            Ok(reader) => write!(formatter, "Stream {{ reader: {:?} }}", *reader),
+            Err(_) => write!(formatter, "Stream {{ reader: <locked> }}"),
+        }
+    }
+}
+
+/// Streams with a reader are compared by identity since their content is not known until read
+impl PartialEq for Stream {
+    fn eq(&self, other: &Stream) -> bool {
+        Arc::ptr_eq(&self.reader, &other.reader) || (self.is_empty() && other.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(mut reader: Reader) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        Ok(body)
+    }
+
+    #[test]
+    fn test_read_length() {
+        let reader = Reader::new(Framing::Length(5), b"ab".to_vec(), Box::new(&b"cdeGET"[..]));
+        assert_eq!(read_all(reader).unwrap(), b"abcde".to_vec());
+
+        let reader = Reader::new(Framing::Length(5), b"ab".to_vec(), Box::new(&b"c"[..]));
+        assert_eq!(
+            read_all(reader).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let reader = Reader::new(Framing::Length(0), Vec::new(), Box::new(&b"abc"[..]));
+        assert!(reader.is_done());
+        assert!(read_all(reader).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_chunked() {
+        let reader = Reader::new(
+            Framing::Chunked,
+            b"4;name=value\r\nWi".to_vec(),
+            Box::new(&b"ki\r\n5\r\npedia\r\n0\r\nX-Checksum: 1\r\n\r\nGET"[..]),
+        );
+        assert_eq!(read_all(reader).unwrap(), b"Wikipedia".to_vec());
+
+        let reader = Reader::new(Framing::Chunked, Vec::new(), Box::new(&b"x\r\n"[..]));
+        assert_eq!(read_all(reader).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let reader = Reader::new(Framing::Chunked, Vec::new(), Box::new(&b"1\r\nab\r\n"[..]));
+        assert_eq!(read_all(reader).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let reader = Reader::new(Framing::Chunked, Vec::new(), Box::new(&b"3\r\nab"[..]));
+        assert_eq!(
+            read_all(reader).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_stream() {
+        let reader = Reader::new(Framing::Length(1), b"a".to_vec(), Box::new(io::empty()));
+        let stream = Stream::new(reader);
+        let copy = stream.clone();
+        assert_eq!(stream, copy);
+        assert_ne!(stream, Stream::default());
+        assert!(copy.take().is_some());
+        assert!(stream.take().is_none());
+        assert!(stream.is_empty());
+        assert_eq!(stream, Stream::default());
+    }
+}
@@ -0,0 +1,125 @@
+//! # HTTP Cache-Control
+//! Typed directives of `Cache-Control` response headers, see RFC 7234 section 5.2.2 and RFC 8246
+//! for `immutable`.
+
+use std::fmt;
+
+/// # Builds the value of a `Cache-Control` header
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::cache_control::CacheControl;
+/// let cache_control = CacheControl::new().public().max_age(31536000).immutable();
+/// assert_eq!(cache_control.to_string(), "public, max-age=31536000, immutable");
+/// assert_eq!(CacheControl::new().no_store().to_string(), "no-store");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheControl {
+    is_immutable: bool,
+    is_no_cache: bool,
+    is_no_store: bool,
+    is_private: bool,
+    is_public: bool,
+    max_age: Option<u64>,
+}
+
+impl CacheControl {
+    pub fn new() -> CacheControl {
+        CacheControl {
+            is_immutable: false,
+            is_no_cache: false,
+            is_no_store: false,
+            is_private: false,
+            is_public: false,
+            max_age: None,
+        }
+    }
+
+    /// Clients won't revalidate while the response is fresh, for files with fingerprinted URLs
+    pub fn immutable(mut self) -> CacheControl {
+        self.is_immutable = true;
+        self
+    }
+
+    /// Seconds the response is fresh
+    pub fn max_age(mut self, seconds: u64) -> CacheControl {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Caches must revalidate before using a stored response
+    pub fn no_cache(mut self) -> CacheControl {
+        self.is_no_cache = true;
+        self
+    }
+
+    /// Caches must not store the response at all
+    pub fn no_store(mut self) -> CacheControl {
+        self.is_no_store = true;
+        self
+    }
+
+    /// Only the client may store the response, replaces `public`
+    pub fn private(mut self) -> CacheControl {
+        self.is_private = true;
+        self.is_public = false;
+        self
+    }
+
+    /// Shared caches may store the response, replaces `private`
+    pub fn public(mut self) -> CacheControl {
+        self.is_public = true;
+        self.is_private = false;
+        self
+    }
+
+    pub fn get_max_age(&self) -> Option<u64> {
+        self.max_age
+    }
+
+    pub fn is_no_store(&self) -> bool {
+        self.is_no_store
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mut directives: Vec<String> = Vec::new();
+        if self.is_public {
+            directives.push("public".to_string());
+        }
+        if self.is_private {
+            directives.push("private".to_string());
+        }
+        if self.is_no_cache {
+            directives.push("no-cache".to_string());
+        }
+        if self.is_no_store {
+            directives.push("no-store".to_string());
+        }
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age));
+        }
+        if self.is_immutable {
+            directives.push("immutable".to_string());
+        }
+        write!(formatter, "{}", directives.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_string() {
+        assert_eq!(CacheControl::new().to_string(), "");
+        assert_eq!(
+            CacheControl::new().public().private().max_age(0).to_string(),
+            "private, max-age=0"
+        );
+        assert_eq!(
+            CacheControl::new().no_cache().no_store().to_string(),
+            "no-cache, no-store"
+        );
+        assert_eq!(CacheControl::new().max_age(60).get_max_age(), Some(60));
+    }
+}
@@ -0,0 +1,74 @@
+//! # HTTP chunked transfer coding
+//! Encodes a body of unknown length as it's written, see RFC 7230 section 4.1. Decoding is
+//! done by the parser and the body reader.
+
+use std::io::{self, Write};
+
+/// # Writes every non-empty write as one chunk
+/// ```rust
+/// use std::io::Write;
+/// use milstian_internet_framework::application_layer::http::chunked::Encoder;
+/// let mut encoder = Encoder::new(Vec::new());
+/// encoder.write_all(b"Wiki").unwrap();
+/// encoder.write_all(b"pedia").unwrap();
+/// let output = encoder.finish().unwrap();
+/// assert_eq!(output, b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n".to_vec());
+/// ```
+#[derive(Debug)]
+pub struct Encoder<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W) -> Encoder<W> {
+        Encoder { inner }
+    }
+
+    /// Write the last chunk without trailers, returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(b"0\r\n\r\n")?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // A empty chunk would end the body
+        if data.is_empty() {
+            return Ok(0);
+        }
+        self.inner.write_all(format!("{:x}\r\n", data.len()).as_bytes())?;
+        self.inner.write_all(data)?;
+        self.inner.write_all(b"\r\n")?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use application_layer::http::body::{Framing, Reader};
+    use std::io::{self, Read};
+
+    #[test]
+    fn test_encoder() {
+        let mut encoder = Encoder::new(Vec::new());
+        assert_eq!(encoder.write(b"").unwrap(), 0);
+        let data = vec![b'x'; 300];
+        io::copy(&mut &data[..], &mut encoder).unwrap();
+        let output = encoder.finish().unwrap();
+        assert!(output.starts_with(b"12c\r\nxxx"));
+
+        // Decodes to what was written
+        let mut reader = Reader::new(Framing::Chunked, output, Box::new(io::empty()));
+        let mut decoded = Vec::new();
+        reader.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+        assert!(reader.is_done());
+    }
+}
@@ -0,0 +1,287 @@
+//! # HTTP response compression
+//! Compresses response bodies with the gzip or deflate content coding the client accepts,
+//! see RFC 7231 section 5.3.4 and RFC 7230 section 4.2. The `br` coding of RFC 7932 is
+//! available with the `brotli` feature.
+
+#[cfg(feature = "brotli")]
+use std::io::Write;
+
+#[cfg(feature = "brotli")]
+use brotli;
+
+use application_layer::http::header::parse;
+use application_layer::http::response;
+use deflate;
+
+/// Media types compressed by default, text formats that usually shrink a lot
+pub const DEFAULT_TYPES: [&str; 6] = [
+    "text/*",
+    "application/javascript",
+    "application/json",
+    "application/xml",
+    "application/wasm",
+    "image/svg+xml",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coding {
+    #[cfg(feature = "brotli")]
+    Brotli,
+    Deflate,
+    Gzip,
+}
+
+impl Coding {
+    pub fn get_name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "brotli")]
+            Coding::Brotli => "br",
+            Coding::Deflate => "deflate",
+            Coding::Gzip => "gzip",
+        }
+    }
+
+    /// Preferred coding of a `Accept-Encoding` value, when codings are equally preferred br
+    /// wins over gzip which wins over deflate
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::compression::Coding;
+    /// assert_eq!(Coding::from_accept_encoding("deflate, gzip"), Some(Coding::Gzip));
+    /// assert_eq!(Coding::from_accept_encoding("gzip;q=0.5, deflate"), Some(Coding::Deflate));
+    /// assert_eq!(Coding::from_accept_encoding("gzip;q=0, identity"), None);
+    /// ```
+    pub fn from_accept_encoding(accept_encoding: &str) -> Option<Coding> {
+        let items = parse::get_quality_items(accept_encoding);
+        let get_quality = |names: &[&str]| {
+            items
+                .iter()
+                .find(|item| names.iter().any(|name| item.value.eq_ignore_ascii_case(name)))
+                .map(|item| item.quality)
+        };
+        let wildcard = get_quality(&["*"]).unwrap_or(0);
+        let gzip = get_quality(&["gzip", "x-gzip"]).unwrap_or(wildcard);
+        let deflate = get_quality(&["deflate"]).unwrap_or(wildcard);
+        let mut preferred = None;
+        if gzip > 0 || deflate > 0 {
+            preferred = if gzip >= deflate {
+                Some((Coding::Gzip, gzip))
+            } else {
+                Some((Coding::Deflate, deflate))
+            };
+        }
+        #[cfg(feature = "brotli")]
+        {
+            let brotli = get_quality(&["br"]).unwrap_or(wildcard);
+            let is_preferred = match preferred {
+                Some((_, quality)) => brotli >= quality,
+                None => true,
+            };
+            if brotli > 0 && is_preferred {
+                preferred = Some((Coding::Brotli, brotli));
+            }
+        }
+        preferred.map(|(coding, _)| coding)
+    }
+
+    /// Encode with a level from 1 to 9, used as the brotli quality
+    pub fn encode(self, data: &[u8], level: u32) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "brotli")]
+            Coding::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, level, 22);
+                // Writing to memory only fails if it can't be allocated
+                let _ = writer.write_all(data);
+                writer.into_inner()
+            }
+            Coding::Deflate => deflate::zlib(data, level),
+            Coding::Gzip => deflate::gzip(data, level),
+        }
+    }
+}
+
+/// Whether a `Content-Type` is one of `types`, which may end with `/*` to match a whole type
+pub fn is_compressible(content_type: &str, types: &[String]) -> bool {
+    let (media_type, _) = parse::get_parameters(content_type);
+    types.iter().any(|compressible| {
+        if compressible.ends_with("/*") {
+            let prefix = &compressible[..compressible.len() - 1];
+            media_type.len() > prefix.len()
+                && media_type[..prefix.len()].eq_ignore_ascii_case(prefix)
+        } else {
+            media_type.eq_ignore_ascii_case(compressible)
+        }
+    })
+}
+
+/// Compress the body of a response when it's large enough, of a compressible type and not
+/// already encoded, returns whether it was. Responses that could be compressed get a
+/// `Vary: Accept-Encoding` so caches keep the variants apart.
+pub fn compress(
+    response: &mut response::Message,
+    accept_encoding: Option<&String>,
+    level: u32,
+    min_bytes: usize,
+    types: &[String],
+) -> bool {
+    let get_header = |response: &response::Message, name: &str| {
+        response
+            .headers
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(existing, value)| (existing.clone(), value.clone()))
+    };
+    let is_compressible_type = match get_header(response, "Content-Type") {
+        Some((_, content_type)) => is_compressible(&content_type, types),
+        None => false,
+    };
+    // Partial content refers to ranges of the unencoded body
+    if level == 0
+        || !is_compressible_type
+        || response.status.starts_with("206")
+        || get_header(response, "Content-Encoding").is_some()
+        || get_header(response, "Content-Range").is_some()
+    {
+        return false;
+    }
+
+    match get_header(response, "Vary") {
+        Some((name, value)) => {
+            let is_varied = parse::split_list(&value).iter().any(|field| {
+                *field == "*" || field.eq_ignore_ascii_case("Accept-Encoding")
+            });
+            if !is_varied {
+                response
+                    .headers
+                    .insert(name, format!("{}, Accept-Encoding", value));
+            }
+        }
+        None => {
+            response
+                .headers
+                .insert("Vary".to_string(), "Accept-Encoding".to_string());
+        }
+    }
+
+    let coding = match accept_encoding.and_then(|value| Coding::from_accept_encoding(value)) {
+        Some(coding) => coding,
+        None => return false,
+    };
+    if response.body.len() < min_bytes {
+        return false;
+    }
+    let encoded = coding.encode(&response.body, level);
+    if encoded.len() >= response.body.len() {
+        return false;
+    }
+    response.body = encoded;
+    response.headers.insert(
+        "Content-Encoding".to_string(),
+        coding.get_name().to_string(),
+    );
+    if let Some((name, _)) = get_header(response, "Content-Length") {
+        response.headers.insert(name, response.body.len().to_string());
+    }
+
+    // The encoded body is a different representation, it's no longer byte for byte the same
+    if let Some((name, etag)) = get_header(response, "ETag") {
+        if !etag.starts_with("W/") {
+            response.headers.insert(name, format!("W/{}", etag));
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn get_response(content_type: &str, body: Vec<u8>) -> response::Message {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), content_type.to_string());
+        headers.insert("Content-Length".to_string(), body.len().to_string());
+        headers.insert("ETag".to_string(), "\"abc\"".to_string());
+        response::Message::new("HTTP/1.1".to_string(), "200 OK".to_string(), headers, body)
+    }
+
+    #[test]
+    fn test_from_accept_encoding() {
+        assert_eq!(Coding::from_accept_encoding(""), None);
+        assert_eq!(Coding::from_accept_encoding("identity"), None);
+        assert_eq!(Coding::from_accept_encoding("gzip, *;q=0.5"), Some(Coding::Gzip));
+        assert_eq!(
+            Coding::from_accept_encoding("*;q=0.5, gzip;q=0, br;q=0"),
+            Some(Coding::Deflate)
+        );
+        assert_eq!(Coding::from_accept_encoding("X-GZIP"), Some(Coding::Gzip));
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn test_brotli() {
+        use std::io::Read;
+
+        assert_eq!(Coding::from_accept_encoding("gzip, deflate, br"), Some(Coding::Brotli));
+        assert_eq!(Coding::from_accept_encoding("br;q=0.5, gzip"), Some(Coding::Gzip));
+        assert_eq!(Coding::from_accept_encoding("*"), Some(Coding::Brotli));
+        assert_eq!(Coding::from_accept_encoding("br"), Some(Coding::Brotli));
+
+        let body = "<p>Compressible</p>".repeat(100).into_bytes();
+        let encoded = Coding::Brotli.encode(&body, 6);
+        let mut decoded = Vec::new();
+        brotli::Decompressor::new(&encoded[..], 4096)
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn test_is_compressible() {
+        let types: Vec<String> = DEFAULT_TYPES.iter().map(|value| value.to_string()).collect();
+        assert!(is_compressible("text/html; charset=utf-8", &types));
+        assert!(is_compressible("Application/JSON", &types));
+        assert!(!is_compressible("image/png", &types));
+        assert!(!is_compressible("text/", &types));
+        assert!(!is_compressible("text/html", &[]));
+    }
+
+    #[test]
+    fn test_compress() {
+        let types = vec!["text/*".to_string()];
+        let gzip = "gzip".to_string();
+        let body = "<p>Compressible</p>".repeat(100).into_bytes();
+
+        let mut response = get_response("text/html", body.clone());
+        assert!(compress(&mut response, Some(&gzip), 6, 1024, &types));
+        assert_eq!(response.headers.get("Content-Encoding"), Some(&gzip));
+        assert_eq!(
+            response.headers.get("Content-Length"),
+            Some(&response.body.len().to_string())
+        );
+        assert_eq!(response.headers.get("ETag"), Some(&"W/\"abc\"".to_string()));
+        assert_eq!(
+            response.headers.get("Vary"),
+            Some(&"Accept-Encoding".to_string())
+        );
+        assert!(response.body.len() < body.len() / 10);
+
+        // Too small, not accepted or of a other type
+        let mut response = get_response("text/html", b"<p>Small</p>".to_vec());
+        assert!(!compress(&mut response, Some(&gzip), 6, 1024, &types));
+        assert_eq!(response.body, b"<p>Small</p>".to_vec());
+        let mut response = get_response("text/html", body.clone());
+        response
+            .headers
+            .insert("Vary".to_string(), "Origin".to_string());
+        assert!(!compress(&mut response, None, 6, 1024, &types));
+        assert_eq!(
+            response.headers.get("Vary"),
+            Some(&"Origin, Accept-Encoding".to_string())
+        );
+        let mut response = get_response("image/png", body.clone());
+        assert!(!compress(&mut response, Some(&gzip), 6, 1024, &types));
+        assert!(!response.headers.contains_key("Vary"));
+        let mut response = get_response("text/html", body.clone());
+        assert!(!compress(&mut response, Some(&gzip), 0, 1024, &types));
+    }
+}
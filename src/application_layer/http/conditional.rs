@@ -0,0 +1,219 @@
+//! # HTTP conditional requests
+//! Typed `If-Modified-Since` and `If-None-Match` request headers, see RFC 7232.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use application_layer::http::date;
+use application_layer::http::header::{parse, Headers};
+use application_layer::http::request;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityTag {
+    pub tag: String,
+    pub weak: bool,
+}
+
+impl EntityTag {
+    pub fn new(tag: String, weak: bool) -> EntityTag {
+        EntityTag { tag, weak }
+    }
+
+    /// Parse a entity-tag, unquoted tags are accepted for compatibility with older clients.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::conditional::EntityTag;
+    /// let etag = EntityTag::from_str("W/\"abc\"").unwrap();
+    /// assert_eq!(EntityTag::new("abc".to_string(), true), etag);
+    /// ```
+    pub fn from_str(value: &str) -> Option<EntityTag> {
+        let mut value = value.trim();
+        let mut weak = false;
+        if value.starts_with("W/") {
+            weak = true;
+            value = &value[2..];
+        }
+        if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            value = &value[1..value.len() - 1];
+        }
+        if value.is_empty() || value.contains('"') {
+            return None;
+        }
+        Some(EntityTag::new(value.to_string(), weak))
+    }
+
+    /// Weak comparison, used by If-None-Match
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IfNoneMatch {
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl IfNoneMatch {
+    pub fn from_str(value: &str) -> Option<IfNoneMatch> {
+        let value = value.trim();
+        if value == "*" {
+            return Some(IfNoneMatch::Any);
+        }
+
+        let tags: Vec<EntityTag> = parse::split_list(value)
+            .into_iter()
+            .filter_map(EntityTag::from_str)
+            .collect();
+        if tags.is_empty() {
+            return None;
+        }
+        Some(IfNoneMatch::Tags(tags))
+    }
+
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match self {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Tags(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        }
+    }
+}
+
+/// # Holds the conditions of a request
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::conditional::{Conditions, EntityTag};
+/// use milstian_internet_framework::application_layer::http::header::Headers;
+/// let mut headers = Headers::new();
+/// headers.insert("If-None-Match".to_string(), "\"abc\", \"def\"".to_string());
+/// let conditions = Conditions::from_headers(&headers);
+/// assert!(conditions.is_not_modified(None, Some(&EntityTag::new("def".to_string(), false))));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conditions {
+    pub if_modified_since: Option<SystemTime>,
+    pub if_none_match: Option<IfNoneMatch>,
+}
+
+impl Conditions {
+    pub fn from_headers(headers: &Headers) -> Conditions {
+        let mut if_modified_since = None;
+        if let Some(value) = headers.get("If-Modified-Since") {
+            if_modified_since = date::parse(value);
+        }
+        let mut if_none_match = None;
+        let values = headers.get_all("If-None-Match");
+        if !values.is_empty() {
+            // Fields with list values may be split over multiple lines
+            let values: Vec<&str> = values.iter().map(|value| value.as_str()).collect();
+            if_none_match = IfNoneMatch::from_str(&values.join(","));
+        }
+        Conditions {
+            if_modified_since,
+            if_none_match,
+        }
+    }
+
+    pub fn from_request(request_message: &request::Message) -> Conditions {
+        Conditions::from_headers(&request_message.headers)
+    }
+
+    /// Should a GET or HEAD request be answered with 304 Not Modified?
+    /// If-None-Match takes precedence over If-Modified-Since when both are present.
+    pub fn is_not_modified(
+        &self,
+        last_modified: Option<SystemTime>,
+        etag: Option<&EntityTag>,
+    ) -> bool {
+        if let Some(if_none_match) = &self.if_none_match {
+            if let Some(etag) = etag {
+                return if_none_match.matches(etag);
+            }
+            return false;
+        }
+        if let (Some(if_modified_since), Some(last_modified)) =
+            (self.if_modified_since, last_modified)
+        {
+            // HTTP-dates only have precision in seconds
+            if let (Ok(if_modified_since), Ok(last_modified)) = (
+                if_modified_since.duration_since(UNIX_EPOCH),
+                last_modified.duration_since(UNIX_EPOCH),
+            ) {
+                return last_modified.as_secs() <= if_modified_since.as_secs();
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_entity_tag() {
+        assert_eq!(
+            EntityTag::from_str("\"abc\""),
+            Some(EntityTag::new("abc".to_string(), false))
+        );
+        assert_eq!(
+            EntityTag::from_str(" W/\"abc\" "),
+            Some(EntityTag::new("abc".to_string(), true))
+        );
+        assert_eq!(
+            EntityTag::from_str("12345"),
+            Some(EntityTag::new("12345".to_string(), false))
+        );
+        assert_eq!(EntityTag::from_str("\"\""), None);
+        assert_eq!(EntityTag::from_str("a\"bc"), None);
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        assert_eq!(IfNoneMatch::from_str(" * "), Some(IfNoneMatch::Any));
+        assert_eq!(
+            IfNoneMatch::from_str("\"a,b\", W/\"c\""),
+            Some(IfNoneMatch::Tags(vec![
+                EntityTag::new("a,b".to_string(), false),
+                EntityTag::new("c".to_string(), true),
+            ]))
+        );
+        assert_eq!(IfNoneMatch::from_str(""), None);
+
+        let if_none_match = IfNoneMatch::from_str("W/\"c\"").unwrap();
+        assert!(if_none_match.matches(&EntityTag::new("c".to_string(), false)));
+        assert!(!if_none_match.matches(&EntityTag::new("d".to_string(), false)));
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let last_modified = UNIX_EPOCH + Duration::new(1_000_000, 500);
+        let etag = EntityTag::new("abc".to_string(), false);
+
+        let conditions = Conditions::from_headers(&Headers::new());
+        assert!(!conditions.is_not_modified(Some(last_modified), Some(&etag)));
+
+        let mut headers = Headers::new();
+        headers.insert(
+            "If-Modified-Since".to_string(),
+            "Mon, 12 Jan 1970 13:46:40 GMT".to_string(),
+        );
+        let conditions = Conditions::from_headers(&headers);
+        assert_eq!(
+            conditions.if_modified_since,
+            Some(UNIX_EPOCH + Duration::new(1_000_000, 0))
+        );
+        assert!(conditions.is_not_modified(Some(last_modified), Some(&etag)));
+        assert!(!conditions.is_not_modified(
+            Some(last_modified + Duration::new(1, 0)),
+            Some(&etag)
+        ));
+
+        // If-None-Match takes precedence
+        headers.insert("If-None-Match".to_string(), "\"def\"".to_string());
+        let conditions = Conditions::from_headers(&headers);
+        assert!(!conditions.is_not_modified(Some(last_modified), Some(&etag)));
+        headers.insert("If-None-Match".to_string(), "\"def\", \"abc\"".to_string());
+        let conditions = Conditions::from_headers(&headers);
+        assert!(conditions.is_not_modified(Some(last_modified), Some(&etag)));
+    }
+}
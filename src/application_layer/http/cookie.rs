@@ -0,0 +1,325 @@
+//! # HTTP cookies
+//! Cookie jar built from the `Cookie` request headers, see RFC 6265 section 5.4, and a builder
+//! for `Set-Cookie` response headers, see RFC 6265 section 4.1.
+
+use std::fmt;
+use std::time::SystemTime;
+
+use application_layer::http::date;
+use application_layer::http::header::Headers;
+use application_layer::http::request;
+
+/// # Holds cookie pairs in the order they were sent
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::cookie::Jar;
+/// use milstian_internet_framework::application_layer::http::header::Headers;
+/// let mut headers = Headers::new();
+/// headers.insert("Cookie".to_string(), "theme=dark; lang=sv".to_string());
+/// headers.insert("Cookie".to_string(), "session=abc".to_string());
+/// let jar = Jar::from_headers(&headers);
+/// assert_eq!(jar.get("lang"), Some(&"sv".to_string()));
+/// assert_eq!(jar.get("session"), Some(&"abc".to_string()));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Jar {
+    pairs: Vec<(String, String)>,
+}
+
+impl Jar {
+    pub fn new() -> Jar {
+        Jar { pairs: Vec::new() }
+    }
+
+    /// Parse the value of a `Cookie` header into name-value pairs
+    pub fn get_pairs(value: &str) -> Vec<(String, String)> {
+        let mut pairs = Vec::new();
+        for pair in value.split(';') {
+            let mut parts = pair.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => continue,
+            };
+            if name.is_empty() {
+                continue;
+            }
+            let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                &value[1..value.len() - 1]
+            } else {
+                value
+            };
+            pairs.push((name.to_string(), value.to_string()));
+        }
+        pairs
+    }
+
+    /// Cookies from all `Cookie` header lines are merged, in the order they were received
+    pub fn from_headers(headers: &Headers) -> Jar {
+        let mut jar = Jar::new();
+        for value in headers.get_all("Cookie") {
+            jar.pairs.extend(Jar::get_pairs(value));
+        }
+        jar
+    }
+
+    pub fn from_request(request_message: &request::Message) -> Jar {
+        Jar::from_headers(&request_message.headers)
+    }
+
+    /// Get the first value of cookie, names are case-sensitive
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Get all values of cookie, clients may send the same name for different paths
+    pub fn get_all(&self, name: &str) -> Vec<&String> {
+        self.pairs
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+}
+
+/// Whether clients send the cookie with requests from other sites
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SameSite {
+    Lax,
+    None,
+    Strict,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+            SameSite::Strict => "Strict",
+        };
+        write!(formatter, "{}", value)
+    }
+}
+
+/// # Builds the value of a `Set-Cookie` header
+/// Values are percent-encoded so they can hold any text, decode them with `uri::decode`.
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::cookie::{SameSite, SetCookie};
+/// let cookie = SetCookie::new("theme", "dark blue")
+///     .path("/")
+///     .max_age(3600)
+///     .http_only()
+///     .same_site(SameSite::Lax);
+/// assert_eq!(
+///     cookie.to_string(),
+///     "theme=dark%20blue; Max-Age=3600; Path=/; HttpOnly; SameSite=Lax"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetCookie {
+    domain: Option<String>,
+    expires: Option<SystemTime>,
+    is_http_only: bool,
+    is_secure: bool,
+    max_age: Option<i64>,
+    name: String,
+    path: Option<String>,
+    same_site: Option<SameSite>,
+    value: String,
+}
+
+impl SetCookie {
+    pub fn new(name: &str, value: &str) -> SetCookie {
+        SetCookie {
+            domain: None,
+            expires: None,
+            is_http_only: false,
+            is_secure: false,
+            max_age: None,
+            name: name.to_string(),
+            path: None,
+            same_site: None,
+            value: value.to_string(),
+        }
+    }
+
+    /// Cookie that makes clients delete a cookie set before with the same domain and path
+    pub fn removal(name: &str) -> SetCookie {
+        SetCookie::new(name, "")
+            .max_age(0)
+            .expires(::std::time::UNIX_EPOCH)
+    }
+
+    pub fn domain(mut self, domain: &str) -> SetCookie {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn expires(mut self, expires: SystemTime) -> SetCookie {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Hide the cookie from scripts
+    pub fn http_only(mut self) -> SetCookie {
+        self.is_http_only = true;
+        self
+    }
+
+    /// Seconds until the cookie expires, takes precedence over `expires` in clients
+    pub fn max_age(mut self, seconds: i64) -> SetCookie {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> SetCookie {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// `SameSite::None` also makes the cookie secure since clients reject it otherwise
+    pub fn same_site(mut self, same_site: SameSite) -> SetCookie {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Only send the cookie over encrypted connections
+    pub fn secure(mut self) -> SetCookie {
+        self.is_secure = true;
+        self
+    }
+
+    /// Percent-encode the bytes that are not allowed in a cookie name or value
+    fn encode(value: &str, is_name: bool) -> String {
+        let mut encoded = String::new();
+        for byte in value.bytes() {
+            let is_allowed = if is_name {
+                Headers::is_token(&(byte as char).to_string()) && byte != b'%'
+            } else {
+                byte > 0x20 && byte < 0x7f && !b"\";,\\%".contains(&byte)
+            };
+            if is_allowed {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        encoded
+    }
+
+    /// Remove what would end the attribute or the header field
+    fn get_attribute_value(value: &str) -> String {
+        value
+            .chars()
+            .filter(|character| *character != ';' && !character.is_control())
+            .collect()
+    }
+}
+
+impl fmt::Display for SetCookie {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}={}",
+            SetCookie::encode(&self.name, true),
+            SetCookie::encode(&self.value, false)
+        )?;
+        if let Some(expires) = self.expires {
+            write!(formatter, "; Expires={}", date::format(expires))?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(formatter, "; Max-Age={}", max_age)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(formatter, "; Domain={}", SetCookie::get_attribute_value(domain))?;
+        }
+        if let Some(path) = &self.path {
+            write!(formatter, "; Path={}", SetCookie::get_attribute_value(path))?;
+        }
+        if self.is_secure || self.same_site == Some(SameSite::None) {
+            write!(formatter, "; Secure")?;
+        }
+        if self.is_http_only {
+            write!(formatter, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(formatter, "; SameSite={}", same_site)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_pairs() {
+        assert_eq!(
+            Jar::get_pairs(" a=1;b=\"two\" ; invalid; =empty; c="),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "two".to_string()),
+                ("c".to_string(), "".to_string()),
+            ]
+        );
+        assert!(Jar::get_pairs("").is_empty());
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = Headers::new();
+        assert!(Jar::from_headers(&headers).is_empty());
+
+        headers.insert("Cookie".to_string(), "id=1; theme=dark".to_string());
+        headers.insert("cookie".to_string(), "id=2".to_string());
+        let jar = Jar::from_headers(&headers);
+        assert_eq!(jar.len(), 3);
+        assert_eq!(jar.get("id"), Some(&"1".to_string()));
+        assert_eq!(jar.get_all("id"), vec![&"1".to_string(), &"2".to_string()]);
+        assert_eq!(jar.get("theme"), Some(&"dark".to_string()));
+        assert!(!jar.contains_key("Theme"));
+    }
+
+    #[test]
+    fn test_set_cookie() {
+        assert_eq!(SetCookie::new("id", "1").to_string(), "id=1");
+        assert_eq!(
+            SetCookie::new("a b", "x;y,\"z\"%\u{e5}").to_string(),
+            "a%20b=x%3By%2C%22z%22%25%C3%A5"
+        );
+        assert_eq!(
+            SetCookie::new("id", "1")
+                .domain("example.com")
+                .path("/app; Secure")
+                .secure()
+                .same_site(SameSite::Strict)
+                .to_string(),
+            "id=1; Domain=example.com; Path=/app Secure; Secure; SameSite=Strict"
+        );
+        assert_eq!(
+            SetCookie::new("id", "1")
+                .same_site(SameSite::None)
+                .to_string(),
+            "id=1; Secure; SameSite=None"
+        );
+        assert_eq!(
+            SetCookie::removal("id").to_string(),
+            "id=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=0"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! # HTTP dates
+//! Parsing of the three HTTP-date formats and formatting as IMF-fixdate, see RFC 7231 section
+//! 7.1.1.1. Dates are always in GMT so no time-zone database is needed.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const LONG_DAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format time as IMF-fixdate, times before 1970 are formatted as the epoch
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::date;
+/// use std::time::{Duration, UNIX_EPOCH};
+/// let time = UNIX_EPOCH + Duration::new(784111777, 0);
+/// assert_eq!(date::format(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+pub fn format(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let days = (seconds / 86_400) as i64;
+    let seconds_of_day = seconds % 86_400;
+    let (year, month, day) = get_civil_from_days(days);
+
+    // 1970-01-01 was a Thursday
+    let weekday = ((days + 3) % 7) as usize;
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[weekday],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Parse IMF-fixdate, obsolete RFC 850 and ANSI C asctime() formats
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::date;
+/// let expected = date::parse("Sun, 06 Nov 1994 08:49:37 GMT");
+/// assert!(expected.is_some());
+/// assert_eq!(date::parse("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+/// assert_eq!(date::parse("Sun Nov  6 08:49:37 1994"), expected);
+/// ```
+pub fn parse(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let parts: Vec<&str> = value.split_whitespace().collect();
+
+    let (weekday, day, month, year, time) = if value.contains(',') {
+        match parts.len() {
+            // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+            6 if parts[5] == "GMT" => {
+                let weekday = parts[0].trim_end_matches(',');
+                if !DAYS.contains(&weekday) {
+                    return None;
+                }
+                (weekday, parts[1], parts[2], parts[3].parse().ok()?, parts[4])
+            }
+            // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+            4 if parts[3] == "GMT" => {
+                let weekday = parts[0].trim_end_matches(',');
+                if !LONG_DAYS.contains(&weekday) {
+                    return None;
+                }
+                let date: Vec<&str> = parts[1].split('-').collect();
+                if date.len() != 3 || date[2].len() != 2 {
+                    return None;
+                }
+                let year: i64 = date[2].parse().ok()?;
+
+                // Two-digit years that appear to be more than 50 years in the future are
+                // in the past, see RFC 7231 section 7.1.1.1
+                let current_year = get_civil_from_days(get_days_since_epoch(SystemTime::now())).0;
+                let mut year = current_year / 100 * 100 + year;
+                if year > current_year + 50 {
+                    year -= 100;
+                }
+                (&weekday[..3], date[0], date[1], year, parts[2])
+            }
+            _ => return None,
+        }
+    } else if parts.len() == 5 {
+        // asctime: Sun Nov  6 08:49:37 1994
+        if !DAYS.contains(&parts[0]) {
+            return None;
+        }
+        (parts[0], parts[2], parts[1], parts[4].parse().ok()?, parts[3])
+    } else {
+        return None;
+    };
+
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let time: Vec<i64> = time
+        .split(':')
+        .map(|part| if part.len() == 2 { part.parse().ok() } else { None })
+        .collect::<Option<Vec<i64>>>()?;
+    if time.len() != 3
+        || time[0] > 23
+        || time[1] > 59
+        || time[2] > 60
+        || day < 1
+        || day > get_days_in_month(year, month)
+        || year < 1970
+    {
+        return None;
+    }
+
+    let days = get_days_from_civil(year, month, day);
+    if DAYS[((days + 3) % 7) as usize] != &weekday[..3] {
+        return None;
+    }
+    let seconds = days * 86_400 + time[0] * 3600 + time[1] * 60 + time[2];
+    Some(UNIX_EPOCH + Duration::new(seconds as u64, 0))
+}
+
+fn get_days_since_epoch(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| (duration.as_secs() / 86_400) as i64)
+        .unwrap_or(0)
+}
+
+fn get_days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn get_days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day of days since 1970-01-01
+fn get_civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            format(UNIX_EPOCH + Duration::new(951_782_400, 999)),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+        assert_eq!(
+            format(UNIX_EPOCH + Duration::new(4_102_444_799, 0)),
+            "Thu, 31 Dec 2099 23:59:59 GMT"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("Tue, 29 Feb 2000 00:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::new(951_782_400, 0))
+        );
+        assert_eq!(parse("Thu Jan  1 00:00:00 1970"), Some(UNIX_EPOCH));
+        assert_eq!(parse("Tue, 29 Feb 2001 00:00:00 GMT"), None);
+        assert_eq!(parse("Wed, 29 Feb 2000 00:00:00 GMT"), None);
+        assert_eq!(parse("Tue, 29 Feb 2000 24:00:00 GMT"), None);
+        assert_eq!(parse("Tue, 29 Feb 2000 00:00:00 CET"), None);
+        assert_eq!(parse("Tuesday, 29-Feb-2000 00:00:00 GMT"), None);
+        assert_eq!(parse("yesterday"), None);
+
+        let days = get_days_since_epoch(SystemTime::now()) as u64;
+        let today = UNIX_EPOCH + Duration::new(days * 86_400, 0);
+        assert_eq!(parse(&format(today)), Some(today));
+    }
+}
@@ -0,0 +1,123 @@
+//! # HTTP fetch metadata
+//! Tells requests a page of another site made a browser send from requests of the site itself
+//! by the `Sec-Fetch-Site` header, or by the `Origin` for browsers without it. Refusing
+//! cross-site requests that change state stops cross-site request forgery without tokens, see
+//! the W3C Fetch Metadata Request Headers.
+
+use std::fmt;
+
+use application_layer::http::request::{self, Method};
+use application_layer::http::websocket;
+
+/// # Which sites may make state-changing requests
+/// Requests without `Sec-Fetch-Site` and `Origin` are not from a browser and are accepted.
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::fetch_metadata::Policy;
+/// use milstian_internet_framework::application_layer::http::request::Message;
+/// let request_message = Message::from_tcp_stream(
+///     b"POST /transfer HTTP/1.1\r\nHost: bank.example\r\nSec-Fetch-Site: cross-site\r\n\r\n",
+/// ).unwrap();
+/// assert!(Policy::SameOrigin.check(&request_message).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Policy {
+    /// Pages of the same origin and of other origins like `https://app.example.com`
+    Origins(Vec<String>),
+    SameOrigin,
+    /// Pages of the same site, like other subdomains of it. Browsers without
+    /// `Sec-Fetch-Site` must send a `Origin` of the same origin.
+    SameSite,
+}
+
+impl Policy {
+    /// Accept or refuse a request with the reason, only state-changing requests are refused
+    pub fn check(&self, request_message: &request::Message) -> Result<(), String> {
+        if !is_state_changing(request_message.request_line.method) {
+            return Ok(());
+        }
+        let site = match request_message.headers.get("Sec-Fetch-Site") {
+            Some(site) => site.trim().to_ascii_lowercase(),
+            None if request_message.headers.get("Origin").is_none() => return Ok(()),
+            None => return self.check_origin(request_message),
+        };
+        match site.as_str() {
+            // The user started the request, like by typing the address
+            "none" | "same-origin" => Ok(()),
+            "same-site" if *self == Policy::SameSite => Ok(()),
+            "same-site" | "cross-site" => match self {
+                Policy::Origins(_) => self.check_origin(request_message),
+                _ => Err(format!("Refused {} request", site)),
+            },
+            _ => Err(format!("Unknown Sec-Fetch-Site {:?}", site)),
+        }
+    }
+
+    /// Same origin, or one of the origins of the policy
+    fn check_origin(&self, request_message: &request::Message) -> Result<(), String> {
+        let same_origin = websocket::check_origin(request_message, &[]);
+        match self {
+            Policy::Origins(origins) if same_origin.is_err() => {
+                websocket::check_origin(request_message, origins)
+            }
+            _ => same_origin,
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Policy::Origins(origins) => write!(formatter, "origins:{}", origins.join(",")),
+            Policy::SameOrigin => write!(formatter, "same-origin"),
+            Policy::SameSite => write!(formatter, "same-site"),
+        }
+    }
+}
+
+/// May requests of the method change state on the server? See RFC 7231 section 4.2.1.
+pub fn is_state_changing(method: Method) -> bool {
+    !matches!(
+        method,
+        Method::Get | Method::Head | Method::Options | Method::Trace
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(policy: &Policy, method: &str, headers: &str) -> Result<(), String> {
+        let request = format!(
+            "{} /account HTTP/1.1\r\nHost: example.com\r\n{}\r\n",
+            method, headers
+        );
+        policy.check(&request::Message::from_tcp_stream(request.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn test_check() {
+        let origins = Policy::Origins(vec!["https://app.example.com".to_string()]);
+        let policies = [Policy::SameOrigin, Policy::SameSite, origins.clone()];
+        for policy in policies.iter() {
+            assert_eq!(check(policy, "GET", "Sec-Fetch-Site: cross-site\r\n"), Ok(()));
+            assert_eq!(check(policy, "POST", ""), Ok(()));
+            assert_eq!(check(policy, "POST", "Sec-Fetch-Site: same-origin\r\n"), Ok(()));
+            assert_eq!(check(policy, "DELETE", "Sec-Fetch-Site: none\r\n"), Ok(()));
+            assert!(check(policy, "PUT", "Sec-Fetch-Site: cross-site\r\n").is_err());
+            assert!(check(policy, "PUT", "Sec-Fetch-Site: other\r\n").is_err());
+            assert_eq!(check(policy, "POST", "Origin: https://example.com\r\n"), Ok(()));
+            assert!(check(policy, "POST", "Origin: https://evil.com\r\n").is_err());
+            assert!(check(policy, "POST", "Origin: null\r\n").is_err());
+        }
+
+        let same_site = "Sec-Fetch-Site: same-site\r\nOrigin: https://app.example.com\r\n";
+        assert!(check(&Policy::SameOrigin, "POST", same_site).is_err());
+        assert_eq!(check(&Policy::SameSite, "POST", same_site), Ok(()));
+        assert_eq!(check(&origins, "POST", same_site), Ok(()));
+        let cross_site = "Sec-Fetch-Site: cross-site\r\nOrigin: https://app.example.com\r\n";
+        assert_eq!(check(&origins, "PATCH", cross_site), Ok(()));
+        assert_eq!(check(&origins, "POST", "Origin: https://app.example.com\r\n"), Ok(()));
+        let cross_site = "Sec-Fetch-Site: cross-site\r\nOrigin: https://evil.com\r\n";
+        assert!(check(&origins, "PATCH", cross_site).is_err());
+    }
+}
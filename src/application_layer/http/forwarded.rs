@@ -0,0 +1,226 @@
+//! # HTTP forwarded client
+//! Finds the address of the client behind trusted proxies from the `Forwarded` header,
+//! see RFC 7239, or the `X-Forwarded-For` header when `Forwarded` is missing.
+
+use std::net::{IpAddr, SocketAddr};
+
+use application_layer::http::header::{parse, Headers};
+
+/// # A IPv4 or IPv6 network like `10.0.0.0/8`
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::forwarded::Cidr;
+/// let network = Cidr::from_str("10.0.0.0/8").unwrap();
+/// assert!(network.contains(&"10.1.2.3".parse().unwrap()));
+/// assert!(!network.contains(&"11.0.0.1".parse().unwrap()));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cidr {
+    pub address: IpAddr,
+    pub prefix: u8,
+}
+
+impl Cidr {
+    /// Parse a network, a address without prefix length is a single host
+    pub fn from_str(value: &str) -> Result<Cidr, String> {
+        let mut parts = value.trim().splitn(2, '/');
+        let address: IpAddr = match parts.next().unwrap_or("").parse() {
+            Ok(address) => address,
+            Err(_) => return Err(format!("Invalid network address in '{}'", value)),
+        };
+        let maximum = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(prefix) => match prefix.parse::<u8>() {
+                Ok(prefix) if prefix <= maximum => prefix,
+                _ => return Err(format!("Invalid prefix length in '{}'", value)),
+            },
+            None => maximum,
+        };
+        Ok(Cidr { address, prefix })
+    }
+
+    /// Parse a comma-separated list of networks
+    pub fn from_list(value: &str) -> Result<Vec<Cidr>, String> {
+        parse::split_list(value)
+            .into_iter()
+            .map(Cidr::from_str)
+            .collect()
+    }
+
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = if self.prefix == 0 {
+                    0
+                } else {
+                    !0u32 << (32 - u32::from(self.prefix))
+                };
+                u32::from(network) & mask == u32::from(*address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = if self.prefix == 0 {
+                    0
+                } else {
+                    !0u128 << (128 - u32::from(self.prefix))
+                };
+                u128::from(network) & mask == u128::from(*address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Addresses of the client and the proxies in between, the closest hop last.
+/// Hops that are obfuscated, `unknown` or invalid are `None`.
+pub fn get_hops(headers: &Headers) -> Vec<Option<IpAddr>> {
+    let forwarded = headers.get_all("Forwarded");
+    if !forwarded.is_empty() {
+        let mut hops = Vec::new();
+        for value in forwarded {
+            for element in parse::split_list(value) {
+                let node = element.split(';').find_map(|pair| {
+                    let mut name_value = pair.splitn(2, '=');
+                    match name_value.next() {
+                        Some(name) if name.trim().eq_ignore_ascii_case("for") => {
+                            Some(parse::unquote(name_value.next().unwrap_or("")))
+                        }
+                        _ => None,
+                    }
+                });
+                hops.push(node.and_then(|node| get_node_address(&node)));
+            }
+        }
+        return hops;
+    }
+    headers
+        .get_all("X-Forwarded-For")
+        .into_iter()
+        .flat_map(|value| parse::split_list(value))
+        .map(get_node_address)
+        .collect()
+}
+
+/// Address of the client, hops are only believed while they were added by trusted proxies.
+/// When the peer is not a trusted proxy it's address is returned as is.
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::forwarded::{self, Cidr};
+/// use milstian_internet_framework::application_layer::http::header::Headers;
+/// let mut headers = Headers::new();
+/// headers.insert("X-Forwarded-For".to_string(), "203.0.113.7, 10.0.0.2".to_string());
+/// let trusted = vec![Cidr::from_str("10.0.0.0/8").unwrap()];
+/// let client = forwarded::get_client_addr(&"10.0.0.1:4000".parse().unwrap(), &headers, &trusted);
+/// assert_eq!(client.to_string(), "203.0.113.7");
+/// ```
+pub fn get_client_addr(socket: &SocketAddr, headers: &Headers, trusted_proxies: &[Cidr]) -> IpAddr {
+    let is_trusted =
+        |address: &IpAddr| trusted_proxies.iter().any(|network| network.contains(address));
+    let mut client = socket.ip();
+    if !is_trusted(&client) {
+        return client;
+    }
+    for hop in get_hops(headers).into_iter().rev() {
+        match hop {
+            Some(address) => {
+                client = address;
+                if !is_trusted(&client) {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    client
+}
+
+/// Address of a node like `192.0.2.43:47011` or `[2001:db8::1]:4711`
+fn get_node_address(node: &str) -> Option<IpAddr> {
+    let node = node.trim();
+    if node.starts_with('[') {
+        return node[1..node.find(']')?].parse().ok();
+    }
+    if let Ok(address) = node.parse() {
+        return Some(address);
+    }
+    node.parse::<SocketAddr>().ok().map(|socket| socket.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cidr() {
+        let network = Cidr::from_str("192.168.1.0/24").unwrap();
+        assert!(network.contains(&"192.168.1.255".parse().unwrap()));
+        assert!(!network.contains(&"192.168.2.1".parse().unwrap()));
+        assert!(!network.contains(&"::1".parse().unwrap()));
+        assert!(Cidr::from_str("0.0.0.0/0")
+            .unwrap()
+            .contains(&"8.8.8.8".parse().unwrap()));
+        let network = Cidr::from_str("fd00::/8").unwrap();
+        assert!(network.contains(&"fd12::1".parse().unwrap()));
+        assert!(!network.contains(&"fe80::1".parse().unwrap()));
+        assert_eq!(Cidr::from_str("::1").unwrap().prefix, 128);
+        assert!(Cidr::from_str("10.0.0.0/33").is_err());
+        assert!(Cidr::from_str("localhost").is_err());
+        assert_eq!(Cidr::from_list("10.0.0.0/8, ::1").unwrap().len(), 2);
+        assert!(Cidr::from_list("10.0.0.0/8, x").is_err());
+    }
+
+    #[test]
+    fn test_get_hops() {
+        let mut headers = Headers::new();
+        headers.insert("X-Forwarded-For".to_string(), "1.2.3.4, unknown".to_string());
+        headers.insert("X-Forwarded-For".to_string(), "10.0.0.2:80".to_string());
+        assert_eq!(
+            get_hops(&headers),
+            vec![
+                Some("1.2.3.4".parse().unwrap()),
+                None,
+                Some("10.0.0.2".parse().unwrap()),
+            ]
+        );
+
+        // Forwarded takes precedence over X-Forwarded-For
+        headers.insert(
+            "Forwarded".to_string(),
+            "for=\"[2001:db8::1]:4711\";proto=https, For=_hidden, by=10.0.0.3;for=10.0.0.4"
+                .to_string(),
+        );
+        assert_eq!(
+            get_hops(&headers),
+            vec![
+                Some("2001:db8::1".parse().unwrap()),
+                None,
+                Some("10.0.0.4".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_client_addr() {
+        let trusted = Cidr::from_list("10.0.0.0/8").unwrap();
+        let proxy: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        let mut headers = Headers::new();
+        assert_eq!(get_client_addr(&proxy, &headers, &trusted), proxy.ip());
+
+        headers.insert(
+            "X-Forwarded-For".to_string(),
+            "6.6.6.6, 203.0.113.7, 10.0.0.2".to_string(),
+        );
+        assert_eq!(
+            get_client_addr(&proxy, &headers, &trusted).to_string(),
+            "203.0.113.7"
+        );
+
+        // Headers from untrusted peers are ignored
+        let remote: SocketAddr = "198.51.100.1:1000".parse().unwrap();
+        assert_eq!(get_client_addr(&remote, &headers, &trusted), remote.ip());
+        assert_eq!(get_client_addr(&proxy, &headers, &[]), proxy.ip());
+
+        // Nothing before a unknown hop can be believed
+        let mut headers = Headers::new();
+        headers.insert("X-Forwarded-For".to_string(), "6.6.6.6, unknown, 10.0.0.2".to_string());
+        assert_eq!(get_client_addr(&proxy, &headers, &trusted).to_string(), "10.0.0.2");
+    }
+}
@@ -0,0 +1,231 @@
+//! # HTTP header fields
+//! Ordered collection of header fields where a name may occur multiple times, see RFC 7230.
+//! Field names are compared case-insensitively, received names get a canonical casing.
+
+pub mod parse;
+
+use std::slice;
+
+/// Names that don't follow the capitalized words casing
+const SPECIAL_NAMES: [&str; 13] = [
+    "Content-MD5",
+    "DNT",
+    "ETag",
+    "Sec-WebSocket-Accept",
+    "Sec-WebSocket-Extensions",
+    "Sec-WebSocket-Key",
+    "Sec-WebSocket-Protocol",
+    "Sec-WebSocket-Version",
+    "TE",
+    "WWW-Authenticate",
+    "X-DNS-Prefetch-Control",
+    "X-UA-Compatible",
+    "X-XSS-Protection",
+];
+
+/// # Holds header fields in the order they were received
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::header::Headers;
+/// let mut headers = Headers::new();
+/// headers.insert("Via".to_string(), "1.0 fred".to_string());
+/// headers.insert("via".to_string(), "1.1 p.example.net".to_string());
+/// assert_eq!(headers.get_first("VIA"), Some(&"1.0 fred".to_string()));
+/// assert_eq!(headers.get_all("Via").len(), 2);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Headers {
+    fields: Vec<(String, String)>,
+}
+
+impl Headers {
+    pub fn new() -> Headers {
+        Headers { fields: Vec::new() }
+    }
+
+    /// Parse a header field line into name and value, the name must be a token
+    /// and the value may not contain control characters other than horizontal tab
+    pub fn get_field(line: &str) -> Option<(String, String)> {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next()?;
+        let value = parts.next()?.trim_matches(|character| character == ' ' || character == '\t');
+        if !Headers::is_token(name)
+            || value
+                .chars()
+                .any(|character| character.is_control() && character != '\t')
+        {
+            return None;
+        }
+        Some((name.to_string(), value.to_string()))
+    }
+
+    /// Canonical casing of a field name, like `Content-Type` for `content-type`, so code and
+    /// logs see the same name whatever casing the client used
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::header::Headers;
+    /// assert_eq!(Headers::get_canonical_name("x-FORWARDED-for"), "X-Forwarded-For");
+    /// assert_eq!(Headers::get_canonical_name("etag"), "ETag");
+    /// ```
+    pub fn get_canonical_name(name: &str) -> String {
+        if let Some(special) = SPECIAL_NAMES
+            .iter()
+            .find(|special| special.eq_ignore_ascii_case(name))
+        {
+            return special.to_string();
+        }
+        let mut canonical = String::with_capacity(name.len());
+        let mut is_word_start = true;
+        for character in name.chars() {
+            if is_word_start {
+                canonical.push(character.to_ascii_uppercase());
+            } else {
+                canonical.push(character.to_ascii_lowercase());
+            }
+            is_word_start = character == '-';
+        }
+        canonical
+    }
+
+    /// Value without control characters other than horizontal tab, so a value reflected from
+    /// user input can't end the field and start another field or the body
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::header::Headers;
+    /// assert_eq!(
+    ///     Headers::get_sanitized_value("/home\r\nSet-Cookie: id=1"),
+    ///     "/homeSet-Cookie: id=1"
+    /// );
+    /// ```
+    pub fn get_sanitized_value(value: &str) -> String {
+        value
+            .chars()
+            .filter(|character| !character.is_control() || *character == '\t')
+            .collect::<String>()
+            .trim_matches(|character| character == ' ' || character == '\t')
+            .to_string()
+    }
+
+    /// Is value a non-empty token of visible US-ASCII characters except delimiters?
+    pub fn is_token(value: &str) -> bool {
+        !value.is_empty()
+            && value.bytes().all(|byte| {
+                byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+            })
+    }
+
+    /// Append a field, previous fields with the same name are kept
+    pub fn insert(&mut self, name: String, value: String) {
+        self.fields.push((name, value));
+    }
+
+    /// Same as get_first, kept for compatibility with code written for a HashMap
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.get_first(name)
+    }
+
+    pub fn get_first(&self, name: &str) -> Option<&String> {
+        self.fields
+            .iter()
+            .find(|(field_name, _)| field_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<&String> {
+        self.fields
+            .iter()
+            .filter(|(field_name, _)| field_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get_first(name).is_some()
+    }
+
+    pub fn iter(&self) -> slice::Iter<(String, String)> {
+        self.fields.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_field() {
+        assert_eq!(
+            Headers::get_field("Host: localhost:8888"),
+            Some(("Host".to_string(), "localhost:8888".to_string()))
+        );
+        assert_eq!(
+            Headers::get_field("Accept:text/html "),
+            Some(("Accept".to_string(), "text/html".to_string()))
+        );
+        assert_eq!(
+            Headers::get_field("X-Name: Åsa Öberg"),
+            Some(("X-Name".to_string(), "Åsa Öberg".to_string()))
+        );
+        assert_eq!(Headers::get_field("Accept"), None);
+        assert_eq!(Headers::get_field(": text/html"), None);
+        assert_eq!(Headers::get_field("Accept : text/html"), None);
+        assert_eq!(Headers::get_field(" Accept: text/html"), None);
+        assert_eq!(Headers::get_field("X-Ä: text/html"), None);
+        assert_eq!(Headers::get_field("Accept: text\0html"), None);
+    }
+
+    #[test]
+    fn test_get_canonical_name() {
+        assert_eq!(Headers::get_canonical_name("host"), "Host");
+        assert_eq!(Headers::get_canonical_name("CONTENT-LENGTH"), "Content-Length");
+        assert_eq!(Headers::get_canonical_name("x-request-id"), "X-Request-Id");
+        assert_eq!(Headers::get_canonical_name("sec-websocket-key"), "Sec-WebSocket-Key");
+        assert_eq!(Headers::get_canonical_name("te"), "TE");
+        assert_eq!(Headers::get_canonical_name("a--b_c"), "A--B_c");
+    }
+
+    #[test]
+    fn test_get_sanitized_value() {
+        assert_eq!(Headers::get_sanitized_value("text/html"), "text/html");
+        assert_eq!(Headers::get_sanitized_value(" a\tb "), "a\tb");
+        assert_eq!(Headers::get_sanitized_value("a\rb\nc\0d\u{7f}e\u{85}"), "abcde");
+        assert_eq!(Headers::get_sanitized_value("Åsa"), "Åsa");
+    }
+
+    #[test]
+    fn test_is_token() {
+        assert!(Headers::is_token("Content-Type"));
+        assert!(Headers::is_token("x!#$%&'*+-.^_`|~1"));
+        assert!(!Headers::is_token(""));
+        assert!(!Headers::is_token("a b"));
+        assert!(!Headers::is_token("a:b"));
+        assert!(!Headers::is_token("a\"b"));
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let mut headers = Headers::new();
+        assert!(headers.is_empty());
+        assert_eq!(headers.get("Set-Cookie"), None);
+        assert!(headers.get_all("Set-Cookie").is_empty());
+
+        headers.insert("Set-Cookie".to_string(), "a=1".to_string());
+        headers.insert("Content-Type".to_string(), "text/html".to_string());
+        headers.insert("set-cookie".to_string(), "b=2".to_string());
+        assert_eq!(headers.len(), 3);
+        assert!(headers.contains_key("content-type"));
+        assert_eq!(headers.get("Set-Cookie"), Some(&"a=1".to_string()));
+        assert_eq!(
+            headers.get_all("SET-COOKIE"),
+            vec![&"a=1".to_string(), &"b=2".to_string()]
+        );
+
+        let names: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Set-Cookie", "Content-Type", "set-cookie"]);
+    }
+}
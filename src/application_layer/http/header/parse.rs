@@ -0,0 +1,209 @@
+//! # HTTP header value parsing
+//! Lists, quoted strings, parameters and quality values, see RFC 7230 section 3.2.6 and 7
+//! and RFC 7231 section 5.3.1.
+
+/// Split a comma-separated list, commas inside quoted strings are kept and empty elements
+/// are skipped
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::header::parse;
+/// assert_eq!(parse::split_list("gzip, , \"a,b\""), vec!["gzip", "\"a,b\""]);
+/// ```
+pub fn split_list(value: &str) -> Vec<&str> {
+    split_unquoted(value, ',')
+        .into_iter()
+        .filter(|element| !element.is_empty())
+        .collect()
+}
+
+/// Split on separator outside of quoted strings, parts are trimmed
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut is_quoted = false;
+    let mut is_escaped = false;
+    let mut start = 0;
+    for (index, character) in value.char_indices() {
+        if is_escaped {
+            is_escaped = false;
+        } else if is_quoted && character == '\\' {
+            is_escaped = true;
+        } else if character == '"' {
+            is_quoted = !is_quoted;
+        } else if character == separator && !is_quoted {
+            parts.push(value[start..index].trim());
+            start = index + character.len_utf8();
+        }
+    }
+    parts.push(value[start..].trim());
+    parts
+}
+
+/// Remove surrounding quotes and escapes of a quoted-string, other values are returned trimmed
+pub fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return value.to_string();
+    }
+    let mut unquoted = String::new();
+    let mut is_escaped = false;
+    for character in value[1..value.len() - 1].chars() {
+        if !is_escaped && character == '\\' {
+            is_escaped = true;
+            continue;
+        }
+        is_escaped = false;
+        unquoted.push(character);
+    }
+    unquoted
+}
+
+/// Split a value like `text/html; charset="utf-8"` into it's value and parameters,
+/// parameter names are lower-cased and parameter values unquoted
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::header::parse;
+/// let (value, parameters) = parse::get_parameters("text/html; Charset=\"utf-8\"");
+/// assert_eq!(value, "text/html");
+/// assert_eq!(parameters, vec![("charset".to_string(), "utf-8".to_string())]);
+/// ```
+pub fn get_parameters(value: &str) -> (String, Vec<(String, String)>) {
+    let mut parts = split_unquoted(value, ';').into_iter();
+    let value = parts.next().unwrap_or("").to_string();
+    let mut parameters = Vec::new();
+    for part in parts {
+        let mut name_value = part.splitn(2, '=');
+        let name = name_value.next().unwrap_or("").trim();
+        if name.is_empty() {
+            continue;
+        }
+        let parameter_value = unquote(name_value.next().unwrap_or(""));
+        parameters.push((name.to_lowercase(), parameter_value));
+    }
+    (value, parameters)
+}
+
+/// Get value of parameter, names are compared case-insensitively
+pub fn get_parameter<'a>(parameters: &'a [(String, String)], name: &str) -> Option<&'a String> {
+    parameters
+        .iter()
+        .find(|(parameter_name, _)| parameter_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+/// Parse a q-value into thousandths, i.e. `0.5` becomes 500
+pub fn get_quality(value: &str) -> Option<u16> {
+    let value = value.trim();
+    let mut parts = value.splitn(2, '.');
+    let integer = parts.next()?;
+    let fraction = parts.next().unwrap_or("");
+    if fraction.len() > 3 || !fraction.chars().all(|character| character.is_ascii_digit()) {
+        return None;
+    }
+    let fraction: u16 = format!("{:0<3}", fraction).parse().ok()?;
+    match integer {
+        "0" => Some(fraction),
+        "1" if fraction == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityItem {
+    pub parameters: Vec<(String, String)>,
+    pub quality: u16,
+    pub value: String,
+}
+
+/// Parse a list with quality values like `Accept-Encoding`, sorted by descending quality.
+/// Elements with equal quality keep their order and invalid q-values count as zero.
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::header::parse;
+/// let items = parse::get_quality_items("gzip;q=0.5, br, identity;q=0");
+/// assert_eq!(items[0].value, "br");
+/// assert_eq!(items[1].quality, 500);
+/// ```
+pub fn get_quality_items(value: &str) -> Vec<QualityItem> {
+    let mut items: Vec<QualityItem> = split_list(value)
+        .into_iter()
+        .map(|element| {
+            let (value, mut parameters) = get_parameters(element);
+            let mut quality = 1000;
+            if let Some(position) = parameters.iter().position(|(name, _)| name == "q") {
+                quality = get_quality(&parameters[position].1).unwrap_or(0);
+                parameters.remove(position);
+            }
+            QualityItem {
+                parameters,
+                quality,
+                value,
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| b.quality.cmp(&a.quality));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(split_list("a, b ,c"), vec!["a", "b", "c"]);
+        assert_eq!(split_list(",,a,,"), vec!["a"]);
+        assert_eq!(
+            split_list("\"x,\\\"y\", W/\"z\""),
+            vec!["\"x,\\\"y\"", "W/\"z\""]
+        );
+        assert!(split_list("").is_empty());
+    }
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote(" \"a\\\"b\\\\c\" "), "a\"b\\c");
+        assert_eq!(unquote("plain"), "plain");
+        assert_eq!(unquote("\""), "\"");
+    }
+
+    #[test]
+    fn test_get_parameters() {
+        let (value, parameters) =
+            get_parameters("multipart/form-data; boundary=\"a;b\" ; Name=x; invalid");
+        assert_eq!(value, "multipart/form-data");
+        assert_eq!(
+            parameters,
+            vec![
+                ("boundary".to_string(), "a;b".to_string()),
+                ("name".to_string(), "x".to_string()),
+                ("invalid".to_string(), "".to_string()),
+            ]
+        );
+        assert_eq!(get_parameter(&parameters, "NAME"), Some(&"x".to_string()));
+        assert_eq!(get_parameter(&parameters, "charset"), None);
+    }
+
+    #[test]
+    fn test_get_quality() {
+        assert_eq!(get_quality("1"), Some(1000));
+        assert_eq!(get_quality("1.000"), Some(1000));
+        assert_eq!(get_quality("0.8"), Some(800));
+        assert_eq!(get_quality("0.05"), Some(50));
+        assert_eq!(get_quality("0"), Some(0));
+        assert_eq!(get_quality("1.5"), None);
+        assert_eq!(get_quality("0.1234"), None);
+        assert_eq!(get_quality("abc"), None);
+    }
+
+    #[test]
+    fn test_get_quality_items() {
+        let items = get_quality_items("text/html;level=1;q=0.7, text/plain, */*;q=x");
+        let values: Vec<&str> = items.iter().map(|item| item.value.as_str()).collect();
+        assert_eq!(values, vec!["text/plain", "text/html", "*/*"]);
+        assert_eq!(
+            items[1].parameters,
+            vec![("level".to_string(), "1".to_string())]
+        );
+        assert_eq!(items[2].quality, 0);
+    }
+}
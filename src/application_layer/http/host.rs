@@ -0,0 +1,163 @@
+//! # HTTP host
+//! Typed `Host` request header and the target host of a request, see RFC 7230 section 5.4.
+
+use std::fmt;
+
+use application_layer::http::request::{Message, Protocol};
+use application_layer::http::uri::Uri;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Host {
+    /// Lower-cased registered name, IPv4 address or IPv6 address in brackets
+    pub name: String,
+    pub port: Option<u16>,
+}
+
+impl Host {
+    /// Parse a host with optional port
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::host::Host;
+    /// let host = Host::from_str("Example.com:8080").unwrap();
+    /// assert_eq!(host.name, "example.com");
+    /// assert_eq!(host.port, Some(8080));
+    /// ```
+    pub fn from_str(value: &str) -> Option<Host> {
+        let uri = Uri::from_authority(value.trim())?;
+        if uri.authority.as_ref()?.contains('@') {
+            return None;
+        }
+        let name = uri.get_host()?.to_lowercase();
+        let is_valid_name = if name.starts_with('[') {
+            name.len() > 2
+                && name[1..name.len() - 1]
+                    .chars()
+                    .all(|character| character.is_ascii_hexdigit() || ".:".contains(character))
+        } else {
+            !name.is_empty()
+                && name.chars().all(|character| {
+                    character.is_ascii_alphanumeric() || "-._~!$&'()*+,;=%".contains(character)
+                })
+        };
+        if !is_valid_name {
+            return None;
+        }
+
+        // A port delimiter must be followed by a valid port if present
+        let after_name = &value.trim()[name.len()..];
+        let port = match uri.get_port() {
+            Some(port) => Some(port),
+            None if after_name.is_empty() || after_name == ":" => None,
+            None => return None,
+        };
+        Some(Host { name, port })
+    }
+
+    /// Host of request, the authority of a absolute-form request target takes precedence
+    /// over the `Host` header
+    pub fn from_request(request_message: &Message) -> Option<Host> {
+        if let Some(authority) = &request_message.request_line.uri.authority {
+            return Host::from_str(authority);
+        }
+        Host::from_str(request_message.headers.get("Host")?)
+    }
+
+    /// A request must be rejected when it has more than one Host header, a invalid Host
+    /// header or when it's a HTTP/1.1 request without Host header.
+    pub fn validate(request_message: &Message) -> Result<(), String> {
+        let values = request_message.headers.get_all("Host");
+        match values.len() {
+            0 if request_message.request_line.protocol == Protocol::V1_1 => {
+                Err("Missing Host header".to_string())
+            }
+            0 => Ok(()),
+            // Intermediaries may have combined several Host headers into a list
+            1 if values[0].contains(',') => Err("Multiple Host headers".to_string()),
+            1 => {
+                // Host is empty when the target URI has no authority
+                if values[0].is_empty() || Host::from_str(values[0]).is_some() {
+                    Ok(())
+                } else {
+                    Err(format!("Invalid Host header {:?}", values[0]))
+                }
+            }
+            _ => Err("Multiple Host headers".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.port {
+            Some(port) => write!(formatter, "{}:{}", self.name, port),
+            None => write!(formatter, "{}", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            Host::from_str("localhost"),
+            Some(Host {
+                name: "localhost".to_string(),
+                port: None,
+            })
+        );
+        assert_eq!(
+            Host::from_str("[::1]:8888"),
+            Some(Host {
+                name: "[::1]".to_string(),
+                port: Some(8888),
+            })
+        );
+        assert_eq!(Host::from_str("127.0.0.1:").unwrap().to_string(), "127.0.0.1");
+        assert_eq!(Host::from_str(""), None);
+        assert_eq!(Host::from_str("a b"), None);
+        assert_eq!(Host::from_str("user@example.com"), None);
+        assert_eq!(Host::from_str("example.com:http"), None);
+        assert_eq!(Host::from_str("example.com:99999"), None);
+        assert_eq!(Host::from_str("example.com/path"), None);
+        assert_eq!(Host::from_str("[zz]"), None);
+    }
+
+    #[test]
+    fn test_from_request() {
+        let request =
+            Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost: Example.com\r\n\r\n").unwrap();
+        assert_eq!(Host::from_request(&request).unwrap().name, "example.com");
+
+        let request = Message::from_tcp_stream(
+            b"GET http://other.com:8080/ HTTP/1.1\r\nHost: example.com\r\n\r\n",
+        ).unwrap();
+        assert_eq!(Host::from_request(&request).unwrap().to_string(), "other.com:8080");
+    }
+
+    #[test]
+    fn test_validate() {
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(Host::validate(&request.unwrap()).is_ok());
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost:\r\n\r\n");
+        assert!(Host::validate(&request.unwrap()).is_ok());
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.0\r\n\r\n");
+        assert!(Host::validate(&request.unwrap()).is_ok());
+
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.1\r\n\r\n");
+        assert!(Host::validate(&request.unwrap()).is_err());
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.0\r\nHost: a b\r\n\r\n");
+        assert!(Host::validate(&request.unwrap()).is_err());
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost: a\r\nHost: b\r\n\r\n");
+        assert!(Host::validate(&request.unwrap()).is_err());
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.1\r\nhost: a\r\nHOST: a\r\n\r\n");
+        assert!(Host::validate(&request.unwrap()).is_err());
+        let request = Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost: a,b\r\n\r\n");
+        assert_eq!(
+            Host::validate(&request.unwrap()),
+            Err("Multiple Host headers".to_string())
+        );
+    }
+}
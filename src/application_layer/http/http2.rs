@@ -0,0 +1,74 @@
+//! # HTTP/2 connection preface
+//! Detects clients that start HTTP/2 with prior knowledge, see RFC 7540 section 3.5, and
+//! builds the frames that turn them away since only HTTP/1 is served.
+
+/// First bytes sent by a HTTP/2 client
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Request-line part of the preface, enough to tell HTTP/2 clients apart
+const PREFACE_LINE: &[u8] = b"PRI * HTTP/2.0\r\n";
+
+const FRAME_GOAWAY: u8 = 0x7;
+const FRAME_SETTINGS: u8 = 0x4;
+
+/// Error code asking the client to retry with HTTP/1.1
+const HTTP_1_1_REQUIRED: u32 = 0xd;
+
+/// Does the data start with the HTTP/2 connection preface?
+pub fn is_preface(data: &[u8]) -> bool {
+    data.starts_with(PREFACE_LINE)
+}
+
+/// A server preface followed by a GOAWAY frame with `HTTP_1_1_REQUIRED` and a reason
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::http2;
+/// let frames = http2::get_rejection("use HTTP/1.1");
+/// assert_eq!(frames.len(), 9 + 9 + 8 + 12);
+/// ```
+pub fn get_rejection(reason: &str) -> Vec<u8> {
+    let mut frames = get_frame(FRAME_SETTINGS, &[]);
+    let mut payload = Vec::with_capacity(8 + reason.len());
+    // No streams were processed
+    payload.extend_from_slice(&0u32.to_be_bytes());
+    payload.extend_from_slice(&HTTP_1_1_REQUIRED.to_be_bytes());
+    payload.extend_from_slice(reason.as_bytes());
+    frames.extend(get_frame(FRAME_GOAWAY, &payload));
+    frames
+}
+
+/// Frame on the connection control stream without flags
+fn get_frame(frame_type: u8, payload: &[u8]) -> Vec<u8> {
+    let length = payload.len() as u32;
+    let mut frame = length.to_be_bytes()[1..].to_vec();
+    frame.push(frame_type);
+    frame.push(0);
+    frame.extend_from_slice(&0u32.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_preface() {
+        assert!(is_preface(PREFACE));
+        assert!(is_preface(b"PRI * HTTP/2.0\r\n"));
+        assert!(!is_preface(b"PRI * HTTP/2.0"));
+        assert!(!is_preface(b"GET / HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn test_get_rejection() {
+        assert_eq!(
+            get_rejection("no"),
+            vec![
+                0, 0, 0, 4, 0, 0, 0, 0, 0, // SETTINGS
+                0, 0, 10, 7, 0, 0, 0, 0, 0, // GOAWAY
+                0, 0, 0, 0, 0, 0, 0, 13, b'n', b'o',
+            ]
+        );
+    }
+}
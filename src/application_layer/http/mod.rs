@@ -1,11 +1,24 @@
 extern crate milstian_http;
 
-pub mod response
-{
-    pub use milstian_http::response::*;
-}
+pub mod arguments;
+pub mod authorization;
+pub mod body;
+pub mod cache_control;
+pub mod chunked;
+pub mod compression;
+pub mod conditional;
+pub mod cookie;
+pub mod date;
+pub mod fetch_metadata;
+pub mod forwarded;
+pub mod header;
+pub mod host;
+pub mod http2;
+pub mod parser;
+pub mod range;
+pub mod request;
+pub mod response;
+pub mod status;
+pub mod uri;
+pub mod websocket;
 
-pub mod request
-{
-    pub use milstian_http::request::*;
-}
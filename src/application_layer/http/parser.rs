@@ -0,0 +1,867 @@
+//! # HTTP request parser
+//! Resumable parser that consumes data as it arrives from a socket, see RFC 7230 section 3.
+
+use std::fmt;
+use std::mem;
+use std::str;
+
+use application_layer::http::body::{Framing, Stream};
+use application_layer::http::header::{parse, Headers};
+use application_layer::http::http2;
+use application_layer::http::request::{find, Line, Message, ParseError, Protocol};
+
+#[derive(Debug, PartialEq)]
+pub enum ParseState {
+    Complete(Message),
+    Incomplete,
+    Invalid(ParseError),
+    TooLarge(Limit),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Limit {
+    Body,
+    Header,
+    RequestLine,
+}
+
+/// # Maximum sizes in bytes of the parts of a request
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+    /// Accept request-lines without a protocol as HTTP/0.9 simple requests
+    pub allow_http_0_9: bool,
+    pub max_body_bytes: usize,
+    pub max_header_bytes: usize,
+    pub max_request_line: usize,
+    /// Bodies larger than this and chunked bodies are left on the connection for responders
+    /// to stream, the body limit does not apply to them
+    pub stream_body_bytes: Option<usize>,
+}
+
+impl Limits {
+    pub fn unlimited() -> Limits {
+        Limits {
+            allow_http_0_9: true,
+            max_body_bytes: usize::max_value(),
+            max_header_bytes: usize::max_value(),
+            max_request_line: usize::max_value(),
+            stream_body_bytes: None,
+        }
+    }
+}
+
+/// # Decides from the request-line whether the body of a request is read
+/// Bodies nobody reads are discarded as they arrive instead of buffered.
+pub struct BodyFilter(Box<Fn(&Line) -> bool + Send>);
+
+impl BodyFilter {
+    pub fn new(filter: Box<Fn(&Line) -> bool + Send>) -> BodyFilter {
+        BodyFilter(filter)
+    }
+
+    pub fn needs_body(&self, request_line: &Line) -> bool {
+        (self.0)(request_line)
+    }
+}
+
+impl fmt::Debug for BodyFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BodyFilter")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Stage {
+    Body(usize),
+    ChunkData(usize),
+    ChunkSize,
+    ChunkTrailer,
+    Head,
+}
+
+/// # Parses a request from data in the order it is received
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::parser::{ParseState, Parser};
+/// let mut parser = Parser::new();
+/// let state = parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nab");
+/// assert_eq!(state, ParseState::Incomplete);
+/// match parser.feed(b"cde") {
+///     ParseState::Complete(message) => assert_eq!(message.request_line.raw, "POST / HTTP/1.1"),
+///     _ => panic!("Expected complete message"),
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Parser {
+    body: Vec<u8>,
+    body_filter: Option<BodyFilter>,
+    buffer: Vec<u8>,
+    /// Bytes of the current body that were discarded
+    discarded: usize,
+    headers: Headers,
+    /// Is the body of the current message discarded as it arrives?
+    is_discarding: bool,
+    is_framed: bool,
+    limits: Limits,
+    request_line: Option<Line>,
+    stage: Stage,
+    streamed: Option<Framing>,
+    trailers: Headers,
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser::with_limits(Limits::unlimited())
+    }
+
+    pub fn with_limits(limits: Limits) -> Parser {
+        Parser {
+            body: Vec::new(),
+            body_filter: None,
+            buffer: Vec::new(),
+            discarded: 0,
+            headers: Headers::new(),
+            is_discarding: false,
+            is_framed: false,
+            limits,
+            request_line: None,
+            stage: Stage::Head,
+            streamed: None,
+            trailers: Headers::new(),
+        }
+    }
+
+    /// Discard bodies of requests the filter says are not read
+    pub fn set_body_filter(&mut self, body_filter: BodyFilter) {
+        self.body_filter = Some(body_filter);
+    }
+
+    /// Was the body of the last completed message discarded?
+    pub fn is_discarding(&self) -> bool {
+        self.is_discarding
+    }
+
+    /// Bytes received but not consumed by the last completed message
+    pub fn get_buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Did the last completed message have Content-Length or Transfer-Encoding headers?
+    pub fn is_framed(&self) -> bool {
+        self.is_framed
+    }
+
+    /// Was the body of the last completed message left on the connection?
+    pub fn is_streamed(&self) -> bool {
+        self.streamed.is_some()
+    }
+
+    /// Framing and the bytes received so far of a body left on the connection
+    pub fn take_streamed(&mut self) -> Option<(Framing, Vec<u8>)> {
+        let framing = self.streamed?;
+        Some((framing, self.buffer.drain(..).collect()))
+    }
+
+    /// Push received data to the parser
+    pub fn feed(&mut self, data: &[u8]) -> ParseState {
+        self.buffer.extend_from_slice(data);
+        loop {
+            match self.stage {
+                Stage::Head => {
+                    let line_end = match find(&self.buffer, b"\r\n", 0) {
+                        Some(position) => position,
+                        None if self.buffer.len() > self.limits.max_request_line => {
+                            return ParseState::TooLarge(Limit::RequestLine);
+                        }
+                        None => return ParseState::Incomplete,
+                    };
+
+                    // Empty lines before the request-line should be ignored
+                    if line_end == 0 {
+                        self.buffer.drain(..2);
+                        continue;
+                    }
+                    if line_end > self.limits.max_request_line {
+                        return ParseState::TooLarge(Limit::RequestLine);
+                    }
+                    if http2::is_preface(&self.buffer) {
+                        return ParseState::Invalid(ParseError::Http2Preface);
+                    }
+
+                    // A HTTP/0.9 simple request has no header section
+                    let is_simple_request = match str::from_utf8(&self.buffer[..line_end]) {
+                        Ok(line) => line.split_whitespace().count() < 3,
+                        Err(_) => return ParseState::Invalid(ParseError::InvalidEncoding),
+                    };
+                    let head_end = if is_simple_request {
+                        line_end
+                    } else {
+                        let head_end = find(&self.buffer, b"\r\n\r\n", 0);
+
+                        // Header fields start after the request-line
+                        let header_bytes = head_end.unwrap_or(self.buffer.len()) - line_end;
+                        if header_bytes > self.limits.max_header_bytes {
+                            return ParseState::TooLarge(Limit::Header);
+                        }
+                        match head_end {
+                            Some(position) => position,
+                            None => return ParseState::Incomplete,
+                        }
+                    };
+
+                    let head: Vec<u8> = self.buffer.drain(..head_end).collect();
+                    let separator_length = if is_simple_request { 2 } else { 4 };
+                    self.buffer.drain(..separator_length);
+                    if let Err(error) = self.set_head(&head) {
+                        return ParseState::Invalid(error);
+                    }
+                    if let Some(framing) = self.get_streamed_framing() {
+                        self.streamed = Some(framing);
+                        return self.complete();
+                    }
+                    if let Stage::Body(length) = self.stage {
+                        if length > self.limits.max_body_bytes {
+                            return ParseState::TooLarge(Limit::Body);
+                        }
+                    }
+                }
+                Stage::Body(length) if self.is_discarding => {
+                    let available = length.min(self.buffer.len());
+                    self.buffer.drain(..available);
+                    self.discarded += available;
+                    if available < length {
+                        self.stage = Stage::Body(length - available);
+                        return ParseState::Incomplete;
+                    }
+                    return self.complete();
+                }
+                Stage::Body(length) => {
+                    if self.buffer.len() < length {
+                        return ParseState::Incomplete;
+                    }
+                    self.body = self.buffer.drain(..length).collect();
+                    return self.complete();
+                }
+                Stage::ChunkSize => {
+                    // Chunk-size lines with their extensions count towards the header limit
+                    let line_end = match find(&self.buffer, b"\r\n", 0) {
+                        Some(position) => position,
+                        None if self.buffer.len() > self.limits.max_header_bytes => {
+                            return ParseState::TooLarge(Limit::Header);
+                        }
+                        None => return ParseState::Incomplete,
+                    };
+                    if line_end + 2 > self.limits.max_header_bytes {
+                        return ParseState::TooLarge(Limit::Header);
+                    }
+                    let line: Vec<u8> = self.buffer.drain(..line_end + 2).collect();
+                    let size = match Parser::get_chunk_size(&line[..line_end]) {
+                        Some(size) => size,
+                        None => return ParseState::Invalid(ParseError::InvalidChunk),
+                    };
+                    if size == 0 {
+                        self.stage = Stage::ChunkTrailer;
+                    } else if size
+                        > self
+                            .limits
+                            .max_body_bytes
+                            .saturating_sub(self.body.len() + self.discarded)
+                    {
+                        return ParseState::TooLarge(Limit::Body);
+                    } else {
+                        self.stage = Stage::ChunkData(size);
+                    }
+                }
+                Stage::ChunkData(length) => {
+                    if self.buffer.len() < length + 2 {
+                        return ParseState::Incomplete;
+                    }
+                    if &self.buffer[length..length + 2] != b"\r\n" {
+                        return ParseState::Invalid(ParseError::InvalidChunk);
+                    }
+                    if self.is_discarding {
+                        self.buffer.drain(..length);
+                        self.discarded += length;
+                    } else {
+                        self.body.extend(self.buffer.drain(..length));
+                    }
+                    self.buffer.drain(..2);
+                    self.stage = Stage::ChunkSize;
+                }
+                Stage::ChunkTrailer => {
+                    // Trailer fields count towards the header limit
+                    let trailer_bytes: usize = self
+                        .trailers
+                        .iter()
+                        .map(|(name, value)| name.len() + value.len() + 4)
+                        .sum();
+                    let line_end = match find(&self.buffer, b"\r\n", 0) {
+                        Some(position) => position,
+                        None if trailer_bytes + self.buffer.len()
+                            > self.limits.max_header_bytes =>
+                        {
+                            return ParseState::TooLarge(Limit::Header);
+                        }
+                        None => return ParseState::Incomplete,
+                    };
+                    if trailer_bytes + line_end + 2 > self.limits.max_header_bytes {
+                        return ParseState::TooLarge(Limit::Header);
+                    }
+                    let line: Vec<u8> = self.buffer.drain(..line_end + 2).collect();
+                    if line_end == 0 {
+                        return self.complete();
+                    }
+                    let field = str::from_utf8(&line[..line_end])
+                        .ok()
+                        .and_then(Headers::get_field);
+                    match field {
+                        Some((name, value)) => self.trailers.insert(name, value),
+                        None => {
+                            let line = String::from_utf8_lossy(&line[..line_end]).to_string();
+                            return ParseState::Invalid(ParseError::InvalidHeader(line));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Signal that no more data will be received, a partially received message is completed
+    /// if it's header section could be parsed.
+    pub fn finish(&mut self) -> ParseState {
+        match self.stage {
+            Stage::Head => {
+                let mut length = self.buffer.len();
+                while length > 0 && self.buffer[length - 1] == 0 {
+                    length -= 1;
+                }
+                if length == 0 {
+                    return ParseState::Incomplete;
+                }
+                let head: Vec<u8> = self.buffer.drain(..).take(length).collect();
+                if let Err(error) = self.set_head(&head) {
+                    return ParseState::Invalid(error);
+                }
+                if let Stage::Body(_) = self.stage {
+                    return self.complete();
+                }
+                ParseState::Incomplete
+            }
+            Stage::Body(_) => {
+                if self.is_discarding {
+                    self.buffer.clear();
+                } else {
+                    self.body = self.buffer.drain(..).collect();
+                }
+                self.complete()
+            }
+            _ => ParseState::Invalid(ParseError::Incomplete),
+        }
+    }
+
+    /// Framing of a body that should be left on the connection instead of buffered, discarded
+    /// bodies are read off the connection so it can be reused
+    fn get_streamed_framing(&self) -> Option<Framing> {
+        if self.is_discarding {
+            return None;
+        }
+        let threshold = self.limits.stream_body_bytes?;
+        match self.stage {
+            Stage::Body(length) if length > threshold => Some(Framing::Length(length)),
+            Stage::ChunkSize => Some(Framing::Chunked),
+            _ => None,
+        }
+    }
+
+    /// Size of a chunk from a chunk-size line, extensions are ignored. The size is only
+    /// hexadecimal digits and whitespace is only allowed before extensions, see RFC 7230
+    /// section 4.1, so a proxy in front can't frame the body differently.
+    pub fn get_chunk_size(line: &[u8]) -> Option<usize> {
+        let digits = line.iter().take_while(|byte| byte.is_ascii_hexdigit()).count();
+        let rest = &line[digits..];
+        match rest.iter().position(|byte| *byte != b' ' && *byte != b'\t') {
+            None if rest.is_empty() => {}
+            Some(position) if rest[position] == b';' => {}
+            _ => return None,
+        }
+        let size = str::from_utf8(&line[..digits]).ok()?;
+        usize::from_str_radix(size, 16).ok()
+    }
+
+    /// Parse request-line and header fields and decide how the body is framed
+    fn set_head(&mut self, head: &[u8]) -> Result<(), ParseError> {
+        // Field values may contain UTF-8 but the request-line is restricted to US-ASCII
+        let head = str::from_utf8(head).map_err(|_| ParseError::InvalidEncoding)?;
+        let mut lines = head.split("\r\n");
+        let request_line = match lines.next() {
+            Some(line) if line.is_ascii() => Message::get_request_line(line)?,
+            _ => return Err(ParseError::InvalidEncoding),
+        };
+        // Without a protocol any garbage line would look like a valid request
+        if request_line.protocol == Protocol::V0_9 && !self.limits.allow_http_0_9 {
+            return Err(ParseError::InvalidRequestLine(request_line.raw));
+        }
+        self.streamed = None;
+        let mut headers = Headers::new();
+        if request_line.protocol != Protocol::V0_9 {
+            for line in lines {
+                if line.starts_with(&[' ', '\t'][..]) {
+                    return Err(ParseError::FoldedHeader(line.to_string()));
+                }
+                match Headers::get_field(line) {
+                    Some((name, value)) => {
+                        headers.insert(Headers::get_canonical_name(&name), value)
+                    }
+                    None => return Err(ParseError::InvalidHeader(line.to_string())),
+                }
+            }
+        }
+
+        self.is_framed = true;
+        self.stage = Stage::Body(0);
+        let transfer_codings: Vec<&str> = headers
+            .get_all("Transfer-Encoding")
+            .iter()
+            .flat_map(|value| parse::split_list(value))
+            .collect();
+        let content_length = Parser::get_content_length(&headers)?;
+        if !transfer_codings.is_empty() {
+            // Senders must not use both, see RFC 7230 section 3.3.3
+            if content_length.is_some() {
+                return Err(ParseError::AmbiguousFraming(
+                    "both Transfer-Encoding and Content-Length".to_string(),
+                ));
+            }
+            match transfer_codings.last() {
+                Some(coding) if coding.eq_ignore_ascii_case("chunked") => {
+                    self.stage = Stage::ChunkSize
+                }
+                _ => {
+                    return Err(ParseError::AmbiguousFraming(
+                        "Transfer-Encoding does not end with chunked".to_string(),
+                    ))
+                }
+            }
+        } else if let Some(content_length) = content_length {
+            self.stage = Stage::Body(content_length);
+        } else {
+            self.is_framed = false;
+        }
+
+        self.discarded = 0;
+        self.is_discarding = match &self.body_filter {
+            Some(body_filter) => self.is_framed && !body_filter.needs_body(&request_line),
+            None => false,
+        };
+        self.headers = headers;
+        self.request_line = Some(request_line);
+        Ok(())
+    }
+
+    /// Content-Length from all fields and list elements, repeated values must be identical
+    fn get_content_length(headers: &Headers) -> Result<Option<usize>, ParseError> {
+        let mut content_length = None;
+        for value in headers.get_all("Content-Length") {
+            for element in parse::split_list(value) {
+                // Signs and spaces are accepted by parse but not by RFC 7230 section 3.3.2
+                let length = match element.parse::<usize>() {
+                    Ok(length) if element.bytes().all(|byte| byte.is_ascii_digit()) => length,
+                    _ => return Err(ParseError::InvalidContentLength(value.to_string())),
+                };
+                if content_length.map_or(false, |previous| previous != length) {
+                    return Err(ParseError::AmbiguousFraming(
+                        "conflicting Content-Length values".to_string(),
+                    ));
+                }
+                content_length = Some(length);
+            }
+        }
+        Ok(content_length)
+    }
+
+    /// Build message and prepare parser for the next message
+    fn complete(&mut self) -> ParseState {
+        let headers = mem::replace(&mut self.headers, Headers::new());
+        let body = mem::replace(&mut self.body, Vec::new());
+        let trailers = mem::replace(&mut self.trailers, Headers::new());
+        self.stage = Stage::Head;
+        match self.request_line.take() {
+            Some(request_line) => ParseState::Complete(Message {
+                body: Message::get_message_body(&body, &headers),
+                body_stream: Stream::default(),
+                request_id: Message::get_new_request_id(&headers),
+                headers,
+                request_line,
+                trailers,
+            }),
+            None => ParseState::Invalid(ParseError::Incomplete),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use application_layer::http::request::{BodyContentType, Method};
+    use application_layer::http::arguments::Arguments;
+    use application_layer::http::status::HttpStatus;
+
+    fn get_message(state: ParseState) -> Message {
+        match state {
+            ParseState::Complete(message) => message,
+            state => panic!("Expected complete message, got {:?}", state),
+        }
+    }
+
+    #[test]
+    fn test_feed_byte_by_byte() {
+        let request = b"\r\nGET /index.htm HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let mut parser = Parser::new();
+        for byte in request[..request.len() - 1].iter() {
+            assert_eq!(parser.feed(&[*byte]), ParseState::Incomplete);
+        }
+        let message = get_message(parser.feed(&request[request.len() - 1..]));
+        assert_eq!(message.request_line.method, Method::Get);
+        assert_eq!(message.headers.get("Host"), Some(&"localhost".to_string()));
+        assert!(parser.get_buffer().is_empty());
+        assert!(!parser.is_framed());
+    }
+
+    #[test]
+    fn test_feed_normalized_headers() {
+        let mut parser = Parser::new();
+        let message = get_message(parser.feed(
+            b"GET / HTTP/1.1\r\nhost: localhost\r\nx-CUSTOM:\t a  b \t\r\netag: \"x\"\r\n\r\n",
+        ));
+        let fields: Vec<(&str, &str)> = message
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("Host", "localhost"), ("X-Custom", "a  b"), ("ETag", "\"x\"")]
+        );
+    }
+
+    #[test]
+    fn test_feed_content_length() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 7\r\n\r\na=1"),
+            ParseState::Incomplete
+        );
+        let message = get_message(parser.feed(b"&b=2GET"));
+        let mut expected = Arguments::new();
+        expected.insert("a".to_string(), "1".to_string());
+        expected.insert("b".to_string(), "2".to_string());
+        assert_eq!(message.body, BodyContentType::SinglePart(expected));
+        assert_eq!(parser.get_buffer(), b"GET");
+        assert!(parser.is_framed());
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nContent-Length: abc\r\n\r\n"),
+            ParseState::Invalid(ParseError::InvalidContentLength("abc".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_framing() {
+        let is_ambiguous = |data: &[u8]| match Parser::new().feed(data) {
+            ParseState::Invalid(ParseError::AmbiguousFraming(_)) => true,
+            _ => false,
+        };
+        assert!(is_ambiguous(
+            b"POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n"
+        ));
+        assert!(is_ambiguous(
+            b"POST / HTTP/1.1\r\nContent-Length: 3\r\nContent-Length: 4\r\n\r\n"
+        ));
+        assert!(is_ambiguous(b"POST / HTTP/1.1\r\nContent-Length: 3, 4\r\n\r\n"));
+        assert!(is_ambiguous(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n"
+        ));
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nContent-Length: +3\r\n\r\nabc"),
+            ParseState::Invalid(ParseError::InvalidContentLength("+3".to_string()))
+        );
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nHost: a\r\n b\r\n\r\n"),
+            ParseState::Invalid(ParseError::FoldedHeader(" b".to_string()))
+        );
+
+        // Repeated identical values are the same length
+        let mut parser = Parser::new();
+        get_message(parser.feed(
+            b"POST / HTTP/1.1\r\nContent-Length: 3\r\nContent-Length: 3, 3\r\n\r\nabcGET",
+        ));
+        assert_eq!(parser.get_buffer(), b"GET");
+        assert_eq!(
+            ParseError::FoldedHeader(String::new()).get_status(),
+            HttpStatus::BadRequest
+        );
+        assert_eq!(
+            ParseError::AmbiguousFraming(String::new()).get_status(),
+            HttpStatus::BadRequest
+        );
+    }
+
+    #[test]
+    fn test_feed_chunked() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\na=1"),
+            ParseState::Incomplete
+        );
+        assert_eq!(parser.feed(b"\r\n4;ext=1\r\n&b=2\r\n"), ParseState::Incomplete);
+        assert_eq!(parser.feed(b"0\r\nContent-MD5: abc\r\n"), ParseState::Incomplete);
+        let message = get_message(parser.feed(b"X-Checksum: 123\r\n\r\n"));
+        assert_eq!(message.trailers.get("Content-MD5"), Some(&"abc".to_string()));
+        assert_eq!(message.trailers.get("X-Checksum"), Some(&"123".to_string()));
+        assert!(message.headers.get("X-Checksum").is_none());
+        let mut expected = Arguments::new();
+        expected.insert("a".to_string(), "1".to_string());
+        expected.insert("b".to_string(), "2".to_string());
+        assert_eq!(message.body, BodyContentType::SinglePart(expected));
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nx\r\n"),
+            ParseState::Invalid(ParseError::InvalidChunk)
+        );
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nab\r\n"),
+            ParseState::Invalid(ParseError::InvalidChunk)
+        );
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nbad\r\n"),
+            ParseState::Invalid(ParseError::InvalidHeader("bad".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_get_chunk_size() {
+        assert_eq!(Parser::get_chunk_size(b"1a"), Some(26));
+        assert_eq!(Parser::get_chunk_size(b"1A;name=value"), Some(26));
+        assert_eq!(Parser::get_chunk_size(b"5 \t;ext"), Some(5));
+        assert_eq!(Parser::get_chunk_size(b"0"), Some(0));
+        assert_eq!(Parser::get_chunk_size(b"+5"), None);
+        assert_eq!(Parser::get_chunk_size(b" 5"), None);
+        assert_eq!(Parser::get_chunk_size(b"5 "), None);
+        assert_eq!(Parser::get_chunk_size(b"0x5"), None);
+        assert_eq!(Parser::get_chunk_size(b""), None);
+        assert_eq!(Parser::get_chunk_size(b";ext"), None);
+        assert_eq!(Parser::get_chunk_size(b"10000000000000000"), None);
+    }
+
+    #[test]
+    fn test_feed_limits() {
+        let limits = Limits {
+            allow_http_0_9: true,
+            max_body_bytes: 4,
+            max_header_bytes: 32,
+            max_request_line: 16,
+            stream_body_bytes: None,
+        };
+
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(parser.feed(b"GET /0123456789"), ParseState::Incomplete);
+        assert_eq!(
+            parser.feed(b"ab HTTP/1.1\r\n"),
+            ParseState::TooLarge(Limit::RequestLine)
+        );
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(
+            parser.feed(b"GET /0123456789abcdef"),
+            ParseState::TooLarge(Limit::RequestLine)
+        );
+
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(
+            parser.feed(b"GET / HTTP/1.1\r\nHost: localhost\r\n"),
+            ParseState::Incomplete
+        );
+        assert_eq!(
+            parser.feed(b"Accept: text/html\r\n"),
+            ParseState::TooLarge(Limit::Header)
+        );
+        let mut parser = Parser::with_limits(limits.clone());
+        assert!(match parser.feed(b"GET / HTTP/1.1\r\nHost: localhost1234567890\r\n\r\n") {
+            ParseState::Complete(_) => true,
+            _ => false,
+        });
+
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\n"),
+            ParseState::TooLarge(Limit::Body)
+        );
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(
+            parser.feed(
+                b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\n"
+            ),
+            ParseState::TooLarge(Limit::Body)
+        );
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1;ext="),
+            ParseState::Incomplete
+        );
+        assert_eq!(
+            parser.feed(b"0123456789abcdef0123456789abcdef"),
+            ParseState::TooLarge(Limit::Header)
+        );
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            ParseState::Incomplete
+        );
+        assert_eq!(
+            parser.feed(b"1;0123456789abcdef0123456789abcdef\r\n"),
+            ParseState::TooLarge(Limit::Header)
+        );
+        let mut parser = Parser::with_limits(limits);
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n"),
+            ParseState::Incomplete
+        );
+        assert_eq!(
+            parser.feed(b"X-Checksum: 0123456789abcdef0123456789\r\n"),
+            ParseState::TooLarge(Limit::Header)
+        );
+    }
+
+    #[test]
+    fn test_feed_streamed() {
+        let limits = Limits {
+            stream_body_bytes: Some(4),
+            ..Limits::unlimited()
+        };
+        let mut parser = Parser::with_limits(limits.clone());
+        let message =
+            get_message(parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nabcd"));
+        assert_eq!(message.body, BodyContentType::SinglePart(Arguments::from_str("abcd")));
+        assert!(!parser.is_streamed());
+        assert_eq!(parser.take_streamed(), None);
+
+        let mut parser = Parser::with_limits(limits.clone());
+        get_message(parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nab"));
+        assert!(parser.is_streamed());
+        assert_eq!(
+            parser.take_streamed(),
+            Some((Framing::Length(5), b"ab".to_vec()))
+        );
+
+        let mut parser = Parser::with_limits(limits);
+        get_message(parser.feed(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"));
+        assert_eq!(parser.take_streamed(), Some((Framing::Chunked, Vec::new())));
+    }
+
+    #[test]
+    fn test_feed_discarded() {
+        let limits = Limits {
+            max_body_bytes: 8,
+            stream_body_bytes: Some(4),
+            ..Limits::unlimited()
+        };
+        let mut parser = Parser::with_limits(limits);
+        parser.set_body_filter(BodyFilter::new(Box::new(|request_line: &Line| {
+            request_line.method == Method::Post
+        })));
+
+        // Discarded as it arrives, not left on the connection and the next request follows
+        let request = b"PUT / HTTP/1.1\r\nContent-Length: 6\r\n\r\nabc";
+        assert_eq!(parser.feed(request), ParseState::Incomplete);
+        assert!(parser.is_discarding());
+        assert!(parser.get_buffer().is_empty());
+        let message = get_message(parser.feed(b"defGET / HTTP/1.1\r\n"));
+        assert_eq!(message.body, BodyContentType::SinglePart(Arguments::new()));
+        assert!(!parser.is_streamed());
+        assert_eq!(parser.get_buffer(), b"GET / HTTP/1.1\r\n");
+
+        let mut parser = Parser::with_limits(Limits {
+            max_body_bytes: 8,
+            ..Limits::unlimited()
+        });
+        parser.set_body_filter(BodyFilter::new(Box::new(|_: &Line| false)));
+        let request = b"PUT / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nabcd\r\n0\r\n\r\n";
+        let message = get_message(parser.feed(request));
+        assert_eq!(message.body, BodyContentType::SinglePart(Arguments::new()));
+
+        // Discarded bodies still count towards the limit
+        let request = b"PUT / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nabcdef\r\n6\r\n";
+        assert_eq!(parser.feed(request), ParseState::TooLarge(Limit::Body));
+
+        // Requests the filter wants are read as before
+        let mut parser = Parser::new();
+        parser.set_body_filter(BodyFilter::new(Box::new(|_: &Line| true)));
+        let message =
+            get_message(parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 3\r\n\r\na=1"));
+        assert!(!parser.is_discarding());
+        let mut expected = Arguments::new();
+        expected.insert("a".to_string(), "1".to_string());
+        assert_eq!(message.body, BodyContentType::SinglePart(expected));
+    }
+
+    #[test]
+    fn test_feed_http2_preface() {
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(b"PRI * HTTP/2.0"), ParseState::Incomplete);
+        assert_eq!(
+            parser.feed(b"\r\n\r\nSM\r\n\r\n"),
+            ParseState::Invalid(ParseError::Http2Preface)
+        );
+    }
+
+    #[test]
+    fn test_feed_simple_request() {
+        let mut parser = Parser::new();
+        let message = get_message(parser.feed(b"/index.htm\r\n"));
+        assert_eq!(message.request_line.protocol, Protocol::V0_9);
+
+        let limits = Limits {
+            allow_http_0_9: false,
+            ..Limits::unlimited()
+        };
+        let mut parser = Parser::with_limits(limits.clone());
+        assert_eq!(
+            parser.feed(b"GET /index.htm\r\n"),
+            ParseState::Invalid(ParseError::InvalidRequestLine("GET /index.htm".to_string()))
+        );
+        let mut parser = Parser::with_limits(limits);
+        let message = get_message(parser.feed(b"GET /index.htm HTTP/1.0\r\n\r\n"));
+        assert_eq!(message.request_line.protocol, Protocol::V1_0);
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(b"GET / HTTP/1.0"), ParseState::Incomplete);
+        let message = get_message(parser.finish());
+        assert_eq!(message.request_line.protocol, Protocol::V1_0);
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\na=1"),
+            ParseState::Incomplete
+        );
+        let message = get_message(parser.finish());
+        let mut expected = Arguments::new();
+        expected.insert("a".to_string(), "1".to_string());
+        assert_eq!(message.body, BodyContentType::SinglePart(expected));
+
+        let mut parser = Parser::new();
+        assert_eq!(parser.finish(), ParseState::Incomplete);
+        assert_eq!(parser.feed(b"GET / HTTP/9.9"), ParseState::Incomplete);
+        assert_eq!(
+            parser.finish(),
+            ParseState::Invalid(ParseError::UnsupportedProtocol("HTTP/9.9".to_string()))
+        );
+    }
+}
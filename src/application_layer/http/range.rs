@@ -0,0 +1,262 @@
+//! # HTTP byte ranges
+//! Typed `Range` and `If-Range` request headers and `multipart/byteranges` bodies, see
+//! RFC 7233.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use application_layer::http::conditional::EntityTag;
+use application_layer::http::date;
+use application_layer::http::header::{parse, Headers};
+use application_layer::http::request;
+
+/// Requests with more ranges are answered with the whole representation, many small ranges
+/// cost more to send than they save
+pub const MAX_RANGES: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteRange {
+    /// From a first byte to the end of the representation
+    From(u64),
+    /// From a first to a last byte, both included
+    FromTo(u64, u64),
+    /// The last bytes of the representation
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// First and last byte of the range in a representation of `length` bytes, none when the
+    /// range is not satisfiable
+    pub fn resolve(&self, length: u64) -> Option<(u64, u64)> {
+        match *self {
+            ByteRange::From(first) if first < length => Some((first, length - 1)),
+            ByteRange::FromTo(first, last) if first < length => {
+                Some((first, last.min(length - 1)))
+            }
+            ByteRange::Suffix(suffix) if suffix > 0 && length > 0 => {
+                Some((length - suffix.min(length), length - 1))
+            }
+            _ => None,
+        }
+    }
+
+    fn from_str(value: &str) -> Option<ByteRange> {
+        let mut parts = value.trim().splitn(2, '-');
+        let first = parts.next()?.trim();
+        let last = parts.next()?.trim();
+        let parse_number = |value: &str| {
+            if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+                None
+            } else {
+                value.parse::<u64>().ok()
+            }
+        };
+        if first.is_empty() {
+            return parse_number(last).map(ByteRange::Suffix);
+        }
+        let first = parse_number(first)?;
+        if last.is_empty() {
+            return Some(ByteRange::From(first));
+        }
+        let last = parse_number(last)?;
+        if last < first {
+            return None;
+        }
+        Some(ByteRange::FromTo(first, last))
+    }
+}
+
+/// # Byte ranges requested by a client
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::range::Ranges;
+/// let ranges = Ranges::from_str("bytes=0-4, -3").unwrap();
+/// assert_eq!(ranges.resolve(10), vec![(0, 4), (7, 9)]);
+/// assert_eq!(Ranges::from_str("items=0-4"), None);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ranges {
+    pub ranges: Vec<ByteRange>,
+}
+
+impl Ranges {
+    /// Parse a `Range` header value of the bytes unit, none when it's invalid or has more than
+    /// `MAX_RANGES` ranges so it can be ignored
+    pub fn from_str(value: &str) -> Option<Ranges> {
+        let value = value.trim();
+        let specs = match value.get(..6) {
+            Some(unit) if unit.eq_ignore_ascii_case("bytes=") => parse::split_list(&value[6..]),
+            _ => return None,
+        };
+        if specs.is_empty() || specs.len() > MAX_RANGES {
+            return None;
+        }
+        let mut ranges = Vec::with_capacity(specs.len());
+        for spec in specs {
+            ranges.push(ByteRange::from_str(spec)?);
+        }
+        Some(Ranges { ranges })
+    }
+
+    pub fn from_headers(headers: &Headers) -> Option<Ranges> {
+        Ranges::from_str(headers.get("Range")?)
+    }
+
+    pub fn from_request(request_message: &request::Message) -> Option<Ranges> {
+        Ranges::from_headers(&request_message.headers)
+    }
+
+    /// Satisfiable ranges in a representation of `length` bytes, in the requested order unless
+    /// some overlap, then they are sorted and coalesced
+    pub fn resolve(&self, length: u64) -> Vec<(u64, u64)> {
+        let mut resolved: Vec<(u64, u64)> = self
+            .ranges
+            .iter()
+            .filter_map(|range| range.resolve(length))
+            .collect();
+        let is_overlapping = resolved.iter().enumerate().any(|(index, first)| {
+            resolved[index + 1..]
+                .iter()
+                .any(|second| first.0 <= second.1 && second.0 <= first.1)
+        });
+        if !is_overlapping {
+            return resolved;
+        }
+        resolved.sort();
+        let mut coalesced: Vec<(u64, u64)> = Vec::with_capacity(resolved.len());
+        for (first, last) in resolved {
+            match coalesced.last_mut() {
+                Some(previous) if first <= previous.1 => previous.1 = previous.1.max(last),
+                _ => coalesced.push((first, last)),
+            }
+        }
+        coalesced
+    }
+
+    /// Do ranges apply to the current representation? A `If-Range` must equal it's strong
+    /// entity-tag or last modification, otherwise the whole representation is sent.
+    pub fn is_if_range_satisfied(
+        headers: &Headers,
+        etag: Option<&EntityTag>,
+        last_modified: Option<SystemTime>,
+    ) -> bool {
+        let value = match headers.get("If-Range") {
+            Some(value) => value,
+            None => return true,
+        };
+        if let Some(if_range) = date::parse(value) {
+            let seconds = |time: SystemTime| {
+                time.duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|duration| duration.as_secs())
+            };
+            return match last_modified {
+                Some(last_modified) => seconds(if_range) == seconds(last_modified),
+                None => false,
+            };
+        }
+        match (EntityTag::from_str(value), etag) {
+            (Some(if_range), Some(etag)) => {
+                !if_range.weak && !etag.weak && if_range.tag == etag.tag
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Value of a `Content-Range` header for bytes `first` to `last` of `length`
+pub fn get_content_range(first: u64, last: u64, length: u64) -> String {
+    format!("bytes {}-{}/{}", first, last, length)
+}
+
+/// `multipart/byteranges` body with a part for each range of `body`, every part has the
+/// Content-Type of the representation and it's own Content-Range, see RFC 7233 appendix A
+pub fn get_multipart(
+    body: &[u8],
+    ranges: &[(u64, u64)],
+    content_type: &str,
+    boundary: &str,
+) -> Vec<u8> {
+    let length = body.len() as u64;
+    let mut multipart = Vec::new();
+    for (first, last) in ranges {
+        multipart.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                boundary,
+                content_type,
+                get_content_range(*first, *last, length)
+            ).as_bytes(),
+        );
+        multipart.extend_from_slice(&body[*first as usize..=*last as usize]);
+        multipart.extend_from_slice(b"\r\n");
+    }
+    multipart.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    multipart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            Ranges::from_str("Bytes=0-0, 5-, -10 ,2-9"),
+            Some(Ranges {
+                ranges: vec![
+                    ByteRange::FromTo(0, 0),
+                    ByteRange::From(5),
+                    ByteRange::Suffix(10),
+                    ByteRange::FromTo(2, 9),
+                ],
+            })
+        );
+        assert_eq!(Ranges::from_str("bytes="), None);
+        assert_eq!(Ranges::from_str("bytes=5-1"), None);
+        assert_eq!(Ranges::from_str("bytes=-"), None);
+        assert_eq!(Ranges::from_str("bytes=a-b"), None);
+        assert_eq!(Ranges::from_str("bytes=+1-2"), None);
+        assert_eq!(Ranges::from_str("0-1"), None);
+        assert_eq!(Ranges::from_str("bytés=0-1"), None);
+        let many = vec!["0-0"; MAX_RANGES + 1].join(",");
+        assert_eq!(Ranges::from_str(&format!("bytes={}", many)), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let ranges = Ranges::from_str("bytes=8-20,0-1,-0,30-").unwrap();
+        assert_eq!(ranges.resolve(10), vec![(8, 9), (0, 1)]);
+        assert_eq!(ranges.resolve(0), Vec::new());
+        let ranges = Ranges::from_str("bytes=5-9,0-2,2-5,-50").unwrap();
+        assert_eq!(ranges.resolve(10), vec![(0, 9)]);
+        let ranges = Ranges::from_str("bytes=6-8,0-2,1-3").unwrap();
+        assert_eq!(ranges.resolve(10), vec![(0, 3), (6, 8)]);
+    }
+
+    #[test]
+    fn test_is_if_range_satisfied() {
+        let etag = EntityTag::from_str("\"abc\"").unwrap();
+        let last_modified = date::parse("Sun, 06 Nov 1994 08:49:37 GMT");
+        let is_satisfied = |if_range: &str| {
+            let mut headers = Headers::new();
+            headers.insert("If-Range".to_string(), if_range.to_string());
+            Ranges::is_if_range_satisfied(&headers, Some(&etag), last_modified)
+        };
+        assert!(Ranges::is_if_range_satisfied(&Headers::new(), None, None));
+        assert!(is_satisfied("\"abc\""));
+        assert!(!is_satisfied("W/\"abc\""));
+        assert!(!is_satisfied("\"def\""));
+        assert!(is_satisfied("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert!(!is_satisfied("Sun, 06 Nov 1994 08:49:38 GMT"));
+    }
+
+    #[test]
+    fn test_get_multipart() {
+        let multipart = get_multipart(b"0123456789", &[(0, 1), (7, 9)], "text/plain", "XYZ");
+        assert_eq!(
+            String::from_utf8(multipart).unwrap(),
+            "--XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 7-9/10\r\n\r\n789\r\n\
+             --XYZ--\r\n"
+        );
+    }
+}
@@ -0,0 +1,855 @@
+//! # HTTP request message
+//! Decodes requests received via TCP, see RFC 7230.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str;
+use std::str::FromStr;
+
+use application_layer::http::arguments::Arguments;
+use application_layer::http::body;
+use application_layer::http::forwarded::{self, Cidr};
+use application_layer::http::header::{parse, Headers};
+use application_layer::http::parser::{Limit, ParseState, Parser};
+use application_layer::http::status::HttpStatus;
+use application_layer::http::uri::Uri;
+use application_layer::http::websocket;
+use mime::MediaType;
+use random;
+
+/// Longest incoming request identifier that is honored
+const MAX_REQUEST_ID: usize = 128;
+
+/// # Decoded body, the variant is chosen by the media type of `Content-Type`
+#[derive(Debug, PartialEq)]
+pub enum BodyContentType {
+    /// Body of a `application/json` or `+json` media type, kept as text
+    Json(String),
+    MultiPart(HashMap<String, MultiPartValue>),
+    /// Body of any other media type, or a body that could not be decoded as it's media type
+    Other(MediaType, Vec<u8>),
+    /// Body of `application/x-www-form-urlencoded` or a body without `Content-Type`
+    SinglePart(Arguments),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MultiPartValue {
+    pub body: Vec<u8>,
+    pub headers: Headers,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Line {
+    pub method: Method,
+    pub protocol: Protocol,
+    pub query_arguments: Arguments,
+    pub query_string: String,
+    pub raw: String,
+    /// Path of request target as received, before normalization
+    pub raw_path: String,
+    /// Raw request target
+    pub request_uri: String,
+    /// Normalized path of request target, same as `uri.path`
+    pub request_uri_base: String,
+    /// Request target with a normalized path and without fragment
+    pub uri: Uri,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Method {
+    Connect,
+    Delete,
+    Get,
+    Head,
+    Options,
+    Patch,
+    Post,
+    Put,
+    Trace,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Protocol {
+    V0_9,
+    V1_0,
+    V1_1,
+    V2_0,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let method = match self {
+            Method::Connect => "CONNECT",
+            Method::Delete => "DELETE",
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Options => "OPTIONS",
+            Method::Patch => "PATCH",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Trace => "TRACE",
+        };
+        write!(formatter, "{}", method)
+    }
+}
+
+/// Methods are case-sensitive
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::request::Method;
+/// let method: Method = "PATCH".parse().unwrap();
+/// assert_eq!(method, Method::Patch);
+/// assert_eq!(method.to_string(), "PATCH");
+/// assert!("patch".parse::<Method>().is_err());
+/// ```
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(method: &str) -> Result<Method, String> {
+        match method {
+            "CONNECT" => Ok(Method::Connect),
+            "DELETE" => Ok(Method::Delete),
+            "GET" => Ok(Method::Get),
+            "HEAD" => Ok(Method::Head),
+            "OPTIONS" => Ok(Method::Options),
+            "PATCH" => Ok(Method::Patch),
+            "POST" => Ok(Method::Post),
+            "PUT" => Ok(Method::Put),
+            "TRACE" => Ok(Method::Trace),
+            _ => Err(format!("Unsupported method {:?}", method)),
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let protocol = match self {
+            Protocol::V0_9 => "HTTP/0.9",
+            Protocol::V1_0 => "HTTP/1.0",
+            Protocol::V1_1 => "HTTP/1.1",
+            Protocol::V2_0 => "HTTP/2.0",
+        };
+        write!(formatter, "{}", protocol)
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(protocol: &str) -> Result<Protocol, String> {
+        match protocol {
+            "HTTP/0.9" => Ok(Protocol::V0_9),
+            "HTTP/1.0" => Ok(Protocol::V1_0),
+            "HTTP/1.1" => Ok(Protocol::V1_1),
+            "HTTP/2.0" => Ok(Protocol::V2_0),
+            _ => Err(format!("Unsupported protocol {:?}", protocol)),
+        }
+    }
+}
+
+/// # Reasons a request could not be decoded
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// Framing headers disagree on where the body ends, proxies could read another request
+    AmbiguousFraming(String),
+    /// Header field continued on the next line, see RFC 7230 section 3.2.4
+    FoldedHeader(String),
+    /// Client started a HTTP/2 connection with prior knowledge
+    Http2Preface,
+    /// Data ended before the request was complete
+    Incomplete,
+    InvalidChunk,
+    /// Head is not UTF-8 or request-line is not US-ASCII
+    InvalidEncoding,
+    InvalidHeader(String),
+    InvalidContentLength(String),
+    InvalidRequestLine(String),
+    InvalidRequestTarget(String),
+    TooLarge(Limit),
+    UnsupportedMethod(String),
+    UnsupportedProtocol(String),
+}
+
+impl ParseError {
+    /// Status of the response to a request that could not be decoded
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::request::{Message, ParseError};
+    /// let error = Message::from_tcp_stream(b"GET / HTTP/3.0\r\n\r\n").unwrap_err();
+    /// assert_eq!(error, ParseError::UnsupportedProtocol("HTTP/3.0".to_string()));
+    /// assert_eq!(error.get_status().to_string(), "505 HTTP Version Not Supported");
+    /// ```
+    pub fn get_status(&self) -> HttpStatus {
+        match self {
+            ParseError::TooLarge(Limit::Body) => HttpStatus::PayloadTooLarge,
+            ParseError::TooLarge(Limit::Header) => HttpStatus::RequestHeaderFieldsTooLarge,
+            ParseError::TooLarge(Limit::RequestLine) => HttpStatus::UriTooLong,
+            ParseError::UnsupportedMethod(_) => HttpStatus::NotImplemented,
+            ParseError::Http2Preface | ParseError::UnsupportedProtocol(_) => {
+                HttpStatus::HttpVersionNotSupported
+            }
+            _ => HttpStatus::BadRequest,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::AmbiguousFraming(reason) => {
+                write!(formatter, "Ambiguous message framing, {}", reason)
+            }
+            ParseError::FoldedHeader(line) => {
+                write!(formatter, "Obsolete line folding in header field {:?}", line)
+            }
+            ParseError::Http2Preface => {
+                write!(formatter, "HTTP/2 connection preface, only HTTP/1 is supported")
+            }
+            ParseError::Incomplete => write!(formatter, "Request ended before it was complete"),
+            ParseError::InvalidChunk => write!(formatter, "Invalid chunked body"),
+            ParseError::InvalidEncoding => write!(formatter, "Invalid encoding of request head"),
+            ParseError::InvalidHeader(line) => write!(formatter, "Invalid header field {:?}", line),
+            ParseError::InvalidContentLength(value) => {
+                write!(formatter, "Invalid Content-Length {:?}", value)
+            }
+            ParseError::InvalidRequestLine(line) => {
+                write!(formatter, "Invalid request-line {:?}", line)
+            }
+            ParseError::InvalidRequestTarget(target) => {
+                write!(formatter, "Invalid request target {:?}", target)
+            }
+            ParseError::TooLarge(limit) => write!(formatter, "Request exceeds {:?} limit", limit),
+            ParseError::UnsupportedMethod(method) => {
+                write!(formatter, "Unsupported method {:?}", method)
+            }
+            ParseError::UnsupportedProtocol(protocol) => {
+                write!(formatter, "Unsupported protocol {:?}", protocol)
+            }
+        }
+    }
+}
+
+impl Line {
+    pub fn get_method(&self) -> Method {
+        self.method
+    }
+
+    pub fn get_protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn get_query_arguments(&self) -> &Arguments {
+        &self.query_arguments
+    }
+
+    pub fn get_raw_path(&self) -> &str {
+        &self.raw_path
+    }
+
+    pub fn get_uri(&self) -> &Uri {
+        &self.uri
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub body: BodyContentType,
+    /// Body left on the connection since it was too large to buffer, see `body_reader`
+    pub body_stream: body::Stream,
+    pub headers: Headers,
+    /// Incoming `X-Request-Id` or a generated identifier, used to trace a request in logs
+    pub request_id: String,
+    pub request_line: Line,
+    /// Fields sent after a chunked body, kept apart from headers since they arrive late
+    pub trailers: Headers,
+}
+
+impl Message {
+    pub fn get_body(&self) -> &BodyContentType {
+        &self.body
+    }
+
+    /// Reader of a body that was left on the connection, only the first call gets it.
+    /// Bodies are streamed when they are larger than `Config::stream_body_bytes`.
+    pub fn body_reader(&self) -> Option<body::Reader> {
+        self.body_stream.take()
+    }
+
+    pub fn get_headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Does the request ask to switch the connection to the WebSocket protocol?
+    pub fn is_websocket_upgrade(&self) -> bool {
+        websocket::is_upgrade(&self.headers)
+    }
+
+    /// Validate a WebSocket handshake and get the `Sec-WebSocket-Accept` response value
+    pub fn get_websocket_accept(&self) -> Result<String, String> {
+        websocket::validate(self)
+    }
+
+    /// Media type of the body with parameters like `charset` and `boundary`
+    pub fn get_content_type(&self) -> Option<MediaType> {
+        self.headers
+            .get("Content-Type")
+            .and_then(|content_type| MediaType::from_str(content_type))
+    }
+
+    /// Address of the client behind trusted proxies, see `forwarded::get_client_addr`
+    pub fn get_client_addr(&self, socket: &SocketAddr, trusted_proxies: &[Cidr]) -> IpAddr {
+        forwarded::get_client_addr(socket, &self.headers, trusted_proxies)
+    }
+
+    pub fn get_method(&self) -> Method {
+        self.request_line.method
+    }
+
+    pub fn get_protocol(&self) -> Protocol {
+        self.request_line.protocol
+    }
+
+    /// Get decoded query argument of request target
+    pub fn get_query_argument(&self, name: &str) -> Option<&String> {
+        self.request_line.query_arguments.get(name)
+    }
+
+    pub fn get_request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// A well-formed incoming `X-Request-Id` is honored, otherwise a random identifier of
+    /// 32 hexadecimal digits is generated
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::header::Headers;
+    /// use milstian_internet_framework::application_layer::http::request::Message;
+    /// let mut headers = Headers::new();
+    /// assert_eq!(Message::get_new_request_id(&headers).len(), 32);
+    /// headers.insert("X-Request-Id".to_string(), "abc-123".to_string());
+    /// assert_eq!(Message::get_new_request_id(&headers), "abc-123");
+    /// ```
+    pub fn get_new_request_id(headers: &Headers) -> String {
+        if let Some(request_id) = headers.get_first("X-Request-Id") {
+            let is_valid = !request_id.is_empty()
+                && request_id.len() <= MAX_REQUEST_ID
+                && request_id.chars().all(|character| {
+                    character.is_ascii_alphanumeric() || "-_.:/+=".contains(character)
+                });
+            if is_valid {
+                return request_id.clone();
+            }
+        }
+        format!("{:016x}{:016x}", random::next_u64(), random::next_u64())
+    }
+
+    pub fn get_trailers(&self) -> &Headers {
+        &self.trailers
+    }
+
+    pub fn get_uri(&self) -> &Uri {
+        &self.request_line.uri
+    }
+
+    pub fn get_protocol_text(protocol: &Protocol) -> String {
+        protocol.to_string()
+    }
+
+    /// Decode a request message from bytes
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::request;
+    /// let request = request::Message::from_tcp_stream(
+    ///     b"GET /index.htm?page=2 HTTP/1.1\r\nHost: localhost\r\n\r\n"
+    /// ).unwrap();
+    /// assert_eq!(request.request_line.method, request::Method::Get);
+    /// assert_eq!(request.request_line.request_uri_base, "/index.htm".to_string());
+    /// assert_eq!(request.headers.get("Host"), Some(&"localhost".to_string()));
+    /// ```
+    pub fn from_tcp_stream(request: &[u8]) -> Result<Message, ParseError> {
+        let mut parser = Parser::new();
+        let state = match parser.feed(request) {
+            ParseState::Incomplete => parser.finish(),
+            state => state,
+        };
+        match state {
+            ParseState::Complete(mut message) => {
+                // Data following a request without framing headers is treated as it's body
+                if !parser.is_framed() && !parser.get_buffer().is_empty() {
+                    message.body =
+                        Message::get_message_body(parser.get_buffer(), &message.headers);
+                }
+                Ok(message)
+            }
+            ParseState::Incomplete => Err(ParseError::Incomplete),
+            ParseState::Invalid(error) => Err(error),
+            ParseState::TooLarge(limit) => Err(ParseError::TooLarge(limit)),
+        }
+    }
+
+    /// A line with only a request target is treated as a HTTP/0.9 simple request
+    pub fn get_request_line(line: &str) -> Result<Line, ParseError> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (method, request_uri, protocol) = match parts.len() {
+            1 => (Method::Get, parts[0], Protocol::V0_9),
+            2 => (Message::get_line_method(line, parts[0])?, parts[1], Protocol::V0_9),
+            3 => (
+                Message::get_line_method(line, parts[0])?,
+                parts[1],
+                Message::get_line_protocol(line, parts[2])?,
+            ),
+            _ => return Err(ParseError::InvalidRequestLine(line.to_string())),
+        };
+
+        // CONNECT requests have a authority-form request target, see RFC 7230 section 5.3
+        let uri = if method == Method::Connect {
+            Uri::from_authority(request_uri)
+        } else {
+            Uri::from_str(request_uri)
+        };
+        let mut uri =
+            uri.ok_or_else(|| ParseError::InvalidRequestTarget(request_uri.to_string()))?;
+
+        // Fragments are never sent by clients and dot segments must not escape any root
+        let raw_path = uri.path.clone();
+        uri.path = uri.get_normalized_path();
+        uri.fragment = None;
+        let request_uri_base = uri.path.clone();
+        let query_string = uri.query.clone().unwrap_or_default();
+        let query_arguments = Message::get_query_arguments(&query_string);
+
+        Ok(Line {
+            method,
+            protocol,
+            query_arguments,
+            query_string,
+            raw: line.to_string(),
+            raw_path,
+            request_uri: request_uri.to_string(),
+            request_uri_base,
+            uri,
+        })
+    }
+
+    /// Methods that are tokens are well-formed but not implemented
+    fn get_line_method(line: &str, method: &str) -> Result<Method, ParseError> {
+        match method.parse() {
+            Ok(method) => Ok(method),
+            Err(_) if Headers::is_token(method) => {
+                Err(ParseError::UnsupportedMethod(method.to_string()))
+            }
+            Err(_) => Err(ParseError::InvalidRequestLine(line.to_string())),
+        }
+    }
+
+    /// Versions in the HTTP-version format are well-formed but not supported
+    fn get_line_protocol(line: &str, protocol: &str) -> Result<Protocol, ParseError> {
+        match protocol.parse() {
+            Ok(protocol) => Ok(protocol),
+            Err(_) if protocol.starts_with("HTTP/") => {
+                Err(ParseError::UnsupportedProtocol(protocol.to_string()))
+            }
+            Err(_) => Err(ParseError::InvalidRequestLine(line.to_string())),
+        }
+    }
+
+    pub fn get_request_method(method: &str) -> Option<Method> {
+        method.parse().ok()
+    }
+
+    pub fn get_request_protocol(protocol: &str) -> Option<Protocol> {
+        protocol.parse().ok()
+    }
+
+    /// Decode arguments in the application/x-www-form-urlencoded format
+    pub fn get_query_arguments(query: &str) -> Arguments {
+        Arguments::from_str(query)
+    }
+
+    pub fn get_message_body(body: &[u8], headers: &Headers) -> BodyContentType {
+        let mut length = body.len();
+        while length > 0 && body[length - 1] == 0 {
+            length -= 1;
+        }
+        let body = &body[..length];
+
+        let media_type = match headers
+            .get("Content-Type")
+            .and_then(|content_type| MediaType::from_str(content_type))
+        {
+            Some(media_type) => media_type,
+            None => return Message::get_urlencoded_body(body),
+        };
+        match (media_type.top_level.as_str(), media_type.subtype.as_str()) {
+            ("application", "x-www-form-urlencoded") => Message::get_urlencoded_body(body),
+            ("multipart", "form-data") => match media_type.get_parameter("boundary") {
+                Some(boundary) => {
+                    BodyContentType::MultiPart(Message::get_multipart_body(body, boundary))
+                }
+                None => BodyContentType::Other(media_type.clone(), body.to_vec()),
+            },
+            ("application", subtype) if subtype == "json" || subtype.ends_with("+json") => {
+                match str::from_utf8(body) {
+                    Ok(text) => BodyContentType::Json(text.to_string()),
+                    Err(_) => BodyContentType::Other(media_type, body.to_vec()),
+                }
+            }
+            _ => BodyContentType::Other(media_type, body.to_vec()),
+        }
+    }
+
+    fn get_urlencoded_body(body: &[u8]) -> BodyContentType {
+        match str::from_utf8(body) {
+            Ok(body) => BodyContentType::SinglePart(Message::get_query_arguments(body)),
+            Err(_) => BodyContentType::SinglePart(Arguments::new()),
+        }
+    }
+
+    fn get_multipart_body(body: &[u8], boundary: &str) -> HashMap<String, MultiPartValue> {
+        let mut values = HashMap::new();
+        let delimiter = format!("--{}", boundary).into_bytes();
+
+        let mut start = match find(body, &delimiter, 0) {
+            Some(position) => position + delimiter.len(),
+            None => return values,
+        };
+        while let Some(end) = find(body, &delimiter, start) {
+            let mut part = &body[start..end];
+            if part.starts_with(b"\r\n") {
+                part = &part[2..];
+            }
+            if part.ends_with(b"\r\n") {
+                part = &part[..part.len() - 2];
+            }
+
+            if let Some(position) = find(part, b"\r\n\r\n", 0) {
+                let mut headers = Headers::new();
+                if let Ok(head) = str::from_utf8(&part[..position]) {
+                    for line in head.split("\r\n") {
+                        if let Some((name, value)) = Headers::get_field(line) {
+                            headers.insert(name, value);
+                        }
+                    }
+                }
+                let name = headers
+                    .get("Content-Disposition")
+                    .and_then(|disposition| get_disposition_name(disposition));
+                if let Some(name) = name {
+                    values.insert(
+                        name,
+                        MultiPartValue {
+                            body: part[position + 4..].to_vec(),
+                            headers,
+                        },
+                    );
+                }
+            }
+
+            start = end + delimiter.len();
+        }
+        values
+    }
+}
+
+fn get_disposition_name(disposition: &str) -> Option<String> {
+    let (_, parameters) = parse::get_parameters(disposition);
+    parse::get_parameter(&parameters, "name").cloned()
+}
+
+/// Decode percent-encoded characters and plus signs as spaces
+pub fn decode_percent(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < bytes.len() => {
+                match (get_hex_value(bytes[index + 1]), get_hex_value(bytes[index + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push(high * 16 + low);
+                        index += 2;
+                    }
+                    _ => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn get_hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Find position of needle in haystack starting from offset
+pub fn find(haystack: &[u8], needle: &[u8], offset: usize) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    (offset..haystack.len() - needle.len() + 1)
+        .find(|&position| &haystack[position..position + needle.len()] == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accessors() {
+        let request = Message::from_tcp_stream(
+            b"PUT /a?b=c&b=d HTTP/1.0\r\nContent-Length: 3\r\n\r\nd=e",
+        ).unwrap();
+        assert_eq!(request.get_method(), Method::Put);
+        assert_eq!(request.get_protocol(), Protocol::V1_0);
+        assert_eq!(request.get_uri().path, "/a");
+        assert_eq!(request.get_query_argument("b"), Some(&"c".to_string()));
+        assert_eq!(request.get_headers().get("Content-Length"), Some(&"3".to_string()));
+        assert!(request.get_trailers().is_empty());
+        let mut expected = Arguments::new();
+        expected.insert("d".to_string(), "e".to_string());
+        assert_eq!(request.get_body(), &BodyContentType::SinglePart(expected));
+        assert_eq!(request.request_line.get_method(), Method::Put);
+        assert_eq!(
+            request.request_line.get_query_arguments().get_all("b"),
+            vec![&"c".to_string(), &"d".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_request_id() {
+        let request = Message::from_tcp_stream(
+            b"GET / HTTP/1.1\r\nHost: a\r\nX-Request-Id: 4a2f-c1\r\n\r\n",
+        ).unwrap();
+        assert_eq!(request.get_request_id(), "4a2f-c1");
+
+        let first = Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        let second = Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        assert_ne!(first.request_id, second.request_id);
+        assert!(first.request_id.chars().all(|character| character.is_ascii_hexdigit()));
+
+        let mut headers = Headers::new();
+        headers.insert("X-Request-Id".to_string(), "a b".to_string());
+        assert_eq!(Message::get_new_request_id(&headers).len(), 32);
+        headers = Headers::new();
+        headers.insert("X-Request-Id".to_string(), "a".repeat(MAX_REQUEST_ID + 1));
+        assert_eq!(Message::get_new_request_id(&headers).len(), 32);
+    }
+
+    #[test]
+    fn test_method_protocol() {
+        for method in [
+            "CONNECT", "DELETE", "GET", "HEAD", "OPTIONS", "PATCH", "POST", "PUT", "TRACE",
+        ].iter()
+        {
+            assert_eq!(method.parse::<Method>().unwrap().to_string(), *method);
+        }
+        for protocol in ["HTTP/0.9", "HTTP/1.0", "HTTP/1.1", "HTTP/2.0"].iter() {
+            assert_eq!(protocol.parse::<Protocol>().unwrap().to_string(), *protocol);
+        }
+        assert!("Get".parse::<Method>().is_err());
+        assert!("HTTP/3.0".parse::<Protocol>().is_err());
+    }
+
+    #[test]
+    fn test_from_tcp_stream() {
+        let request = Message::from_tcp_stream(
+            b"GET /index.htm?a=1&b=hello+world%21 HTTP/1.1\r\nHost: localhost\r\n\
+              Accept: text/html\r\nAccept: text/plain\r\n\r\n",
+        ).unwrap();
+        assert_eq!(request.request_line.method, Method::Get);
+        assert_eq!(request.request_line.protocol, Protocol::V1_1);
+        assert_eq!(request.request_line.request_uri, "/index.htm?a=1&b=hello+world%21");
+        assert_eq!(request.request_line.request_uri_base, "/index.htm");
+        assert_eq!(request.request_line.query_string, "a=1&b=hello+world%21");
+        assert_eq!(
+            request.request_line.query_arguments.get("b"),
+            Some(&"hello world!".to_string())
+        );
+        assert_eq!(request.headers.get("host"), Some(&"localhost".to_string()));
+        assert_eq!(
+            request.headers.get_all("Accept"),
+            vec![&"text/html".to_string(), &"text/plain".to_string()]
+        );
+
+        // Absolute-form and authority-form request targets
+        let request =
+            Message::from_tcp_stream(b"GET http://example.com/a?b=1 HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(request.request_line.uri.get_host(), Some("example.com"));
+        assert_eq!(request.request_line.request_uri_base, "/a");
+        assert_eq!(request.request_line.query_string, "b=1");
+        let request =
+            Message::from_tcp_stream(b"GET /a//b/../c/.?d=/../#e HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(request.request_line.get_raw_path(), "/a//b/../c/.");
+        assert_eq!(request.request_line.request_uri_base, "/a/c/");
+        assert_eq!(request.request_line.uri.to_string(), "/a/c/?d=/../");
+        assert_eq!(request.request_line.request_uri, "/a//b/../c/.?d=/../#e");
+        let request =
+            Message::from_tcp_stream(b"CONNECT example.com:443 HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(request.request_line.uri.get_port(), Some(443));
+        assert_eq!(
+            Message::from_tcp_stream(b"CONNECT /index.htm HTTP/1.1\r\n\r\n"),
+            Err(ParseError::InvalidRequestTarget("/index.htm".to_string()))
+        );
+
+        // Request line without line-break
+        let request = Message::from_tcp_stream(b"GET /index2.htm HTTP/1.0").unwrap();
+        assert_eq!(request.request_line.protocol, Protocol::V1_0);
+        assert!(request.headers.is_empty());
+
+        // HTTP/0.9 simple request
+        let request = Message::from_tcp_stream(b"/index.htm\r\n").unwrap();
+        assert_eq!(request.request_line.method, Method::Get);
+        assert_eq!(request.request_line.protocol, Protocol::V0_9);
+
+        assert_eq!(
+            Message::from_tcp_stream(b"FETCH / HTTP/1.1\r\n\r\n"),
+            Err(ParseError::UnsupportedMethod("FETCH".to_string()))
+        );
+        assert_eq!(
+            Message::from_tcp_stream(b"G(T / HTTP/1.1\r\n\r\n"),
+            Err(ParseError::InvalidRequestLine("G(T / HTTP/1.1".to_string()))
+        );
+        assert_eq!(
+            Message::from_tcp_stream(b"GET / HTTP/3.0\r\n\r\n"),
+            Err(ParseError::UnsupportedProtocol("HTTP/3.0".to_string()))
+        );
+        assert_eq!(
+            Message::from_tcp_stream(b"GET / FTP\r\n\r\n"),
+            Err(ParseError::InvalidRequestLine("GET / FTP".to_string()))
+        );
+        assert_eq!(Message::from_tcp_stream(b""), Err(ParseError::Incomplete));
+        assert_eq!(
+            Message::from_tcp_stream("GET /ö HTTP/1.1".as_bytes()),
+            Err(ParseError::InvalidEncoding)
+        );
+        assert_eq!(
+            Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost : localhost\r\n\r\n"),
+            Err(ParseError::InvalidHeader("Host : localhost".to_string()))
+        );
+        assert_eq!(
+            Message::from_tcp_stream(b"GET / HTTP/1.1\r\nX-Name: \xff\r\n\r\n"),
+            Err(ParseError::InvalidEncoding)
+        );
+        assert_eq!(
+            Message::from_tcp_stream(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1"),
+            Err(ParseError::Incomplete)
+        );
+    }
+
+    #[test]
+    fn test_from_tcp_stream_utf8() {
+        let request = Message::from_tcp_stream(
+            "POST / HTTP/1.1\r\nX-Name: Åsa\r\nContent-Length: 13\r\n\r\nname=Jönsson"
+                .as_bytes(),
+        ).unwrap();
+        assert_eq!(request.headers.get("X-Name"), Some(&"Åsa".to_string()));
+        let mut expected = Arguments::new();
+        expected.insert("name".to_string(), "Jönsson".to_string());
+        assert_eq!(request.body, BodyContentType::SinglePart(expected));
+
+        let mut request: Vec<u8> = b"POST / HTTP/1.1\r\n\
+            Content-Type: multipart/form-data; boundary=X\r\n\
+            Content-Length: 62\r\n\r\n\
+            --X\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\n"
+            .to_vec();
+        request.extend_from_slice(&[0xff, 0x00, 0xfe]);
+        request.extend_from_slice(b"\r\n--X--");
+        let request = Message::from_tcp_stream(&request).unwrap();
+        if let BodyContentType::MultiPart(values) = request.body {
+            assert_eq!(values.get("file").unwrap().body, vec![0xff, 0x00, 0xfe]);
+        } else {
+            panic!("Expected multipart body");
+        }
+    }
+
+    #[test]
+    fn test_body() {
+        let mut request: Vec<u8> =
+            b"POST / HTTP/1.0\r\nAgent: Random browser\r\n\r\ntest=abc&empty".to_vec();
+        request.push(0);
+        request.push(0);
+        let request = Message::from_tcp_stream(&request).unwrap();
+        let mut expected = Arguments::new();
+        expected.insert("test".to_string(), "abc".to_string());
+        expected.insert("empty".to_string(), "".to_string());
+        assert_eq!(request.body, BodyContentType::SinglePart(expected));
+
+        let request = Message::from_tcp_stream(
+            b"POST / HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=\"XyZ\"\r\n\r\n\
+              --XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+              Content-Type: text/plain\r\n\r\nline 1\r\nline 2\r\n\
+              --XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nHello\r\n\
+              --XyZ--\r\n",
+        ).unwrap();
+        match request.body {
+            BodyContentType::MultiPart(body) => {
+                assert_eq!(body.len(), 2);
+                let file = body.get("file").unwrap();
+                assert_eq!(file.body, b"line 1\r\nline 2".to_vec());
+                assert_eq!(file.headers.get("Content-Type"), Some(&"text/plain".to_string()));
+                assert_eq!(body.get("title").unwrap().body, b"Hello".to_vec());
+            }
+            _ => panic!("Expected multi-part body"),
+        }
+    }
+
+    #[test]
+    fn test_body_media_types() {
+        let request = Message::from_tcp_stream(
+            b"POST / HTTP/1.1\r\nContent-Type: application/vnd.api+json; charset=utf-8\r\n\
+              Content-Length: 8\r\n\r\n{\"a\":1}",
+        ).unwrap();
+        assert_eq!(request.body, BodyContentType::Json("{\"a\":1}".to_string()));
+        assert_eq!(
+            request.get_content_type().unwrap().get_parameter("charset"),
+            Some(&"utf-8".to_string())
+        );
+
+        let request = Message::from_tcp_stream(
+            b"POST / HTTP/1.1\r\nContent-Type: Application/X-WWW-Form-Urlencoded\r\n\
+              Content-Length: 3\r\n\r\na=1",
+        ).unwrap();
+        let mut expected = Arguments::new();
+        expected.insert("a".to_string(), "1".to_string());
+        assert_eq!(request.body, BodyContentType::SinglePart(expected));
+
+        let request = Message::from_tcp_stream(
+            b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 3\r\n\r\na=1",
+        ).unwrap();
+        assert_eq!(
+            request.body,
+            BodyContentType::Other(MediaType::new("text", "plain"), b"a=1".to_vec())
+        );
+
+        // A multipart body without boundary can not be split
+        let request = Message::from_tcp_stream(
+            b"POST / HTTP/1.1\r\nContent-Type: multipart/form-data\r\nContent-Length: 1\r\n\r\nx",
+        ).unwrap();
+        match request.body {
+            BodyContentType::Other(media_type, body) => {
+                assert_eq!(media_type.get_essence(), "multipart/form-data");
+                assert_eq!(body, b"x".to_vec());
+            }
+            _ => panic!("Expected other body"),
+        }
+    }
+
+    #[test]
+    fn test_decode_percent() {
+        assert_eq!(decode_percent("a%20b+c"), "a b c");
+        assert_eq!(decode_percent("100%"), "100%");
+        assert_eq!(decode_percent("%zz%4"), "%zz%4");
+        assert_eq!(decode_percent("%C3%B6"), "ö");
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find(b"abcabc", b"bc", 0), Some(1));
+        assert_eq!(find(b"abcabc", b"bc", 2), Some(4));
+        assert_eq!(find(b"abc", b"abcd", 0), None);
+        assert_eq!(find(b"abc", b"", 0), None);
+    }
+}
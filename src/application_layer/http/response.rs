@@ -0,0 +1,302 @@
+//! # HTTP response
+//! Messages written to the client and a builder so responders don't have to assemble
+//! status, header fields and body by hand.
+
+use std::collections::HashMap;
+
+use application_layer::http::header::Headers;
+use application_layer::http::request::Protocol;
+use application_layer::http::status::HttpStatus;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde_json;
+
+pub use milstian_http::response::*;
+
+/// Response headers written first and in this order, the fields of the status and framing
+/// come before caching and security fields. Others follow sorted by name.
+pub const DEFAULT_HEADER_ORDER: [&str; 22] = [
+    "Date",
+    "Server",
+    "Location",
+    "Content-Type",
+    "Content-Length",
+    "Transfer-Encoding",
+    "Content-Encoding",
+    "Content-Range",
+    "Content-Disposition",
+    "Content-Language",
+    "Connection",
+    "Keep-Alive",
+    "Allow",
+    "Accept-Ranges",
+    "Retry-After",
+    "WWW-Authenticate",
+    "Cache-Control",
+    "Expires",
+    "ETag",
+    "Last-Modified",
+    "Vary",
+    "Content-Security-Policy",
+];
+
+/// Header fields of a response in the order they are written, first the names of `order`,
+/// then the names of the default order and then the rest by case-insensitive name so the same
+/// response is always written the same way
+pub fn get_ordered_headers<'a>(
+    headers: &'a HashMap<String, String>,
+    order: &[String],
+) -> Vec<(&'a String, &'a String)> {
+    let get_rank = |name: &str| {
+        order
+            .iter()
+            .map(|name| name.as_str())
+            .chain(DEFAULT_HEADER_ORDER.iter().cloned())
+            .position(|ordered| ordered.eq_ignore_ascii_case(name))
+            .unwrap_or(usize::MAX)
+    };
+    let mut ordered: Vec<(&String, &String)> = headers.iter().collect();
+    ordered.sort_by_key(|(name, _)| (get_rank(name), name.to_ascii_lowercase(), *name));
+    ordered
+}
+
+/// Serialize a response with it's header fields in order, see `get_ordered_headers`. Fields
+/// with names that are not tokens are left out and control characters are removed from
+/// values, responders filling in header maps by hand can't split the response.
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::response::{self, Response};
+/// let message = Response::ok()
+///     .header("X-Request-Id", "1")
+///     .header("Content-Type", "text/plain")
+///     .body("Hi")
+///     .build();
+/// let bytes = response::to_ordered_bytes(&message, &[]);
+/// assert_eq!(
+///     String::from_utf8(bytes).unwrap(),
+///     "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\
+///      X-Request-Id: 1\r\n\r\nHi"
+/// );
+/// ```
+pub fn to_ordered_bytes(message: &Message, order: &[String]) -> Vec<u8> {
+    let mut head = format!("{} {}\r\n", message.protocol, message.status);
+    for (name, value) in get_ordered_headers(&message.headers, order) {
+        if Headers::is_token(name) {
+            head.push_str(&format!("{}: {}\r\n", name, Headers::get_sanitized_value(value)));
+        }
+    }
+    head.push_str("\r\n");
+    let mut bytes = head.into_bytes();
+    bytes.extend_from_slice(&message.body);
+    bytes
+}
+
+/// # Builds a response message
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::response::Response;
+/// let bytes = Response::ok()
+///     .header("Content-Type", "text/plain")
+///     .body("Hello")
+///     .to_bytes();
+/// let text = String::from_utf8(bytes).unwrap();
+/// assert!(text.starts_with("HTTP/1.1 200 OK\r\n"));
+/// assert!(text.contains("Content-Length: 5\r\n"));
+/// assert!(text.ends_with("\r\n\r\nHello"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    body: Vec<u8>,
+    headers: HashMap<String, String>,
+    protocol: Protocol,
+    status: HttpStatus,
+}
+
+impl Response {
+    /// Response without header fields or body
+    pub fn new(status: HttpStatus) -> Response {
+        Response {
+            body: Vec::new(),
+            headers: HashMap::new(),
+            protocol: Protocol::V1_1,
+            status,
+        }
+    }
+
+    pub fn ok() -> Response {
+        Response::new(HttpStatus::Ok)
+    }
+
+    pub fn not_found() -> Response {
+        Response::new(HttpStatus::NotFound)
+    }
+
+    /// Response with `value` serialized as JSON, or a 500 Internal Server Error with a JSON
+    /// reason when it can't be serialized, like a map with keys that are not strings
+    #[cfg(feature = "serde")]
+    pub fn json<T: ?Sized + Serialize>(value: &T) -> Response {
+        let (status, body) = match serde_json::to_vec(value) {
+            Ok(body) => (HttpStatus::Ok, body),
+            Err(_) => (
+                HttpStatus::InternalServerError,
+                b"{\"status\":500,\"reason\":\"Failed to serialize response\"}".to_vec(),
+            ),
+        };
+        Response::new(status)
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(body)
+    }
+
+    /// Set a header field, a field with the same name in any case is replaced. Fields with a
+    /// name that is not a token are ignored and control characters are removed from the value,
+    /// so user input reflected in a header can't split the response.
+    pub fn header(mut self, name: &str, value: &str) -> Response {
+        if !Headers::is_token(name) {
+            return self;
+        }
+        self.headers.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+        self.headers
+            .insert(name.to_string(), Headers::get_sanitized_value(value));
+        self
+    }
+
+    /// Set the body and it's Content-Length
+    pub fn body<T: Into<Vec<u8>>>(mut self, body: T) -> Response {
+        self.body = body.into();
+        let length = self.body.len().to_string();
+        self.header("Content-Length", &length)
+    }
+
+    /// Answer with the protocol of the request, defaults to HTTP/1.1
+    pub fn protocol(mut self, protocol: &Protocol) -> Response {
+        self.protocol = *protocol;
+        self
+    }
+
+    /// Value of a header field by case-insensitive name
+    pub fn get_header(&self, name: &str) -> Option<&String> {
+        self.headers
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    pub fn build(self) -> Message {
+        Message::new(
+            self.protocol.to_string(),
+            self.status.to_string(),
+            self.headers,
+            self.body,
+        )
+    }
+
+    /// Serialize to the bytes written to the connection
+    pub fn to_bytes(self) -> Vec<u8> {
+        self.build().to_bytes()
+    }
+}
+
+impl From<Response> for Message {
+    fn from(response: Response) -> Message {
+        response.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let response = Response::not_found()
+            .protocol(&Protocol::V1_0)
+            .header("content-type", "text/plain")
+            .header("Content-Type", "text/html")
+            .body("Missing");
+        assert_eq!(
+            response.get_header("CONTENT-TYPE"),
+            Some(&"text/html".to_string())
+        );
+        assert_eq!(response.get_header("Content-Length"), Some(&"7".to_string()));
+
+        let message = response.build();
+        assert_eq!(message.protocol, "HTTP/1.0");
+        assert_eq!(message.status, "404 Not Found");
+        assert_eq!(message.headers.len(), 2);
+        assert_eq!(message.body, b"Missing".to_vec());
+
+        let message: Message = Response::new(HttpStatus::NoContent).into();
+        assert!(message.headers.is_empty());
+        assert!(message.body.is_empty());
+    }
+
+    #[test]
+    fn test_header_injection() {
+        let response = Response::new(HttpStatus::Found)
+            .header("Location", "/next\r\nSet-Cookie: admin=1\r\n\r\n<script>")
+            .header("X-Bad\r\nSet-Cookie", "admin=1")
+            .header("X Space", "1");
+        assert_eq!(
+            response.get_header("Location"),
+            Some(&"/nextSet-Cookie: admin=1<script>".to_string())
+        );
+        assert_eq!(response.headers.len(), 1);
+
+        let mut headers = HashMap::new();
+        headers.insert("Location".to_string(), "/a\r\nSet-Cookie: admin=1".to_string());
+        headers.insert("Bad\r\nName".to_string(), "1".to_string());
+        let message = Message::new(
+            "HTTP/1.1".to_string(),
+            "302 Found".to_string(),
+            headers,
+            Vec::new(),
+        );
+        assert_eq!(
+            String::from_utf8(to_ordered_bytes(&message, &[])).unwrap(),
+            "HTTP/1.1 302 Found\r\nLocation: /aSet-Cookie: admin=1\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_get_ordered_headers() {
+        let mut headers = HashMap::new();
+        for name in &["x-b", "X-a", "Vary", "Content-Length", "date", "Server", "Link"] {
+            headers.insert(name.to_string(), String::new());
+        }
+        let get_names = |order: &[String]| -> Vec<String> {
+            get_ordered_headers(&headers, order)
+                .into_iter()
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        assert_eq!(
+            get_names(&[]),
+            vec!["date", "Server", "Content-Length", "Vary", "Link", "X-a", "x-b"]
+        );
+        assert_eq!(
+            get_names(&["x-b".to_string(), "vary".to_string()]),
+            vec!["x-b", "Vary", "date", "Server", "Content-Length", "Link", "X-a"]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        use std::collections::BTreeMap;
+
+        let mut users = BTreeMap::new();
+        users.insert("alice", vec![1, 2]);
+        let message = Response::json(&users).build();
+        assert_eq!(message.status, "200 OK");
+        assert_eq!(
+            message.headers.get("Content-Type"),
+            Some(&"application/json; charset=utf-8".to_string())
+        );
+        assert_eq!(message.body, b"{\"alice\":[1,2]}".to_vec());
+
+        let mut invalid = BTreeMap::new();
+        invalid.insert((1, 2), "tuple keys are not strings");
+        let message = Response::json(&invalid).build();
+        assert_eq!(message.status, "500 Internal Server Error");
+        assert_eq!(message.headers.get("Content-Length"), Some(&"54".to_string()));
+    }
+}
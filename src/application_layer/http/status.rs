@@ -0,0 +1,250 @@
+//! # HTTP status codes
+//! Standard status codes with their reason phrases, see RFC 7231 section 6 and the IANA
+//! status code registry.
+
+use std::fmt;
+
+/// # A status code, displayed as the status part of a status-line
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::status::HttpStatus;
+/// assert_eq!(HttpStatus::NotFound.to_string(), "404 Not Found");
+/// assert_eq!(HttpStatus::from_code(304), Some(HttpStatus::NotModified));
+/// assert!(HttpStatus::Created.is_success());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HttpStatus {
+    Continue,
+    SwitchingProtocols,
+    EarlyHints,
+    Ok,
+    Created,
+    Accepted,
+    NoContent,
+    PartialContent,
+    MovedPermanently,
+    Found,
+    SeeOther,
+    NotModified,
+    TemporaryRedirect,
+    PermanentRedirect,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    NotAcceptable,
+    RequestTimeout,
+    Conflict,
+    Gone,
+    LengthRequired,
+    PreconditionFailed,
+    PayloadTooLarge,
+    UriTooLong,
+    UnsupportedMediaType,
+    RangeNotSatisfiable,
+    ExpectationFailed,
+    UpgradeRequired,
+    TooManyRequests,
+    RequestHeaderFieldsTooLarge,
+    InternalServerError,
+    NotImplemented,
+    BadGateway,
+    ServiceUnavailable,
+    GatewayTimeout,
+    HttpVersionNotSupported,
+}
+
+/// Every status in code order
+const ALL: [HttpStatus; 39] = [
+    HttpStatus::Continue,
+    HttpStatus::SwitchingProtocols,
+    HttpStatus::EarlyHints,
+    HttpStatus::Ok,
+    HttpStatus::Created,
+    HttpStatus::Accepted,
+    HttpStatus::NoContent,
+    HttpStatus::PartialContent,
+    HttpStatus::MovedPermanently,
+    HttpStatus::Found,
+    HttpStatus::SeeOther,
+    HttpStatus::NotModified,
+    HttpStatus::TemporaryRedirect,
+    HttpStatus::PermanentRedirect,
+    HttpStatus::BadRequest,
+    HttpStatus::Unauthorized,
+    HttpStatus::Forbidden,
+    HttpStatus::NotFound,
+    HttpStatus::MethodNotAllowed,
+    HttpStatus::NotAcceptable,
+    HttpStatus::RequestTimeout,
+    HttpStatus::Conflict,
+    HttpStatus::Gone,
+    HttpStatus::LengthRequired,
+    HttpStatus::PreconditionFailed,
+    HttpStatus::PayloadTooLarge,
+    HttpStatus::UriTooLong,
+    HttpStatus::UnsupportedMediaType,
+    HttpStatus::RangeNotSatisfiable,
+    HttpStatus::ExpectationFailed,
+    HttpStatus::UpgradeRequired,
+    HttpStatus::TooManyRequests,
+    HttpStatus::RequestHeaderFieldsTooLarge,
+    HttpStatus::InternalServerError,
+    HttpStatus::NotImplemented,
+    HttpStatus::BadGateway,
+    HttpStatus::ServiceUnavailable,
+    HttpStatus::GatewayTimeout,
+    HttpStatus::HttpVersionNotSupported,
+];
+
+impl HttpStatus {
+    pub fn from_code(code: u16) -> Option<HttpStatus> {
+        ALL.iter().cloned().find(|status| status.get_code() == code)
+    }
+
+    pub fn get_code(self) -> u16 {
+        match self {
+            HttpStatus::Continue => 100,
+            HttpStatus::SwitchingProtocols => 101,
+            HttpStatus::EarlyHints => 103,
+            HttpStatus::Ok => 200,
+            HttpStatus::Created => 201,
+            HttpStatus::Accepted => 202,
+            HttpStatus::NoContent => 204,
+            HttpStatus::PartialContent => 206,
+            HttpStatus::MovedPermanently => 301,
+            HttpStatus::Found => 302,
+            HttpStatus::SeeOther => 303,
+            HttpStatus::NotModified => 304,
+            HttpStatus::TemporaryRedirect => 307,
+            HttpStatus::PermanentRedirect => 308,
+            HttpStatus::BadRequest => 400,
+            HttpStatus::Unauthorized => 401,
+            HttpStatus::Forbidden => 403,
+            HttpStatus::NotFound => 404,
+            HttpStatus::MethodNotAllowed => 405,
+            HttpStatus::NotAcceptable => 406,
+            HttpStatus::RequestTimeout => 408,
+            HttpStatus::Conflict => 409,
+            HttpStatus::Gone => 410,
+            HttpStatus::LengthRequired => 411,
+            HttpStatus::PreconditionFailed => 412,
+            HttpStatus::PayloadTooLarge => 413,
+            HttpStatus::UriTooLong => 414,
+            HttpStatus::UnsupportedMediaType => 415,
+            HttpStatus::RangeNotSatisfiable => 416,
+            HttpStatus::ExpectationFailed => 417,
+            HttpStatus::UpgradeRequired => 426,
+            HttpStatus::TooManyRequests => 429,
+            HttpStatus::RequestHeaderFieldsTooLarge => 431,
+            HttpStatus::InternalServerError => 500,
+            HttpStatus::NotImplemented => 501,
+            HttpStatus::BadGateway => 502,
+            HttpStatus::ServiceUnavailable => 503,
+            HttpStatus::GatewayTimeout => 504,
+            HttpStatus::HttpVersionNotSupported => 505,
+        }
+    }
+
+    pub fn get_reason(self) -> &'static str {
+        match self {
+            HttpStatus::Continue => "Continue",
+            HttpStatus::SwitchingProtocols => "Switching Protocols",
+            HttpStatus::EarlyHints => "Early Hints",
+            HttpStatus::Ok => "OK",
+            HttpStatus::Created => "Created",
+            HttpStatus::Accepted => "Accepted",
+            HttpStatus::NoContent => "No Content",
+            HttpStatus::PartialContent => "Partial Content",
+            HttpStatus::MovedPermanently => "Moved Permanently",
+            HttpStatus::Found => "Found",
+            HttpStatus::SeeOther => "See Other",
+            HttpStatus::NotModified => "Not Modified",
+            HttpStatus::TemporaryRedirect => "Temporary Redirect",
+            HttpStatus::PermanentRedirect => "Permanent Redirect",
+            HttpStatus::BadRequest => "Bad Request",
+            HttpStatus::Unauthorized => "Unauthorized",
+            HttpStatus::Forbidden => "Forbidden",
+            HttpStatus::NotFound => "Not Found",
+            HttpStatus::MethodNotAllowed => "Method Not Allowed",
+            HttpStatus::NotAcceptable => "Not Acceptable",
+            HttpStatus::RequestTimeout => "Request Timeout",
+            HttpStatus::Conflict => "Conflict",
+            HttpStatus::Gone => "Gone",
+            HttpStatus::LengthRequired => "Length Required",
+            HttpStatus::PreconditionFailed => "Precondition Failed",
+            HttpStatus::PayloadTooLarge => "Payload Too Large",
+            HttpStatus::UriTooLong => "URI Too Long",
+            HttpStatus::UnsupportedMediaType => "Unsupported Media Type",
+            HttpStatus::RangeNotSatisfiable => "Range Not Satisfiable",
+            HttpStatus::ExpectationFailed => "Expectation Failed",
+            HttpStatus::UpgradeRequired => "Upgrade Required",
+            HttpStatus::TooManyRequests => "Too Many Requests",
+            HttpStatus::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            HttpStatus::InternalServerError => "Internal Server Error",
+            HttpStatus::NotImplemented => "Not Implemented",
+            HttpStatus::BadGateway => "Bad Gateway",
+            HttpStatus::ServiceUnavailable => "Service Unavailable",
+            HttpStatus::GatewayTimeout => "Gateway Timeout",
+            HttpStatus::HttpVersionNotSupported => "HTTP Version Not Supported",
+        }
+    }
+
+    /// 1xx, the request was received and is being processed
+    pub fn is_informational(self) -> bool {
+        self.get_code() / 100 == 1
+    }
+
+    /// 2xx
+    pub fn is_success(self) -> bool {
+        self.get_code() / 100 == 2
+    }
+
+    /// 3xx
+    pub fn is_redirection(self) -> bool {
+        self.get_code() / 100 == 3
+    }
+
+    /// 4xx
+    pub fn is_client_error(self) -> bool {
+        self.get_code() / 100 == 4
+    }
+
+    /// 5xx
+    pub fn is_server_error(self) -> bool {
+        self.get_code() / 100 == 5
+    }
+}
+
+impl fmt::Display for HttpStatus {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} {}", self.get_code(), self.get_reason())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        for status in ALL.iter() {
+            assert_eq!(HttpStatus::from_code(status.get_code()), Some(*status));
+        }
+        let codes: Vec<u16> = ALL.iter().map(|status| status.get_code()).collect();
+        let mut sorted = codes.clone();
+        sorted.sort();
+        assert_eq!(codes, sorted);
+        assert_eq!(HttpStatus::from_code(299), None);
+
+        assert!(HttpStatus::Continue.is_informational());
+        assert!(HttpStatus::Found.is_redirection());
+        assert!(HttpStatus::Gone.is_client_error());
+        assert!(HttpStatus::BadGateway.is_server_error());
+        assert!(!HttpStatus::NotModified.is_success());
+        assert_eq!(
+            HttpStatus::RequestHeaderFieldsTooLarge.to_string(),
+            "431 Request Header Fields Too Large"
+        );
+    }
+}
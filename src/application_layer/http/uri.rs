@@ -0,0 +1,427 @@
+//! # Uniform Resource Identifier
+//! Structured request targets and links, see RFC 3986 and RFC 7230 section 5.3.
+
+use std::fmt;
+
+/// # Holds the components of a URI
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::uri::Uri;
+/// let uri = Uri::from_str("HTTP://Example.com:80/a%2fb/c?page=2").unwrap();
+/// assert_eq!(uri.scheme, Some("HTTP".to_string()));
+/// assert_eq!(uri.get_segments(), vec!["a/b".to_string(), "c".to_string()]);
+/// assert_eq!(uri.normalize().to_string(), "http://example.com/a%2Fb/c?page=2");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Uri {
+    pub authority: Option<String>,
+    pub fragment: Option<String>,
+    pub path: String,
+    pub query: Option<String>,
+    pub scheme: Option<String>,
+}
+
+impl Uri {
+    pub fn new() -> Uri {
+        Uri {
+            authority: None,
+            fragment: None,
+            path: String::new(),
+            query: None,
+            scheme: None,
+        }
+    }
+
+    /// Parse a URI reference like a origin-form or absolute-form request target.
+    /// A scheme is only recognized when followed by an authority.
+    pub fn from_str(value: &str) -> Option<Uri> {
+        if value.is_empty()
+            || !value
+                .chars()
+                .all(|character| character.is_ascii_graphic())
+        {
+            return None;
+        }
+
+        let mut uri = Uri::new();
+        let mut rest = value;
+        if let Some(position) = rest.find('#') {
+            uri.fragment = Some(rest[position + 1..].to_string());
+            rest = &rest[..position];
+        }
+        if let Some(position) = rest.find('?') {
+            uri.query = Some(rest[position + 1..].to_string());
+            rest = &rest[..position];
+        }
+        // Paths can contain `://` too, like `/redirect/http://example.com`
+        let scheme_end = if rest.starts_with('/') {
+            None
+        } else {
+            rest.find("://")
+        };
+        if let Some(position) = scheme_end {
+            let scheme = &rest[..position];
+            if !is_scheme(scheme) {
+                return None;
+            }
+            uri.scheme = Some(scheme.to_string());
+            rest = &rest[position + 1..];
+        }
+        if rest.starts_with("//") {
+            let end = rest[2..].find('/').map_or(rest.len(), |position| position + 2);
+            uri.authority = Some(rest[2..end].to_string());
+            rest = &rest[end..];
+        }
+        uri.path = rest.to_string();
+        Some(uri)
+    }
+
+    /// Parse the authority-form used by CONNECT requests, i.e. `example.com:443`
+    pub fn from_authority(value: &str) -> Option<Uri> {
+        if value.is_empty()
+            || value.contains(|character: char| "/?#".contains(character))
+            || !value
+                .chars()
+                .all(|character| character.is_ascii_graphic())
+        {
+            return None;
+        }
+        Some(Uri::new().with_authority(value))
+    }
+
+    pub fn with_authority(mut self, authority: &str) -> Uri {
+        self.authority = Some(authority.to_string());
+        self
+    }
+
+    pub fn with_fragment(mut self, fragment: &str) -> Uri {
+        self.fragment = Some(fragment.to_string());
+        self
+    }
+
+    pub fn with_path(mut self, path: &str) -> Uri {
+        self.path = path.to_string();
+        self
+    }
+
+    pub fn with_query(mut self, query: &str) -> Uri {
+        self.query = Some(query.to_string());
+        self
+    }
+
+    pub fn with_scheme(mut self, scheme: &str) -> Uri {
+        self.scheme = Some(scheme.to_string());
+        self
+    }
+
+    /// Host of authority without user information and port
+    pub fn get_host(&self) -> Option<&str> {
+        let authority = self.authority.as_ref()?;
+        let host = match authority.rfind('@') {
+            Some(position) => &authority[position + 1..],
+            None => authority,
+        };
+        if host.starts_with('[') {
+            return host.find(']').map(|position| &host[..position + 1]);
+        }
+        Some(host.splitn(2, ':').next().unwrap_or(host))
+    }
+
+    pub fn get_port(&self) -> Option<u16> {
+        let authority = self.authority.as_ref()?;
+        let host = &authority[authority.rfind('@').map_or(0, |position| position + 1)..];
+        let after_host = match host.rfind(']') {
+            Some(position) => &host[position + 1..],
+            None => host,
+        };
+        let position = after_host.rfind(':')?;
+        after_host[position + 1..].parse().ok()
+    }
+
+    /// Percent-decoded path segments, empty segments are skipped
+    pub fn get_segments(&self) -> Vec<String> {
+        self.path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(decode)
+            .collect()
+    }
+
+    /// Path with dot segments resolved and duplicate slashes collapsed, see `normalize_path`
+    pub fn get_normalized_path(&self) -> String {
+        normalize_path(&self.path)
+    }
+
+    /// Path with query, as used in a origin-form request target
+    pub fn get_path_and_query(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Syntax-based normalization, see RFC 3986 section 6.2.2. The case of scheme and host
+    /// is lowered, percent-encodings are normalized and default ports are removed.
+    pub fn normalize(&self) -> Uri {
+        let scheme = self.scheme.as_ref().map(|scheme| scheme.to_lowercase());
+        let authority = self.authority.as_ref().map(|authority| {
+            let (userinfo, host) = match authority.rfind('@') {
+                Some(position) => (&authority[..position + 1], &authority[position + 1..]),
+                None => ("", authority.as_str()),
+            };
+            let mut host = host.to_lowercase();
+            let default_port = match scheme.as_ref().map(|scheme| scheme.as_str()) {
+                Some("http") | Some("ws") => Some(":80"),
+                Some("https") | Some("wss") => Some(":443"),
+                _ => None,
+            };
+            if let Some(default_port) = default_port {
+                if host.ends_with(default_port) {
+                    let length = host.len() - default_port.len();
+                    host.truncate(length);
+                }
+            }
+            if host.ends_with(':') {
+                host.pop();
+            }
+            format!("{}{}", userinfo, host)
+        });
+        let mut path = normalize_percent_encoding(&self.path);
+        if path.is_empty() && scheme.is_some() && authority.is_some() {
+            path = "/".to_string();
+        }
+        Uri {
+            authority,
+            fragment: self.fragment.as_ref().map(|value| normalize_percent_encoding(value)),
+            path,
+            query: self.query.as_ref().map(|value| normalize_percent_encoding(value)),
+            scheme,
+        }
+    }
+
+    /// Are URIs equal after normalization?
+    pub fn equivalent(&self, other: &Uri) -> bool {
+        self.normalize() == other.normalize()
+    }
+}
+
+impl fmt::Display for Uri {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(formatter, "{}:", scheme)?;
+        }
+        if let Some(authority) = &self.authority {
+            if self.scheme.is_some() || self.path.starts_with('/') {
+                write!(formatter, "//")?;
+            }
+            write!(formatter, "{}", authority)?;
+        }
+        write!(formatter, "{}", self.path)?;
+        if let Some(query) = &self.query {
+            write!(formatter, "?{}", query)?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(formatter, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_scheme(value: &str) -> bool {
+    let mut characters = value.chars();
+    match characters.next() {
+        Some(character) if character.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    characters.all(|character| character.is_ascii_alphanumeric() || "+-.".contains(character))
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~".contains(&byte)
+}
+
+/// Resolve `.` and `..` segments and collapse duplicate slashes of a absolute path, see
+/// RFC 3986 section 5.2.4. Percent-encoded dots are resolved too and `..` never climbs
+/// above the root. Paths that do not start with a slash are returned as is.
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::uri::normalize_path;
+/// assert_eq!(normalize_path("/a//b/./c/../d"), "/a/b/d");
+/// assert_eq!(normalize_path("/%2e%2e/etc/passwd"), "/etc/passwd");
+/// ```
+pub fn normalize_path(path: &str) -> String {
+    if !path.starts_with('/') {
+        return path.to_string();
+    }
+    let path = normalize_percent_encoding(path);
+    let parts: Vec<&str> = path[1..].split('/').collect();
+    let mut segments: Vec<&str> = Vec::with_capacity(parts.len());
+    let mut is_directory = false;
+    for (index, part) in parts.iter().enumerate() {
+        is_directory = index + 1 == parts.len();
+        match *part {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            part => {
+                segments.push(part);
+                is_directory = false;
+            }
+        }
+    }
+    let mut normalized = format!("/{}", segments.join("/"));
+    if is_directory && !segments.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// Decode percent-encoded octets, unlike form decoding a `+` is kept as is
+pub fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            if let Some(byte) = get_octet(bytes, index + 1) {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Upper-case hexadecimal digits and decode octets of unreserved characters
+fn normalize_percent_encoding(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut normalized = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            if let Some(byte) = get_octet(bytes, index + 1) {
+                if is_unreserved(byte) {
+                    normalized.push(byte);
+                } else {
+                    normalized.extend_from_slice(format!("%{:02X}", byte).as_bytes());
+                }
+                index += 3;
+                continue;
+            }
+        }
+        normalized.push(bytes[index]);
+        index += 1;
+    }
+    // Only ASCII is decoded or added, other bytes are copied so the value stays valid UTF-8
+    String::from_utf8(normalized).unwrap_or_else(|_| value.to_string())
+}
+
+/// Value of two hexadecimal digits starting at index
+fn get_octet(bytes: &[u8], index: usize) -> Option<u8> {
+    let high = (*bytes.get(index)? as char).to_digit(16)?;
+    let low = (*bytes.get(index + 1)? as char).to_digit(16)?;
+    Some((high * 16 + low) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let uri = Uri::from_str("/index.htm?page=2#top").unwrap();
+        assert_eq!(uri.scheme, None);
+        assert_eq!(uri.authority, None);
+        assert_eq!(uri.path, "/index.htm");
+        assert_eq!(uri.query, Some("page=2".to_string()));
+        assert_eq!(uri.fragment, Some("top".to_string()));
+
+        let uri = Uri::from_str("https://user@[::1]:8080/a/b?").unwrap();
+        assert_eq!(uri.scheme, Some("https".to_string()));
+        assert_eq!(uri.authority, Some("user@[::1]:8080".to_string()));
+        assert_eq!(uri.get_host(), Some("[::1]"));
+        assert_eq!(uri.get_port(), Some(8080));
+        assert_eq!(uri.path, "/a/b");
+        assert_eq!(uri.query, Some("".to_string()));
+        assert_eq!(uri.to_string(), "https://user@[::1]:8080/a/b?");
+
+        let uri = Uri::from_str("http://example.com").unwrap();
+        assert_eq!(uri.get_host(), Some("example.com"));
+        assert_eq!(uri.get_port(), None);
+        assert_eq!(uri.path, "");
+
+        assert_eq!(Uri::from_str("*").unwrap().path, "*");
+        assert_eq!(Uri::from_str(""), None);
+        assert_eq!(Uri::from_str("/a b"), None);
+        assert_eq!(Uri::from_str("1http://example.com/"), None);
+
+        let uri = Uri::from_str("/a/http://b?c").unwrap();
+        assert_eq!(uri.scheme, None);
+        assert_eq!(uri.authority, None);
+        assert_eq!(uri.path, "/a/http://b");
+        assert_eq!(uri.to_string(), "/a/http://b?c");
+    }
+
+    #[test]
+    fn test_from_authority() {
+        let uri = Uri::from_authority("example.com:443").unwrap();
+        assert_eq!(uri.get_host(), Some("example.com"));
+        assert_eq!(uri.get_port(), Some(443));
+        assert_eq!(uri.to_string(), "example.com:443");
+        assert_eq!(Uri::from_authority("example.com/"), None);
+    }
+
+    #[test]
+    fn test_builder() {
+        let uri = Uri::from_str("/old?a=1")
+            .unwrap()
+            .with_scheme("https")
+            .with_authority("example.com")
+            .with_path("/new")
+            .with_fragment("top");
+        assert_eq!(uri.to_string(), "https://example.com/new?a=1#top");
+        assert_eq!(uri.get_path_and_query(), "/new?a=1");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let uri = Uri::from_str("HTTPS://User@Example.COM:443/%7euser/%2f?q=%3a").unwrap();
+        assert_eq!(
+            uri.normalize().to_string(),
+            "https://User@example.com/~user/%2F?q=%3A"
+        );
+        assert!(Uri::from_str("http://example.com")
+            .unwrap()
+            .equivalent(&Uri::from_str("http://EXAMPLE.com:80/").unwrap()));
+        assert!(!Uri::from_str("/a")
+            .unwrap()
+            .equivalent(&Uri::from_str("/A").unwrap()));
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/"), "/");
+        assert_eq!(normalize_path("//"), "/");
+        assert_eq!(normalize_path("/a/b/"), "/a/b/");
+        assert_eq!(normalize_path("/a//b//"), "/a/b/");
+        assert_eq!(normalize_path("/a/./b/."), "/a/b/");
+        assert_eq!(normalize_path("/a/b/.."), "/a/");
+        assert_eq!(normalize_path("/a/.."), "/");
+        assert_eq!(normalize_path("/../../a"), "/a");
+        assert_eq!(normalize_path("/a/%2E%2e/b"), "/b");
+        assert_eq!(normalize_path("/a/..%2F/b"), "/a/..%2F/b");
+        assert_eq!(normalize_path("/a/.b/..c"), "/a/.b/..c");
+        assert_eq!(normalize_path("/é/./ö%c3%a5"), "/é/ö%C3%A5");
+        assert_eq!(normalize_path("*"), "*");
+        assert_eq!(normalize_path(""), "");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("a%20b+c%2F%zz%4"), "a b+c/%zz%4");
+        assert_eq!(decode("%C3%B6"), "ö");
+        assert_eq!(decode("%éé"), "%éé");
+    }
+}
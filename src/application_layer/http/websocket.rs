@@ -0,0 +1,193 @@
+//! # HTTP WebSocket upgrade
+//! Detects requests asking to open a WebSocket connection and validates their handshake,
+//! see RFC 6455 section 4.2. Responders complete the handshake with a `101` response.
+//! Browsers let any page open sockets to any server, so the `Origin` of upgrades is checked.
+
+use application_layer::http::header::{parse, Headers};
+use application_layer::http::request::{self, Method, Protocol};
+use base64;
+use digest;
+
+/// Appended to the client key before hashing
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The only protocol version, sent in `Sec-WebSocket-Version` when a client asks for another
+pub const VERSION: &str = "13";
+
+/// Does the request ask to switch the connection to the WebSocket protocol?
+pub fn is_upgrade(headers: &Headers) -> bool {
+    let has_option = |name: &str, option: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .flat_map(|value| parse::split_list(value))
+            .any(|value| value.eq_ignore_ascii_case(option))
+    };
+    has_option("Upgrade", "websocket") && has_option("Connection", "upgrade")
+}
+
+/// Value of `Sec-WebSocket-Accept` for a `Sec-WebSocket-Key`
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::websocket;
+/// assert_eq!(
+///     websocket::get_accept("dGhlIHNhbXBsZSBub25jZQ=="),
+///     "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+/// );
+/// ```
+pub fn get_accept(key: &str) -> String {
+    base64::encode(&digest::sha1(format!("{}{}", key.trim(), GUID).as_bytes()))
+}
+
+/// Validate the handshake of a upgrade request, returns the `Sec-WebSocket-Accept` value
+pub fn validate(request_message: &request::Message) -> Result<String, String> {
+    if !is_upgrade(&request_message.headers) {
+        return Err("Request is not a WebSocket upgrade".to_string());
+    }
+    if request_message.request_line.method != Method::Get {
+        return Err(format!(
+            "WebSocket upgrade must use GET, got {}",
+            request_message.request_line.method
+        ));
+    }
+    if request_message.request_line.protocol != Protocol::V1_1 {
+        return Err("WebSocket upgrade requires HTTP/1.1".to_string());
+    }
+    match request_message.headers.get("Sec-WebSocket-Version") {
+        Some(version) if version.trim() == VERSION => {}
+        Some(version) => return Err(format!("Unsupported WebSocket version {:?}", version)),
+        None => return Err("Missing Sec-WebSocket-Version header".to_string()),
+    }
+    let key = match request_message.headers.get_all("Sec-WebSocket-Key").as_slice() {
+        [key] => key.trim().to_string(),
+        [] => return Err("Missing Sec-WebSocket-Key header".to_string()),
+        _ => return Err("Multiple Sec-WebSocket-Key headers".to_string()),
+    };
+    // The key is a base64-encoded random 16 byte nonce
+    match base64::decode(&key) {
+        Some(ref nonce) if nonce.len() == 16 => Ok(get_accept(&key)),
+        _ => Err(format!("Invalid Sec-WebSocket-Key {:?}", key)),
+    }
+}
+
+/// Was the upgrade opened by a page of one of `origins`, like `https://example.com`, or of the
+/// same origin as the `Host` when none are given? Browsers always send the `Origin` of the page
+/// opening a socket, see RFC 6455 section 10.2.
+pub fn check_origin(request_message: &request::Message, origins: &[String]) -> Result<(), String> {
+    let origin = match request_message.headers.get("Origin") {
+        Some(origin) => origin.trim().trim_end_matches('/').to_ascii_lowercase(),
+        None => return Err("Missing Origin header".to_string()),
+    };
+    let is_allowed = if origins.is_empty() {
+        let host = request_message
+            .headers
+            .get("Host")
+            .map(|host| host.trim().to_ascii_lowercase());
+        let authority = match origin.find("://") {
+            Some(index) => get_authority(&origin[..index], &origin[index + 3..]),
+            None => None,
+        };
+        match (authority, host) {
+            (Some((scheme, authority)), Some(host)) => {
+                get_authority(scheme, &host).map(|(_, host)| host) == Some(authority)
+            }
+            _ => false,
+        }
+    } else {
+        origins
+            .iter()
+            .any(|allowed| allowed.trim().trim_end_matches('/').eq_ignore_ascii_case(&origin))
+    };
+    if is_allowed {
+        Ok(())
+    } else {
+        Err(format!("Origin {:?} is not allowed", origin))
+    }
+}
+
+/// Authority of a origin without the default port of it's scheme
+fn get_authority<'a>(scheme: &'a str, authority: &'a str) -> Option<(&'a str, &'a str)> {
+    let default_port = match scheme {
+        "http" | "ws" => ":80",
+        "https" | "wss" => ":443",
+        _ => return None,
+    };
+    if authority.is_empty() || authority.contains('/') {
+        return None;
+    }
+    Some((scheme, authority.strip_suffix(default_port).unwrap_or(authority)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_message(request: &str) -> request::Message {
+        request::Message::from_tcp_stream(request.replace('\n', "\r\n").as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        let request = "GET /chat HTTP/1.1\nHost: localhost\nUpgrade: WebSocket\n\
+                       Connection: keep-alive, Upgrade\n\
+                       Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\n\
+                       Sec-WebSocket-Version: 13\n\n";
+        assert_eq!(
+            validate(&get_message(request)),
+            Ok("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string())
+        );
+
+        let message = get_message("GET / HTTP/1.1\nHost: localhost\nUpgrade: websocket\n\n");
+        assert!(!is_upgrade(&message.headers));
+        assert!(validate(&message).is_err());
+
+        let invalid = [
+            request.replace("GET", "POST"),
+            request.replace("HTTP/1.1", "HTTP/1.0"),
+            request.replace("Version: 13", "Version: 8"),
+            request.replace("Sec-WebSocket-Version: 13\n", ""),
+            request.replace("dGhlIHNhbXBsZSBub25jZQ==", "c2hvcnQ="),
+            request.replace("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\n", ""),
+            request.replace("Host: localhost\n", "Sec-WebSocket-Key: YQ==\nHost: localhost\n"),
+        ];
+        for request in invalid.iter() {
+            assert!(validate(&get_message(request)).is_err(), "{}", request);
+        }
+    }
+
+    #[test]
+    fn test_check_origin() {
+        let get_upgrade = |host: &str, origin: Option<&str>| {
+            let origin = origin
+                .map(|origin| format!("Origin: {}\n", origin))
+                .unwrap_or_default();
+            get_message(&format!("GET /chat HTTP/1.1\nHost: {}\n{}\n", host, origin))
+        };
+        let same = [
+            ("example.com", "https://example.com"),
+            ("Example.com:443", "https://example.com/"),
+            ("example.com", "http://example.com:80"),
+            ("localhost:8888", "http://localhost:8888"),
+        ];
+        for (host, origin) in same.iter() {
+            assert_eq!(check_origin(&get_upgrade(host, Some(origin)), &[]), Ok(()));
+        }
+        let other = [
+            ("example.com", Some("https://evil.com")),
+            ("example.com", Some("https://example.com.evil.com")),
+            ("localhost:8888", Some("http://localhost:9999")),
+            ("example.com", Some("null")),
+            ("example.com", Some("file://example.com")),
+            ("example.com", None),
+        ];
+        for (host, origin) in other.iter() {
+            assert!(check_origin(&get_upgrade(host, *origin), &[]).is_err());
+        }
+
+        let origins = vec!["https://app.example.com".to_string()];
+        let message = get_upgrade("api.example.com", Some("https://APP.example.com"));
+        assert_eq!(check_origin(&message, &origins), Ok(()));
+        let message = get_upgrade("api.example.com", Some("https://api.example.com"));
+        assert!(check_origin(&message, &origins).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! # Audit trail
+//! Structured events of authentication and access decisions, written as one JSON object per
+//! line so security reviews can follow who tried to do what from where.
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use application_layer::http::forwarded::Cidr;
+use application_layer::http::request;
+use response::tcp::http::admin;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    AccessDenied,
+    AuthenticationFailed,
+    AuthenticationSucceeded,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Kind::AccessDenied => "access_denied",
+            Kind::AuthenticationFailed => "authentication_failed",
+            Kind::AuthenticationSucceeded => "authentication_succeeded",
+        };
+        write!(formatter, "{}", name)
+    }
+}
+
+/// # Authentication or access decision
+/// ```rust
+/// use milstian_internet_framework::audit::{Event, Kind};
+/// let event = Event::new(Kind::AuthenticationFailed, "POST /login", "203.0.113.7")
+///     .identity("alice")
+///     .reason("Wrong password");
+/// let json = event.to_json();
+/// assert!(json.contains("\"event\":\"authentication_failed\",\"identity\":\"alice\""));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub client: String,
+    /// Id of the identity, none when it is not known
+    pub identity: Option<String>,
+    pub kind: Kind,
+    pub reason: String,
+    /// Method and path of the request
+    pub route: String,
+    pub time: SystemTime,
+}
+
+impl Event {
+    pub fn new(kind: Kind, route: &str, client: &str) -> Event {
+        Event {
+            client: client.to_string(),
+            identity: None,
+            kind,
+            reason: String::new(),
+            route: route.to_string(),
+            time: SystemTime::now(),
+        }
+    }
+
+    /// Event of a request from it's client, which is the forwarded client when the request
+    /// came from a trusted proxy
+    pub fn from_request(
+        kind: Kind,
+        request_message: &request::Message,
+        socket: &SocketAddr,
+        trusted_proxies: &[Cidr],
+    ) -> Event {
+        Event::new(
+            kind,
+            &format!(
+                "{} {}",
+                request_message.request_line.method, request_message.request_line.uri.path
+            ),
+            &request_message
+                .get_client_addr(socket, trusted_proxies)
+                .to_string(),
+        )
+    }
+
+    pub fn identity(mut self, identity: &str) -> Event {
+        self.identity = Some(identity.to_string());
+        self
+    }
+
+    pub fn reason(mut self, reason: &str) -> Event {
+        self.reason = reason.to_string();
+        self
+    }
+
+    /// JSON object on a single line with the time in seconds since the unix epoch
+    pub fn to_json(&self) -> String {
+        let time = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let identity = match &self.identity {
+            Some(identity) => admin::Responder::get_json_string(identity),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"time\":{},\"event\":\"{}\",\"identity\":{},\"route\":{},\"reason\":{},\"client\":{}}}",
+            time,
+            self.kind,
+            identity,
+            admin::Responder::get_json_string(&self.route),
+            admin::Responder::get_json_string(&self.reason),
+            admin::Responder::get_json_string(&self.client)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_to_json() {
+        let mut event = Event::new(Kind::AccessDenied, "DELETE /users/7", "10.0.0.1")
+            .reason("Missing role \"admin\"");
+        event.time = UNIX_EPOCH + Duration::from_secs(1546300800);
+        assert_eq!(
+            event.to_json(),
+            "{\"time\":1546300800,\"event\":\"access_denied\",\"identity\":null,\
+             \"route\":\"DELETE /users/7\",\"reason\":\"Missing role \\\"admin\\\"\",\
+             \"client\":\"10.0.0.1\"}"
+        );
+    }
+
+    #[test]
+    fn test_from_request() {
+        let request_message = request::Message::from_tcp_stream(
+            b"GET /users/7?a=b HTTP/1.1\r\nHost: a\r\nX-Forwarded-For: 203.0.113.7\r\n\r\n",
+        ).unwrap();
+        let socket: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let event = Event::from_request(
+            Kind::AuthenticationSucceeded,
+            &request_message,
+            &socket,
+            &[],
+        );
+        assert_eq!(event.route, "GET /users/7");
+        assert_eq!(event.client, "10.0.0.1");
+        let trusted = Cidr::from_list("10.0.0.0/8").unwrap();
+        let event = Event::from_request(
+            Kind::AuthenticationSucceeded,
+            &request_message,
+            &socket,
+            &trusted,
+        );
+        assert_eq!(event.client, "203.0.113.7");
+    }
+}
@@ -0,0 +1,76 @@
+//! # Base64 encoding and decoding
+//! Standard alphabet with padding, see RFC 4648.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let first = chunk[0] as u32;
+        let second = *chunk.get(1).unwrap_or(&0) as u32;
+        let third = *chunk.get(2).unwrap_or(&0) as u32;
+        let triple = (first << 16) | (second << 8) | third;
+        encoded.push(ALPHABET[(triple >> 18) as usize & 63] as char);
+        encoded.push(ALPHABET[(triple >> 12) as usize & 63] as char);
+        if chunk.len() > 1 {
+            encoded.push(ALPHABET[(triple >> 6) as usize & 63] as char);
+        } else {
+            encoded.push('=');
+        }
+        if chunk.len() > 2 {
+            encoded.push(ALPHABET[triple as usize & 63] as char);
+        } else {
+            encoded.push('=');
+        }
+    }
+    encoded
+}
+
+/// Decode data, padding is optional
+pub fn decode(data: &str) -> Option<Vec<u8>> {
+    let data = data.trim_end_matches('=');
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for character in data.bytes() {
+        let value = match ALPHABET.iter().position(|&letter| letter == character) {
+            Some(value) => value as u32,
+            None => return None,
+        };
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if bits >= 6 {
+        return None;
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(""), Some(Vec::new()));
+        assert_eq!(decode("Zg=="), Some(b"f".to_vec()));
+        assert_eq!(decode("Zm8"), Some(b"fo".to_vec()));
+        assert_eq!(decode("Zm9vYmFy"), Some(b"foobar".to_vec()));
+        assert_eq!(decode("Zm9v!"), None);
+        assert_eq!(decode("Z"), None);
+    }
+}
@@ -0,0 +1,334 @@
+//! # Configuration check
+//! Validates a configuration without serving anything, like `nginx -t`. Limits are checked,
+//! the server address is bound and released and files and directories are looked up.
+
+use std::fmt;
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+
+use Config;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Passed,
+    /// The server can start but will likely not behave as intended
+    Warning,
+    Failed,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            Status::Passed => "ok",
+            Status::Warning => "warning",
+            Status::Failed => "failed",
+        };
+        write!(formatter, "{}", status)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Check {
+    /// Configuration field or resource that was checked
+    pub name: String,
+    pub message: String,
+    pub status: Status,
+}
+
+/// # Outcome of every check, in the order they were made
+/// ```rust
+/// use milstian_internet_framework::check::{Report, Status};
+/// use milstian_internet_framework::Config;
+/// let arguments = ["", "localhost", "8888", "4", "index.htm", "./html/", "404.htm", "1024"];
+/// let config =
+///     Config::from_env_args(arguments.iter().map(|argument| argument.to_string()).collect())
+///         .unwrap();
+/// let report = Report::from_config(&config);
+/// assert_eq!(report.checks[0].name, "server_limit");
+/// assert_eq!(report.checks[0].status, Status::Passed);
+/// println!("{}", report);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn new() -> Report {
+        Report::default()
+    }
+
+    /// Check configuration and the resources it refers to
+    pub fn from_config(config: &Config) -> Report {
+        let mut report = Report::new();
+        report.check_limits(config);
+        report.check_bind(config);
+        report.check_root(config);
+        report.check_output_files(config);
+        if let Some(admin_path) = &config.admin_path {
+            if admin_path.starts_with('/') {
+                report.add("admin_path", Status::Passed, admin_path.clone());
+            } else {
+                report.add(
+                    "admin_path",
+                    Status::Failed,
+                    format!("{} does not start with a slash", admin_path),
+                );
+            }
+        }
+        report
+    }
+
+    /// Check the file-system root and the pages in it, done when the server starts
+    pub fn from_files(config: &Config) -> Report {
+        let mut report = Report::new();
+        report.check_root(config);
+        report
+    }
+
+    pub fn add(&mut self, name: &str, status: Status, message: String) {
+        self.checks.push(Check {
+            name: name.to_string(),
+            message,
+            status,
+        });
+    }
+
+    /// Is the configuration usable, warnings are allowed
+    pub fn is_ok(&self) -> bool {
+        !self.checks.iter().any(|check| check.status == Status::Failed)
+    }
+
+    pub fn get_failed(&self) -> Vec<&Check> {
+        self.checks
+            .iter()
+            .filter(|check| check.status == Status::Failed)
+            .collect()
+    }
+
+    /// Checks that failed or gave a warning
+    pub fn get_problems(&self) -> Vec<&Check> {
+        self.checks
+            .iter()
+            .filter(|check| check.status != Status::Passed)
+            .collect()
+    }
+
+    fn check_limits(&mut self, config: &Config) {
+        let positive = [
+            ("server_limit", config.server_limit),
+            ("tcp_limit", config.tcp_limit),
+            ("max_header_bytes", config.max_header_bytes),
+            ("max_request_line", config.max_request_line),
+            ("keep_alive_max_requests", config.keep_alive_max_requests),
+        ];
+        for (name, value) in positive.iter() {
+            if *value == 0 {
+                self.add(name, Status::Failed, "must be greater than 0".to_string());
+            } else {
+                self.add(name, Status::Passed, value.to_string());
+            }
+        }
+        if config.max_body_bytes > config.tcp_limit {
+            self.add(
+                "max_body_bytes",
+                Status::Warning,
+                format!(
+                    "{} is larger than tcp_limit {}, bodies are cut at the TCP limit",
+                    config.max_body_bytes, config.tcp_limit
+                ),
+            );
+        }
+    }
+
+    fn check_bind(&mut self, config: &Config) {
+        let address = format!("{}:{}", config.server_host, config.server_port);
+        match TcpListener::bind(&address) {
+            Ok(_) => self.add("server", Status::Passed, format!("can bind to {}", address)),
+            Err(error) => self.add(
+                "server",
+                Status::Failed,
+                format!("failed to bind to {}, error: {}", address, error),
+            ),
+        }
+    }
+
+    fn check_root(&mut self, config: &Config) {
+        let root = Path::new(&config.filesystem_root);
+        if let Err(error) = fs::read_dir(root) {
+            self.add(
+                "filesystem_root",
+                Status::Failed,
+                format!("{} is not a readable directory, error: {}", root.display(), error),
+            );
+            return;
+        }
+        self.add("filesystem_root", Status::Passed, root.display().to_string());
+
+        let indexes = &config.filesystem_directory_index;
+        match indexes.iter().map(|index| root.join(index)).find(|path| path.is_file()) {
+            Some(path) => self.add(
+                "filesystem_directory_index",
+                Status::Passed,
+                path.display().to_string(),
+            ),
+            None => self.add(
+                "filesystem_directory_index",
+                Status::Warning,
+                format!("none of {} is a file in the root", indexes.join(", ")),
+            ),
+        }
+        let path = root.join(&config.file_not_found_file);
+        if path.is_file() {
+            self.add("file_not_found_file", Status::Passed, path.display().to_string());
+        } else {
+            self.add(
+                "file_not_found_file",
+                Status::Warning,
+                format!("{} is not a file in the root", path.display()),
+            );
+        }
+        for (status, file) in config.error_pages.iter() {
+            let path = root.join(file);
+            if path.is_file() {
+                self.add("error_pages", Status::Passed, path.display().to_string());
+            } else {
+                self.add(
+                    "error_pages",
+                    Status::Warning,
+                    format!("{} of status {} is not a file in the root", path.display(), status),
+                );
+            }
+        }
+    }
+
+    /// Files are created when the server starts so only their directories must exist
+    fn check_output_files(&mut self, config: &Config) {
+        let files = [
+            ("access_log_file", &config.access_log_file),
+            ("audit_log_file", &config.audit_log_file),
+            ("crash_report_file", &config.crash_report_file),
+            ("feedback_error_file", &config.feedback_error_file),
+            ("feedback_info_file", &config.feedback_info_file),
+        ];
+        for (name, file) in files.iter() {
+            let file = match file {
+                Some(file) => file,
+                None => continue,
+            };
+            let directory = match Path::new(file).parent() {
+                Some(directory) if !directory.as_os_str().is_empty() => directory,
+                _ => Path::new("."),
+            };
+            if Path::new(file).is_dir() {
+                self.add(name, Status::Failed, format!("{} is a directory", file));
+            } else if directory.is_dir() {
+                self.add(name, Status::Passed, file.to_string());
+            } else {
+                self.add(
+                    name,
+                    Status::Failed,
+                    format!("directory {} does not exist", directory.display()),
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for check in self.checks.iter() {
+            writeln!(formatter, "{}: {}: {}", check.status, check.name, check.message)?;
+        }
+        if self.is_ok() {
+            write!(formatter, "configuration test is successful")
+        } else {
+            write!(formatter, "configuration test failed")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_config() -> Config {
+        Config {
+            server_host: "127.0.0.1".to_string(),
+            server_port: 0,
+            ..Config::for_tests()
+        }
+    }
+
+    #[test]
+    fn test_from_config() {
+        let report = Report::from_config(&get_config());
+        assert!(report.is_ok(), "{}", report);
+        assert!(report.to_string().ends_with("configuration test is successful"));
+
+        let mut config = get_config();
+        config.server_limit = 0;
+        config.filesystem_root = "./missing/".to_string();
+        config.access_log_file = Some("./missing/access.log".to_string());
+        config.feedback_info_file = Some("info.log".to_string());
+        config.admin_path = Some("admin".to_string());
+        let report = Report::from_config(&config);
+        assert!(!report.is_ok());
+        let failed: Vec<&str> = report
+            .get_failed()
+            .iter()
+            .map(|check| check.name.as_str())
+            .collect();
+        assert_eq!(
+            failed,
+            vec!["server_limit", "filesystem_root", "access_log_file", "admin_path"]
+        );
+        assert!(report.to_string().contains("failed: server_limit: must be greater than 0\n"));
+    }
+
+    #[test]
+    fn test_check_bind() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut config = get_config();
+        config.server_port = listener.local_addr().unwrap().port();
+        let mut report = Report::new();
+        report.check_bind(&config);
+        assert_eq!(report.checks[0].status, Status::Failed);
+    }
+
+    #[test]
+    fn test_check_root() {
+        let mut config = get_config()
+            .error_page(404, "404.htm")
+            .error_page(500, "500.htm");
+        config.file_not_found_file = "missing.htm".to_string();
+        let mut report = Report::new();
+        report.check_root(&config);
+        let statuses: Vec<Status> = report.checks.iter().map(|check| check.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::Passed,
+                Status::Passed,
+                Status::Warning,
+                Status::Passed,
+                Status::Warning
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_files() {
+        assert!(Report::from_files(&get_config()).get_problems().is_empty());
+        let mut config = get_config();
+        config.filesystem_root = "./missing/".to_string();
+        let report = Report::from_files(&config);
+        let problems: Vec<&str> = report
+            .get_problems()
+            .iter()
+            .map(|check| check.name.as_str())
+            .collect();
+        assert_eq!(problems, vec!["filesystem_root"]);
+    }
+}
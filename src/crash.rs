@@ -0,0 +1,184 @@
+//! # Crash handler
+//! Writes a diagnostic report to the configured crash report file when a thread panics
+//! and then aborts the process, the report is meant to be attached to bug reports.
+
+use std::backtrace::Backtrace;
+use std::fs::File;
+use std::io::Write;
+use std::panic;
+use std::process;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use {Application, Config};
+
+/// Words in configuration names whose values are left out of reports
+const SECRET_WORDS: [&str; 5] = ["key", "password", "secret", "token", "credential"];
+
+/// Install the crash handler when a crash report file is configured
+pub fn install(application: &Application) {
+    let filename = match &application.get_config().crash_report_file {
+        Some(filename) => filename.clone(),
+        None => return,
+    };
+    let application = application.clone();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = get_report(
+            &info.to_string(),
+            &Backtrace::force_capture().to_string(),
+            &application,
+        );
+        default_hook(info);
+        match File::create(&filename).and_then(|mut file| file.write_all(report.as_bytes())) {
+            Ok(()) => eprintln!("Wrote crash report to {}", filename),
+            Err(error) => eprintln!(
+                "Failed to write crash report to {}, error: {}\n{}",
+                filename, error, report
+            ),
+        }
+        process::abort();
+    }));
+}
+
+/// Describe the panic together with the state of the application
+pub fn get_report(panic: &str, backtrace: &str, application: &Application) -> String {
+    let time = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(time) => time.as_secs(),
+        Err(_) => 0,
+    };
+    let mut report = format!(
+        "Crash report\ntime: {}\nthread: {}\npanic: {}\n\nBacktrace:\n{}\n",
+        time,
+        thread::current().name().unwrap_or("unnamed"),
+        panic,
+        backtrace.trim_end()
+    );
+
+    let config = application.get_config();
+    report.push_str(&format!("\nPool:\nworkers: {}\n", config.server_limit));
+
+    // The panicking thread may hold the table lock
+    match application.get_connections().try_get_snapshot() {
+        Some(connections) => {
+            report.push_str(&format!("\nConnections ({} open):\n", connections.len()));
+            for connection in connections {
+                report.push_str(&format!(
+                    "{} client={} state={} requests={} request_uri={:?} duration_ms={} \
+                     received={} sent={}\n",
+                    connection.id,
+                    connection.client,
+                    connection.state,
+                    connection.requests,
+                    connection.request_uri,
+                    connection.get_duration().as_millis(),
+                    connection.bytes_received,
+                    connection.bytes_sent
+                ));
+            }
+        }
+        None => report.push_str("\nConnections: unavailable, table is locked\n"),
+    }
+
+    report.push_str(&format!(
+        "\nRecent requests:\n{}\nConfig:\n{}\n",
+        application.get_trace().dump(),
+        get_redacted_config(config)
+    ));
+    report
+}
+
+/// Configuration with the values of secret looking fields replaced
+pub fn get_redacted_config(config: &Config) -> String {
+    redact(&format!("{:#?}", config))
+}
+
+/// Replace values of secret looking fields in pretty printed debug output
+fn redact(debug: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    // Indentation of a redacted field whose value spans multiple lines
+    let mut skipped_indent: Option<usize> = None;
+    for line in debug.lines() {
+        let indent = line.len() - line.trim_start().len();
+        if let Some(skipped) = skipped_indent {
+            if indent > skipped {
+                continue;
+            }
+            skipped_indent = None;
+            if indent == skipped && line.trim_start().starts_with(&[')', '}', ']'][..]) {
+                continue;
+            }
+        }
+        let separator = match line.find(": ") {
+            Some(separator) => separator,
+            None => {
+                lines.push(line.to_string());
+                continue;
+            }
+        };
+        let name = line[..separator].trim().to_lowercase();
+        if SECRET_WORDS.iter().any(|word| name.contains(word)) {
+            lines.push(format!("{}: \"<redacted>\",", &line[..separator]));
+            if line.ends_with(&['(', '{', '['][..]) {
+                skipped_indent = Some(indent);
+            }
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_config() -> Config {
+        Config {
+            trace_capacity: 2,
+            ..Config::for_tests()
+        }
+    }
+
+    #[test]
+    fn test_get_redacted_config() {
+        let mut config = get_config();
+        config.content_security_policy = Some("default-src 'self'".to_string());
+        let redacted = get_redacted_config(&config);
+        assert!(redacted.contains("    server_host: \"localhost\","));
+        assert!(redacted.contains("default-src 'self'"));
+        assert!(redacted.starts_with("Config {"));
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("Config {\n    session_key: \"abc\",\n    Password: None,\n    host: \"a\",\n}"),
+            "Config {\n    session_key: \"<redacted>\",\n    Password: \"<redacted>\",\n    \
+             host: \"a\",\n}"
+        );
+        assert_eq!(
+            redact("Config {\n    api_token: Some(\n        \"abc\",\n    ),\n    port: 80,\n}"),
+            "Config {\n    api_token: \"<redacted>\",\n    port: 80,\n}"
+        );
+    }
+
+    #[test]
+    fn test_get_report() {
+        let application = Application::new(get_config());
+        let socket = "127.0.0.1:8888".parse().unwrap();
+        let registration = application.get_connections().open(socket);
+        registration.set_request("/index.htm", "1");
+        application.get_trace().record("HTTP completed route=\"/\"".to_string());
+
+        let report = get_report("panicked at 'boom'", "0: main\n", &application);
+        assert!(report.starts_with("Crash report\n"));
+        assert!(report.contains("panic: panicked at 'boom'\n"));
+        assert!(report.contains("\nBacktrace:\n0: main\n"));
+        assert!(report.contains("workers: 4\n"));
+        assert!(report.contains("Connections (1 open):\n"));
+        assert!(report.contains("client=127.0.0.1:8888 state=idle requests=1"));
+        assert!(report.contains("1 HTTP completed route=\"/\"\n"));
+        assert!(report.contains("server_port: 4040,"));
+    }
+}
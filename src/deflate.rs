@@ -0,0 +1,358 @@
+//! # DEFLATE compression
+//! Compressed data formats implemented without external crates, raw DEFLATE with fixed Huffman
+//! codes (RFC 1951) wrapped as zlib (RFC 1950) or gzip (RFC 1952).
+
+use std::cmp;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Matches searched per position for each level, more finds longer matches but is slower
+const MAX_CHAIN: [usize; 10] = [0, 4, 8, 16, 32, 64, 128, 256, 1024, 4096];
+
+const HASH_SIZE: usize = 1 << 15;
+const MAX_MATCH: usize = 258;
+const MIN_MATCH: usize = 3;
+const WINDOW_SIZE: usize = 32768;
+
+/// Writes bits starting with the least significant bit of every byte
+struct BitWriter {
+    bits: u32,
+    count: u32,
+    output: Vec<u8>,
+}
+
+impl BitWriter {
+    fn new(capacity: usize) -> BitWriter {
+        BitWriter {
+            bits: 0,
+            count: 0,
+            output: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.bits |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.output.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Huffman codes are packed starting with the most significant bit
+    fn write_code(&mut self, code: u32, length: u32) {
+        let mut reversed = 0;
+        for index in 0..length {
+            reversed |= ((code >> index) & 1) << (length - 1 - index);
+        }
+        self.write_bits(reversed, length);
+    }
+
+    /// Symbol of the fixed literal/length alphabet, see RFC 1951 section 3.2.6
+    fn write_symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn write_match(&mut self, length: usize, distance: usize) {
+        let index = LENGTH_BASE
+            .iter()
+            .rposition(|base| *base as usize <= length)
+            .unwrap_or(0);
+        self.write_symbol(257 + index as u32);
+        self.write_bits(
+            (length - LENGTH_BASE[index] as usize) as u32,
+            u32::from(LENGTH_EXTRA[index]),
+        );
+
+        let index = DISTANCE_BASE
+            .iter()
+            .rposition(|base| *base as usize <= distance)
+            .unwrap_or(0);
+        self.write_code(index as u32, 5);
+        self.write_bits(
+            (distance - DISTANCE_BASE[index] as usize) as u32,
+            u32::from(DISTANCE_EXTRA[index]),
+        );
+    }
+
+    /// Pad the last byte with zeros
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.output.push(self.bits as u8);
+        }
+        self.output
+    }
+}
+
+/// Raw DEFLATE data, level 0 stores the data and 1 to 9 trade speed for size
+pub fn compress(data: &[u8], level: u32) -> Vec<u8> {
+    let level = cmp::min(level, 9) as usize;
+    if level == 0 {
+        return store(data);
+    }
+
+    let mut writer = BitWriter::new(data.len() / 2 + 16);
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    // Latest position of every hash and the position before it with the same hash
+    let mut head: Vec<Option<usize>> = vec![None; HASH_SIZE];
+    let mut previous: Vec<Option<usize>> = vec![None; WINDOW_SIZE];
+    let insert = |position: usize, head: &mut [Option<usize>], previous: &mut [Option<usize>]| {
+        if position + MIN_MATCH <= data.len() {
+            let hash = get_hash(&data[position..]);
+            previous[position % WINDOW_SIZE] = head[hash];
+            head[hash] = Some(position);
+        }
+    };
+
+    let mut position = 0;
+    while position < data.len() {
+        let mut best_length = 0;
+        let mut best_distance = 0;
+        if position + MIN_MATCH <= data.len() {
+            let maximum = cmp::min(MAX_MATCH, data.len() - position);
+            let mut candidate = head[get_hash(&data[position..])];
+            let mut chain = MAX_CHAIN[level];
+            while let Some(start) = candidate {
+                let distance = position - start;
+                if chain == 0 || distance > WINDOW_SIZE {
+                    break;
+                }
+                let mut length = 0;
+                while length < maximum && data[start + length] == data[position + length] {
+                    length += 1;
+                }
+                if length > best_length {
+                    best_length = length;
+                    best_distance = distance;
+                    if length == maximum {
+                        break;
+                    }
+                }
+                candidate = previous[start % WINDOW_SIZE];
+                chain -= 1;
+            }
+        }
+
+        if best_length >= MIN_MATCH {
+            writer.write_match(best_length, best_distance);
+            for index in position..position + best_length {
+                insert(index, &mut head, &mut previous);
+            }
+            position += best_length;
+        } else {
+            writer.write_symbol(u32::from(data[position]));
+            insert(position, &mut head, &mut previous);
+            position += 1;
+        }
+    }
+    writer.write_symbol(256);
+    writer.finish()
+}
+
+/// Uncompressed blocks of at most 65535 bytes
+fn store(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() + data.len() / 65535 * 5 + 5);
+    let mut blocks = data.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        output.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        output.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let length = block.len() as u16;
+        output.extend_from_slice(&[length as u8, (length >> 8) as u8]);
+        output.extend_from_slice(&[!length as u8, (!length >> 8) as u8]);
+        output.extend_from_slice(block);
+    }
+    output
+}
+
+fn get_hash(data: &[u8]) -> usize {
+    ((data[0] as usize) << 10 ^ (data[1] as usize) << 5 ^ data[2] as usize) & (HASH_SIZE - 1)
+}
+
+/// The zlib format, used by the `deflate` HTTP content coding
+pub fn zlib(data: &[u8], level: u32) -> Vec<u8> {
+    // Header flags are chosen so the two bytes are a multiple of 31
+    let flags = match level {
+        0 | 1 => 0x01,
+        7..=9 => 0xda,
+        _ => 0x9c,
+    };
+    let mut output = vec![0x78, flags];
+    output.extend_from_slice(&compress(data, level));
+    output.extend_from_slice(&get_adler32(data).to_be_bytes());
+    output
+}
+
+/// The gzip format without file name or modification time
+pub fn gzip(data: &[u8], level: u32) -> Vec<u8> {
+    let mut output = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    output.extend_from_slice(&compress(data, level));
+    output.extend_from_slice(&get_crc32(data).to_le_bytes());
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    output
+}
+
+fn get_adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    // Sums can grow for 5552 bytes before they must be reduced
+    for block in data.chunks(5552) {
+        for byte in block {
+            a += u32::from(*byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+fn get_crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (index, entry) in table.iter_mut().enumerate() {
+        let mut value = index as u32;
+        for _ in 0..8 {
+            value = if value & 1 == 1 {
+                0xedb8_8320 ^ (value >> 1)
+            } else {
+                value >> 1
+            };
+        }
+        *entry = value;
+    }
+    !data.iter().fold(!0, |crc, byte| {
+        table[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decoder for stored and fixed Huffman blocks, the only kinds written by `compress`
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut output: Vec<u8> = Vec::new();
+        let mut bit = 0;
+        let read_bits = |count: usize, bit: &mut usize| -> usize {
+            let mut value = 0;
+            for index in 0..count {
+                value |= ((data[*bit / 8] >> (*bit % 8)) as usize & 1) << index;
+                *bit += 1;
+            }
+            value
+        };
+        loop {
+            let is_final = read_bits(1, &mut bit) == 1;
+            match read_bits(2, &mut bit) {
+                0 => {
+                    let start = (bit + 7) / 8;
+                    let length = data[start] as usize | (data[start + 1] as usize) << 8;
+                    output.extend_from_slice(&data[start + 4..start + 4 + length]);
+                    bit = (start + 4 + length) * 8;
+                }
+                1 => loop {
+                    // Codes are read most significant bit first
+                    let mut code = 0;
+                    let mut length = 0;
+                    let symbol = loop {
+                        code = code << 1 | read_bits(1, &mut bit);
+                        length += 1;
+                        match (length, code) {
+                            (7, 0..=0x17) => break code + 256,
+                            (8, 0x30..=0xbf) => break code - 0x30,
+                            (8, 0xc0..=0xc7) => break code - 0xc0 + 280,
+                            (9, 0x190..=0x1ff) => break code - 0x190 + 144,
+                            _ => {}
+                        }
+                    };
+                    if symbol < 256 {
+                        output.push(symbol as u8);
+                    } else if symbol == 256 {
+                        break;
+                    } else {
+                        let index = symbol - 257;
+                        let length = LENGTH_BASE[index] as usize
+                            + read_bits(LENGTH_EXTRA[index] as usize, &mut bit);
+                        let mut index = 0;
+                        for _ in 0..5 {
+                            index = index << 1 | read_bits(1, &mut bit);
+                        }
+                        let distance = DISTANCE_BASE[index] as usize
+                            + read_bits(DISTANCE_EXTRA[index] as usize, &mut bit);
+                        for _ in 0..length {
+                            let byte = output[output.len() - distance];
+                            output.push(byte);
+                        }
+                    }
+                },
+                block_type => panic!("Unexpected block type {}", block_type),
+            }
+            if is_final {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress() {
+        let text = "<p>Hello, hello, hello! This repeats, this repeats.</p>\n".repeat(200);
+        let mut binary: Vec<u8> = Vec::new();
+        let mut state: u32 = 1;
+        for _ in 0..70_000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            binary.push((state >> 16) as u8);
+        }
+        for data in &[Vec::new(), b"a".to_vec(), text.into_bytes(), binary] {
+            for level in &[0, 1, 6, 9] {
+                let compressed = compress(data, *level);
+                assert_eq!(&inflate(&compressed), data);
+            }
+        }
+        let text = "<p>Hello, hello, hello! This repeats, this repeats.</p>\n".repeat(200);
+        assert!(compress(text.as_bytes(), 6).len() < text.len() / 20);
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(get_crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(get_adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let data = b"Hello, Hello, Hello";
+        let gzip = gzip(data, 6);
+        assert_eq!(&gzip[..3], &[0x1f, 0x8b, 8]);
+        assert_eq!(&gzip[gzip.len() - 4..], &[19, 0, 0, 0]);
+        assert_eq!(inflate(&gzip[10..gzip.len() - 8]), data.to_vec());
+
+        for level in 0..10 {
+            let zlib = zlib(data, level);
+            assert_eq!((u16::from(zlib[0]) << 8 | u16::from(zlib[1])) % 31, 0);
+            assert_eq!(&zlib[zlib.len() - 4..], &get_adler32(data).to_be_bytes());
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! # Feedback levels
+//! Which feedback messages are written, messages of disabled levels are skipped before they are
+//! formatted so they cost nothing in the request path.
+
+use std::str::FromStr;
+
+/// # Levels from quietest to most verbose, every level includes the ones before it
+/// ```rust
+/// use milstian_internet_framework::feedback::Level;
+/// use std::str::FromStr;
+/// assert_eq!(Level::from_str("error"), Ok(Level::Error));
+/// assert!(Level::Info >= Level::Error);
+/// assert!(Level::Off < Level::Error);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Level {
+    Off,
+    Error,
+    Info,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(level: &str) -> Result<Level, String> {
+        match level.to_lowercase().as_str() {
+            "off" => Ok(Level::Off),
+            "error" => Ok(Level::Error),
+            "info" => Ok(Level::Info),
+            _ => Err(format!("Unknown feedback level {}", level)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use {Application, Config};
+
+    #[test]
+    fn test_lazy_feedback() {
+        let application = Application::new(Config {
+            feedback_level: Level::Error,
+            ..Config::for_tests()
+        });
+        let is_formatted = Cell::new(false);
+        application.info(|| {
+            is_formatted.set(true);
+            "Skipped".to_string()
+        });
+        assert!(!is_formatted.get());
+        application.error(|| {
+            is_formatted.set(true);
+            "Written".to_string()
+        });
+        assert!(is_formatted.get());
+        assert_eq!(Level::from_str("INFO"), Ok(Level::Info));
+        assert!(Level::from_str("debug").is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! # Signing keyring
+//! Keys for signing cookies, sessions and tokens, shared by all clones of the application. The
+//! newest key signs and all keys verify, so rotating keys doesn't invalidate earlier signatures.
+
+use std::sync::{Arc, RwLock};
+
+use digest;
+use secret::Secret;
+
+/// # Keys with the one used for signing first
+#[derive(Clone, Debug)]
+pub struct Keyring {
+    keys: Arc<RwLock<Vec<(String, Secret)>>>,
+}
+
+impl Keyring {
+    pub fn new() -> Keyring {
+        Keyring {
+            keys: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Sign with a new key from now on, the `retained` newest older keys are kept for
+    /// verification and the rest are removed
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::keyring::Keyring;
+    /// use milstian_internet_framework::secret::Secret;
+    /// let keyring = Keyring::new();
+    /// keyring.rotate("2019-01", Secret::new("first"), 1).unwrap();
+    /// let signed = keyring.sign("user=42").unwrap();
+    /// keyring.rotate("2019-02", Secret::new("second"), 1).unwrap();
+    /// assert_eq!(keyring.verify(&signed), Some("user=42".to_string()));
+    /// ```
+    pub fn rotate(&self, id: &str, secret: Secret, retained: usize) -> Result<(), String> {
+        if id.is_empty() || id.contains('.') {
+            return Err(format!("Invalid signing key id '{}'", id));
+        }
+        let mut keys = match self.keys.write() {
+            Ok(keys) => keys,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if keys.iter().any(|(existing, _)| existing == id) {
+            return Err(format!("Signing key id '{}' is already used", id));
+        }
+        keys.insert(0, (id.to_string(), secret));
+        keys.truncate(retained + 1);
+        Ok(())
+    }
+
+    /// Stop accepting signatures of a key, for example when it leaked
+    pub fn remove(&self, id: &str) {
+        match self.keys.write() {
+            Ok(mut keys) => keys.retain(|(existing, _)| existing != id),
+            Err(poisoned) => poisoned.into_inner().retain(|(existing, _)| existing != id),
+        }
+    }
+
+    /// Ids of the keys, the one used for signing first
+    pub fn get_ids(&self) -> Vec<String> {
+        match self.keys.read() {
+            Ok(keys) => keys.iter().map(|(id, _)| id.clone()).collect(),
+            Err(poisoned) => poisoned
+                .into_inner()
+                .iter()
+                .map(|(id, _)| id.clone())
+                .collect(),
+        }
+    }
+
+    /// Value followed by the id of the signing key and a HMAC-SHA-384 signature
+    pub fn sign(&self, value: &str) -> Result<String, String> {
+        let (id, secret) = match self.get_key(None) {
+            Some(key) => key,
+            None => return Err("There is no signing key".to_string()),
+        };
+        let signed = format!("{}.{}", value, id);
+        let signature = Keyring::get_signature(&secret, &signed);
+        Ok(format!("{}.{}", signed, signature))
+    }
+
+    /// Value of a signed value when any key in the keyring signed it
+    pub fn verify(&self, signed: &str) -> Option<String> {
+        let mut parts = signed.rsplitn(3, '.');
+        let signature = parts.next()?;
+        let id = parts.next()?;
+        let value = parts.next()?;
+        let (_, secret) = self.get_key(Some(id))?;
+        let expected = Keyring::get_signature(&secret, &format!("{}.{}", value, id));
+
+        // Compare in constant time so signatures can't be guessed byte by byte
+        if expected.len() != signature.len() {
+            return None;
+        }
+        let difference = expected
+            .bytes()
+            .zip(signature.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b));
+        if difference == 0 {
+            Some(value.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Key with id, or the signing key
+    fn get_key(&self, id: Option<&str>) -> Option<(String, Secret)> {
+        let keys = match self.keys.read() {
+            Ok(keys) => keys,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match id {
+            Some(id) => keys.iter().find(|(existing, _)| existing == id).cloned(),
+            None => keys.first().cloned(),
+        }
+    }
+
+    fn get_signature(secret: &Secret, signed: &str) -> String {
+        digest::to_hex(&digest::hmac_sha384(
+            secret.get_value().as_bytes(),
+            signed.as_bytes(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        let keyring = Keyring::new();
+        assert!(keyring.sign("a").is_err());
+        keyring.rotate("1", Secret::new("one"), 1).unwrap();
+        let first = keyring.sign("a.b").unwrap();
+        assert!(first.starts_with("a.b.1."));
+        assert_eq!(keyring.verify(&first), Some("a.b".to_string()));
+
+        // Older keys verify until they are no longer retained
+        keyring.rotate("2", Secret::new("two"), 1).unwrap();
+        let second = keyring.sign("a.b").unwrap();
+        assert!(second.starts_with("a.b.2."));
+        assert_eq!(keyring.verify(&first), Some("a.b".to_string()));
+        assert!(keyring.rotate("2", Secret::new("again"), 1).is_err());
+        assert!(keyring.rotate("3.0", Secret::new("three"), 1).is_err());
+        keyring.rotate("3", Secret::new("three"), 1).unwrap();
+        assert_eq!(keyring.get_ids(), vec!["3".to_string(), "2".to_string()]);
+        assert_eq!(keyring.verify(&first), None);
+        assert_eq!(keyring.verify(&second), Some("a.b".to_string()));
+        keyring.remove("2");
+        assert_eq!(keyring.verify(&second), None);
+
+        // Tampered values and signatures are rejected
+        let signed = keyring.sign("user=42").unwrap();
+        assert_eq!(keyring.verify(&signed.replace("42", "43")), None);
+        assert_eq!(keyring.verify(&signed[..signed.len() - 1]), None);
+        assert_eq!(keyring.verify("user=42"), None);
+        assert!(!format!("{:?}", keyring).contains("three"));
+    }
+}
@@ -21,23 +21,61 @@
 //! extern crate milstian_internet_framework;
 //! ```
 
+#[cfg(feature = "brotli")]
+extern crate brotli;
 extern crate milstian_feedback;
 extern crate milstian_http;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
+pub mod access;
+pub mod access_log;
 pub mod application_layer;
+pub mod audit;
+mod base64;
+pub mod check;
+pub mod crash;
+mod deflate;
+mod digest;
+pub mod feedback;
+pub mod keyring;
 pub mod mime;
+mod random;
+pub mod remember;
 pub mod response;
+#[cfg(feature = "scaffold")]
+pub mod scaffold;
+pub mod secret;
+pub mod session;
 mod thread;
 pub mod transport_layer;
 
-extern crate chrono;
-
 use std::env;
 use std::fs;
 use std::path::PathBuf;
 
+use application_layer::http::cache_control::CacheControl;
+use application_layer::http::compression;
+use application_layer::http::forwarded::Cidr;
+use feedback::Level;
+use keyring::Keyring;
 use milstian_feedback::Feedback;
-use response::tcp::http::{error, file_not_found, filesystem, ResponderInterface};
+use remember::Tokens;
+#[cfg(feature = "chaos")]
+use response::tcp::chaos::Chaos;
+use response::tcp::connections::Table;
+use response::tcp::http::assets::Assets;
+use response::tcp::http::canned::Canned;
+use response::tcp::http::context::Context;
+use response::tcp::http::filesystem::EntityTagSource;
+use response::tcp::http::{admin, error, file_not_found, filesystem, ResponderInterface};
+use response::tcp::jobs::Jobs;
+use response::tcp::latency::Latencies;
+use response::tcp::raw::HandlerInterface;
+use response::tcp::trace::Ring;
+use session::Sessions;
 
 #[derive(Clone, Debug)]
 /// # Holds application configuration, can be created in different ways.
@@ -48,15 +86,84 @@ use response::tcp::http::{error, file_not_found, filesystem, ResponderInterface}
 /// assert!(config.is_err()); // Expected fail since environment variables is missing
 /// ```
 pub struct Config {
+    pub access_log_file: Option<String>,
+    /// Path prefix of the admin endpoints for loopback clients, disabled when not set
+    pub admin_path: Option<String>,
+    /// Accept HTTP/0.9 simple requests that only have a method and path or just a path
+    pub allow_http_0_9: bool,
+    /// File audit events of authentication and access decisions are appended to
+    pub audit_log_file: Option<String>,
+    /// Cache-Control of static files by extension, see `cache_policy`, other files may be
+    /// cached for 30 days
+    pub cache_policies: Vec<(String, CacheControl)>,
+    /// Faults injected into responses, only with the `chaos` feature
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<Chaos>,
+    /// Level from 1 to 9 of gzip and deflate response compression, 0 disables compression
+    pub compression_level: u32,
+    /// Smaller response bodies are sent uncompressed
+    pub compression_min_bytes: usize,
+    /// Media types of compressed responses, `text/*` matches every text type
+    pub compression_types: Vec<String>,
+    pub content_security_policy: Option<String>,
+    /// File a crash report is written to when a thread panics, the process is aborted afterwards
+    pub crash_report_file: Option<String>,
+    pub deploy_epoch: Option<String>,
+    /// Files relative to the file-system root sent as body of error responses without one,
+    /// by status code, see `error_page`
+    pub error_pages: Vec<(u16, String)>,
     pub feedback_error_file: Option<String>,
     pub feedback_info_file: Option<String>,
+    /// Feedback of less severe levels is not written, `Level::Off` writes none
+    pub feedback_level: Level,
     pub file_not_found_file: String,
-    pub filesystem_directory_index: String,
+    /// Path prefixes below which directories without a index file are listed or not, the
+    /// longest matching prefix applies and directories are not listed by default
+    pub filesystem_autoindex: Vec<(String, bool)>,
+    /// Files sent for requests of a directory, the first that exists in it is sent
+    pub filesystem_directory_index: Vec<String>,
+    /// Entity-tags of files are computed from their modification time and size or content
+    pub filesystem_etag: EntityTagSource,
+    /// Canonical path of the directory static files are sent from, files outside of it are not
     pub filesystem_root: String,
+    /// Names of response headers written first and in this order, before the others in the
+    /// order of `response::DEFAULT_HEADER_ORDER`
+    pub header_order: Vec<String>,
+    /// Number of requests answered on a connection before it's closed, 1 disables keep-alive
+    pub keep_alive_max_requests: usize,
+    /// Seconds a connection may be idle before it's closed, 0 waits forever
+    pub keep_alive_timeout: u64,
+    pub max_body_bytes: usize,
+    pub max_header_bytes: usize,
+    pub max_request_line: usize,
+    /// Media types of static files by extension, see `media_type`, they take precedence over
+    /// the built-in types of `mime::from_filename`
+    pub media_types: Vec<(String, String)>,
+    /// Reset connections of clients over a limit or dropped by chaos instead of closing them
+    /// gracefully, saves ports held in `TIME_WAIT` but the error response may be lost
+    pub reset_on_force_close: bool,
     pub server_limit: usize,
     pub server_host: String,
-    pub server_port: u32,
+    /// Sent in the `Server` header of every response, no header is sent when not set
+    pub server_name: Option<String>,
+    pub server_port: u16,
+    /// Most sessions a identity can have at the same time, the least recently seen are ended
+    /// when it's exceeded, unlimited when not set
+    pub session_limit: Option<usize>,
+    /// Bodies larger than this and chunked bodies are read by responders with
+    /// `request::Message::body_reader` instead of being buffered, disabled when not set
+    pub stream_body_bytes: Option<usize>,
+    /// Check that the bodies of responses match their `Content-Length` and that chunked
+    /// bodies are terminated, mismatches panic instead of only closing the connection
+    pub strict_framing: bool,
     pub tcp_limit: usize,
+    /// Number of request summaries kept for post-mortem debugging, 0 disables tracing
+    pub trace_capacity: usize,
+    /// Networks of proxies whose `Forwarded` and `X-Forwarded-For` headers are believed
+    pub trusted_proxies: Vec<Cidr>,
+    /// Seconds each worker gets to finish its job when the server shuts down, workers still
+    /// running after it are reported with their requests and left behind, 0 waits forever
+    pub worker_shutdown_timeout: u64,
 }
 
 impl Config {
@@ -83,6 +190,31 @@ impl Config {
         }
     }
 
+    /// Parse and validate a TCP port to listen on
+    pub fn get_server_port(value: &str) -> Result<u16, String> {
+        let port: u64 = match value.trim().parse() {
+            Ok(port) => port,
+            Err(_) => {
+                return Err(format!(
+                    "Failed to parse server port '{}', expected a number between 1 and 65535!",
+                    value
+                ))
+            }
+        };
+        if port == 0 {
+            return Err(
+                "Server port 0 is reserved, expected a number between 1 and 65535!".to_string(),
+            );
+        }
+        if port > 65535 {
+            return Err(format!(
+                "Server port {} is out of range, expected a number between 1 and 65535!",
+                port
+            ));
+        }
+        Ok(port as u16)
+    }
+
     /// This method takes a vector of strings and creates a config struct based on argument vector
     pub fn from_env_args(args: Vec<String>) -> Result<Config, String> {
         if args.len() < 8 {
@@ -92,31 +224,229 @@ impl Config {
             Ok(num) => num,
             Err(_) => return Err("Failed to parse server limit!".to_string()),
         };
-        let server_port: u32 = match args[2].clone().parse() {
-            Ok(num) => num,
-            Err(_) => return Err("Failed to parse server port!".to_string()),
-        };
         let server_host = args[1].clone();
-        let filesystem_directory_index = args[4].clone();
-        let filesystem_root = Config::get_canonical_root(&args[5])?;
+        if server_host.trim().is_empty() {
+            return Err("Server host can not be empty!".to_string());
+        }
+        let server_port = Config::get_server_port(&args[2])?;
+        let filesystem_directory_index = args[4]
+            .split(',')
+            .map(|index| index.trim().to_string())
+            .filter(|index| !index.is_empty())
+            .collect();
+        // A missing root is warned about when the server starts, built-in pages are served
+        let filesystem_root = Config::get_canonical_root(&args[5])
+            .unwrap_or_else(|_| args[5].trim_end_matches('/').to_string());
         let file_not_found_file = args[6].clone();
         let tcp_limit: usize = match args[7].clone().parse() {
             Ok(num) => num,
             Err(_) => return Err("Failed to parse TCP limit!".to_string()),
         };
         Ok(Config {
+            access_log_file: Option::None,
+            admin_path: Option::None,
+            allow_http_0_9: false,
+            audit_log_file: Option::None,
+            cache_policies: Vec::new(),
+            #[cfg(feature = "chaos")]
+            chaos: Option::None,
+            compression_level: 6,
+            compression_min_bytes: 1024,
+            compression_types: compression::DEFAULT_TYPES
+                .iter()
+                .map(|media_type| media_type.to_string())
+                .collect(),
+            content_security_policy: Option::None,
+            crash_report_file: Option::None,
+            deploy_epoch: Option::None,
+            error_pages: Vec::new(),
             feedback_error_file: Option::None,
             feedback_info_file: Option::None,
+            feedback_level: Level::Info,
+            filesystem_autoindex: Vec::new(),
             filesystem_directory_index,
+            filesystem_etag: EntityTagSource::Metadata,
             file_not_found_file,
             filesystem_root,
+            header_order: Vec::new(),
+            keep_alive_max_requests: 100,
+            keep_alive_timeout: 5,
+            max_body_bytes: tcp_limit,
+            max_header_bytes: 8192,
+            max_request_line: 8192,
+            media_types: Vec::new(),
+            reset_on_force_close: false,
             server_limit,
             server_host,
+            server_name: Some("Milstian".to_string()),
             server_port,
+            session_limit: Option::None,
+            stream_body_bytes: Option::None,
+            strict_framing: cfg!(debug_assertions),
             tcp_limit,
+            trace_capacity: 100,
+            trusted_proxies: Vec::new(),
+            worker_shutdown_timeout: 30,
         })
     }
 
+    /// Configuration the unit tests start from, they change the fields they test
+    #[cfg(test)]
+    pub fn for_tests() -> Config {
+        Config {
+            access_log_file: Option::None,
+            admin_path: Option::None,
+            allow_http_0_9: false,
+            audit_log_file: Option::None,
+            cache_policies: Vec::new(),
+            #[cfg(feature = "chaos")]
+            chaos: Option::None,
+            compression_level: 0,
+            compression_min_bytes: 1024,
+            compression_types: Vec::new(),
+            content_security_policy: Option::None,
+            crash_report_file: Option::None,
+            deploy_epoch: Option::None,
+            error_pages: Vec::new(),
+            feedback_error_file: Option::None,
+            feedback_info_file: Option::None,
+            feedback_level: Level::Info,
+            filesystem_autoindex: Vec::new(),
+            filesystem_directory_index: vec!["index.htm".to_string()],
+            filesystem_etag: EntityTagSource::Metadata,
+            file_not_found_file: "404.htm".to_string(),
+            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
+            header_order: Vec::new(),
+            keep_alive_max_requests: 100,
+            keep_alive_timeout: 5,
+            max_body_bytes: 1024,
+            max_header_bytes: 8192,
+            max_request_line: 8192,
+            media_types: Vec::new(),
+            reset_on_force_close: false,
+            server_host: "localhost".to_string(),
+            server_name: Option::None,
+            server_limit: 4,
+            server_port: 4040,
+            session_limit: Option::None,
+            stream_body_bytes: Option::None,
+            strict_framing: false,
+            tcp_limit: 1024,
+            trace_capacity: 0,
+            trusted_proxies: Vec::new(),
+            worker_shutdown_timeout: 30,
+        }
+    }
+
+    /// Send a file relative to the file-system root as body of error responses with `status`,
+    /// replaces the page registered before for it
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::Config;
+    /// let config = Config::from_env_args(vec![
+    ///     String::from("ignore this"),
+    ///     String::from("127.0.0.1"),
+    ///     String::from("7878"),
+    ///     String::from("4"),
+    ///     String::from("index.htm"),
+    ///     String::from("./html/"),
+    ///     String::from("404.htm"),
+    ///     String::from("1024"),
+    /// ])
+    /// .unwrap()
+    /// .error_page(404, "404.htm")
+    /// .error_page(404, "errors/404.htm");
+    /// assert_eq!(config.error_pages, vec![(404, "errors/404.htm".to_string())]);
+    /// ```
+    pub fn error_page(mut self, status: u16, filename: &str) -> Config {
+        self.error_pages.retain(|(existing, _)| *existing != status);
+        self.error_pages.push((status, filename.to_string()));
+        self
+    }
+
+    /// Send static files with `extension` with a Cache-Control, replaces the policy set before
+    /// for it. Extensions are compared case-insensitively.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::application_layer::http::cache_control::CacheControl;
+    /// use milstian_internet_framework::Config;
+    /// let config = Config::from_env_args(vec![
+    ///     String::from("ignore this"),
+    ///     String::from("127.0.0.1"),
+    ///     String::from("7878"),
+    ///     String::from("4"),
+    ///     String::from("index.htm"),
+    ///     String::from("./html/"),
+    ///     String::from("404.htm"),
+    ///     String::from("1024"),
+    /// ])
+    /// .unwrap()
+    /// .cache_policy("css", CacheControl::new().public().max_age(31536000).immutable())
+    /// .cache_policy("htm", CacheControl::new().no_cache());
+    /// assert_eq!(config.cache_policies.len(), 2);
+    /// ```
+    pub fn cache_policy(mut self, extension: &str, cache_control: CacheControl) -> Config {
+        let extension = extension.trim_start_matches('.').to_lowercase();
+        self.cache_policies
+            .retain(|(existing, _)| *existing != extension);
+        self.cache_policies.push((extension, cache_control));
+        self
+    }
+
+    /// Send static files with `extension` with a Content-Type, replaces the type set before for
+    /// it. Extensions are compared case-insensitively.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::Config;
+    /// let config = Config::from_env_args(vec![
+    ///     String::from("ignore this"),
+    ///     String::from("127.0.0.1"),
+    ///     String::from("7878"),
+    ///     String::from("4"),
+    ///     String::from("index.htm"),
+    ///     String::from("./html/"),
+    ///     String::from("404.htm"),
+    ///     String::from("1024"),
+    /// ])
+    /// .unwrap()
+    /// .media_type(".md", "text/markdown; charset=utf-8")
+    /// .media_type("htm", "text/html; charset=utf-8");
+    /// assert_eq!(config.media_types.len(), 2);
+    /// ```
+    pub fn media_type(mut self, extension: &str, media_type: &str) -> Config {
+        let extension = extension.trim_start_matches('.').to_lowercase();
+        self.media_types.retain(|(existing, _)| *existing != extension);
+        self.media_types.push((extension, media_type.to_string()));
+        self
+    }
+
+    /// List directories without a index file at `path` and below when `enabled`, replaces the
+    /// setting made before for the path. Settings for longer paths apply to their subtrees.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::Config;
+    /// let config = Config::from_env_args(vec![
+    ///     String::from("ignore this"),
+    ///     String::from("127.0.0.1"),
+    ///     String::from("7878"),
+    ///     String::from("4"),
+    ///     String::from("index.htm"),
+    ///     String::from("./html/"),
+    ///     String::from("404.htm"),
+    ///     String::from("1024"),
+    /// ])
+    /// .unwrap()
+    /// .autoindex("/", true)
+    /// .autoindex("/private/", false);
+    /// assert_eq!(config.filesystem_autoindex.len(), 2);
+    /// ```
+    pub fn autoindex(mut self, path: &str, enabled: bool) -> Config {
+        self.filesystem_autoindex
+            .retain(|(existing, _)| existing.as_str() != path);
+        self.filesystem_autoindex.push((path.to_string(), enabled));
+        self
+    }
+
     /// This method collects arguments from environment and passes them on to method from_env_args
     /// # Example
     /// ```rust
@@ -139,27 +469,158 @@ impl Config {
 /// ```
 #[derive(Clone, Debug)]
 pub struct Application {
+    access_log: Option<access_log::Writer>,
+    assets: Assets,
+    audit_log: Option<access_log::Writer>,
+    canned: Canned,
     config: Config,
+    connections: Table,
+    context: Context,
     feedback: Feedback,
+    jobs: Jobs,
+    keyring: Keyring,
+    latencies: Latencies,
+    remember_tokens: Tokens,
+    sessions: Sessions,
+    trace: Ring,
 }
 
 impl Application {
     pub fn new(config: Config) -> Application {
         let feedback = Feedback::new(config.feedback_error_file.clone(), config.feedback_info_file.clone());
+        let open_log = |filename: &Option<String>| match filename {
+            Some(filename) => match access_log::Writer::from_file(filename) {
+                Ok(writer) => Some(writer),
+                Err(error) => {
+                    feedback.error(error);
+                    None
+                }
+            },
+            None => None,
+        };
+        let access_log = open_log(&config.access_log_file);
+        let audit_log = open_log(&config.audit_log_file);
+        let sessions = Sessions::new(config.session_limit);
+        let trace = Ring::new(config.trace_capacity);
         Application {
+            access_log,
+            assets: Assets::new(),
+            audit_log,
+            canned: Canned::new(&config),
             config,
+            connections: Table::new(),
+            context: Context::new(),
             feedback,
+            jobs: Jobs::new(),
+            keyring: Keyring::new(),
+            latencies: Latencies::new(),
+            remember_tokens: Tokens::new(sessions.clone()),
+            sessions,
+            trace,
+        }
+    }
+
+    pub fn get_access_log(&self) -> Option<&access_log::Writer> {
+        self.access_log.as_ref()
+    }
+
+    /// Write a access log line, falls back to info feedback when no access log is configured
+    pub fn log_access(&self, line: String) {
+        match &self.access_log {
+            Some(access_log) => access_log.log(line),
+            None => self.info(|| line),
+        }
+    }
+
+    pub fn get_audit_log(&self) -> Option<&access_log::Writer> {
+        self.audit_log.as_ref()
+    }
+
+    /// Write a audit event to the audit log, falls back to info feedback when no audit log is
+    /// configured
+    pub fn audit(&self, event: audit::Event) {
+        match &self.audit_log {
+            Some(audit_log) => audit_log.log(event.to_json()),
+            None => self.info(|| format!("Audit - {}", event.to_json())),
         }
     }
 
+    /// Write error feedback when the level includes errors, `message` is only called then
+    pub fn error<F: FnOnce() -> String>(&self, message: F) {
+        if self.config.feedback_level >= Level::Error {
+            self.feedback.error(message());
+        }
+    }
+
+    /// Write info feedback when the level includes info, `message` is only called then
+    pub fn info<F: FnOnce() -> String>(&self, message: F) {
+        if self.config.feedback_level >= Level::Info {
+            self.feedback.info(message());
+        }
+    }
+
+    pub fn get_assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    /// Get the error responses serialized at startup, shared by all workers
+    pub fn get_canned(&self) -> &Canned {
+        &self.canned
+    }
+
     pub fn get_config(&self) -> &Config {
         &self.config
     }
 
+    /// Get the table of open connections, shared by all workers
+    pub fn get_connections(&self) -> &Table {
+        &self.connections
+    }
+
+    /// Get the context of the request currently being processed
+    pub fn get_context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+
     pub fn get_feedback(&self) -> &Feedback {
         &self.feedback
     }
 
+    /// Get the statistics of the jobs run by the workers, shared by all workers
+    pub fn get_jobs(&self) -> &Jobs {
+        &self.jobs
+    }
+
+    /// Get the keys for signing cookies and tokens, shared by all workers so rotating a key
+    /// applies to all of them
+    pub fn get_keyring(&self) -> &Keyring {
+        &self.keyring
+    }
+
+    /// Get the request latency histograms, shared by all workers
+    pub fn get_latencies(&self) -> &Latencies {
+        &self.latencies
+    }
+
+    /// Get the remembered logins, shared by all workers
+    pub fn get_remember_tokens(&self) -> &Tokens {
+        &self.remember_tokens
+    }
+
+    /// Get the open sessions, shared by all workers
+    pub fn get_sessions(&self) -> &Sessions {
+        &self.sessions
+    }
+
+    /// Get the summaries of the last requests, shared by all workers
+    pub fn get_trace(&self) -> &Ring {
+        &self.trace
+    }
+
     /// Create a new TCP HTTP application
     /// # Example
     /// ```rust,should_panic
@@ -180,6 +641,15 @@ impl Application {
         transport_layer::TCP::http(&self, responders)
     }
 
+    /// Create a new TCP HTTP application that also serves raw protocol handlers
+    pub fn tcp_http_and_raw(
+        &self,
+        responders: Vec<Box<ResponderInterface + Send>>,
+        handlers: Vec<Box<HandlerInterface + Send>>,
+    ) {
+        transport_layer::TCP::http_and_raw(&self, responders, handlers)
+    }
+
     /// Create a new TCP HTTP application with the legacy responders
     /// # Example
     /// ```rust,should_panic
@@ -193,6 +663,7 @@ impl Application {
     // TODO Use example that doesn't panic
     pub fn tcp_http_with_legacy_responders(&self) {
         let responders: Vec<Box<ResponderInterface + Send>> = vec![
+            Box::new(admin::Responder::new()),
             Box::new(filesystem::Responder::new()),
             Box::new(file_not_found::Responder::new()),
             Box::new(error::Responder::new()),
@@ -215,6 +686,7 @@ impl Application {
     ) {
         let responders: Vec<Box<ResponderInterface + Send>> = vec![
             custom,
+            Box::new(admin::Responder::new()),
             Box::new(filesystem::Responder::new()),
             Box::new(file_not_found::Responder::new()),
             Box::new(error::Responder::new()),
@@ -261,5 +733,57 @@ mod tests {
             String::from("404.htm"),
         ]);
         assert!(response.is_err());
+
+        // Directory index files are separated by commas
+        let response = Config::from_env_args(vec![
+            String::from("ignore this"),
+            String::from("127.0.0.1"),
+            String::from("7878"),
+            String::from("4"),
+            String::from("index.html, index.htm,"),
+            String::from("./html/"),
+            String::from("404.htm"),
+            String::from("1024"),
+        ]);
+        assert_eq!(
+            response.unwrap().filesystem_directory_index,
+            vec!["index.html".to_string(), "index.htm".to_string()]
+        );
+
+        // Missing root is served with built-in pages
+        let response = Config::from_env_args(vec![
+            String::from("ignore this"),
+            String::from("127.0.0.1"),
+            String::from("7878"),
+            String::from("4"),
+            String::from("index.htm"),
+            String::from("./htmls/"),
+            String::from("404.htm"),
+            String::from("1024"),
+        ]);
+        assert_eq!(response.unwrap().filesystem_root, "./htmls");
+
+        // Port is out of range
+        let response = Config::from_env_args(vec![
+            String::from("ignore this"),
+            String::from("127.0.0.1"),
+            String::from("78780"),
+            String::from("4"),
+            String::from("index.htm"),
+            String::from("./html/"),
+            String::from("404.htm"),
+            String::from("1024"),
+        ]);
+        assert!(response.is_err());
+    }
+
+    #[test]
+    fn get_server_port() {
+        assert_eq!(Config::get_server_port("8080"), Ok(8080));
+        assert_eq!(Config::get_server_port(" 65535 "), Ok(65535));
+        assert!(Config::get_server_port("0").unwrap_err().contains("reserved"));
+        assert!(Config::get_server_port("65536").unwrap_err().contains("out of range"));
+        assert!(Config::get_server_port("99999999999999999999").is_err());
+        assert!(Config::get_server_port("http").is_err());
     }
 }
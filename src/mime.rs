@@ -1,5 +1,169 @@
 //! # Handling MIME types
 
+use std::fmt;
+use std::path::Path;
+
+use application_layer::http::header::parse;
+use application_layer::http::header::Headers;
+
+/// # Media type with parameters, see RFC 7231 section 3.1.1.1
+/// Type and subtype are lower-cased since they are case-insensitive.
+/// ```rust
+/// use milstian_internet_framework::mime::MediaType;
+/// let media_type = MediaType::from_str("Text/HTML; charset=UTF-8").unwrap();
+/// assert_eq!(media_type.get_essence(), "text/html");
+/// assert_eq!(media_type.get_parameter("Charset"), Some(&"UTF-8".to_string()));
+/// assert!(MediaType::from_str("text/*").unwrap().matches(&media_type));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaType {
+    pub parameters: Vec<(String, String)>,
+    pub subtype: String,
+    pub top_level: String,
+}
+
+impl MediaType {
+    pub fn new(top_level: &str, subtype: &str) -> MediaType {
+        MediaType {
+            parameters: Vec::new(),
+            subtype: subtype.to_lowercase(),
+            top_level: top_level.to_lowercase(),
+        }
+    }
+
+    pub fn from_str(value: &str) -> Option<MediaType> {
+        let (essence, parameters) = parse::get_parameters(value);
+        let mut parts = essence.splitn(2, '/');
+        let top_level = parts.next()?;
+        let subtype = parts.next()?;
+        if !Headers::is_token(top_level) || !Headers::is_token(subtype) {
+            return None;
+        }
+        let mut media_type = MediaType::new(top_level, subtype);
+        media_type.parameters = parameters;
+        Some(media_type)
+    }
+
+    /// Media type of file based on it's extension
+    pub fn from_filename(filename: &str) -> MediaType {
+        MediaType::from_str(&from_filename(filename))
+            .unwrap_or_else(|| MediaType::new("application", "octet-stream"))
+    }
+
+    /// Type and subtype without parameters
+    pub fn get_essence(&self) -> String {
+        format!("{}/{}", self.top_level, self.subtype)
+    }
+
+    /// Get value of parameter, names are compared case-insensitively
+    pub fn get_parameter(&self, name: &str) -> Option<&String> {
+        parse::get_parameter(&self.parameters, name)
+    }
+
+    pub fn essence_eq(&self, other: &MediaType) -> bool {
+        self.top_level == other.top_level && self.subtype == other.subtype
+    }
+
+    /// Does this media range like `text/*` include other media type?
+    /// Parameters of the range must also be present on the other media type.
+    pub fn matches(&self, other: &MediaType) -> bool {
+        if self.top_level != "*" && self.top_level != other.top_level {
+            return false;
+        }
+        if self.subtype != "*" && self.subtype != other.subtype {
+            return false;
+        }
+        self.parameters.iter().all(|(name, value)| {
+            other
+                .get_parameter(name)
+                .map_or(false, |other_value| other_value.eq_ignore_ascii_case(value))
+        })
+    }
+
+    /// Number of specific parts, more specific media ranges take precedence
+    fn get_precedence(&self) -> usize {
+        match (self.top_level.as_str(), self.subtype.as_str()) {
+            ("*", _) => 0,
+            (_, "*") => 1,
+            _ => 2 + self.parameters.len(),
+        }
+    }
+
+    /// Select the available media type the client prefers according to it's `Accept` header,
+    /// see RFC 7231 section 5.3.2. Available types are assumed to be in server preference order.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::mime::MediaType;
+    /// let available = vec![MediaType::new("text", "html"), MediaType::new("application", "json")];
+    /// let selected = MediaType::negotiate("application/*;q=0.9, text/html;q=0.5", &available);
+    /// assert_eq!(selected, Some(&available[1]));
+    /// ```
+    pub fn negotiate<'a>(accept: &str, available: &'a [MediaType]) -> Option<&'a MediaType> {
+        let ranges: Vec<(MediaType, u16)> = parse::get_quality_items(accept)
+            .into_iter()
+            .filter_map(|item| {
+                let mut range = MediaType::from_str(&item.value)?;
+                range.parameters = item.parameters;
+                Some((range, item.quality))
+            })
+            .collect();
+        if ranges.is_empty() {
+            return available.first();
+        }
+
+        let mut selected: Option<(&MediaType, u16)> = None;
+        for media_type in available {
+            let quality = ranges
+                .iter()
+                .filter(|(range, _)| range.matches(media_type))
+                .max_by_key(|(range, _)| range.get_precedence())
+                .map_or(0, |(_, quality)| *quality);
+            if quality > 0 && selected.map_or(true, |(_, best)| quality > best) {
+                selected = Some((media_type, quality));
+            }
+        }
+        selected.map(|(media_type, _)| media_type)
+    }
+
+    /// Is this media type textual and thereby worth compressing?
+    pub fn is_compressible(&self) -> bool {
+        if self.top_level == "text" {
+            return true;
+        }
+        if self.subtype.ends_with("+json") || self.subtype.ends_with("+xml") {
+            return true;
+        }
+        match self.get_essence().as_str() {
+            "application/ecmascript"
+            | "application/javascript"
+            | "application/json"
+            | "application/typescript"
+            | "application/xml"
+            | "image/svg+xml" => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.get_essence())?;
+        for (name, value) in self.parameters.iter() {
+            if Headers::is_token(value) {
+                write!(formatter, "; {}={}", name, value)?;
+            } else {
+                write!(
+                    formatter,
+                    "; {}=\"{}\"",
+                    name,
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Determine MIME based on filename.
 // # Example
 /// ```rust
@@ -9,84 +173,113 @@
 /// ```
 // @see https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Complete_list_of_MIME_types
 pub fn from_filename(filename: &str) -> String {
+    from_filename_with(filename, &[])
+}
+
+/// Determine MIME based on filename, `media_types` of extensions take precedence over the
+/// built-in ones. Extensions are compared case-insensitively.
+/// # Example
+/// ```rust
+/// use milstian_internet_framework::mime;
+/// let media_types = vec![("js".to_string(), "text/javascript".to_string())];
+/// assert_eq!(mime::from_filename_with("app.JS", &media_types), "text/javascript");
+/// assert_eq!(mime::from_filename_with("app.css", &media_types), "text/css");
+/// ```
+pub fn from_filename_with(filename: &str, media_types: &[(String, String)]) -> String {
     let mut mime = "application/octet-stream";
-    let parts: Vec<&str> = filename.rsplitn(2, ".").collect();
-    if !parts.is_empty() {
-        if let Some(extension) = parts.get(0) {
-            let extension: String = extension.to_lowercase();
-            mime = match extension.as_ref() {
-                "aac" => "audio/aac",
-                "abw" => "application/x-abiword",
-                "avi" => "video/x-msvideo",
-                "azw" => "application/vnd.amazon.ebook",
-                "bmp" => "image/bmp",
-                "bz" => "application/x-bzip",
-                "bz2" => "application/x-bzip2",
-                "csh" => "application/x-csh",
-                "css" => "text/css",
-                "csv" => "text/csv",
-                "doc" => "application/msword",
-                "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-                "eot" => "application/vnd.ms-fontobject",
-                "epub" => "application/epub+zip",
-                "es" => "application/ecmascript",
-                "gif" => "image/gif",
-                "html" => "text/html",
-                "htm" => "text/html",
-                "ico" => "image/x-icon",
-                "ics" => "text/calendar",
-                "jar" => "application/java-archive",
-                "jpeg" => "image/jpeg",
-                "jpg" => "image/jpg",
-                "js" => "application/javascript",
-                "json" => "application/json",
-                "mid" => "audo/midi",
-                "midi" => "audo/midi",
-                "mpeg" => "video/mpeg",
-                "mpkg" => "application/vnd.apple.installer+xml",
-                "odp" => "application/vnd.oasis.opendocument.presentation",
-                "ods" => "application/vnd.oasis.opendocument.spreadsheet",
-                "odt" => "application/vnd.oasis.opendocument.text",
-                "oga" => "audio/ogg",
-                "ogv" => "video/ogg",
-                "ogx" => "application/ogg",
-                "otf" => "font/otf",
-                "png" => "image/png",
-                "pdf" => "application/pdf",
-                "ppt" => "application/vnd.ms-powerpoint",
-                "pptx" => {
-                    "application/vnd.openxmlformats-officedocument.presentationml.presentation"
-                }
-                "rar" => "application/x-rar-compressed",
-                "rtf" => "application/rtf",
-                "sh" => "application/x-sh",
-                "svg" => "image/svg+xml",
-                "swf" => "application/x-shockwave-flash",
-                "tar" => "application/x-tar",
-                "tif" => "image/tiff",
-                "tiff" => "image/tiff",
-                "ts" => "application/typescript",
-                "ttf" => "font/ttf",
-                "txt" => "text/plain",
-                "vsd" => "application/vnd.visio",
-                "wav" => "audio/wav",
-                "weba" => "audio/webm",
-                "webm" => "video/webm",
-                "webp" => "image/webp",
-                "woff" => "font/woff",
-                "woff2" => "font/woff2",
-                "xhtml" => "application/xhtml+xml",
-                "xls" => "application/vnd.ms-excel",
-                "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-                "xml" => "application/xml",
-                "xul" => "application/vnd.mozilla.xul+xml",
-                "zip" => "application/zip",
-                "3gp" => "video/3gpp",
-                "3g2" => "video/3gpp2",
-                "7z" => "application/x-7z-compressed",
-                _ => "application/octet-stream",
-            };
+    if let Some(extension) = Path::new(filename).extension() {
+        let extension: String = extension.to_string_lossy().to_lowercase();
+        if let Some((_, media_type)) = media_types
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(&extension))
+        {
+            return media_type.to_string();
         }
+        mime = match extension.as_ref() {
+            "aac" => "audio/aac",
+            "abw" => "application/x-abiword",
+            "apng" => "image/apng",
+            "avi" => "video/x-msvideo",
+            "avif" => "image/avif",
+            "azw" => "application/vnd.amazon.ebook",
+            "bmp" => "image/bmp",
+            "bz" => "application/x-bzip",
+            "bz2" => "application/x-bzip2",
+            "csh" => "application/x-csh",
+            "css" => "text/css",
+            "csv" => "text/csv",
+            "doc" => "application/msword",
+            "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "eot" => "application/vnd.ms-fontobject",
+            "epub" => "application/epub+zip",
+            "es" => "application/ecmascript",
+            "gif" => "image/gif",
+            "gz" => "application/gzip",
+            "html" => "text/html",
+            "htm" => "text/html",
+            "ico" => "image/x-icon",
+            "ics" => "text/calendar",
+            "jar" => "application/java-archive",
+            "jpeg" => "image/jpeg",
+            "jpg" => "image/jpeg",
+            "js" => "application/javascript",
+            "json" => "application/json",
+            "jsonld" => "application/ld+json",
+            "map" => "application/json",
+            "md" => "text/markdown",
+            "mid" => "audio/midi",
+            "midi" => "audio/midi",
+            "mjs" => "application/javascript",
+            "mp3" => "audio/mpeg",
+            "mp4" => "video/mp4",
+            "mpeg" => "video/mpeg",
+            "mpkg" => "application/vnd.apple.installer+xml",
+            "odp" => "application/vnd.oasis.opendocument.presentation",
+            "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+            "odt" => "application/vnd.oasis.opendocument.text",
+            "oga" => "audio/ogg",
+            "ogg" => "audio/ogg",
+            "ogv" => "video/ogg",
+            "ogx" => "application/ogg",
+            "opus" => "audio/opus",
+            "otf" => "font/otf",
+            "png" => "image/png",
+            "pdf" => "application/pdf",
+            "ppt" => "application/vnd.ms-powerpoint",
+            "pptx" => {
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+            }
+            "rar" => "application/x-rar-compressed",
+            "rtf" => "application/rtf",
+            "sh" => "application/x-sh",
+            "svg" => "image/svg+xml",
+            "swf" => "application/x-shockwave-flash",
+            "tar" => "application/x-tar",
+            "tif" => "image/tiff",
+            "tiff" => "image/tiff",
+            "ts" => "application/typescript",
+            "ttf" => "font/ttf",
+            "txt" => "text/plain",
+            "vsd" => "application/vnd.visio",
+            "wasm" => "application/wasm",
+            "wav" => "audio/wav",
+            "weba" => "audio/webm",
+            "webm" => "video/webm",
+            "webmanifest" => "application/manifest+json",
+            "webp" => "image/webp",
+            "woff" => "font/woff",
+            "woff2" => "font/woff2",
+            "xhtml" => "application/xhtml+xml",
+            "xls" => "application/vnd.ms-excel",
+            "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "xml" => "application/xml",
+            "xul" => "application/vnd.mozilla.xul+xml",
+            "zip" => "application/zip",
+            "3gp" => "video/3gpp",
+            "3g2" => "video/3gpp2",
+            "7z" => "application/x-7z-compressed",
+            _ => "application/octet-stream",
+        };
     }
     mime.to_string()
 }
@@ -107,5 +300,64 @@ mod tests
             from_filename("random.random"),
             "application/octet-stream"
         );
+        assert_eq!(from_filename("/www.example/README"), "application/octet-stream");
+        assert_eq!(from_filename("photo.JPG"), "image/jpeg");
+        assert_eq!(from_filename("module.wasm"), "application/wasm");
+
+        let media_types = vec![("wasm".to_string(), "application/x-custom".to_string())];
+        assert_eq!(from_filename_with("module.wasm", &media_types), "application/x-custom");
+        assert_eq!(from_filename_with("font.woff2", &media_types), "font/woff2");
+    }
+
+    #[test]
+    fn test_media_type() {
+        let media_type = MediaType::from_str("multipart/form-data; boundary=\"a b\"").unwrap();
+        assert_eq!(media_type.top_level, "multipart");
+        assert_eq!(media_type.subtype, "form-data");
+        assert_eq!(media_type.get_parameter("boundary"), Some(&"a b".to_string()));
+        assert_eq!(
+            media_type.to_string(),
+            "multipart/form-data; boundary=\"a b\""
+        );
+        assert!(media_type.essence_eq(&MediaType::new("Multipart", "Form-Data")));
+        assert_eq!(MediaType::from_str("text"), None);
+        assert_eq!(MediaType::from_str("text/ html"), None);
+        assert_eq!(MediaType::from_filename("style.css").to_string(), "text/css");
+    }
+
+    #[test]
+    fn test_matches() {
+        let html = MediaType::from_str("text/html; level=1").unwrap();
+        assert!(MediaType::from_str("*/*").unwrap().matches(&html));
+        assert!(MediaType::from_str("text/*").unwrap().matches(&html));
+        assert!(MediaType::from_str("text/html;level=1").unwrap().matches(&html));
+        assert!(!MediaType::from_str("text/html;level=2").unwrap().matches(&html));
+        assert!(!MediaType::from_str("image/*").unwrap().matches(&html));
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let available = vec![
+            MediaType::new("application", "json"),
+            MediaType::new("text", "html"),
+        ];
+        assert_eq!(MediaType::negotiate("", &available), Some(&available[0]));
+        assert_eq!(
+            MediaType::negotiate("text/html, */*;q=0.1", &available),
+            Some(&available[1])
+        );
+        assert_eq!(
+            MediaType::negotiate("*/*, application/json;q=0", &available),
+            Some(&available[1])
+        );
+        assert_eq!(MediaType::negotiate("image/png", &available), None);
+    }
+
+    #[test]
+    fn test_is_compressible() {
+        assert!(MediaType::new("text", "css").is_compressible());
+        assert!(MediaType::new("application", "ld+json").is_compressible());
+        assert!(MediaType::new("image", "svg+xml").is_compressible());
+        assert!(!MediaType::new("image", "png").is_compressible());
     }
 }
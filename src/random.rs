@@ -0,0 +1,71 @@
+//! # Pseudo-random numbers
+//! Based on the randomly keyed hasher of the standard library so no external crate is needed.
+//! They are not fit for secrets, those are read from the operating system with `secure_bytes`.
+
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Get the next random number
+pub fn next_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(COUNTER.fetch_add(1, Ordering::Relaxed));
+    if let Ok(duration) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u64(duration.as_secs());
+        hasher.write_u32(duration.subsec_nanos());
+    }
+    hasher.finish()
+}
+
+/// Get a vector of random bytes of specified length
+pub fn bytes(length: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(length);
+    while bytes.len() < length {
+        let number = next_u64();
+        for index in 0..8 {
+            if bytes.len() < length {
+                bytes.push((number >> (index * 8)) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+/// Get random bytes from the random source of the operating system, for secrets like session
+/// ids and tokens. Fails when it's not available instead of falling back to `bytes`.
+pub fn secure_bytes(length: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0; length];
+    File::open("/dev/urandom")
+        .and_then(|mut source| source.read_exact(&mut bytes))
+        .map_err(|error| format!("Failed to read random bytes from the system, error: {}", error))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_u64() {
+        assert_ne!(next_u64(), next_u64());
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(bytes(0).len(), 0);
+        assert_eq!(bytes(13).len(), 13);
+        assert_ne!(bytes(16), bytes(16));
+    }
+
+    #[test]
+    fn test_secure_bytes() {
+        assert_eq!(secure_bytes(0).unwrap().len(), 0);
+        assert_eq!(secure_bytes(32).unwrap().len(), 32);
+        assert_ne!(secure_bytes(16).unwrap(), secure_bytes(16).unwrap());
+    }
+}
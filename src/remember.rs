@@ -0,0 +1,242 @@
+//! # Remember-me tokens
+//! Persistent logins with a series and a token that changes on every use, see "Improved
+//! Persistent Login Cookie Best Practice". A token used twice means the cookie was stolen, then
+//! all remembered logins and sessions of the identity are ended.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use application_layer::http::cookie::{SameSite, SetCookie};
+use digest;
+use random;
+use session::{Session, Sessions};
+
+/// Name of the cookie holding the series and token
+pub const COOKIE: &str = "remember";
+
+/// Remembered logins last 30 days from their last use
+pub const MAX_AGE: u64 = 2592000;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Series {
+    expires: SystemTime,
+    identity: String,
+    token_hash: String,
+}
+
+/// What a remember-me cookie was good for
+#[derive(Clone, Debug, PartialEq)]
+pub enum Remembered {
+    /// A new session was started, send the cookie with the next token
+    Login(Session, SetCookie),
+    /// The series is unknown or expired, remove the cookie
+    Unknown,
+    /// A old token of the series of the identity was used
+    Stolen(String),
+}
+
+/// # Remembered logins by series, shared by all clones of the application
+#[derive(Clone, Debug)]
+pub struct Tokens {
+    series: Arc<Mutex<HashMap<String, Series>>>,
+    sessions: Sessions,
+}
+
+impl Tokens {
+    /// Sessions are started in and ended from `sessions`
+    pub fn new(sessions: Sessions) -> Tokens {
+        Tokens {
+            series: Arc::new(Mutex::new(HashMap::new())),
+            sessions,
+        }
+    }
+
+    /// Remember a identity after it logged in, returns the cookie to send. Series and tokens
+    /// are read from the random source of the operating system, fails when it's not available.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::remember::{Remembered, Tokens};
+    /// use milstian_internet_framework::session::Sessions;
+    /// let tokens = Tokens::new(Sessions::new(None));
+    /// let cookie = tokens.remember("alice").unwrap();
+    /// let value = cookie.to_string()[9..].split(';').next().unwrap().to_string();
+    /// match tokens.login(&value, "10.0.0.1", "Firefox").unwrap() {
+    ///     Remembered::Login(session, _) => assert_eq!(session.identity, "alice"),
+    ///     _ => panic!("Expected a login"),
+    /// }
+    /// assert_eq!(
+    ///     tokens.login(&value, "10.0.0.2", "Curl"),
+    ///     Ok(Remembered::Stolen("alice".to_string()))
+    /// );
+    /// ```
+    pub fn remember(&self, identity: &str) -> Result<SetCookie, String> {
+        let series = digest::to_hex(&random::secure_bytes(16)?);
+        Tokens::get_cookie(&mut self.lock(), &series, identity)
+    }
+
+    /// Start a session from the value of a remember-me cookie, the token is replaced so each
+    /// cookie can only be used once. Fails when no session could be started.
+    pub fn login(
+        &self,
+        value: &str,
+        client: &str,
+        user_agent: &str,
+    ) -> Result<Remembered, String> {
+        let mut parts = value.splitn(2, ':');
+        let series = parts.next().unwrap_or("");
+        let token = match parts.next() {
+            Some(token) => token,
+            None => return Ok(Remembered::Unknown),
+        };
+
+        // The token is checked and replaced under one lock so it can't be used twice at once
+        let mut all_series = self.lock();
+        let existing = match all_series.get(series) {
+            Some(existing) => existing.clone(),
+            None => return Ok(Remembered::Unknown),
+        };
+        if existing.expires < SystemTime::now() {
+            all_series.remove(series);
+            return Ok(Remembered::Unknown);
+        }
+        if existing.token_hash != Tokens::get_hash(token) {
+            all_series.retain(|_, other| other.identity != existing.identity);
+            drop(all_series);
+            self.end_sessions(&existing.identity);
+            return Ok(Remembered::Stolen(existing.identity));
+        }
+        let cookie = Tokens::get_cookie(&mut all_series, series, &existing.identity)?;
+        drop(all_series);
+        let session = self
+            .sessions
+            .create(&existing.identity, client, user_agent)?;
+        Ok(Remembered::Login(session, cookie))
+    }
+
+    /// Stop remembering the login of a cookie, for example when logging out
+    pub fn forget(&self, value: &str) -> SetCookie {
+        let series = value.split(':').next().unwrap_or("");
+        self.lock().remove(series);
+        SetCookie::removal(COOKIE).path("/")
+    }
+
+    /// Stop remembering all logins of identity and end it's sessions
+    pub fn forget_identity(&self, identity: &str) {
+        self.lock().retain(|_, series| series.identity != identity);
+        self.end_sessions(identity);
+    }
+
+    fn end_sessions(&self, identity: &str) {
+        for session in self.sessions.get_by_identity(identity) {
+            self.sessions.remove(&session.id);
+        }
+    }
+
+    /// Store a new token for series in `all_series` and build it's cookie
+    fn get_cookie(
+        all_series: &mut HashMap<String, Series>,
+        series: &str,
+        identity: &str,
+    ) -> Result<SetCookie, String> {
+        let token = digest::to_hex(&random::secure_bytes(16)?);
+        all_series.insert(
+            series.to_string(),
+            Series {
+                expires: SystemTime::now() + Duration::from_secs(MAX_AGE),
+                identity: identity.to_string(),
+                token_hash: Tokens::get_hash(&token),
+            },
+        );
+        Ok(SetCookie::new(COOKIE, &format!("{}:{}", series, token))
+            .path("/")
+            .max_age(MAX_AGE as i64)
+            .http_only()
+            .same_site(SameSite::Lax))
+    }
+
+    /// Only hashes of tokens are kept so a copy of the store can't be used to log in
+    fn get_hash(token: &str) -> String {
+        digest::to_hex(&digest::sha384(token.as_bytes()))
+    }
+
+    fn lock(&self) -> MutexGuard<HashMap<String, Series>> {
+        match self.series.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+    use std::thread;
+
+    fn get_value(cookie: &SetCookie) -> String {
+        let cookie = cookie.to_string();
+        let pair = cookie.split(';').next().unwrap();
+        pair[COOKIE.len() + 1..].to_string()
+    }
+
+    #[test]
+    fn test_login() {
+        let sessions = Sessions::new(None);
+        let tokens = Tokens::new(sessions.clone());
+        let first = get_value(&tokens.remember("alice").unwrap());
+        let other = get_value(&tokens.remember("bob").unwrap());
+        assert_eq!(tokens.login("unknown", "10.0.0.1", "Firefox"), Ok(Remembered::Unknown));
+        assert_eq!(tokens.login("unknown:token", "10.0.0.1", "Firefox"), Ok(Remembered::Unknown));
+
+        // Every login rotates the token but keeps the series
+        let second = match tokens.login(&first, "10.0.0.1", "Firefox").unwrap() {
+            Remembered::Login(session, cookie) => {
+                assert_eq!(session.identity, "alice");
+                assert!(cookie.to_string().contains("HttpOnly"));
+                get_value(&cookie)
+            }
+            remembered => panic!("Expected login, got {:?}", remembered),
+        };
+        assert_ne!(first, second);
+        assert_eq!(first.split(':').next(), second.split(':').next());
+        assert_eq!(sessions.get_by_identity("alice").len(), 1);
+
+        // Reusing the first token ends everything of the identity
+        assert_eq!(
+            tokens.login(&first, "10.0.0.2", "Curl"),
+            Ok(Remembered::Stolen("alice".to_string()))
+        );
+        assert!(sessions.get_by_identity("alice").is_empty());
+        assert_eq!(tokens.login(&second, "10.0.0.1", "Firefox"), Ok(Remembered::Unknown));
+        match tokens.login(&other, "10.0.0.3", "Safari").unwrap() {
+            Remembered::Login(session, _) => assert_eq!(session.identity, "bob"),
+            remembered => panic!("Expected login, got {:?}", remembered),
+        }
+
+        tokens.forget(&other);
+        assert_eq!(tokens.login(&other, "10.0.0.3", "Safari"), Ok(Remembered::Unknown));
+    }
+
+    #[test]
+    fn test_concurrent_login() {
+        let tokens = Tokens::new(Sessions::new(None));
+        let value = get_value(&tokens.remember("alice").unwrap());
+        let barrier = Arc::new(Barrier::new(8));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let (tokens, value, barrier) = (tokens.clone(), value.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    tokens.login(&value, "10.0.0.1", "Firefox")
+                })
+            })
+            .collect();
+        let logins = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap().unwrap())
+            .filter(|remembered| matches!(remembered, Remembered::Login(_, _)))
+            .count();
+        assert_eq!(logins, 1);
+    }
+}
@@ -0,0 +1,137 @@
+//! # TCP fault injection
+//! Used for testing how clients handle a misbehaving server, i.e. validating retry behavior.
+//! Should never be enabled in production.
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use application_layer::http::response;
+use application_layer::http::status::HttpStatus;
+use random;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+    InternalServerError,
+    Latency(Duration),
+    Reset,
+    Truncate,
+}
+
+/// # Holds the percentage of requests that should be affected by each fault
+/// ```rust
+/// use milstian_internet_framework::response::tcp::chaos::Chaos;
+/// use std::time::Duration;
+/// let mut chaos = Chaos::new();
+/// chaos.error_percentage = 10;
+/// chaos.latency = Duration::from_millis(500);
+/// chaos.latency_percentage = 25;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Chaos {
+    pub error_percentage: u8,
+    pub latency: Duration,
+    pub latency_percentage: u8,
+    pub reset_percentage: u8,
+    pub truncate_percentage: u8,
+}
+
+impl Chaos {
+    pub fn new() -> Chaos {
+        Chaos {
+            error_percentage: 0,
+            latency: Duration::from_secs(0),
+            latency_percentage: 0,
+            reset_percentage: 0,
+            truncate_percentage: 0,
+        }
+    }
+
+    fn is_hit(percentage: u8) -> bool {
+        percentage > 0 && random::next_u64() % 100 < percentage as u64
+    }
+
+    /// Randomly select faults for a request, latency can be combined with one of the other faults
+    pub fn get_faults(&self) -> Vec<Fault> {
+        let mut faults = Vec::new();
+        if Chaos::is_hit(self.latency_percentage) {
+            faults.push(Fault::Latency(self.latency));
+        }
+        if Chaos::is_hit(self.reset_percentage) {
+            faults.push(Fault::Reset);
+        } else if Chaos::is_hit(self.error_percentage) {
+            faults.push(Fault::InternalServerError);
+        } else if Chaos::is_hit(self.truncate_percentage) {
+            faults.push(Fault::Truncate);
+        }
+        faults
+    }
+
+    /// Apply faults to a response, returns None if connection should be closed without a response
+    pub fn apply(faults: &[Fault], response: Vec<u8>) -> Option<Vec<u8>> {
+        let mut response = response;
+        for fault in faults {
+            match fault {
+                Fault::InternalServerError => {
+                    let mut headers: HashMap<String, String> = HashMap::new();
+                    headers.insert("Content-Length".to_string(), "0".to_string());
+                    response = response::Message::new(
+                        "HTTP/1.1".to_string(),
+                        HttpStatus::InternalServerError.to_string(),
+                        headers,
+                        Vec::new(),
+                    ).to_bytes();
+                }
+                Fault::Latency(duration) => {
+                    thread::sleep(*duration);
+                }
+                Fault::Reset => {
+                    return None;
+                }
+                Fault::Truncate => {
+                    let length = response.len() / 2;
+                    response.truncate(length);
+                }
+            }
+        }
+        Some(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_faults() {
+        let chaos = Chaos::new();
+        assert!(chaos.get_faults().is_empty());
+
+        let mut chaos = Chaos::new();
+        chaos.latency = Duration::from_millis(5);
+        chaos.latency_percentage = 100;
+        chaos.reset_percentage = 100;
+        chaos.error_percentage = 100;
+        assert_eq!(
+            chaos.get_faults(),
+            vec![Fault::Latency(Duration::from_millis(5)), Fault::Reset]
+        );
+
+        let mut chaos = Chaos::new();
+        chaos.truncate_percentage = 100;
+        assert_eq!(chaos.get_faults(), vec![Fault::Truncate]);
+    }
+
+    #[test]
+    fn test_apply() {
+        let response = b"HTTP/1.1 200 OK\r\n\r\nabcdef".to_vec();
+        assert_eq!(Chaos::apply(&[], response.clone()), Some(response.clone()));
+        assert_eq!(Chaos::apply(&[Fault::Reset], response.clone()), None);
+        assert_eq!(
+            Chaos::apply(&[Fault::Truncate], response.clone()),
+            Some(b"HTTP/1.1 200".to_vec())
+        );
+        let given_response = Chaos::apply(&[Fault::InternalServerError], response.clone()).unwrap();
+        assert!(given_response.starts_with(b"HTTP/1.1 500 Internal Server Error\r\n"));
+    }
+}
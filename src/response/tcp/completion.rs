@@ -0,0 +1,160 @@
+//! # Request completion event
+//! Describes everything about how a request on a TCP stream was handled in one feedback line.
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// Response was written to the stream
+    Answered,
+    /// Response was dropped by chaos faults
+    Dropped,
+    /// No responder answered the request
+    Unanswered,
+    /// Request could not be decoded as HTTP
+    Undecoded,
+    /// Writing the response failed
+    WriteFailed,
+}
+
+/// # Timings, sizes, status and client of a request
+/// ```rust
+/// use milstian_internet_framework::response::tcp::completion::{Completion, Outcome};
+/// use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+/// let mut completion = Completion::new(
+///     SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080),
+///     1,
+/// );
+/// completion.status = "200 OK".to_string();
+/// assert!(completion.to_string().contains("status=\"200 OK\""));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub client: SocketAddr,
+    pub keep_alive: bool,
+    pub outcome: Outcome,
+    /// Bytes that did not fit in the TCP limit
+    pub overflow_bytes: u64,
+    pub read_time: Duration,
+    pub received_bytes: u64,
+    /// Identifier of the request, empty when it could not be decoded
+    pub request_id: String,
+    pub request_line: String,
+    /// Position of request on it's connection, starting at 1
+    pub request_number: usize,
+    pub respond_time: Duration,
+    /// Path of the request target
+    pub route: String,
+    pub sent_bytes: usize,
+    pub status: String,
+    /// Time from the start of waiting until the first byte of the request arrived
+    pub wait_time: Duration,
+    pub write_time: Duration,
+}
+
+impl Completion {
+    pub fn new(client: SocketAddr, request_number: usize) -> Completion {
+        Completion {
+            client,
+            keep_alive: false,
+            outcome: Outcome::Undecoded,
+            overflow_bytes: 0,
+            read_time: Duration::from_secs(0),
+            received_bytes: 0,
+            request_id: String::new(),
+            request_line: String::new(),
+            request_number,
+            respond_time: Duration::from_secs(0),
+            route: String::new(),
+            sent_bytes: 0,
+            status: String::new(),
+            wait_time: Duration::from_secs(0),
+            write_time: Duration::from_secs(0),
+        }
+    }
+
+    /// Time from the first byte of the request until the response was written
+    pub fn get_total_time(&self) -> Duration {
+        self.read_time + self.respond_time + self.write_time
+    }
+
+    /// Status of a encoded HTTP response
+    pub fn get_status(response: &[u8]) -> String {
+        let line_end = response
+            .iter()
+            .position(|byte| *byte == b'\r')
+            .unwrap_or(response.len());
+        let line = String::from_utf8_lossy(&response[..line_end]);
+        match line.find(' ') {
+            Some(position) => line[position + 1..].to_string(),
+            None => String::new(),
+        }
+    }
+
+    fn get_milliseconds(duration: Duration) -> f64 {
+        duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0
+    }
+}
+
+impl fmt::Display for Completion {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "HTTP completed client={} request_id={} request={:?} route={:?} status={:?} \
+             outcome={:?} received={} overflow={} sent={} wait_ms={:.3} read_ms={:.3} \
+             respond_ms={:.3} write_ms={:.3} total_ms={:.3} request_number={} keep_alive={}",
+            self.client,
+            if self.request_id.is_empty() { "-" } else { &self.request_id },
+            self.request_line,
+            self.route,
+            self.status,
+            self.outcome,
+            self.received_bytes,
+            self.overflow_bytes,
+            self.sent_bytes,
+            Completion::get_milliseconds(self.wait_time),
+            Completion::get_milliseconds(self.read_time),
+            Completion::get_milliseconds(self.respond_time),
+            Completion::get_milliseconds(self.write_time),
+            Completion::get_milliseconds(self.get_total_time()),
+            self.request_number,
+            self.keep_alive
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_get_status() {
+        assert_eq!(
+            Completion::get_status(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"),
+            "404 Not Found"
+        );
+        assert_eq!(Completion::get_status(b"HTTP/1.1"), "");
+        assert_eq!(Completion::get_status(b""), "");
+    }
+
+    #[test]
+    fn test_display() {
+        let mut completion =
+            Completion::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 80), 2);
+        completion.outcome = Outcome::Answered;
+        completion.request_line = "GET /a\"b HTTP/1.1".to_string();
+        completion.route = "/a\"b".to_string();
+        completion.read_time = Duration::new(1, 500_000);
+        completion.write_time = Duration::new(0, 1_250_000);
+        assert_eq!(
+            completion.to_string(),
+            "HTTP completed client=10.0.0.1:80 request_id=- request=\"GET /a\\\"b HTTP/1.1\" \
+             route=\"/a\\\"b\" status=\"\" outcome=Answered received=0 overflow=0 sent=0 \
+             wait_ms=0.000 read_ms=1000.500 respond_ms=0.000 write_ms=1.250 total_ms=1001.750 \
+             request_number=2 keep_alive=false"
+        );
+    }
+}
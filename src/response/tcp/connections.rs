@@ -0,0 +1,218 @@
+//! # TCP connection table
+//! Keeps track of open connections so they can be inspected while the server is running.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    /// Waiting for the next request on a persistent connection
+    Idle,
+    Reading,
+    Responding,
+    Writing,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self {
+            State::Idle => "idle",
+            State::Reading => "reading",
+            State::Responding => "responding",
+            State::Writing => "writing",
+        };
+        write!(formatter, "{}", state)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Connection {
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub client: SocketAddr,
+    pub id: u64,
+    pub opened: Instant,
+    /// Id of the current or last request
+    pub request_id: String,
+    /// Target of the current or last request
+    pub request_uri: String,
+    pub requests: usize,
+    pub state: State,
+    /// Name of the thread serving the connection
+    pub worker: Option<String>,
+}
+
+impl Connection {
+    /// Time since the connection was accepted
+    pub fn get_duration(&self) -> Duration {
+        self.opened.elapsed()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Connections {
+    connections: HashMap<u64, Connection>,
+    next_id: u64,
+}
+
+/// # Shared by all workers, clones refer to the same table
+/// ```rust
+/// use milstian_internet_framework::response::tcp::connections::{State, Table};
+/// let table = Table::new();
+/// {
+///     let registration = table.open("127.0.0.1:8080".parse().unwrap());
+///     registration.set_state(State::Reading);
+///     assert_eq!(table.get_snapshot()[0].state, State::Reading);
+/// }
+/// assert!(table.get_snapshot().is_empty());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    inner: Arc<Mutex<Connections>>,
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    /// Add a connection, it's removed from the table when the registration is dropped
+    pub fn open(&self, client: SocketAddr) -> Registration {
+        let mut inner = self.lock();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.connections.insert(
+            id,
+            Connection {
+                bytes_received: 0,
+                bytes_sent: 0,
+                client,
+                id,
+                opened: Instant::now(),
+                request_id: String::new(),
+                request_uri: String::new(),
+                requests: 0,
+                state: State::Idle,
+                worker: thread::current().name().map(|name| name.to_string()),
+            },
+        );
+        Registration {
+            id,
+            table: self.clone(),
+        }
+    }
+
+    /// Copy of all open connections, oldest first
+    pub fn get_snapshot(&self) -> Vec<Connection> {
+        let mut connections: Vec<Connection> = self.lock().connections.values().cloned().collect();
+        connections.sort_by_key(|connection| connection.id);
+        connections
+    }
+
+    /// Same as get_snapshot but gives up when the table is locked, used while panicking
+    pub fn try_get_snapshot(&self) -> Option<Vec<Connection>> {
+        let inner = match self.inner.try_lock() {
+            Ok(inner) => inner,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        let mut connections: Vec<Connection> = inner.connections.values().cloned().collect();
+        connections.sort_by_key(|connection| connection.id);
+        Some(connections)
+    }
+
+    fn update<F: FnOnce(&mut Connection)>(&self, id: u64, update: F) {
+        if let Some(connection) = self.lock().connections.get_mut(&id) {
+            update(connection);
+        }
+    }
+
+    /// A panicking worker must not make the table unusable for the others
+    fn lock(&self) -> ::std::sync::MutexGuard<Connections> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// # A open connection in a table
+pub struct Registration {
+    id: u64,
+    table: Table,
+}
+
+impl Registration {
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn add_received(&self, bytes: u64) {
+        self.table
+            .update(self.id, |connection| connection.bytes_received += bytes);
+    }
+
+    pub fn add_sent(&self, bytes: u64) {
+        self.table
+            .update(self.id, |connection| connection.bytes_sent += bytes);
+    }
+
+    /// Start of a new request on the connection
+    pub fn set_request(&self, request_uri: &str, request_id: &str) {
+        self.table.update(self.id, |connection| {
+            connection.request_id = request_id.to_string();
+            connection.request_uri = request_uri.to_string();
+            connection.requests += 1;
+        });
+    }
+
+    pub fn set_state(&self, state: State) {
+        self.table
+            .update(self.id, |connection| connection.state = state);
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.table.lock().connections.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let table = Table::new();
+        let first = table.open("127.0.0.1:1000".parse().unwrap());
+        let second = table.clone().open("[::1]:2000".parse().unwrap());
+        assert_eq!(second.get_id(), first.get_id() + 1);
+
+        first.add_received(10);
+        first.add_received(5);
+        first.add_sent(100);
+        first.set_request("/index.htm", "1-a");
+        first.set_state(State::Writing);
+        let snapshot = table.get_snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].bytes_received, 15);
+        assert_eq!(snapshot[0].bytes_sent, 100);
+        assert_eq!(snapshot[0].request_id, "1-a");
+        assert_eq!(snapshot[0].request_uri, "/index.htm");
+        assert_eq!(snapshot[0].requests, 1);
+        assert_eq!(snapshot[0].state, State::Writing);
+        assert_eq!(snapshot[1].state, State::Idle);
+        assert_eq!(snapshot[1].client.port(), 2000);
+
+        assert_eq!(table.try_get_snapshot(), Some(snapshot));
+        drop(first);
+        let snapshot = table.get_snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].id, second.get_id());
+    }
+}
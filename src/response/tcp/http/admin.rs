@@ -0,0 +1,439 @@
+//! # TCP HTTP Admin response
+//! Used for inspecting a running server, only answers loopback clients. The endpoints
+//! `connections`, `jobs`, `latency`, `metrics` and `trace` are found below the configured admin
+//! path.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use application_layer::http::request;
+use application_layer::http::response;
+use application_layer::http::status::HttpStatus;
+use response::tcp::connections::Connection;
+use response::tcp::jobs;
+use response::tcp::latency::{Snapshot, Summary};
+use response::tcp::trace::Entry;
+
+use response::tcp::http::capabilities::Capabilities;
+use response::tcp::http::ResponderInterface;
+use Application;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Endpoint {
+    Connections,
+    Jobs,
+    Latency,
+    Metrics,
+    Trace,
+}
+
+#[derive(Clone)]
+pub struct Responder {
+    pub endpoint: Option<Endpoint>,
+}
+
+impl Responder {
+    pub fn new() -> Responder {
+        Responder { endpoint: None }
+    }
+
+    /// Find endpoint of a path below the admin path
+    pub fn get_endpoint(admin_path: &str, path: &str) -> Option<Endpoint> {
+        if !path.starts_with(admin_path) {
+            return None;
+        }
+        match path[admin_path.len()..].trim_start_matches('/') {
+            "connections" => Some(Endpoint::Connections),
+            "jobs" => Some(Endpoint::Jobs),
+            "latency" => Some(Endpoint::Latency),
+            "metrics" => Some(Endpoint::Metrics),
+            "trace" => Some(Endpoint::Trace),
+            _ => None,
+        }
+    }
+
+    /// Encode connections as a JSON document
+    pub fn get_connections_json(connections: &[Connection]) -> String {
+        let connections: Vec<String> = connections
+            .iter()
+            .map(|connection| {
+                let duration = connection.get_duration();
+                format!(
+                    "{{\"id\":{},\"client\":{},\"state\":{},\"request_uri\":{},\
+                     \"requests\":{},\"duration_ms\":{},\"bytes_received\":{},\
+                     \"bytes_sent\":{}}}",
+                    connection.id,
+                    Responder::get_json_string(&connection.client.to_string()),
+                    Responder::get_json_string(&connection.state.to_string()),
+                    Responder::get_json_string(&connection.request_uri),
+                    connection.requests,
+                    duration.as_secs() * 1000 + u64::from(duration.subsec_millis()),
+                    connection.bytes_received,
+                    connection.bytes_sent
+                )
+            })
+            .collect();
+        format!("{{\"connections\":[{}]}}", connections.join(","))
+    }
+
+    /// Encode job statistics by class with durations in microseconds as a JSON document
+    pub fn get_jobs_json(snapshot: &[(jobs::Class, jobs::Summary)]) -> String {
+        let classes: Vec<String> = snapshot
+            .iter()
+            .map(|(class, summary)| {
+                format!(
+                    "{}:{{\"panics\":{},\"queue_wait\":{},\"run_time\":{}}}",
+                    Responder::get_json_string(&class.to_string()),
+                    summary.panics,
+                    Responder::get_summary_json(&summary.queue_wait),
+                    Responder::get_summary_json(&summary.run_time)
+                )
+            })
+            .collect();
+        format!("{{\"classes\":{{{}}}}}", classes.join(","))
+    }
+
+    /// Encode latency percentiles in microseconds as a JSON document
+    pub fn get_latency_json(snapshot: &Snapshot) -> String {
+        let routes: Vec<String> = snapshot
+            .routes
+            .iter()
+            .map(|(route, summary)| {
+                format!(
+                    "{}:{}",
+                    Responder::get_json_string(route),
+                    Responder::get_summary_json(summary)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"all\":{},\"routes\":{{{}}}}}",
+            Responder::get_summary_json(&snapshot.all),
+            routes.join(",")
+        )
+    }
+
+    /// Encode latency percentiles in the Prometheus text exposition format
+    pub fn get_metrics_text(snapshot: &Snapshot) -> String {
+        let mut text = String::from(
+            "# HELP milstian_request_duration_seconds Time to read, answer and write requests\n\
+             # TYPE milstian_request_duration_seconds summary\n",
+        );
+        let mut routes: Vec<(String, &Summary)> = vec![(String::new(), &snapshot.all)];
+        routes.extend(snapshot.routes.iter().map(|(route, summary)| {
+            let route = route
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            (format!("route=\"{}\"", route), summary)
+        }));
+        for (labels, summary) in routes {
+            let separator = if labels.is_empty() { "" } else { "," };
+            for (quantile, duration) in &[
+                ("0.5", summary.p50),
+                ("0.95", summary.p95),
+                ("0.99", summary.p99),
+                ("0.999", summary.p999),
+            ] {
+                text.push_str(&format!(
+                    "milstian_request_duration_seconds{{{}{}quantile=\"{}\"}} {}\n",
+                    labels,
+                    separator,
+                    quantile,
+                    Responder::get_seconds(*duration)
+                ));
+            }
+            let labels = if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", labels)
+            };
+            text.push_str(&format!(
+                "milstian_request_duration_seconds_count{} {}\n",
+                labels, summary.count
+            ));
+        }
+        text
+    }
+
+    /// Encode trace entries as a JSON document
+    pub fn get_trace_json(entries: &[Entry]) -> String {
+        let entries: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{{\"sequence\":{},\"summary\":{}}}",
+                    entry.sequence,
+                    Responder::get_json_string(&entry.summary)
+                )
+            })
+            .collect();
+        format!("{{\"requests\":[{}]}}", entries.join(","))
+    }
+
+    fn get_summary_json(summary: &Summary) -> String {
+        format!(
+            "{{\"count\":{},\"p50_us\":{},\"p95_us\":{},\"p99_us\":{},\"p999_us\":{},\
+             \"max_us\":{}}}",
+            summary.count,
+            Responder::get_microseconds(summary.p50),
+            Responder::get_microseconds(summary.p95),
+            Responder::get_microseconds(summary.p99),
+            Responder::get_microseconds(summary.p999),
+            Responder::get_microseconds(summary.max)
+        )
+    }
+
+    fn get_microseconds(duration: Duration) -> u64 {
+        duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+    }
+
+    fn get_seconds(duration: Duration) -> f64 {
+        duration.as_secs() as f64 + f64::from(duration.subsec_micros()) / 1_000_000.0
+    }
+
+    /// Quoted JSON string with quotes, backslashes and control characters escaped
+    pub fn get_json_string(value: &str) -> String {
+        let mut json = String::with_capacity(value.len() + 2);
+        json.push('"');
+        for character in value.chars() {
+            match character {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                character if (character as u32) < 0x20 => {
+                    json.push_str(&format!("\\u{:04x}", character as u32))
+                }
+                character => json.push(character),
+            }
+        }
+        json.push('"');
+        json
+    }
+}
+
+impl ResponderInterface for Responder {
+    fn get_capabilities(&self) -> Capabilities {
+        Capabilities::new()
+            .methods(&[request::Method::Get])
+            .needs_body(false)
+            .cacheable(false)
+    }
+
+    fn matches(
+        &mut self,
+        request_message: &request::Message,
+        application: &Application,
+        socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> bool {
+        self.endpoint = match &application.get_config().admin_path {
+            Some(admin_path)
+                if socket.ip().is_loopback()
+                    && request_message.request_line.method == request::Method::Get =>
+            {
+                Responder::get_endpoint(admin_path, &request_message.request_line.uri.path)
+            }
+            _ => None,
+        };
+        self.endpoint.is_some()
+    }
+
+    fn respond(
+        &self,
+        request_message: &request::Message,
+        application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> Result<response::Message, String> {
+        let protocol = request::Message::get_protocol_text(&request_message.request_line.protocol);
+        let (body, content_type) = match &self.endpoint {
+            Some(Endpoint::Connections) => (
+                Responder::get_connections_json(&application.get_connections().get_snapshot()),
+                "application/json",
+            ),
+            Some(Endpoint::Jobs) => (
+                Responder::get_jobs_json(&application.get_jobs().get_snapshot()),
+                "application/json",
+            ),
+            Some(Endpoint::Latency) => (
+                Responder::get_latency_json(&application.get_latencies().get_snapshot()),
+                "application/json",
+            ),
+            Some(Endpoint::Metrics) => (
+                Responder::get_metrics_text(&application.get_latencies().get_snapshot()),
+                "text/plain; version=0.0.4",
+            ),
+            Some(Endpoint::Trace) => (
+                Responder::get_trace_json(&application.get_trace().get_entries()),
+                "application/json",
+            ),
+            None => return Err("Error: Admin endpoint missing".to_string()),
+        };
+        let body = body.into_bytes();
+        let mut headers: HashMap<String, String> = HashMap::new();
+        headers.insert("Cache-Control".to_string(), "no-store".to_string());
+        headers.insert("Content-Length".to_string(), body.len().to_string());
+        headers.insert("Content-Type".to_string(), content_type.to_string());
+        Ok(response::Message::new(
+            protocol,
+            HttpStatus::Ok.to_string(),
+            headers,
+            body,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use response::tcp::jobs::{Class, Jobs};
+    use response::tcp::latency::Latencies;
+    use std::net::{IpAddr, Ipv4Addr};
+    use Config;
+
+    fn get_application(admin_path: Option<String>) -> Application {
+        Application::new(Config {
+            admin_path,
+            trace_capacity: 4,
+            ..Config::for_tests()
+        })
+    }
+
+    #[test]
+    fn test_matches() {
+        let loopback = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let remote = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 8888);
+        let request_message =
+            request::Message::from_tcp_stream(b"GET /admin/connections HTTP/1.0\r\n\r\n").unwrap();
+
+        let application = get_application(Some("/admin".to_string()));
+        let mut responder = Responder::new();
+        assert!(responder.matches(&request_message, &application, &loopback, &0));
+        assert!(!responder.matches(&request_message, &application, &remote, &0));
+
+        let application = get_application(None);
+        assert!(!responder.matches(&request_message, &application, &loopback, &0));
+    }
+
+    #[test]
+    fn test_get_endpoint() {
+        assert_eq!(
+            Responder::get_endpoint("/admin", "/admin/connections"),
+            Some(Endpoint::Connections)
+        );
+        assert_eq!(Responder::get_endpoint("/admin/", "/admin/trace"), Some(Endpoint::Trace));
+        assert_eq!(Responder::get_endpoint("/admin", "/admin/jobs"), Some(Endpoint::Jobs));
+        assert_eq!(
+            Responder::get_endpoint("/admin", "/admin/latency"),
+            Some(Endpoint::Latency)
+        );
+        assert_eq!(
+            Responder::get_endpoint("/admin", "/admin/metrics"),
+            Some(Endpoint::Metrics)
+        );
+        assert_eq!(Responder::get_endpoint("/admin", "/admin"), None);
+        assert_eq!(Responder::get_endpoint("/admin", "/trace"), None);
+        assert_eq!(Responder::get_endpoint("/admin", "/admin/other"), None);
+    }
+
+    #[test]
+    fn test_respond() {
+        let application = get_application(Some("/admin".to_string()));
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let registration = application.get_connections().open(socket);
+        registration.set_request("/\"quoted\"", "1");
+
+        let request_message =
+            request::Message::from_tcp_stream(b"GET /admin/connections HTTP/1.0\r\n\r\n").unwrap();
+        let mut responder = Responder::new();
+        assert!(responder.matches(&request_message, &application, &socket, &0));
+        let response = responder
+            .respond(&request_message, &application, &socket, &0)
+            .unwrap();
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.starts_with(&format!(
+            "{{\"connections\":[{{\"id\":{},\"client\":\"127.0.0.1:8888\",\"state\":\"idle\",\
+             \"request_uri\":\"/\\\"quoted\\\"\",\"requests\":1,",
+            registration.get_id()
+        )));
+        assert!(body.ends_with("\"bytes_received\":0,\"bytes_sent\":0}]}"));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"application/json".to_string())
+        );
+
+        application.get_trace().record("HTTP completed \"/\"".to_string());
+        let request_message =
+            request::Message::from_tcp_stream(b"GET /admin/trace HTTP/1.0\r\n\r\n").unwrap();
+        assert!(responder.matches(&request_message, &application, &socket, &0));
+        let response = responder
+            .respond(&request_message, &application, &socket, &0)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(response.body).unwrap(),
+            "{\"requests\":[{\"sequence\":1,\"summary\":\"HTTP completed \\\"/\\\"\"}]}"
+        );
+    }
+
+    #[test]
+    fn test_latency() {
+        let latencies = Latencies::new();
+        latencies.record("/", Duration::from_micros(100));
+        latencies.record("/a\"b", Duration::from_micros(1500));
+        let snapshot = latencies.get_snapshot();
+        assert_eq!(
+            Responder::get_latency_json(&snapshot),
+            "{\"all\":{\"count\":2,\"p50_us\":100,\"p95_us\":1500,\"p99_us\":1500,\
+             \"p999_us\":1500,\"max_us\":1500},\"routes\":{\"/\":{\"count\":1,\"p50_us\":100,\
+             \"p95_us\":100,\"p99_us\":100,\"p999_us\":100,\"max_us\":100},\"/a\\\"b\":{\
+             \"count\":1,\"p50_us\":1500,\"p95_us\":1500,\"p99_us\":1500,\"p999_us\":1500,\
+             \"max_us\":1500}}}"
+        );
+
+        let text = Responder::get_metrics_text(&snapshot);
+        assert!(text.contains("milstian_request_duration_seconds{quantile=\"0.5\"} 0.0001\n"));
+        assert!(text.contains("milstian_request_duration_seconds_count 2\n"));
+        assert!(text.contains(
+            "milstian_request_duration_seconds{route=\"/a\\\"b\",quantile=\"0.99\"} 0.0015\n"
+        ));
+        assert!(text.contains("milstian_request_duration_seconds_count{route=\"/\"} 1\n"));
+    }
+
+    #[test]
+    fn test_jobs() {
+        let jobs = Jobs::new();
+        jobs.record(
+            Class::StaticFile,
+            Duration::from_micros(20),
+            Duration::from_micros(100),
+            false,
+        );
+        jobs.record(
+            Class::Connection,
+            Duration::from_micros(5),
+            Duration::from_micros(1500),
+            true,
+        );
+        assert_eq!(
+            Responder::get_jobs_json(&jobs.get_snapshot()),
+            "{\"classes\":{\"connection\":{\"panics\":1,\"queue_wait\":{\"count\":1,\
+             \"p50_us\":5,\"p95_us\":5,\"p99_us\":5,\"p999_us\":5,\"max_us\":5},\
+             \"run_time\":{\"count\":1,\"p50_us\":1500,\"p95_us\":1500,\"p99_us\":1500,\
+             \"p999_us\":1500,\"max_us\":1500}},\"static_file\":{\"panics\":0,\
+             \"queue_wait\":{\"count\":1,\"p50_us\":20,\"p95_us\":20,\"p99_us\":20,\
+             \"p999_us\":20,\"max_us\":20},\"run_time\":{\"count\":1,\"p50_us\":100,\
+             \"p95_us\":100,\"p99_us\":100,\"p999_us\":100,\"max_us\":100}}}}"
+        );
+        assert_eq!(
+            Responder::get_jobs_json(&Jobs::new().get_snapshot()),
+            "{\"classes\":{}}"
+        );
+    }
+
+    #[test]
+    fn test_get_json_string() {
+        assert_eq!(Responder::get_json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+    }
+}
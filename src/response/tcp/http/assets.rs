@@ -0,0 +1,139 @@
+//! # TCP HTTP Assets
+//! Lookup of static files in the file-system root with fingerprints and Subresource Integrity
+//! hashes, so templates can emit `integrity` attributes that always match the served files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use base64;
+use digest;
+use Application;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Asset {
+    pub filename: String,
+    pub fingerprint: String,
+    pub integrity: String,
+    pub length: u64,
+    pub modified: SystemTime,
+    pub path: String,
+}
+
+impl Asset {
+    /// Path with fingerprint as query argument, changes whenever the file contents changes
+    pub fn get_url(&self) -> String {
+        format!("{}?v={}", self.path, self.fingerprint)
+    }
+}
+
+/// # Cache of assets shared between all clones of the application
+#[derive(Clone, Debug)]
+pub struct Assets {
+    cache: Arc<Mutex<HashMap<String, Asset>>>,
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        Assets {
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Lookup asset by request path, hashes are re-calculated when file is modified
+    pub fn get(&self, path: &str, application: &Application) -> Option<Asset> {
+        let root = &application.get_config().filesystem_root;
+        let filename = fs::canonicalize(format!("{}{}", root, path)).ok()?;
+        let filename = filename.to_str()?.to_string();
+        if !filename.starts_with(root) {
+            application.get_feedback().error(format!(
+                "Asset {} is outside of file-system root {}",
+                &filename, root
+            ));
+            return None;
+        }
+
+        let metadata = fs::metadata(&filename).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        let modified = metadata.modified().ok()?;
+        let length = metadata.len();
+
+        if let Ok(cache) = self.cache.lock() {
+            if let Some(asset) = cache.get(path) {
+                if asset.modified == modified && asset.length == length {
+                    return Some(asset.clone());
+                }
+            }
+        }
+
+        let mut data = Vec::new();
+        File::open(&filename).ok()?.read_to_end(&mut data).ok()?;
+        let hash = digest::sha384(&data);
+
+        // Changing the deploy epoch changes all fingerprints
+        let fingerprint = match &application.get_config().deploy_epoch {
+            Some(deploy_epoch) => {
+                let mut seed = hash.clone();
+                seed.extend_from_slice(deploy_epoch.as_bytes());
+                digest::to_hex(&digest::sha384(&seed)[..8])
+            }
+            None => digest::to_hex(&hash[..8]),
+        };
+
+        let asset = Asset {
+            filename,
+            fingerprint,
+            integrity: format!("sha384-{}", base64::encode(&hash)),
+            length,
+            modified,
+            path: path.to_string(),
+        };
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(path.to_string(), asset.clone());
+        }
+        Some(asset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Config;
+
+    #[test]
+    fn test_get() {
+        let config = Config::for_tests();
+        let application = Application::new(config);
+
+        let mut data = Vec::new();
+        File::open("html/css/style.css")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        let expected_integrity = format!("sha384-{}", base64::encode(&digest::sha384(&data)));
+
+        let asset = application
+            .get_assets()
+            .get("/css/style.css", &application)
+            .unwrap();
+        assert_eq!(asset.integrity, expected_integrity);
+        assert_eq!(asset.fingerprint.len(), 16);
+        assert_eq!(asset.get_url(), format!("/css/style.css?v={}", asset.fingerprint));
+
+        // Second lookup is served from cache
+        assert_eq!(
+            application.get_assets().get("/css/style.css", &application),
+            Some(asset)
+        );
+
+        assert_eq!(application.get_assets().get("/css", &application), None);
+        assert_eq!(application.get_assets().get("/missing.css", &application), None);
+        assert_eq!(application.get_assets().get("/../README.md", &application), None);
+    }
+}
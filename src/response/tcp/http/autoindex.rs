@@ -0,0 +1,202 @@
+//! # TCP HTTP Directory listings
+//! HTML pages listing the files of directories without a index file, sent by the filesystem
+//! responder below paths where `Config::autoindex` enables them.
+
+use std::fs;
+use std::path::Path;
+
+use application_layer::http::date;
+use application_layer::http::request;
+use application_layer::http::response;
+use application_layer::http::uri;
+use Config;
+
+/// Whether directories at `path` are listed, the longest configured path prefix decides and
+/// listings are off by default
+/// ```rust
+/// use milstian_internet_framework::response::tcp::http::autoindex;
+/// use milstian_internet_framework::Config;
+/// let config = Config::from_env_args(vec![
+///     String::from("ignore this"),
+///     String::from("127.0.0.1"),
+///     String::from("7878"),
+///     String::from("4"),
+///     String::from("index.htm"),
+///     String::from("./html/"),
+///     String::from("404.htm"),
+///     String::from("1024"),
+/// ])
+/// .unwrap()
+/// .autoindex("/downloads/", true)
+/// .autoindex("/downloads/private/", false);
+/// assert!(autoindex::is_enabled("/downloads/2019/", &config));
+/// assert!(!autoindex::is_enabled("/downloads/private/keys/", &config));
+/// assert!(!autoindex::is_enabled("/", &config));
+/// ```
+pub fn is_enabled(path: &str, config: &Config) -> bool {
+    config
+        .filesystem_autoindex
+        .iter()
+        .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, enabled)| *enabled)
+        .unwrap_or(false)
+}
+
+struct Entry {
+    is_dir: bool,
+    modified: Option<String>,
+    name: String,
+    size: u64,
+}
+
+/// Listing of the requested `directory`, hidden files are left out like the filesystem
+/// responder does
+pub fn get_response(
+    directory: &Path,
+    request_message: &request::Message,
+) -> Result<response::Message, String> {
+    let body = get_listing(directory, &request_message.request_line.uri.path)?;
+    Ok(response::Response::ok()
+        .protocol(&request_message.request_line.protocol)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header("Cache-Control", "no-cache")
+        .body(body)
+        .build())
+}
+
+fn get_listing(directory: &Path, path: &str) -> Result<Vec<u8>, String> {
+    let entries = fs::read_dir(directory).map_err(|error| {
+        format!(
+            "Failed to list directory {}, error: {}",
+            directory.display(),
+            error
+        )
+    })?;
+    let mut entries: Vec<Entry> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            Some(Entry {
+                is_dir: metadata.is_dir(),
+                modified: metadata.modified().ok().map(date::format),
+                name,
+                size: metadata.len(),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+    // Links are absolute so they work whether the path ends with a slash or not
+    let base = if path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/", path)
+    };
+    let title = format!("Index of {}", escape(&uri::decode(&base)));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\
+         <body><h1>{0}</h1>\n<table>\n<tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n",
+        title
+    );
+    if base != "/" {
+        html.push_str(&format!(
+            "<tr><td><a href=\"{}\">../</a></td><td>-</td><td></td></tr>\n",
+            escape(&get_parent(&base))
+        ));
+    }
+    for entry in entries.iter() {
+        let (name, size) = if entry.is_dir {
+            (format!("{}/", entry.name), "-".to_string())
+        } else {
+            (entry.name.clone(), entry.size.to_string())
+        };
+        html.push_str(&format!(
+            "<tr><td><a href=\"{}{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            escape(&base),
+            encode(&name),
+            escape(&name),
+            size,
+            entry
+                .modified
+                .as_ref()
+                .map(|modified| modified.as_str())
+                .unwrap_or("")
+        ));
+    }
+    html.push_str("</table>\n</body></html>\n");
+    Ok(html.into_bytes())
+}
+
+/// Path of the parent directory of `base`, which ends with a slash
+fn get_parent(base: &str) -> String {
+    match base.trim_end_matches('/').rfind('/') {
+        Some(position) => base[..position + 1].to_string(),
+        None => "/".to_string(),
+    }
+}
+
+/// Percent-encode a name for a path segment, the slash of directories is kept
+fn encode(name: &str) -> String {
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_listing() {
+        let listing = get_listing(Path::new("./html/"), "/").unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+        assert!(listing.contains("<title>Index of /</title>"));
+        assert!(!listing.contains("../"));
+        let css = listing.find("<a href=\"/css/\">css/</a>").unwrap();
+        let img = listing.find("<a href=\"/img/\">img/</a>").unwrap();
+        let about = listing
+            .find("<a href=\"/about.htm\">about.htm</a>")
+            .unwrap();
+        assert!(css < img && img < about);
+
+        let listing = get_listing(Path::new("./html/css"), "/css").unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+        assert!(listing.contains("<a href=\"/\">../</a>"));
+        assert!(listing.contains("<a href=\"/css/style.css\">style.css</a></td><td>269</td>"));
+        assert!(get_listing(Path::new("./html/missing"), "/missing/").is_err());
+    }
+
+    #[test]
+    fn test_get_parent() {
+        assert_eq!(get_parent("/a/b/"), "/a/");
+        assert_eq!(get_parent("/a/"), "/");
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("a b&<c>.txt"), "a%20b%26%3Cc%3E.txt");
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! # TCP HTTP Canned responses
+//! Common error responses serialized once at startup, so requests rejected while parsing or
+//! under load are answered with a single write without building the response or reading the
+//! error page again. Only the `Date` is updated, once a second.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use application_layer::http::date;
+use application_layer::http::response;
+use application_layer::http::status::HttpStatus;
+use response::tcp::http::{error_page, Dispatcher};
+use Config;
+
+/// Statuses with a canned response
+pub const STATUSES: [HttpStatus; 7] = [
+    HttpStatus::BadRequest,
+    HttpStatus::NotFound,
+    HttpStatus::RequestTimeout,
+    HttpStatus::PayloadTooLarge,
+    HttpStatus::RequestHeaderFieldsTooLarge,
+    HttpStatus::InternalServerError,
+    HttpStatus::ServiceUnavailable,
+];
+
+/// # Serialized error responses shared by all workers
+/// Responses close the connection. A status whose error page can't be read at startup is left
+/// out, so it's built for each request and the error is logged then.
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::status::HttpStatus;
+/// use milstian_internet_framework::response::tcp::http::canned::Canned;
+/// use milstian_internet_framework::Config;
+/// let config = Config::from_env_args(vec![
+///     String::from("ignore this"),
+///     String::from("127.0.0.1"),
+///     String::from("7878"),
+///     String::from("4"),
+///     String::from("index.htm"),
+///     String::from("./html/"),
+///     String::from("404.htm"),
+///     String::from("1024"),
+/// ]).unwrap();
+/// let canned = Canned::new(&config);
+/// let response = canned.get(HttpStatus::RequestTimeout).unwrap();
+/// assert!(response.starts_with(b"HTTP/1.1 408 Request Timeout\r\n"));
+/// assert!(canned.get(HttpStatus::Ok).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Canned {
+    responses: Arc<RwLock<Responses>>,
+}
+
+#[derive(Clone, Debug)]
+struct Responses {
+    /// Seconds since the Unix epoch of the `Date` in the responses
+    second: u64,
+    responses: Vec<Entry>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    bytes: Arc<Vec<u8>>,
+    /// Where the value of the `Date` header starts
+    date_offset: Option<usize>,
+    status: HttpStatus,
+}
+
+impl Canned {
+    pub fn new(config: &Config) -> Canned {
+        let now = SystemTime::now();
+        let responses = STATUSES
+            .iter()
+            .filter_map(|status| Canned::get_entry(*status, config, now))
+            .collect();
+        Canned {
+            responses: Arc::new(RwLock::new(Responses {
+                second: get_second(now),
+                responses,
+            })),
+        }
+    }
+
+    /// Serialize the response like `Dispatcher::get_error_response` does
+    fn get_entry(status: HttpStatus, config: &Config, now: SystemTime) -> Option<Entry> {
+        let mut response = response::Message::new(
+            "HTTP/1.1".to_string(),
+            status.to_string(),
+            HashMap::new(),
+            Vec::new(),
+        );
+        response
+            .headers
+            .insert("Connection".to_string(), "close".to_string());
+        response
+            .headers
+            .insert("Date".to_string(), date::format(now));
+        if error_page::set_error_page(&mut response, config).is_err() {
+            return None;
+        }
+        Dispatcher::set_default_headers(&mut response, true, &config.server_name);
+        let bytes = response::to_ordered_bytes(&response, &config.header_order);
+        let date_offset = bytes
+            .windows(8)
+            .position(|window| window == b"\r\nDate: ")
+            .map(|position| position + 8);
+        Some(Entry {
+            bytes: Arc::new(bytes),
+            date_offset,
+            status,
+        })
+    }
+
+    /// Serialized response with the current `Date`, the bytes are shared so getting them
+    /// doesn't copy them
+    pub fn get(&self, status: HttpStatus) -> Option<Arc<Vec<u8>>> {
+        let now = SystemTime::now();
+        let second = get_second(now);
+        {
+            let responses = self.responses.read().ok()?;
+            if responses.second == second {
+                return responses.get(status);
+            }
+        }
+        let mut responses = self.responses.write().ok()?;
+        if responses.second != second {
+            responses.set_date(now);
+        }
+        responses.get(status)
+    }
+}
+
+impl Responses {
+    fn get(&self, status: HttpStatus) -> Option<Arc<Vec<u8>>> {
+        self.responses
+            .iter()
+            .find(|entry| entry.status == status)
+            .map(|entry| entry.bytes.clone())
+    }
+
+    /// Dates always have the same length, the new one replaces the old in a copy of each
+    /// response since workers may still be writing the old ones
+    fn set_date(&mut self, now: SystemTime) {
+        let date = date::format(now);
+        for entry in self.responses.iter_mut() {
+            if let Some(offset) = entry.date_offset {
+                let end = offset + date.len();
+                if entry.bytes.get(offset..end).is_some() {
+                    let mut bytes = (*entry.bytes).clone();
+                    bytes[offset..end].copy_from_slice(date.as_bytes());
+                    entry.bytes = Arc::new(bytes);
+                }
+            }
+        }
+        self.second = get_second(now);
+    }
+}
+
+fn get_second(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_set_date() {
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let entry = Canned::get_entry(HttpStatus::BadRequest, &get_config(), now).unwrap();
+        let mut responses = Responses {
+            second: get_second(now),
+            responses: vec![entry],
+        };
+        let before = responses.get(HttpStatus::BadRequest).unwrap();
+        let before = String::from_utf8(before.to_vec()).unwrap();
+        assert!(before.contains("\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\n"));
+        assert!(before.contains("\r\nContent-Length: 0\r\n"));
+        assert!(before.ends_with("\r\nConnection: close\r\n\r\n"));
+
+        responses.set_date(now + Duration::from_secs(1));
+        let after = responses.get(HttpStatus::BadRequest).unwrap();
+        let after = String::from_utf8(after.to_vec()).unwrap();
+        assert_eq!(after, before.replace("08:49:37 GMT", "08:49:38 GMT"));
+        assert_eq!(responses.get(HttpStatus::NotFound), None);
+    }
+
+    #[test]
+    fn test_get() {
+        let canned = Canned::new(&get_config().error_page(404, "404.htm"));
+        for status in STATUSES.iter() {
+            let response = canned.get(*status).unwrap();
+            assert!(response.starts_with(format!("HTTP/1.1 {}\r\n", status).as_bytes()));
+        }
+        let response = canned.get(HttpStatus::NotFound).unwrap();
+        assert!(response.ends_with(&::std::fs::read("html/404.htm").unwrap()));
+        assert!(response
+            .windows(b"Content-Type: text/html".len())
+            .any(|window| window == b"Content-Type: text/html"));
+
+        // Pages that can't be read are left to the requests
+        let canned = Canned::new(&get_config().error_page(500, "missing.htm"));
+        assert!(canned.get(HttpStatus::InternalServerError).is_none());
+        assert!(canned.get(HttpStatus::BadRequest).is_some());
+    }
+
+    fn get_config() -> Config {
+        Config::for_tests()
+    }
+}
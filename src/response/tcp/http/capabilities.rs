@@ -0,0 +1,154 @@
+//! # TCP HTTP Responder capabilities
+//! What a responder can answer, declared up front so the dispatcher can skip responders that
+//! can't match, leave bodies unbuffered nobody reads and answer `Allow` for them.
+
+use application_layer::http::request::Method;
+
+/// Order methods are listed in by `Allow` headers
+const METHODS: [Method; 9] = [
+    Method::Get,
+    Method::Head,
+    Method::Post,
+    Method::Put,
+    Method::Patch,
+    Method::Delete,
+    Method::Options,
+    Method::Connect,
+    Method::Trace,
+];
+
+/// # Methods, paths and behavior of a responder
+/// ```rust
+/// use milstian_internet_framework::application_layer::http::request::Method;
+/// use milstian_internet_framework::response::tcp::http::capabilities::Capabilities;
+/// let capabilities = Capabilities::new()
+///     .methods(&[Method::Get, Method::Head])
+///     .path_prefix("/static/")
+///     .needs_body(false);
+/// assert!(capabilities.accepts(Method::Get, "/static/style.css"));
+/// assert!(!capabilities.accepts(Method::Post, "/static/style.css"));
+/// assert!(!capabilities.accepts(Method::Get, "/api"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    /// Responses may be stored by caches, otherwise `Cache-Control: no-store` is added to
+    /// responses without a Cache-Control
+    pub is_cacheable: bool,
+    /// Methods answered, any method when empty
+    pub methods: Vec<Method>,
+    /// Request bodies are read, bodies of requests only such responders can answer are
+    /// discarded as they arrive
+    pub needs_body: bool,
+    /// Paths answered start with one of these, any path when empty
+    pub path_prefixes: Vec<String>,
+}
+
+impl Capabilities {
+    /// Any method and path with the body read and cacheable responses, what responders that
+    /// declare nothing get
+    pub fn new() -> Capabilities {
+        Capabilities {
+            is_cacheable: true,
+            methods: Vec::new(),
+            needs_body: true,
+            path_prefixes: Vec::new(),
+        }
+    }
+
+    pub fn methods(mut self, methods: &[Method]) -> Capabilities {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    pub fn path_prefix(mut self, path_prefix: &str) -> Capabilities {
+        self.path_prefixes.push(path_prefix.to_string());
+        self
+    }
+
+    pub fn needs_body(mut self, needs_body: bool) -> Capabilities {
+        self.needs_body = needs_body;
+        self
+    }
+
+    pub fn cacheable(mut self, is_cacheable: bool) -> Capabilities {
+        self.is_cacheable = is_cacheable;
+        self
+    }
+
+    /// Responders that answer GET also answer HEAD
+    pub fn accepts(&self, method: Method, path: &str) -> bool {
+        (self.methods.is_empty()
+            || self.methods.contains(&method)
+            || (method == Method::Head && self.methods.contains(&Method::Get)))
+            && self.accepts_path(path)
+    }
+
+    pub fn accepts_path(&self, path: &str) -> bool {
+        self.path_prefixes.is_empty()
+            || self
+                .path_prefixes
+                .iter()
+                .any(|path_prefix| path.starts_with(path_prefix.as_str()))
+    }
+
+    /// Value of a `Allow` header for a path or the whole server, `None` when a responder that
+    /// could answer accepts any method or no responder could answer
+    pub fn get_allow(capabilities: &[Capabilities], path: Option<&str>) -> Option<String> {
+        let mut methods = vec![Method::Options];
+        for capabilities in capabilities {
+            if let Some(path) = path {
+                if !capabilities.accepts_path(path) {
+                    continue;
+                }
+            }
+            if capabilities.methods.is_empty() {
+                return None;
+            }
+            methods.extend_from_slice(&capabilities.methods);
+        }
+        if methods.len() == 1 {
+            return None;
+        }
+        if methods.contains(&Method::Get) {
+            methods.push(Method::Head);
+        }
+        let allow: Vec<String> = METHODS
+            .iter()
+            .filter(|method| methods.contains(method))
+            .map(|method| method.to_string())
+            .collect();
+        Some(allow.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_allow() {
+        let capabilities = vec![
+            Capabilities::new()
+                .methods(&[Method::Get, Method::Head])
+                .path_prefix("/"),
+            Capabilities::new()
+                .methods(&[Method::Post, Method::Get])
+                .path_prefix("/api/"),
+        ];
+        assert_eq!(
+            Capabilities::get_allow(&capabilities, Some("/index.htm")),
+            Some("GET, HEAD, OPTIONS".to_string())
+        );
+        assert_eq!(
+            Capabilities::get_allow(&capabilities, None),
+            Some("GET, HEAD, POST, OPTIONS".to_string())
+        );
+        assert_eq!(Capabilities::get_allow(&capabilities, Some("relative")), None);
+
+        let mut capabilities = capabilities;
+        capabilities.push(Capabilities::new().path_prefix("/api/"));
+        assert_eq!(Capabilities::get_allow(&capabilities, Some("/api/users")), None);
+        assert!(Capabilities::get_allow(&capabilities, Some("/other")).is_some());
+        assert_eq!(Capabilities::get_allow(&capabilities, None), None);
+    }
+}
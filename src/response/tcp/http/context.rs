@@ -0,0 +1,387 @@
+//! # TCP HTTP Request context
+//! Holds values that are generated per request and shared with the responders.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use access::Identity;
+use application_layer::http::cookie::SetCookie;
+use application_layer::http::status::HttpStatus;
+use base64;
+use random;
+use response::tcp::completion::Completion;
+
+/// Called with the completion of a request after it's response was written, also when writing
+/// failed or the client went away
+pub type AfterWrite = Box<FnOnce(&Completion) + Send>;
+
+/// # Created for every request, accessible from responders via the application
+/// ```rust
+/// use milstian_internet_framework::response::tcp::http::context::Context;
+/// let context = Context::new();
+/// let policy = context.get_content_security_policy("script-src 'nonce-{nonce}'");
+/// assert_eq!(policy, format!("script-src 'nonce-{}'", context.csp_nonce));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Context {
+    after_write: AfterWriteHooks,
+    cookies: Arc<Mutex<Vec<SetCookie>>>,
+    /// Read from the random source of the operating system, empty when it's not available so
+    /// `'nonce-'` matches no inline script instead of a guessable nonce
+    pub csp_nonce: String,
+    identity: Arc<Mutex<Option<Identity>>>,
+    interim: InterimWriter,
+    response_body: ResponseBody,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context {
+            after_write: AfterWriteHooks::default(),
+            cookies: Arc::new(Mutex::new(Vec::new())),
+            csp_nonce: random::secure_bytes(16)
+                .map(|bytes| base64::encode(&bytes))
+                .unwrap_or_default(),
+            identity: Arc::new(Mutex::new(None)),
+            interim: InterimWriter::default(),
+            response_body: ResponseBody::default(),
+        }
+    }
+
+    /// Stream the body of the response from `reader` after the head instead of sending the
+    /// body of the message, it's sent chunked since the length is not known
+    pub fn set_response_body(&self, reader: Box<Read + Send>) {
+        if let Ok(mut response_body) = self.response_body.reader.lock() {
+            *response_body = Some((reader, None));
+        }
+    }
+
+    /// Stream `length` bytes of body from `reader` after the body of the message, sent as
+    /// they are with a Content-Length so the connection can be kept open. The connection
+    /// is closed if the reader ends early.
+    pub fn set_response_body_with_length(&self, reader: Box<Read + Send>, length: u64) {
+        if let Ok(mut response_body) = self.response_body.reader.lock() {
+            *response_body = Some((reader, Some(length)));
+        }
+    }
+
+    /// Stream the body of the response from chunks as they are produced, like
+    /// `set_response_body`
+    pub fn set_response_chunks(&self, chunks: Box<Iterator<Item = Vec<u8>> + Send>) {
+        self.set_response_body(Box::new(Chunks {
+            chunks,
+            current: io::Cursor::new(Vec::new()),
+        }));
+    }
+
+    pub fn has_response_body(&self) -> bool {
+        match self.response_body.reader.lock() {
+            Ok(response_body) => response_body.is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// Length of the streamed body when it's known
+    pub fn get_response_length(&self) -> Option<u64> {
+        match self.response_body.reader.lock() {
+            Ok(response_body) => response_body.as_ref().and_then(|(_, length)| *length),
+            Err(_) => None,
+        }
+    }
+
+    /// Take the streamed body, only the first call gets it
+    pub fn take_response_body(&self) -> Option<Box<Read + Send>> {
+        match self.response_body.reader.lock() {
+            Ok(mut response_body) => response_body.take().map(|(reader, _)| reader),
+            Err(_) => None,
+        }
+    }
+
+    /// Send a cookie with the response in it's own `Set-Cookie` header
+    pub fn add_cookie(&self, cookie: SetCookie) {
+        if let Ok(mut cookies) = self.cookies.lock() {
+            cookies.push(cookie);
+        }
+    }
+
+    /// Take the cookies to send, only the first call gets them
+    pub fn take_cookies(&self) -> Vec<SetCookie> {
+        match self.cookies.lock() {
+            Ok(mut cookies) => mem::replace(&mut *cookies, Vec::new()),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Identity the request was authenticated as, for access rules and handlers
+    pub fn set_identity(&self, identity: Identity) {
+        if let Ok(mut existing) = self.identity.lock() {
+            *existing = Some(identity);
+        }
+    }
+
+    pub fn get_identity(&self) -> Option<Identity> {
+        match self.identity.lock() {
+            Ok(identity) => identity.clone(),
+            Err(_) => None,
+        }
+    }
+
+    /// Connection informational responses are written to, only set for clients that
+    /// understand them
+    pub fn set_interim_writer(&self, writer: Box<Write + Send>) {
+        if let Ok(mut existing) = self.interim.writer.lock() {
+            *existing = Some(writer);
+        }
+    }
+
+    /// Send a informational response before the final response, like 103 Early Hints with
+    /// `Link` headers while the final response is built or 100 Continue before reading a
+    /// streamed body. Gives false when the client doesn't understand them, like HTTP/1.0
+    /// clients, see RFC 7231 section 6.2.
+    pub fn send_interim(
+        &self,
+        status: HttpStatus,
+        headers: &[(&str, &str)],
+    ) -> Result<bool, String> {
+        if !status.is_informational() || status == HttpStatus::SwitchingProtocols {
+            return Err(format!("{} is not a interim response", status));
+        }
+        let mut head = format!("HTTP/1.1 {}\r\n", status);
+        for (name, value) in headers {
+            let is_valid = !name.is_empty()
+                && !name.contains(|character: char| character.is_ascii_control())
+                && !value.contains(|character: char| character == '\r' || character == '\n');
+            if !is_valid {
+                return Err(format!("Invalid header {:?} of interim response", name));
+            }
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let mut writer = match self.interim.writer.lock() {
+            Ok(writer) => writer,
+            Err(_) => return Ok(false),
+        };
+        match writer.as_mut() {
+            Some(writer) => writer
+                .write_all(head.as_bytes())
+                .and_then(|_| writer.flush())
+                .map(|_| true)
+                .map_err(|error| format!("Failed to send {}, error: {}", status, error)),
+            None => Ok(false),
+        }
+    }
+
+    /// Run `hook` when the request is completed, for example to finalize metrics, release
+    /// resources or commit audit entries
+    pub fn add_after_write(&self, hook: AfterWrite) {
+        if let Ok(mut hooks) = self.after_write.hooks.lock() {
+            hooks.push(hook);
+        }
+    }
+
+    /// Run the hooks in the order they were added, only the first call runs them
+    pub fn run_after_write(&self, completion: &Completion) {
+        let hooks = match self.after_write.hooks.lock() {
+            Ok(mut hooks) => mem::replace(&mut *hooks, Vec::new()),
+            Err(poisoned) => mem::replace(&mut *poisoned.into_inner(), Vec::new()),
+        };
+        for hook in hooks {
+            hook(completion);
+        }
+    }
+
+    /// Replace the `{nonce}` placeholders in a Content-Security-Policy with the nonce
+    pub fn get_content_security_policy(&self, policy: &str) -> String {
+        policy.replace("{nonce}", &self.csp_nonce)
+    }
+}
+
+/// Shared by the clones of the application serving a request
+#[derive(Clone, Default)]
+struct AfterWriteHooks {
+    hooks: Arc<Mutex<Vec<AfterWrite>>>,
+}
+
+impl fmt::Debug for AfterWriteHooks {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.hooks.try_lock() {
+            Ok(hooks) => write!(formatter, "AfterWriteHooks {{ count: {} }}", hooks.len()),
+            Err(_) => write!(formatter, "AfterWriteHooks {{ hooks: <locked> }}"),
+        }
+    }
+}
+
+/// Shared by the clones of the application serving a request
+#[derive(Clone, Default)]
+struct InterimWriter {
+    writer: Arc<Mutex<Option<Box<Write + Send>>>>,
+}
+
+impl fmt::Debug for InterimWriter {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.writer.try_lock() {
+            Ok(writer) => write!(formatter, "InterimWriter {{ is_set: {} }}", writer.is_some()),
+            Err(_) => write!(formatter, "InterimWriter {{ writer: <locked> }}"),
+        }
+    }
+}
+
+/// Reads the chunks of a iterator one after another
+struct Chunks {
+    chunks: Box<Iterator<Item = Vec<u8>> + Send>,
+    current: io::Cursor<Vec<u8>>,
+}
+
+impl Read for Chunks {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buffer)?;
+            if read > 0 || buffer.is_empty() {
+                return Ok(read);
+            }
+            match self.chunks.next() {
+                Some(chunk) => self.current = io::Cursor::new(chunk),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Shared by the clones of the application serving a request, with the length when known
+#[derive(Clone, Default)]
+struct ResponseBody {
+    reader: Arc<Mutex<Option<(Box<Read + Send>, Option<u64>)>>>,
+}
+
+impl fmt::Debug for ResponseBody {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.reader.try_lock() {
+            Ok(reader) => write!(formatter, "ResponseBody {{ is_set: {} }}", reader.is_some()),
+            Err(_) => write!(formatter, "ResponseBody {{ reader: <locked> }}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use response::tcp::completion::Outcome;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_new() {
+        let context = Context::new();
+        assert_eq!(context.csp_nonce.len(), 24);
+        assert_ne!(context.csp_nonce, Context::new().csp_nonce);
+    }
+
+    #[test]
+    fn test_response_body() {
+        let context = Context::new();
+        assert!(!context.has_response_body());
+        context.set_response_body(Box::new(&b"abc"[..]));
+        let copy = context.clone();
+        assert!(copy.has_response_body());
+
+        let mut body = String::new();
+        copy.take_response_body()
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "abc");
+        assert!(context.take_response_body().is_none());
+    }
+
+    #[test]
+    fn test_response_chunks() {
+        let context = Context::new();
+        let chunks = vec![b"ab".to_vec(), Vec::new(), b"cde".to_vec()];
+        context.set_response_chunks(Box::new(chunks.into_iter()));
+        assert_eq!(context.get_response_length(), None);
+        let mut body = String::new();
+        context
+            .take_response_body()
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "abcde");
+
+        context.set_response_body_with_length(Box::new(&b"abc"[..]), 3);
+        assert_eq!(context.get_response_length(), Some(3));
+    }
+
+    #[test]
+    fn test_after_write() {
+        let context = Context::new();
+        let (sender, receiver) = mpsc::channel();
+        for name in &["metrics", "audit"] {
+            let sender = sender.clone();
+            context.add_after_write(Box::new(move |completion: &Completion| {
+                sender
+                    .send(format!("{} {:?}", name, completion.outcome))
+                    .unwrap();
+            }));
+        }
+        let mut completion = Completion::new("127.0.0.1:8080".parse().unwrap(), 1);
+        completion.outcome = Outcome::WriteFailed;
+        context.clone().run_after_write(&completion);
+        context.run_after_write(&completion);
+        drop(sender);
+        let called: Vec<String> = receiver.iter().collect();
+        assert_eq!(called, vec!["metrics WriteFailed", "audit WriteFailed"]);
+    }
+
+    /// Writes to a buffer the test can read
+    #[derive(Clone, Default)]
+    struct Shared {
+        buffer: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Write for Shared {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.buffer.lock().unwrap().write(data)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_send_interim() {
+        let context = Context::new();
+        assert_eq!(context.send_interim(HttpStatus::Continue, &[]), Ok(false));
+
+        let shared = Shared::default();
+        context.set_interim_writer(Box::new(shared.clone()));
+        let link = ("Link", "</style.css>; rel=preload; as=style");
+        assert_eq!(context.send_interim(HttpStatus::EarlyHints, &[link]), Ok(true));
+        assert_eq!(context.clone().send_interim(HttpStatus::Continue, &[]), Ok(true));
+        assert!(context.send_interim(HttpStatus::Ok, &[]).is_err());
+        assert!(context.send_interim(HttpStatus::SwitchingProtocols, &[]).is_err());
+        assert!(context.send_interim(HttpStatus::EarlyHints, &[("Link", "a\r\nb")]).is_err());
+        assert_eq!(
+            String::from_utf8(shared.buffer.lock().unwrap().clone()).unwrap(),
+            "HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n\
+             HTTP/1.1 100 Continue\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_get_content_security_policy() {
+        let mut context = Context::new();
+        context.csp_nonce = "abc".to_string();
+        assert_eq!(
+            context.get_content_security_policy(
+                "default-src 'self'; script-src 'nonce-{nonce}'; style-src 'nonce-{nonce}'"
+            ),
+            "default-src 'self'; script-src 'nonce-abc'; style-src 'nonce-abc'".to_string()
+        );
+        assert_eq!(
+            context.get_content_security_policy("default-src 'self'"),
+            "default-src 'self'".to_string()
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! # TCP HTTP Downloads
+//! Responses that make clients save the body as a file instead of displaying it, for export
+//! and download endpoints, see RFC 6266.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use application_layer::http::response::Response;
+use mime;
+use response::tcp::http::context::Context;
+
+/// Name used when nothing is left of the filename
+const DEFAULT_FILENAME: &str = "download";
+
+/// # Response with `body` saved by the client as `filename`
+/// The Content-Type follows the extension of the filename.
+/// ```rust
+/// use milstian_internet_framework::response::tcp::http::download;
+/// let response = download::attachment("report.csv", "a,b\n1,2\n");
+/// assert_eq!(response.get_header("Content-Type"), Some(&"text/csv".to_string()));
+/// assert_eq!(
+///     response.get_header("Content-Disposition"),
+///     Some(&"attachment; filename=\"report.csv\"".to_string())
+/// );
+/// assert_eq!(response.get_header("Content-Length"), Some(&"8".to_string()));
+/// ```
+pub fn attachment<T: Into<Vec<u8>>>(filename: &str, body: T) -> Response {
+    let filename = get_filename(filename);
+    Response::ok()
+        .header("Content-Type", &mime::from_filename(&filename))
+        .header("Content-Disposition", &get_content_disposition(&filename))
+        .body(body)
+}
+
+/// Response streaming the file at `path` from the context, saved by the client as `filename`
+/// or the name of the file. The file is not read into memory and is sent with it's length.
+pub fn attachment_file(
+    path: &Path,
+    filename: Option<&str>,
+    context: &Context,
+) -> io::Result<Response> {
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        ));
+    }
+    let filename = match filename {
+        Some(filename) => filename.to_string(),
+        None => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    let response = attachment(&filename, Vec::new());
+    context.set_response_body_with_length(Box::new(file), metadata.len());
+    Ok(response.header("Content-Length", &metadata.len().to_string()))
+}
+
+/// Value of a `Content-Disposition` header for a attachment. Names that are not plain ASCII
+/// get a `filename*` with the UTF-8 name percent-encoded and a ASCII `filename` for older
+/// clients, see RFC 6266 section 4.3 and RFC 5987 section 3.2.
+pub fn get_content_disposition(filename: &str) -> String {
+    let filename = get_filename(filename);
+    let fallback: String = filename
+        .chars()
+        .map(|character| {
+            if character.is_ascii() && character != '"' && character != '\\' {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if fallback == filename {
+        return format!("attachment; filename=\"{}\"", fallback);
+    }
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        encode(&filename)
+    )
+}
+
+/// Last component of the name without control characters, clients must not be told to write
+/// to a directory of their choosing
+fn get_filename(filename: &str) -> String {
+    let filename: String = filename
+        .rsplit(|character| character == '/' || character == '\\')
+        .find(|component| !component.trim().is_empty())
+        .unwrap_or("")
+        .chars()
+        .filter(|character| !character.is_control())
+        .collect();
+    let filename = filename.trim().trim_start_matches('.');
+    if filename.is_empty() {
+        DEFAULT_FILENAME.to_string()
+    } else {
+        filename.to_string()
+    }
+}
+
+/// Percent-encode the bytes that are not `attr-char`, see RFC 5987 section 3.2.1
+fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Read;
+
+    #[test]
+    fn test_get_content_disposition() {
+        assert_eq!(
+            get_content_disposition("export.json"),
+            "attachment; filename=\"export.json\""
+        );
+        assert_eq!(
+            get_content_disposition("Åsa \"räkning\".pdf"),
+            "attachment; filename=\"_sa _r_kning_.pdf\"; \
+             filename*=UTF-8''%C3%85sa%20%22r%C3%A4kning%22.pdf"
+        );
+        assert_eq!(
+            get_content_disposition("../../etc/passwd"),
+            "attachment; filename=\"passwd\""
+        );
+        assert_eq!(
+            get_content_disposition("C:\\temp\\a\r\nb.txt"),
+            "attachment; filename=\"ab.txt\""
+        );
+        assert_eq!(
+            get_content_disposition("reports/"),
+            "attachment; filename=\"reports\""
+        );
+        assert_eq!(
+            get_content_disposition("/.."),
+            "attachment; filename=\"download\""
+        );
+    }
+
+    #[test]
+    fn test_attachment_file() {
+        let context = Context::new();
+        let response = attachment_file(Path::new("html/favicon.ico"), None, &context).unwrap();
+        let expected = fs::read("html/favicon.ico").unwrap();
+        assert_eq!(
+            response.get_header("Content-Disposition"),
+            Some(&"attachment; filename=\"favicon.ico\"".to_string())
+        );
+        assert_eq!(
+            response.get_header("Content-Type"),
+            Some(&"image/x-icon".to_string())
+        );
+        assert_eq!(
+            response.get_header("Content-Length"),
+            Some(&expected.len().to_string())
+        );
+        assert_eq!(context.get_response_length(), Some(expected.len() as u64));
+        let mut body = Vec::new();
+        context
+            .take_response_body()
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body, expected);
+
+        let response =
+            attachment_file(Path::new("html/index.htm"), Some("start.txt"), &context).unwrap();
+        assert_eq!(
+            response.get_header("Content-Type"),
+            Some(&"text/plain".to_string())
+        );
+        assert!(attachment_file(Path::new("html/css"), None, &context).is_err());
+        assert!(attachment_file(Path::new("html/missing"), None, &context).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! # TCP HTTP Trace echo response
+//! Answers TRACE requests with the request as it was received so clients can see what
+//! proxies changed, see RFC 7231 section 4.3.8. Not part of the legacy responders since
+//! headers are reflected back to the client, credentials and cookies are left out.
+
+use std::net::SocketAddr;
+
+use application_layer::http::request::{self, Method};
+use application_layer::http::response;
+
+use response::tcp::http::capabilities::Capabilities;
+use response::tcp::http::ResponderInterface;
+use Application;
+
+/// Header fields that may carry credentials
+const EXCLUDED_HEADERS: [&str; 3] = ["Authorization", "Cookie", "Proxy-Authorization"];
+
+#[derive(Clone, Default)]
+pub struct Responder {}
+
+impl Responder {
+    pub fn new() -> Responder {
+        Responder {}
+    }
+}
+
+impl ResponderInterface for Responder {
+    fn get_capabilities(&self) -> Capabilities {
+        Capabilities::new().methods(&[Method::Trace])
+    }
+
+    fn matches(
+        &mut self,
+        request_message: &request::Message,
+        _application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> bool {
+        request_message.request_line.method == Method::Trace
+    }
+
+    fn respond(
+        &self,
+        request_message: &request::Message,
+        _application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> Result<response::Message, String> {
+        let mut body = format!("{}\r\n", request_message.request_line.raw);
+        for (name, value) in request_message.headers.iter() {
+            if !EXCLUDED_HEADERS
+                .iter()
+                .any(|excluded| excluded.eq_ignore_ascii_case(name))
+            {
+                body.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        body.push_str("\r\n");
+
+        Ok(response::Response::ok()
+            .protocol(&request_message.request_line.protocol)
+            .header("Content-Type", "message/http")
+            .body(body)
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use response::tcp::http::golden::{self, Normalization};
+    use Config;
+
+    #[test]
+    fn test_respond() {
+        let application = Application::new(Config::for_tests());
+        let mut responder = Responder::new();
+        golden::assert_transcript(
+            &mut responder,
+            &application,
+            "TRACE /index.htm HTTP/1.1\nHost: localhost\nVia: 1.1 proxy\nCookie: id=1\n\
+             authorization: Basic YTpi\n\n",
+            "HTTP/1.1 200 OK\nContent-Type: message/http\nContent-Length: 62\n\n\
+             TRACE /index.htm HTTP/1.1\nHost: localhost\nVia: 1.1 proxy\n\n",
+            &Normalization::new(),
+        )
+        .unwrap();
+        assert!(golden::run_transcript(
+            &mut responder,
+            &application,
+            "GET / HTTP/1.1\nHost: localhost\n\n"
+        )
+        .is_err());
+    }
+}
@@ -3,11 +3,13 @@
 
 use application_layer::http::request;
 use application_layer::http::response;
+use application_layer::http::status::HttpStatus;
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use Application;
 
+use response::tcp::http::capabilities::Capabilities;
 use response::tcp::http::ResponderInterface;
 
 #[derive(Clone)]
@@ -20,6 +22,10 @@ impl Responder {
 }
 
 impl ResponderInterface for Responder {
+    fn get_capabilities(&self) -> Capabilities {
+        Capabilities::new().needs_body(false)
+    }
+
     fn matches(
         &mut self,
         _request_message: &request::Message,
@@ -37,7 +43,7 @@ impl ResponderInterface for Responder {
         _socket: &SocketAddr,
         _overflow_bytes: &u64,
     ) -> Result<response::Message, String> {
-        let status_code = "500 Internal Server Error";
+        let status_code = HttpStatus::InternalServerError;
         let protocol = request::Message::get_protocol_text(&request_message.request_line.protocol);
         let headers: HashMap<String, String> = HashMap::new();
         let response_body = Vec::new();
@@ -65,17 +71,7 @@ mod tests {
 
     #[test]
     fn test_matches() {
-        let config = Config {
-            feedback_error_file: Option::None,
-            feedback_info_file: Option::None,
-            filesystem_directory_index: "index.htm".to_string(),
-            file_not_found_file: "404.htm".to_string(),
-            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
-            server_host: "localhost".to_string(),
-            server_limit: 4,
-            server_port: 4040,
-            tcp_limit: 1024,
-        };
+        let config = Config::for_tests();
         let application = Application::new(config);
         let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
         let mut responder = Responder::new();
@@ -110,17 +106,7 @@ mod tests {
 
     #[test]
     fn test_respond() {
-        let config = Config {
-            feedback_error_file: Option::None,
-            feedback_info_file: Option::None,
-            filesystem_directory_index: "index.htm".to_string(),
-            file_not_found_file: "404.htm".to_string(),
-            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
-            server_host: "localhost".to_string(),
-            server_limit: 4,
-            server_port: 4040,
-            tcp_limit: 1024,
-        };
+        let config = Config::for_tests();
         let application = Application::new(config);
         let mut responder = Responder::new();
         let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
@@ -136,7 +122,7 @@ mod tests {
 
         let expected_response = response::Message::new(
             "HTTP/1.1".to_string(),
-            "500 Internal Server Error".to_string(),
+            HttpStatus::InternalServerError.to_string(),
             headers,
             response_body.into_bytes(),
         ).to_bytes();
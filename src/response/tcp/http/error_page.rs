@@ -0,0 +1,110 @@
+//! # TCP HTTP Error pages
+//! Files registered per status code with `Config::error_page`, sent as the body of error
+//! responses that have none so clients get more than a bare status line.
+
+use std::fs;
+
+use application_layer::http::response;
+use mime;
+use Config;
+
+/// Status code of a response status like `404 Not Found`
+fn get_code(status: &str) -> Option<u16> {
+    status.get(..3).and_then(|code| code.parse::<u16>().ok())
+}
+
+/// Filename of the page registered for `status` in the file-system root
+pub fn get_filename(status: u16, config: &Config) -> Option<String> {
+    config
+        .error_pages
+        .iter()
+        .find(|(existing, _)| *existing == status)
+        .map(|(_, filename)| format!("{}/{}", config.filesystem_root, filename))
+}
+
+/// Built-in page with just the status, sent when a page that should be sent is missing
+pub fn get_fallback_page(status: &str) -> Vec<u8> {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\
+         <body><h1>{0}</h1></body></html>\n",
+        status
+    ).into_bytes()
+}
+
+/// Make the registered page the body of a error response without one, returns whether it did.
+/// The built-in page is sent when the registered page can't be read.
+pub fn set_error_page(response: &mut response::Message, config: &Config) -> Result<bool, String> {
+    if !response.body.is_empty() {
+        return Ok(false);
+    }
+    let filename = match get_code(&response.status) {
+        Some(code) if code >= 400 => match get_filename(code, config) {
+            Some(filename) => filename,
+            None => return Ok(false),
+        },
+        _ => return Ok(false),
+    };
+    let (content_type, body, result) = match fs::read(&filename) {
+        Ok(body) => (
+            mime::from_filename_with(&filename, &config.media_types),
+            body,
+            Ok(true),
+        ),
+        Err(error) => (
+            "text/html; charset=utf-8".to_string(),
+            get_fallback_page(&response.status),
+            Err(format!("Failed to read error page {}, error: {}", filename, error)),
+        ),
+    };
+    response.headers.retain(|name, _| {
+        !name.eq_ignore_ascii_case("Content-Type") && !name.eq_ignore_ascii_case("Content-Length")
+    });
+    response
+        .headers
+        .insert("Content-Type".to_string(), content_type);
+    response
+        .headers
+        .insert("Content-Length".to_string(), body.len().to_string());
+    response.body = body;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use application_layer::http::status::HttpStatus;
+
+    #[test]
+    fn test_set_error_page() {
+        let config = Config::for_tests()
+        .error_page(404, "404.htm")
+        .error_page(500, "missing.htm");
+        let page = fs::read("html/404.htm").unwrap();
+
+        let mut response = response::Response::not_found().body("").build();
+        assert_eq!(set_error_page(&mut response, &config), Ok(true));
+        assert_eq!(response.body, page);
+        assert_eq!(
+            response.headers.get("Content-Length"),
+            Some(&page.len().to_string())
+        );
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"text/html".to_string())
+        );
+
+        // Bodies from responders and statuses without pages are kept
+        let mut response = response::Response::not_found().body("Gone").build();
+        assert_eq!(set_error_page(&mut response, &config), Ok(false));
+        assert_eq!(response.body, b"Gone".to_vec());
+        let mut response = response::Response::new(HttpStatus::BadRequest).build();
+        assert_eq!(set_error_page(&mut response, &config), Ok(false));
+        let mut response = response::Response::new(HttpStatus::InternalServerError).build();
+        assert!(set_error_page(&mut response, &config).is_err());
+        assert_eq!(response.body, get_fallback_page("500 Internal Server Error"));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"text/html; charset=utf-8".to_string())
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! # TCP HTTP Server-Sent Events
+//! Responses that stay open and push events to a `EventSource` in the browser as the
+//! application produces them, see the HTML Living Standard section 9.2.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use application_layer::http::response::Response;
+use response::tcp::http::context::Context;
+
+/// Seconds between comments sent while no events are pushed, so proxies and clients don't
+/// close a idle connection
+const DEFAULT_KEEP_ALIVE: u64 = 15;
+
+/// # Event pushed to the client
+/// ```rust
+/// use milstian_internet_framework::response::tcp::http::event_stream::Event;
+/// let event = Event::new("first\nsecond").event("update").id("7");
+/// assert_eq!(
+///     event.to_string(),
+///     "event: update\nid: 7\ndata: first\ndata: second\n\n"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub data: String,
+    /// Name of the event, clients dispatch unnamed events as `message`
+    pub event: Option<String>,
+    /// Sent back by the client in a `Last-Event-ID` header when it reconnects
+    pub id: Option<String>,
+    /// Milliseconds the client waits before reconnecting
+    pub retry: Option<u64>,
+}
+
+impl Event {
+    pub fn new(data: &str) -> Event {
+        Event {
+            data: data.to_string(),
+            event: None,
+            id: None,
+            retry: None,
+        }
+    }
+
+    pub fn event(mut self, event: &str) -> Event {
+        self.event = Some(event.to_string());
+        self
+    }
+
+    pub fn id(mut self, id: &str) -> Event {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn retry(mut self, retry: Duration) -> Event {
+        self.retry = Some(get_milliseconds(retry));
+        self
+    }
+}
+
+impl fmt::Display for Event {
+    /// A field for each line of the data, line breaks in the other fields would end them early
+    /// so they are removed
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let get_field = |value: &str| -> String {
+            value
+                .chars()
+                .filter(|character| *character != '\r' && *character != '\n')
+                .collect()
+        };
+        if let Some(event) = &self.event {
+            writeln!(formatter, "event: {}", get_field(event))?;
+        }
+        if let Some(id) = &self.id {
+            let id: String = get_field(id)
+                .chars()
+                .filter(|character| *character != '\0')
+                .collect();
+            writeln!(formatter, "id: {}", id)?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(formatter, "retry: {}", retry)?;
+        }
+        let data = self.data.replace("\r\n", "\n").replace('\r', "\n");
+        for line in data.split('\n') {
+            writeln!(formatter, "data: {}", line)?;
+        }
+        writeln!(formatter)
+    }
+}
+
+/// # Handle for pushing events to a open stream
+/// Clones push to the same stream, it ends when all of them are dropped.
+#[derive(Clone, Debug)]
+pub struct Sender {
+    sender: mpsc::Sender<Event>,
+}
+
+impl Sender {
+    /// Push a event, fails when the client went away
+    pub fn send(&self, event: Event) -> Result<(), String> {
+        self.sender
+            .send(event)
+            .map_err(|_| "The event stream is closed".to_string())
+    }
+
+    /// Push a unnamed event with `data`
+    pub fn data(&self, data: &str) -> Result<(), String> {
+        self.send(Event::new(data))
+    }
+
+    /// Push a event named `event` with `data`
+    pub fn event(&self, event: &str, data: &str) -> Result<(), String> {
+        self.send(Event::new(data).event(event))
+    }
+}
+
+/// # Builder of a event stream response
+/// The response has no length so it is sent chunked and the connection stays open, the
+/// worker serving it writes events until every sender is dropped or the client goes away.
+/// ```rust
+/// use milstian_internet_framework::response::tcp::http::context::Context;
+/// use milstian_internet_framework::response::tcp::http::event_stream::EventStream;
+/// use std::time::Duration;
+/// let context = Context::new();
+/// let (response, sender) = EventStream::new()
+///     .retry(Duration::from_secs(3))
+///     .start(&context);
+/// assert_eq!(
+///     response.get_header("Content-Type"),
+///     Some(&"text/event-stream".to_string())
+/// );
+/// sender.event("update", "{\"count\":1}").unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct EventStream {
+    keep_alive: Duration,
+    retry: Option<Duration>,
+}
+
+impl EventStream {
+    pub fn new() -> EventStream {
+        EventStream {
+            keep_alive: Duration::from_secs(DEFAULT_KEEP_ALIVE),
+            retry: None,
+        }
+    }
+
+    /// Time without events after which a comment is sent
+    pub fn keep_alive(mut self, keep_alive: Duration) -> EventStream {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Time the client should wait before reconnecting, sent first in the stream
+    pub fn retry(mut self, retry: Duration) -> EventStream {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Stream the events from the context, the response is given by the responder and the
+    /// sender passed to the code producing events
+    pub fn start(self, context: &Context) -> (Response, Sender) {
+        let (sender, receiver) = mpsc::channel();
+        let initial = match self.retry {
+            Some(retry) => format!("retry: {}\n\n", get_milliseconds(retry)),
+            None => String::new(),
+        };
+        context.set_response_body(Box::new(Events {
+            current: io::Cursor::new(initial.into_bytes()),
+            keep_alive: self.keep_alive,
+            receiver,
+        }));
+        let response = Response::ok()
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache");
+        (response, Sender { sender })
+    }
+}
+
+impl Default for EventStream {
+    fn default() -> EventStream {
+        EventStream::new()
+    }
+}
+
+fn get_milliseconds(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
+}
+
+/// Reads the pushed events one after another, waiting for the next one
+struct Events {
+    current: io::Cursor<Vec<u8>>,
+    keep_alive: Duration,
+    receiver: Receiver<Event>,
+}
+
+impl Read for Events {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buffer)?;
+            if read > 0 || buffer.is_empty() {
+                return Ok(read);
+            }
+            let next = match self.receiver.recv_timeout(self.keep_alive) {
+                Ok(event) => event.to_string(),
+                Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.current = io::Cursor::new(next.into_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_event() {
+        let event = Event::new("a\r\nb\rc")
+            .event("up\ndate")
+            .id("1\0")
+            .retry(Duration::from_millis(1500));
+        assert_eq!(
+            event.to_string(),
+            "event: update\nid: 1\nretry: 1500\ndata: a\ndata: b\ndata: c\n\n"
+        );
+        assert_eq!(Event::new("").to_string(), "data: \n\n");
+    }
+
+    #[test]
+    fn test_start() {
+        let context = Context::new();
+        let (response, sender) = EventStream::new()
+            .retry(Duration::from_secs(2))
+            .keep_alive(Duration::from_millis(10))
+            .start(&context);
+        assert_eq!(response.get_header("Content-Length"), None);
+        assert_eq!(
+            response.get_header("Cache-Control"),
+            Some(&"no-cache".to_string())
+        );
+
+        sender.data("first").unwrap();
+        let producer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            sender.event("done", "second").unwrap();
+        });
+        let mut body = String::new();
+        context
+            .take_response_body()
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        producer.join().unwrap();
+        assert!(body.starts_with("retry: 2000\n\ndata: first\n\n: keep-alive\n\n"));
+        assert!(body.ends_with("event: done\ndata: second\n\n"));
+    }
+
+    #[test]
+    fn test_closed() {
+        let context = Context::new();
+        let (_, sender) = EventStream::new().start(&context);
+        drop(context.take_response_body());
+        assert!(sender.data("lost").is_err());
+    }
+}
@@ -1,18 +1,22 @@
 //! # TCP HTTP File not found Response
-//! Used for displaying that a resource was not found on the server.
+//! Used for displaying that a resource was not found on the server. A built-in page is
+//! displayed when the configured file is missing.
 
 use std::net::SocketAddr;
 use std::path::Path;
 
 use application_layer::http::request;
 use application_layer::http::response;
+use application_layer::http::status::HttpStatus;
 
-use response::tcp::http::filesystem;
+use response::tcp::http::capabilities::Capabilities;
+use response::tcp::http::{error_page, filesystem};
 use response::tcp::http::ResponderInterface;
 use Application;
 
 #[derive(Clone)]
 pub struct Responder {
+    /// File displayed, the built-in page is displayed when not set
     pub filename: Option<String>,
 }
 
@@ -23,6 +27,10 @@ impl Responder {
 }
 
 impl ResponderInterface for Responder {
+    fn get_capabilities(&self) -> Capabilities {
+        Capabilities::new().needs_body(false)
+    }
+
     fn matches(
         &mut self,
         _request_message: &request::Message,
@@ -45,8 +53,12 @@ impl ResponderInterface for Responder {
         } else {
             eprintln!("File not found file does not exists {}", &filename);
         }
-        self.filename = Some(filename);
-        return exists && !is_dir;
+        self.filename = if exists && !is_dir {
+            Some(filename)
+        } else {
+            None
+        };
+        true
     }
 
     fn respond(
@@ -57,12 +69,20 @@ impl ResponderInterface for Responder {
         _overflow_bytes: &u64,
     ) -> Result<response::Message, String> {
         if let Some(filename) = &self.filename {
-            let mut response =
-                filesystem::Responder::get_response(filename, &request_message, &application)?;
-            response.status = "404 File Not Found".to_string();
+            let mut response = filesystem::Responder::get_file_response(
+                filename,
+                &request_message,
+                &application,
+                false,
+            )?;
+            response.status = HttpStatus::NotFound.to_string();
             return Ok(response);
         } else {
-            return Err("Error: File Not Found Filename missing".to_string());
+            return Ok(response::Response::not_found()
+                .protocol(&request_message.request_line.protocol)
+                .header("Content-Type", "text/html; charset=utf-8")
+                .body(error_page::get_fallback_page(&HttpStatus::NotFound.to_string()))
+                .build());
         }
     }
 }
@@ -85,17 +105,7 @@ mod tests {
 
     #[test]
     fn matches() {
-        let config = Config {
-            feedback_error_file: Option::None,
-            feedback_info_file: Option::None,
-            filesystem_directory_index: "index.htm".to_string(),
-            file_not_found_file: "404.htm".to_string(),
-            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
-            server_host: "localhost".to_string(),
-            server_limit: 4,
-            server_port: 4040,
-            tcp_limit: 1024,
-        };
+        let config = Config::for_tests();
         let application = Application::new(config);
         let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
         let mut responder = Responder::new();
@@ -119,39 +129,24 @@ mod tests {
         ));
 
         let config = Config {
-            feedback_error_file: Option::None,
-            feedback_info_file: Option::None,
-            filesystem_directory_index: "index.htm".to_string(),
             file_not_found_file: "404_file.htm".to_string(),
-            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
-            server_host: "localhost".to_string(),
-            server_limit: 4,
-            server_port: 4040,
-            tcp_limit: 1024,
+            ..Config::for_tests()
         };
         let application = Application::new(config);
         let mut responder = Responder::new();
-        assert!(!responder.matches(
-            &request::Message::from_tcp_stream(b"GET /index2.htm HTTP/1.0").unwrap(),
-            &application,
-            &socket,
-            &0
-        ));
+        let request = request::Message::from_tcp_stream(b"GET /index2.htm HTTP/1.0").unwrap();
+        assert!(responder.matches(&request, &application, &socket, &0));
+        assert_eq!(responder.filename, None);
+        let response = responder
+            .respond(&request, &application, &socket, &0)
+            .unwrap();
+        assert_eq!(response.status, "404 Not Found");
+        assert_eq!(response.body, error_page::get_fallback_page("404 Not Found"));
     }
 
     #[test]
     fn respond() {
-        let config = Config {
-            feedback_error_file: Option::None,
-            feedback_info_file: Option::None,
-            filesystem_directory_index: "index.htm".to_string(),
-            file_not_found_file: "404.htm".to_string(),
-            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
-            server_host: "localhost".to_string(),
-            server_limit: 4,
-            server_port: 4040,
-            tcp_limit: 1024,
-        };
+        let config = Config::for_tests();
         let application = Application::new(config);
         let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
         let mut responder = Responder::new();
@@ -180,7 +175,12 @@ mod tests {
                 );
                 headers.insert(
                     "ETag".to_string(),
-                    filesystem::Responder::get_modified_hash(&last_modified),
+                    filesystem::Responder::get_etag(
+                        &last_modified,
+                        metadata.len(),
+                        b"",
+                        &application,
+                    ),
                 );
                 let duration = Duration::new(2592000, 0); // TODO Make this dynamic
                 headers.insert(
@@ -195,12 +195,12 @@ mod tests {
         headers.insert("Content-Type".to_string(), mime::from_filename(&filename));
         headers.insert(
             "Cache-Control".to_string(),
-            filesystem::Responder::get_cache_control(&application),
+            filesystem::Responder::get_cache_control(&filename, &application).to_string(),
         );
 
         let expected_response = response::Message::new(
             "HTTP/1.1".to_string(),
-            "404 File Not Found".to_string(),
+            HttpStatus::NotFound.to_string(),
             headers,
             response_body.into_bytes(),
         ).to_bytes();
@@ -1,6 +1,5 @@
 //! # TCP HTTP Filesystem Response
 //! Used for displaying static resources from the server.
-extern crate chrono;
 
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
@@ -8,22 +7,41 @@ use std::fs;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io::prelude::*;
+use std::io::{self, SeekFrom};
 use std::path::Path;
-use std::path::PathBuf;
 use std::time::Duration;
 use std::time::SystemTime;
 
-use chrono::offset::Utc;
-use chrono::{DateTime, TimeZone};
 use std::net::SocketAddr;
 
+use application_layer::http::cache_control::CacheControl;
+use application_layer::http::conditional;
+use application_layer::http::date;
+use application_layer::http::range::{self, Ranges};
 use application_layer::http::request;
 use application_layer::http::response;
+use application_layer::http::status::HttpStatus;
+use application_layer::http::uri;
 
+use digest;
 use mime;
+use random;
+use response::tcp::http::autoindex;
+use response::tcp::http::capabilities::Capabilities;
 use response::tcp::http::ResponderInterface;
+use response::tcp::jobs::{self, Class};
 use Application;
 
+/// # What entity-tags of files are computed from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntityTagSource {
+    /// Hash of the file content, the same on every server but the file is read for every
+    /// request
+    Content,
+    /// Modification time and size, cheap but differs between servers with their own copies
+    Metadata,
+}
+
 #[derive(Clone)]
 pub struct Responder {
     pub filename: Option<String>,
@@ -35,27 +53,13 @@ impl Responder {
     }
 
     pub fn get_metadata_modified_as_rfc7231(modified: SystemTime) -> String {
-        let datetime: DateTime<Utc> = modified.into();
-        format!("{}", datetime.format("%a, %d %b %Y %H:%M:%S GMT"))
+        date::format(modified)
     }
 
     pub fn get_rfc7231_as_systemtime(modified: &String) -> Result<SystemTime, String> {
-        let offset = Utc::now();
-        match offset
-            .offset()
-            .datetime_from_str(&modified, "%a, %d %b %Y %H:%M:%S GMT")
-        {
-            Ok(datetime) => {
-                datetime.with_timezone(&Utc);
-                let modified: SystemTime = datetime.into();
-                return Ok(modified);
-            }
-            Err(error) => {
-                return Err(format!(
-                    "Failed to parse '{}', error: {:?}",
-                    &modified, error
-                ));
-            }
+        match date::parse(&modified) {
+            Some(modified) => Ok(modified),
+            None => Err(format!("Failed to parse '{}'", &modified)),
         }
     }
 
@@ -65,17 +69,121 @@ impl Responder {
         hasher.finish().to_string()
     }
 
-    pub fn get_cache_control(_application: &Application) -> String {
-        return "max-age=2592000".to_string(); // TODO Make this dynamic?
+    /// Quoted entity-tag of a file from it's metadata or content as configured, changing the
+    /// deploy epoch invalidates all previous entity-tags
+    pub fn get_etag(
+        modified: &SystemTime,
+        length: u64,
+        content: &[u8],
+        application: &Application,
+    ) -> String {
+        let deploy_epoch = &application.get_config().deploy_epoch;
+        let tag = match application.get_config().filesystem_etag {
+            EntityTagSource::Content => {
+                let mut digest = digest::sha1(content);
+                if let Some(deploy_epoch) = deploy_epoch {
+                    digest.extend_from_slice(deploy_epoch.as_bytes());
+                    digest = digest::sha1(&digest);
+                }
+                digest::to_hex(&digest)
+            }
+            EntityTagSource::Metadata => {
+                let mut hasher = DefaultHasher::new();
+                modified.hash(&mut hasher);
+                length.hash(&mut hasher);
+                deploy_epoch.hash(&mut hasher);
+                hasher.finish().to_string()
+            }
+        };
+        format!("\"{}\"", tag)
+    }
+
+    /// Cache-Control of the policy configured for the extension of a file, files without one
+    /// are fresh for 30 days
+    pub fn get_cache_control(filename: &str, application: &Application) -> CacheControl {
+        let extension = match Path::new(filename).extension() {
+            Some(extension) => extension.to_string_lossy().to_lowercase(),
+            None => String::new(),
+        };
+        application
+            .get_config()
+            .cache_policies
+            .iter()
+            .find(|(existing, _)| *existing == extension)
+            .map(|(_, cache_control)| cache_control.clone())
+            .unwrap_or_else(|| CacheControl::new().max_age(2592000))
+    }
+
+    /// Content-Type of a file, from the media type configured for it's extension or the
+    /// built-in one
+    pub fn get_content_type(filename: &str, application: &Application) -> String {
+        mime::from_filename_with(filename, &application.get_config().media_types)
+    }
+
+    /// Satisfiable ranges a GET request asks for of the current file, none when the whole file
+    /// is sent and empty when no range is satisfiable
+    pub fn get_ranges(
+        request_message: &request::Message,
+        length: u64,
+        etag: Option<&str>,
+        last_modified: Option<SystemTime>,
+    ) -> Option<Vec<(u64, u64)>> {
+        if request_message.request_line.method != request::Method::Get {
+            return None;
+        }
+        let ranges = Ranges::from_request(request_message)?;
+        if !Ranges::is_if_range_satisfied(
+            &request_message.headers,
+            etag.and_then(conditional::EntityTag::from_str).as_ref(),
+            last_modified,
+        ) {
+            return None;
+        }
+        Some(ranges.resolve(length))
+    }
+
+    /// Bytes `first` to `last` of a file, only they are read
+    pub fn read_range(file: &mut File, first: u64, last: u64) -> io::Result<Vec<u8>> {
+        file.seek(SeekFrom::Start(first))?;
+        let mut content = Vec::with_capacity((last - first + 1) as usize);
+        Read::by_ref(file)
+            .take(last - first + 1)
+            .read_to_end(&mut content)?;
+        if (content.len() as u64) < last - first + 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "File is shorter than it's metadata",
+            ));
+        }
+        Ok(content)
+    }
+
+    /// Decoded request path relative to the file-system root, paths with NUL bytes are refused.
+    /// Dot segments are resolved before decoding, so decoded separators can still form `..`
+    /// segments, the canonical root check of `get_matching_filename` keeps files inside the root.
+    pub fn get_relative_path(path: &str) -> Option<String> {
+        let path = uri::decode(&uri::normalize_path(path));
+        if path.contains('\0') {
+            return None;
+        }
+        Some(path.trim_start_matches('/').to_string())
     }
 
     pub fn get_matching_filename(
         request_message: &request::Message,
         application: &Application,
     ) -> Option<String> {
-        let mut filename = request_message.request_line.request_uri_base.clone();
-        filename = format!("{}{}", application.get_config().filesystem_root, &filename);
-        let temp_filename = PathBuf::from(&filename);
+        // The root is made canonical when the configuration is built
+        let root = Path::new(&application.get_config().filesystem_root);
+        let path = match Responder::get_relative_path(&request_message.request_line.uri.path) {
+            Some(path) => path,
+            None => {
+                application
+                    .error(|| format!("Refused path {:?}", &request_message.request_line.uri.path));
+                return None;
+            }
+        };
+        let temp_filename = root.join(&path);
         let mut is_dir = false;
         match fs::canonicalize(&temp_filename) {
             Ok(canonical_filename) => {
@@ -83,8 +191,10 @@ impl Responder {
                     Some(canonical_filename_string) => {
                         let mut filename = canonical_filename_string.to_string();
 
-                        // Is the file inside file-system root?
-                        if filename.starts_with(&application.get_config().filesystem_root) {
+                        // Is the file inside file-system root? Decoded separators and symbolic
+                        // links can lead out of it, paths are compared by component so a
+                        // sibling like `html-private` is not inside `html`
+                        if canonical_filename.starts_with(root) {
                             let filename_copy = filename.clone();
                             let splits: Vec<&str> = filename_copy.rsplitn(2, '/').collect();
                             if let Some(basename) = splits.get(0) {
@@ -94,52 +204,84 @@ impl Responder {
                                     if exists {
                                         is_dir = Path::new(&filename).is_dir();
                                         if is_dir {
-                                            filename = format!(
-                                                "{}/{}",
-                                                &filename,
-                                                application.get_config().filesystem_directory_index
-                                            );
-                                            exists = Path::new(&filename).exists();
-                                            is_dir = Path::new(&filename).is_dir()
+                                            // The first index file that exists is sent
+                                            let directory = filename.clone();
+                                            for index in application
+                                                .get_config()
+                                                .filesystem_directory_index
+                                                .iter()
+                                            {
+                                                filename = format!("{}/{}", &directory, index);
+                                                exists = Path::new(&filename).exists();
+                                                is_dir = Path::new(&filename).is_dir();
+                                                if exists && !is_dir {
+                                                    break;
+                                                }
+                                            }
+
+                                            // Directories without a index file are listed
+                                            // where it's enabled
+                                            if (!exists || is_dir)
+                                                && autoindex::is_enabled(
+                                                    &request_message.request_line.uri.path,
+                                                    application.get_config(),
+                                                )
+                                            {
+                                                return Some(directory);
+                                            }
                                         }
                                     }
                                     if !exists {
-                                        eprintln!("File does not exists {}", &filename);
+                                        application.error(|| {
+                                            format!("File does not exists {}", &filename)
+                                        });
                                     }
                                     if is_dir {
-                                        eprintln!("File is a directory {}", &filename);
+                                        application.error(|| {
+                                            format!("File is a directory {}", &filename)
+                                        });
                                     }
 
                                     if exists && !is_dir {
                                         return Some(filename);
                                     }
                                 } else {
-                                    eprintln!("Filename {} starts with a dot!", &filename);
+                                    application.error(|| {
+                                        format!("Filename {} starts with a dot!", &filename)
+                                    });
                                 }
                             } else {
-                                eprintln!("Failed to find file base-name {}", &filename);
+                                application.error(|| {
+                                    format!("Failed to find file base-name {}", &filename)
+                                });
                             }
                         } else {
-                            eprintln!(
-                                "File {} is outside of file-system root {}",
-                                &filename,
-                                application.get_config().filesystem_root
-                            );
+                            application.error(|| {
+                                format!(
+                                    "File {} is outside of file-system root {}",
+                                    &filename,
+                                    root.display()
+                                )
+                            });
                         }
                     }
                     None => {
-                        eprintln!(
-                            "Failed to get canonical path string from {:?}",
-                            &canonical_filename
-                        );
+                        application.error(|| {
+                            format!(
+                                "Failed to get canonical path string from {:?}",
+                                &canonical_filename
+                            )
+                        });
                     }
                 }
             }
             Err(error) => {
-                eprintln!(
-                    "Failed to get canonical path to {:?}, error: {}, request: {:?}",
-                    &temp_filename, error, &request_message
-                );
+                application.error(|| {
+                    format!(
+                        "Failed to get canonical path to {:?}, error: {}, request: {:?}",
+                        &temp_filename, error, &request_message
+                    )
+                });
             }
         }
         return None;
@@ -151,113 +293,172 @@ impl Responder {
         request_message: &request::Message,
         application: &Application,
     ) -> Result<response::Message, String> {
-        let mut response_body = Vec::new();
+        Responder::get_file_response(filename, request_message, application, true)
+    }
 
+    /// Response with a file, ranges are only sent when `accept_ranges` so pages sent with
+    /// another status, like the not found page, are always sent whole
+    pub fn get_file_response(
+        filename: &String,
+        request_message: &request::Message,
+        application: &Application,
+        accept_ranges: bool,
+    ) -> Result<response::Message, String> {
         // Try to open the file
-        let file = File::open(filename);
-        match file {
-            Ok(mut file) => {
-                // Try to read the file
-                match file.read_to_end(&mut response_body) {
-                    Ok(_) => {
-                        let mut status_code = "200 OK";
-
-                        let protocol = request::Message::get_protocol_text(
-                            &request_message.request_line.protocol,
-                        );
-                        let mut headers: HashMap<String, String> = HashMap::new();
-
-                        headers.insert("Content-Type".to_string(), mime::from_filename(&filename));
-
-                        if let Ok(metadata) = fs::metadata(&filename) {
-                            headers
-                                .insert("Content-Length".to_string(), metadata.len().to_string());
-
-                            if let Ok(last_modified) = metadata.modified() {
-                                headers.insert(
-                                    "Last-Modified".to_string(),
-                                    Responder::get_metadata_modified_as_rfc7231(last_modified),
-                                );
-                                let etag = Responder::get_modified_hash(&last_modified);
-                                headers.insert("ETag".to_string(), etag.clone());
-
-                                let duration = Duration::new(2592000, 0); // TODO Make this dynamic
-                                headers.insert(
-                                    "Expires".to_string(),
-                                    Responder::get_metadata_modified_as_rfc7231(
-                                        last_modified + duration,
-                                    ),
-                                );
-
-                                if let Some(if_none_match) =
-                                    request_message.headers.get("If-None-Match")
-                                {
-                                    if if_none_match.to_string() == etag {
-                                        status_code = "304 Not Modified";
-                                        response_body = Vec::new();
-                                    }
-                                }
-
-                                if status_code != "304 Not Modified" {
-                                    if let Some(if_modified_since) =
-                                        request_message.headers.get("If-Modified-Since")
-                                    {
-                                        if let Ok(if_modified_since_systemtime) =
-                                            Responder::get_rfc7231_as_systemtime(
-                                                &if_modified_since.to_string(),
-                                            ) {
-                                            if let Ok(duration) = last_modified
-                                                .duration_since(if_modified_since_systemtime)
-                                            {
-                                                /* println!(
-                                                    "{:?} vs {:?} = {}",
-                                                    &if_modified_since_systemtime,
-                                                    &last_modified,
-                                                    duration.as_secs()
-                                                );  */
-                                                if duration.as_secs() <= 0 {
-                                                    status_code = "304 Not Modified";
-                                                    response_body = Vec::new();
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        headers.insert(
-                            "Cache-Control".to_string(),
-                            Responder::get_cache_control(&application),
-                        );
-
-                        // Build HTTP response
-                        return Ok(response::Message::new(
-                            protocol.to_string(),
-                            status_code.to_string(),
-                            headers,
-                            response_body,
-                        ));
-                    }
-                    Err(e) => {
-                        return Err(format!(
-                            "Error: Failed to read file {}, error: {:?}",
-                            filename, e
-                        ));
-                    }
-                }
-            }
+        let mut file = match File::open(filename) {
+            Ok(file) => file,
             Err(e) => {
                 return Err(format!(
                     "Error: Failed to open file {}, error: {:?}",
                     filename, e
                 ));
             }
+        };
+        let metadata = match file.metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                return Err(format!(
+                    "Error: Failed to read metadata of file {}, error: {:?}",
+                    filename, e
+                ));
+            }
+        };
+        let length = metadata.len();
+        let read_error =
+            |e: io::Error| format!("Error: Failed to read file {}, error: {:?}", filename, e);
+
+        // The whole file is only read when it's needed, a single range is read from where it
+        // starts
+        let mut content = None;
+        if application.get_config().filesystem_etag == EntityTagSource::Content {
+            let mut response_body = Vec::new();
+            file.read_to_end(&mut response_body).map_err(read_error)?;
+            content = Some(response_body);
+        }
+
+        let mut status_code = HttpStatus::Ok;
+        let protocol = request::Message::get_protocol_text(&request_message.request_line.protocol);
+        let mut headers: HashMap<String, String> = HashMap::new();
+        let content_type = Responder::get_content_type(&filename, &application);
+        headers.insert("Content-Type".to_string(), content_type.clone());
+        if accept_ranges {
+            headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
+        }
+        headers.insert("Content-Length".to_string(), length.to_string());
+        let cache_control = Responder::get_cache_control(&filename, &application);
+
+        let last_modified = metadata.modified().ok();
+        let mut etag = None;
+        if let Some(last_modified) = last_modified {
+            headers.insert(
+                "Last-Modified".to_string(),
+                Responder::get_metadata_modified_as_rfc7231(last_modified),
+            );
+            let tag = Responder::get_etag(
+                &last_modified,
+                length,
+                content
+                    .as_ref()
+                    .map(|content| content.as_slice())
+                    .unwrap_or(&[]),
+                &application,
+            );
+            headers.insert("ETag".to_string(), tag.clone());
+            etag = Some(tag);
+
+            if let Some(max_age) = cache_control.get_max_age() {
+                headers.insert(
+                    "Expires".to_string(),
+                    Responder::get_metadata_modified_as_rfc7231(
+                        last_modified + Duration::new(max_age, 0),
+                    ),
+                );
+            }
         }
+        headers.insert("Cache-Control".to_string(), cache_control.to_string());
+
+        let conditions = conditional::Conditions::from_request(&request_message);
+        let is_not_modified = last_modified.is_some()
+            && conditions.is_not_modified(
+                last_modified,
+                etag.as_ref()
+                    .and_then(|etag| conditional::EntityTag::from_str(etag))
+                    .as_ref(),
+            );
+        let ranges = if is_not_modified || !accept_ranges {
+            None
+        } else {
+            Responder::get_ranges(
+                request_message,
+                length,
+                etag.as_ref().map(|etag| etag.as_str()),
+                last_modified,
+            )
+        };
+        let response_body = match ranges {
+            _ if is_not_modified => {
+                status_code = HttpStatus::NotModified;
+                Vec::new()
+            }
+            Some(ref ranges) if ranges.is_empty() => {
+                status_code = HttpStatus::RangeNotSatisfiable;
+                headers.insert("Content-Range".to_string(), format!("bytes */{}", length));
+                headers.insert("Content-Length".to_string(), "0".to_string());
+                Vec::new()
+            }
+            Some(ref ranges) if ranges.len() == 1 => {
+                let (first, last) = ranges[0];
+                status_code = HttpStatus::PartialContent;
+                headers.insert(
+                    "Content-Range".to_string(),
+                    range::get_content_range(first, last, length),
+                );
+                headers.insert("Content-Length".to_string(), (last - first + 1).to_string());
+                match content {
+                    Some(content) => content[first as usize..=last as usize].to_vec(),
+                    None => Responder::read_range(&mut file, first, last).map_err(read_error)?,
+                }
+            }
+            Some(ranges) => {
+                let content = match content {
+                    Some(content) => content,
+                    None => Responder::read_range(&mut file, 0, length - 1).map_err(read_error)?,
+                };
+                let boundary = digest::to_hex(&random::bytes(12));
+                let multipart = range::get_multipart(&content, &ranges, &content_type, &boundary);
+                status_code = HttpStatus::PartialContent;
+                headers.insert(
+                    "Content-Type".to_string(),
+                    format!("multipart/byteranges; boundary={}", boundary),
+                );
+                headers.insert("Content-Length".to_string(), multipart.len().to_string());
+                multipart
+            }
+            None => match content {
+                Some(content) => content,
+                None => {
+                    let mut response_body = Vec::new();
+                    file.read_to_end(&mut response_body).map_err(read_error)?;
+                    response_body
+                }
+            },
+        };
+
+        // Build HTTP response
+        Ok(response::Message::new(
+            protocol.to_string(),
+            status_code.to_string(),
+            headers,
+            response_body,
+        ))
     }
 }
 
 impl ResponderInterface for Responder {
+    fn get_capabilities(&self) -> Capabilities {
+        Capabilities::new().needs_body(false)
+    }
+
     fn matches(
         &mut self,
         request_message: &request::Message,
@@ -282,6 +483,10 @@ impl ResponderInterface for Responder {
     ) -> Result<response::Message, String> {
         // Does filename exist?
         if let Some(filename) = &self.filename {
+            jobs::set_class(Class::StaticFile);
+            if Path::new(filename).is_dir() {
+                return autoindex::get_response(Path::new(filename), &request_message);
+            }
             let mut response = Responder::get_response(&filename, &request_message, &application)?;
             // Build HTTP response
             return Ok(response);
@@ -294,22 +499,14 @@ impl ResponderInterface for Responder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use feedback::Level;
+    use response::tcp::http;
     use std::net::{IpAddr, Ipv4Addr};
     use Config;
 
     #[test]
     fn matches() {
-        let config = Config {
-            feedback_error_file: Option::None,
-            feedback_info_file: Option::None,
-            filesystem_directory_index: "index.htm".to_string(),
-            file_not_found_file: "404.htm".to_string(),
-            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
-            server_host: "localhost".to_string(),
-            server_limit: 4,
-            server_port: 4040,
-            tcp_limit: 1024,
-        };
+        let config = Config::for_tests();
         let application = Application::new(config);
 
         let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
@@ -359,19 +556,50 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn get_etag() {
+        let mut config = Config::for_tests();
+        let modified = SystemTime::now();
+        let etag = Responder::get_etag(&modified, 10, b"", &Application::new(config.clone()));
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        assert_ne!(
+            etag,
+            Responder::get_etag(&modified, 11, b"", &Application::new(config.clone()))
+        );
+
+        config.deploy_epoch = Some("1".to_string());
+        let first_epoch_etag =
+            Responder::get_etag(&modified, 10, b"", &Application::new(config.clone()));
+        assert_ne!(etag, first_epoch_etag);
+
+        config.deploy_epoch = Some("2".to_string());
+        let second_epoch_etag =
+            Responder::get_etag(&modified, 10, b"", &Application::new(config.clone()));
+        assert_ne!(first_epoch_etag, second_epoch_etag);
+
+        // Content entity-tags only change with the content
+        config.deploy_epoch = None;
+        config.filesystem_etag = EntityTagSource::Content;
+        let application = Application::new(config.clone());
+        let content_etag = Responder::get_etag(&modified, 10, b"content", &application);
+        assert_eq!(
+            content_etag,
+            Responder::get_etag(&SystemTime::now(), 7, b"content", &application)
+        );
+        assert_ne!(
+            content_etag,
+            Responder::get_etag(&modified, 10, b"changed", &application)
+        );
+        config.deploy_epoch = Some("1".to_string());
+        assert_ne!(
+            content_etag,
+            Responder::get_etag(&modified, 10, b"content", &Application::new(config))
+        );
+    }
+
     #[test]
     fn respond() {
-        let config = Config {
-            feedback_error_file: Option::None,
-            feedback_info_file: Option::None,
-            filesystem_directory_index: "index.htm".to_string(),
-            file_not_found_file: "404.htm".to_string(),
-            filesystem_root: Config::get_canonical_root(&"./html/".to_string()).unwrap(),
-            server_host: "localhost".to_string(),
-            server_limit: 4,
-            server_port: 4040,
-            tcp_limit: 1024,
-        };
+        let config = Config::for_tests();
         let application = Application::new(config);
         let mut responder = Responder::new();
         let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
@@ -397,7 +625,7 @@ mod tests {
                 );
                 headers.insert(
                     "ETag".to_string(),
-                    Responder::get_modified_hash(&last_modified),
+                    Responder::get_etag(&last_modified, metadata.len(), b"", &application),
                 );
                 let duration = Duration::new(2592000, 0); // TODO Make this dynamic
                 headers.insert(
@@ -408,14 +636,15 @@ mod tests {
             headers.insert("Content-Length".to_string(), metadata.len().to_string());
         }
         headers.insert("Content-Type".to_string(), mime::from_filename(&filename));
+        headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
         headers.insert(
             "Cache-Control".to_string(),
-            Responder::get_cache_control(&application),
+            Responder::get_cache_control(&filename, &application).to_string(),
         );
 
         let expected_response = response::Message::new(
             "HTTP/1.1".to_string(),
-            "200 OK".to_string(),
+            HttpStatus::Ok.to_string(),
             headers,
             response_body.into_bytes(),
         ).to_bytes();
@@ -438,7 +667,7 @@ mod tests {
                 );
                 headers.insert(
                     "ETag".to_string(),
-                    Responder::get_modified_hash(&last_modified),
+                    Responder::get_etag(&last_modified, metadata.len(), b"", &application),
                 );
                 let duration = Duration::new(2592000, 0); // TODO Make this dynamic
                 headers.insert(
@@ -447,16 +676,17 @@ mod tests {
                 );
 
                 headers.insert("Content-Type".to_string(), mime::from_filename(&filename));
+                headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
                 headers.insert(
                     "Cache-Control".to_string(),
-                    Responder::get_cache_control(&application),
+                    Responder::get_cache_control(&filename, &application).to_string(),
                 );
 
                 let response_body_empty = Vec::new();
 
                 let expected_response = response::Message::new(
                     "HTTP/1.1".to_string(),
-                    "304 Not Modified".to_string(),
+                    HttpStatus::NotModified.to_string(),
                     headers,
                     response_body_empty,
                 ).to_bytes();
@@ -492,7 +722,7 @@ mod tests {
                 );
                 headers.insert(
                     "ETag".to_string(),
-                    Responder::get_modified_hash(&last_modified),
+                    Responder::get_etag(&last_modified, metadata.len(), b"", &application),
                 );
                 let duration = Duration::new(2592000, 0); // TODO Make this dynamic
                 headers.insert(
@@ -500,9 +730,10 @@ mod tests {
                     Responder::get_metadata_modified_as_rfc7231(last_modified + duration),
                 );
                 headers.insert("Content-Type".to_string(), mime::from_filename(&filename));
+                headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
                 headers.insert(
                     "Cache-Control".to_string(),
-                    Responder::get_cache_control(&application),
+                    Responder::get_cache_control(&filename, &application).to_string(),
                 );
 
                 // Build response body
@@ -512,7 +743,7 @@ mod tests {
 
                 let expected_response = response::Message::new(
                     "HTTP/1.1".to_string(),
-                    "200 OK".to_string(),
+                    HttpStatus::Ok.to_string(),
                     headers,
                     response_body.into_bytes(),
                 ).to_bytes();
@@ -546,7 +777,7 @@ mod tests {
 
                 let request_string = format!(
                     "GET /index.htm HTTP/1.1\r\nIf-None-Match: {}\r\n\r\n",
-                    Responder::get_modified_hash(&last_modified)
+                    Responder::get_etag(&last_modified, metadata.len(), b"", &application)
                 );
                 let request = request::Message::from_tcp_stream(request_string.as_bytes()).unwrap();
                 headers.insert(
@@ -555,7 +786,7 @@ mod tests {
                 );
                 headers.insert(
                     "ETag".to_string(),
-                    Responder::get_modified_hash(&last_modified),
+                    Responder::get_etag(&last_modified, metadata.len(), b"", &application),
                 );
                 let duration = Duration::new(2592000, 0); // TODO Make this dynamic
                 headers.insert(
@@ -564,16 +795,17 @@ mod tests {
                 );
 
                 headers.insert("Content-Type".to_string(), mime::from_filename(&filename));
+                headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
                 headers.insert(
                     "Cache-Control".to_string(),
-                    Responder::get_cache_control(&application),
+                    Responder::get_cache_control(&filename, &application).to_string(),
                 );
 
                 let response_body = Vec::new();
 
                 let expected_response = response::Message::new(
                     "HTTP/1.1".to_string(),
-                    "304 Not Modified".to_string(),
+                    HttpStatus::NotModified.to_string(),
                     headers,
                     response_body,
                 ).to_bytes();
@@ -598,7 +830,7 @@ mod tests {
                 );
                 headers.insert(
                     "ETag".to_string(),
-                    Responder::get_modified_hash(&last_modified),
+                    Responder::get_etag(&last_modified, metadata.len(), b"", &application),
                 );
                 let duration = Duration::new(2592000, 0); // TODO Make this dynamic
                 headers.insert(
@@ -606,9 +838,10 @@ mod tests {
                     Responder::get_metadata_modified_as_rfc7231(last_modified + duration),
                 );
                 headers.insert("Content-Type".to_string(), mime::from_filename(&filename));
+                headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
                 headers.insert(
                     "Cache-Control".to_string(),
-                    Responder::get_cache_control(&application),
+                    Responder::get_cache_control(&filename, &application).to_string(),
                 );
 
                 // Build response body
@@ -618,7 +851,7 @@ mod tests {
 
                 let expected_response = response::Message::new(
                     "HTTP/1.1".to_string(),
-                    "200 OK".to_string(),
+                    HttpStatus::Ok.to_string(),
                     headers,
                     response_body.into_bytes(),
                 ).to_bytes();
@@ -627,7 +860,7 @@ mod tests {
                 let last_modified = last_modified - duration;
                 let request_string = format!(
                     "GET /index.htm HTTP/1.1\r\nIf-None-Match: {}\r\n\r\n",
-                    Responder::get_modified_hash(&last_modified)
+                    Responder::get_etag(&last_modified, metadata.len(), b"", &application)
                 );
                 let request = request::Message::from_tcp_stream(request_string.as_bytes()).unwrap();
                 let given_response = responder
@@ -645,4 +878,305 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn respond_binary() {
+        let config = Config::for_tests();
+        let application = Application::new(config);
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let image = fs::read("html/img/logo1-modified.jpg").unwrap();
+        assert!(String::from_utf8(image.clone()).is_err());
+
+        // Bytes that are not UTF-8 pass the whole responder chain unchanged
+        let request = b"GET /img/logo1-modified.jpg HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let mut dispatcher = http::Dispatcher::new();
+        assert!(dispatcher.feed(request));
+        let responders: Vec<Box<ResponderInterface + Send>> = vec![Box::new(Responder::new())];
+        let (response, _) = dispatcher
+            .respond(request, &application, &socket, responders, &0)
+            .unwrap();
+        assert!(response.ends_with(&image));
+        let head = String::from_utf8_lossy(&response[..response.len() - image.len()]);
+        assert!(head.contains(&format!("Content-Length: {}\r\n", image.len())));
+        assert!(head.contains("Content-Type: image/"));
+    }
+
+    #[test]
+    fn respond_ranges() {
+        let application = Application::new(Config {
+            feedback_level: Level::Off,
+            ..Config::for_tests()
+        });
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let content = fs::read("html/index.htm").unwrap();
+        let respond = |request: &[u8]| {
+            let request = request::Message::from_tcp_stream(request).unwrap();
+            let mut responder = Responder::new();
+            assert!(responder.matches(&request, &application, &socket, &0));
+            responder
+                .respond(&request, &application, &socket, &0)
+                .unwrap()
+        };
+
+        let response =
+            respond(b"GET /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes=0-1,-2\r\n\r\n");
+        assert_eq!(response.status, "206 Partial Content");
+        let content_type = &response.headers["Content-Type"];
+        assert!(content_type.starts_with("multipart/byteranges; boundary="));
+        let boundary = &content_type[31..];
+        let mut expected = format!(
+            "--{}\r\nContent-Type: text/html\r\nContent-Range: bytes 0-1/{}\r\n\r\n",
+            boundary,
+            content.len()
+        ).into_bytes();
+        expected.extend_from_slice(&content[..2]);
+        expected.extend_from_slice(
+            format!(
+                "\r\n--{}\r\nContent-Type: text/html\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                boundary,
+                content.len() - 2,
+                content.len() - 1,
+                content.len()
+            ).as_bytes(),
+        );
+        expected.extend_from_slice(&content[content.len() - 2..]);
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        assert_eq!(response.body, expected);
+        assert_eq!(
+            response.headers["Content-Length"],
+            response.body.len().to_string()
+        );
+
+        // A single range, also from overlapping ones, is read from where it starts
+        for request in [
+            &b"GET /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes=2-5\r\n\r\n"[..],
+            &b"GET /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes=2-3,3-5\r\n\r\n"[..],
+        ].iter()
+        {
+            let response = respond(request);
+            assert_eq!(response.status, "206 Partial Content");
+            assert_eq!(response.body, content[2..6].to_vec());
+            assert_eq!(
+                response.headers["Content-Range"],
+                format!("bytes 2-5/{}", content.len())
+            );
+            assert_eq!(response.headers["Content-Length"], "4");
+            assert_eq!(response.headers["Content-Type"], "text/html");
+        }
+        let response =
+            respond(b"GET /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes=-10000000\r\n\r\n");
+        assert_eq!(response.status, "206 Partial Content");
+        assert_eq!(response.body, content);
+
+        // No satisfiable range
+        let request = format!(
+            "GET /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes={}-\r\n\r\n",
+            content.len()
+        );
+        let response = respond(request.as_bytes());
+        assert_eq!(response.status, "416 Range Not Satisfiable");
+        assert_eq!(
+            response.headers["Content-Range"],
+            format!("bytes */{}", content.len())
+        );
+        assert_eq!(response.headers["Content-Length"], "0");
+        assert!(response.body.is_empty());
+
+        // Ranges of a changed file, HEAD requests and invalid ranges get the whole file
+        for request in [
+            &b"GET /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes=0-1,4-5\r\n\
+               If-Range: \"x\"\r\n\r\n"[..],
+            &b"HEAD /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes=0-1,4-5\r\n\r\n"[..],
+            &b"GET /index.htm HTTP/1.1\r\nHost: a\r\nRange: bytes=5-1\r\n\r\n"[..],
+            &b"GET /index.htm HTTP/1.1\r\nHost: a\r\n\r\n"[..],
+        ].iter()
+        {
+            let response = respond(request);
+            assert_eq!(response.status, "200 OK");
+            assert_eq!(response.body, content);
+            assert_eq!(response.headers["Accept-Ranges"], "bytes");
+        }
+    }
+
+    #[test]
+    fn get_cache_control() {
+        let config = Config {
+            feedback_level: Level::Off,
+            ..Config::for_tests()
+        }.cache_policy(".CSS", CacheControl::new().public().max_age(31536000).immutable())
+        .cache_policy("htm", CacheControl::new().no_cache());
+        let application = Application::new(config);
+        assert_eq!(
+            Responder::get_cache_control("html/css/style.css", &application).to_string(),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            Responder::get_cache_control("html/index.htm", &application).to_string(),
+            "no-cache"
+        );
+        assert_eq!(
+            Responder::get_cache_control("html/favicon.ico", &application).to_string(),
+            "max-age=2592000"
+        );
+
+        // Files without a max-age get no Expires
+        let request = request::Message::from_tcp_stream(
+            b"GET /index.htm HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        ).unwrap();
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let mut responder = Responder::new();
+        assert!(responder.matches(&request, &application, &socket, &0));
+        let response = responder
+            .respond(&request, &application, &socket, &0)
+            .unwrap();
+        assert_eq!(
+            response.headers.get("Cache-Control"),
+            Some(&"no-cache".to_string())
+        );
+        assert!(!response.headers.contains_key("Expires"));
+    }
+
+    #[test]
+    fn get_content_type() {
+        let config = Config {
+            feedback_level: Level::Off,
+            ..Config::for_tests()
+        }.media_type("htm", "text/html; charset=utf-8");
+        let application = Application::new(config);
+        assert_eq!(
+            Responder::get_content_type("html/css/style.css", &application),
+            "text/css"
+        );
+        assert_eq!(
+            Responder::get_content_type("html/img/logo1-modified.jpg", &application),
+            "image/jpeg"
+        );
+
+        let request = request::Message::from_tcp_stream(
+            b"GET /index.htm HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        ).unwrap();
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let mut responder = Responder::new();
+        assert!(responder.matches(&request, &application, &socket, &0));
+        let response = responder
+            .respond(&request, &application, &socket, &0)
+            .unwrap();
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"text/html; charset=utf-8".to_string())
+        );
+    }
+
+    #[test]
+    fn get_matching_filename() {
+        let get_filename = |indexes: &[&str], path: &str| {
+            let application = Application::new(Config {
+                feedback_level: Level::Off,
+                filesystem_directory_index: indexes.iter().map(|index| index.to_string()).collect(),
+                ..Config::for_tests()
+            });
+            let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+            let request = request::Message::from_tcp_stream(request.as_bytes()).unwrap();
+            Responder::get_matching_filename(&request, &application)
+        };
+        let root = Config::get_canonical_root(&"./html/".to_string()).unwrap();
+        assert_eq!(
+            get_filename(&["index.html", "about.htm", "index.htm"], "/"),
+            Some(format!("{}/about.htm", root))
+        );
+        assert_eq!(get_filename(&["index.html", "css"], "/"), None);
+        assert_eq!(get_filename(&[], "/"), None);
+        assert_eq!(get_filename(&["index.htm"], "/css/"), None);
+    }
+
+    #[test]
+    fn traversal() {
+        let get_filename = |root: &str, path: &str| {
+            let application = Application::new(Config {
+                feedback_level: Level::Off,
+                filesystem_root: Config::get_canonical_root(&root.to_string()).unwrap(),
+                ..Config::for_tests()
+            });
+            let mut request =
+                request::Message::from_tcp_stream(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                    .unwrap();
+            // Set after parsing so paths the parser would normalize reach the responder as is
+            request.request_line.uri.path = path.to_string();
+            Responder::get_matching_filename(&request, &application)
+        };
+        for path in &[
+            "/../README.md",
+            "/%2e%2e/README.md",
+            "/..%2fREADME.md",
+            "/..%2FREADME.md",
+            "/%2e%2e%2fREADME.md",
+            "/css/..%2f..%2fREADME.md",
+            "/css/%2E%2E/%2e%2e/src/lib.rs",
+            "/index.htm%00.css",
+        ] {
+            assert_eq!(get_filename("./html/", path), None, "{}", path);
+        }
+
+        // Encoded names and separators that stay inside the root are served
+        let root = Config::get_canonical_root(&"./html/".to_string()).unwrap();
+        assert_eq!(
+            get_filename("./html/", "/css/style%2Ecss"),
+            Some(format!("{}/css/style.css", root))
+        );
+        assert_eq!(
+            get_filename("./html", "/css%2fstyle.css"),
+            Some(format!("{}/css/style.css", root))
+        );
+        assert_eq!(
+            get_filename("./html/", "/css/../about.htm"),
+            Some(format!("{}/about.htm", root))
+        );
+
+        // A sibling directory whose name starts with the name of the root is outside of it
+        let directory = ::std::env::temp_dir().join(format!("milstian-{}", ::std::process::id()));
+        fs::create_dir_all(directory.join("html")).unwrap();
+        fs::create_dir_all(directory.join("html-private")).unwrap();
+        fs::write(directory.join("html-private/secret.txt"), "secret").unwrap();
+        let root = directory.join("html").to_str().unwrap().to_string();
+        assert_eq!(get_filename(&root, "/..%2fhtml-private%2fsecret.txt"), None);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn autoindex() {
+        let config = Config::for_tests()
+            .autoindex("/", true)
+            .autoindex("/img/", false);
+        let application = Application::new(config);
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let get_request = |path: &str| {
+            let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+            request::Message::from_tcp_stream(request.as_bytes()).unwrap()
+        };
+
+        let request = get_request("/css/");
+        let mut responder = Responder::new();
+        assert!(responder.matches(&request, &application, &socket, &0));
+        let response = responder
+            .respond(&request, &application, &socket, &0)
+            .unwrap();
+        assert_eq!(response.status, "200 OK");
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"text/html; charset=utf-8".to_string())
+        );
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("<a href=\"/css/style.css\">style.css</a>"));
+
+        // Directories with a index file and disabled paths are not listed
+        let root = Config::get_canonical_root(&"./html/".to_string()).unwrap();
+        assert_eq!(
+            Responder::get_matching_filename(&get_request("/"), &application),
+            Some(format!("{}/index.htm", root))
+        );
+        assert_eq!(
+            Responder::get_matching_filename(&get_request("/img/"), &application),
+            None
+        );
+    }
 }
@@ -0,0 +1,376 @@
+//! # TCP HTTP Golden files
+//! Used for locking in responder output across refactors, the first run records the response
+//! to a file and subsequent runs compares against it. Set the environment variable
+//! `MILSTIAN_UPDATE_GOLDEN` to re-record files. Responders can also be run against literal
+//! HTTP transcripts, like a request and response copied from `curl -i`.
+
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::str;
+
+use application_layer::http::{request, response};
+use response::tcp::http::ResponderInterface;
+use Application;
+
+/// # Rules for making responses comparable between runs
+/// Values of masked headers are replaced since they change with time or file-system.
+#[derive(Clone, Debug)]
+pub struct Normalization {
+    pub masked_headers: Vec<String>,
+}
+
+impl Normalization {
+    pub fn new() -> Normalization {
+        Normalization {
+            masked_headers: vec![
+                "Date".to_string(),
+                "ETag".to_string(),
+                "Expires".to_string(),
+                "Last-Modified".to_string(),
+            ],
+        }
+    }
+
+    fn is_masked(&self, name: &str) -> bool {
+        self.masked_headers
+            .iter()
+            .any(|masked_header| masked_header.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Serialize response into a stable text format with sorted headers
+pub fn serialize(response: &response::Message, normalization: &Normalization) -> String {
+    let mut serialized = format!("{}\n", &response.status);
+
+    let mut headers: Vec<(&String, &String)> = response.headers.iter().collect();
+    headers.sort();
+    for (name, value) in headers {
+        if normalization.is_masked(name) {
+            serialized.push_str(&format!("{}: <masked>\n", name));
+        } else {
+            serialized.push_str(&format!("{}: {}\n", name, value));
+        }
+    }
+    serialized.push('\n');
+
+    match str::from_utf8(&response.body) {
+        Ok(body) => serialized.push_str(body),
+        Err(_) => serialized.push_str(&format!("<binary body of {} bytes>", response.body.len())),
+    }
+    serialized
+}
+
+/// Compare response with golden file, records the file if it does not exist
+/// # Example
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::env;
+/// use milstian_internet_framework::application_layer::http::response;
+/// use milstian_internet_framework::response::tcp::http::golden;
+/// let response = response::Message::new(
+///     "HTTP/1.1".to_string(),
+///     "200 OK".to_string(),
+///     HashMap::new(),
+///     b"Hello".to_vec(),
+/// );
+/// let filename = env::temp_dir().join("milstian-golden-example.txt");
+/// let filename = filename.to_str().unwrap();
+/// let normalization = golden::Normalization::new();
+/// assert!(golden::assert_golden(filename, &response, &normalization).is_ok());
+/// ```
+pub fn assert_golden(
+    filename: &str,
+    response: &response::Message,
+    normalization: &Normalization,
+) -> Result<(), String> {
+    let given = serialize(&response, &normalization);
+
+    if !Path::new(filename).exists() || env::var("MILSTIAN_UPDATE_GOLDEN").is_ok() {
+        return match File::create(filename) {
+            Ok(mut file) => match file.write_all(given.as_bytes()) {
+                Ok(_) => Ok(()),
+                Err(error) => Err(format!(
+                    "Failed to write golden file {}, error: {}",
+                    filename, error
+                )),
+            },
+            Err(error) => Err(format!(
+                "Failed to create golden file {}, error: {}",
+                filename, error
+            )),
+        };
+    }
+
+    match fs::read_to_string(filename) {
+        Ok(expected) => {
+            if expected == given {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Response differs from golden file {}:\n{}",
+                    filename,
+                    get_diff(&expected, &given)
+                ))
+            }
+        }
+        Err(error) => Err(format!(
+            "Failed to read golden file {}, error: {}",
+            filename, error
+        )),
+    }
+}
+
+/// Line-by-line difference between expected and given text
+pub fn get_diff(expected: &str, given: &str) -> String {
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let given_lines: Vec<&str> = given.lines().collect();
+    let mut diff = String::new();
+    let length = expected_lines.len().max(given_lines.len());
+    for index in 0..length {
+        let expected_line = expected_lines.get(index);
+        let given_line = given_lines.get(index);
+        if expected_line != given_line {
+            if let Some(expected_line) = expected_line {
+                diff.push_str(&format!("{}: - {}\n", index + 1, expected_line));
+            }
+            if let Some(given_line) = given_line {
+                diff.push_str(&format!("{}: + {}\n", index + 1, given_line));
+            }
+        }
+    }
+    diff
+}
+
+/// Run a responder against a literal request from a loopback client. Line feeds are converted
+/// to CRLF when the request has none so transcripts can be written as plain text.
+pub fn run_transcript(
+    responder: &mut ResponderInterface,
+    application: &Application,
+    request: &str,
+) -> Result<response::Message, String> {
+    let request = if request.contains("\r\n") {
+        request.to_string()
+    } else {
+        request.replace('\n', "\r\n")
+    };
+    let request_message = match request::Message::from_tcp_stream(request.as_bytes()) {
+        Ok(request_message) => request_message,
+        Err(error) => return Err(format!("Failed to decode transcript request, error: {}", error)),
+    };
+    let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+    if !responder.matches(&request_message, application, &socket, &0) {
+        return Err("Responder did not match transcript request".to_string());
+    }
+    responder.respond(&request_message, application, &socket, &0)
+}
+
+/// Run a responder against a literal request and compare with the expected response text.
+/// Listed headers must be present, other headers are ignored. Masked headers and the value
+/// `*` match any value.
+/// # Example
+/// ```rust,no_run
+/// use milstian_internet_framework::response::tcp::http::{error, golden};
+/// use milstian_internet_framework::{Application, Config};
+/// let application = Application::new(Config::from_env().unwrap());
+/// golden::assert_transcript(
+///     &mut error::Responder::new(),
+///     &application,
+///     "GET / HTTP/1.1\nHost: localhost\n\n",
+///     "HTTP/1.1 500 Internal Server Error\n\n",
+///     &golden::Normalization::new(),
+/// ).unwrap();
+/// ```
+pub fn assert_transcript(
+    responder: &mut ResponderInterface,
+    application: &Application,
+    request: &str,
+    expected: &str,
+    normalization: &Normalization,
+) -> Result<(), String> {
+    let response = run_transcript(responder, application, request)?;
+    let expected = expected.replace("\r\n", "\n");
+    let (head, body) = match expected.find("\n\n") {
+        Some(position) => (&expected[..position], &expected[position + 2..]),
+        None => (expected.trim_end_matches('\n'), ""),
+    };
+
+    let mut differences = Vec::new();
+    let mut lines = head.lines();
+    let status_line = format!("{} {}", &response.protocol, &response.status);
+    if let Some(expected_status_line) = lines.next() {
+        if expected_status_line != status_line {
+            differences.push(format!("- {}\n+ {}", expected_status_line, status_line));
+        }
+    }
+    for line in lines {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        let given = response
+            .headers
+            .iter()
+            .find(|(given_name, _)| given_name.eq_ignore_ascii_case(name))
+            .map(|(_, given_value)| given_value);
+        match given {
+            Some(given) => {
+                if value != "*" && !normalization.is_masked(name) && value != given {
+                    differences.push(format!("- {}: {}\n+ {}: {}", name, value, name, given));
+                }
+            }
+            None => differences.push(format!("- {}: {}\n+ <missing>", name, value)),
+        }
+    }
+
+    let given_body = String::from_utf8_lossy(&response.body).replace("\r\n", "\n");
+    if given_body != body {
+        differences.push(format!("Body:\n{}", get_diff(body, &given_body)));
+    }
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Response differs from transcript:\n{}",
+            differences.join("\n")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use Config;
+
+    #[derive(Clone)]
+    struct Hello;
+
+    impl ResponderInterface for Hello {
+        fn matches(
+            &mut self,
+            _request_message: &request::Message,
+            _application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> bool {
+            true
+        }
+
+        fn respond(
+            &self,
+            request_message: &request::Message,
+            _application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> Result<response::Message, String> {
+            let mut headers = HashMap::new();
+            headers.insert("Content-Type".to_string(), "text/plain".to_string());
+            headers.insert("Date".to_string(), "Fri, 16 Oct 2026 10:00:00 GMT".to_string());
+            Ok(response::Message::new(
+                request::Message::get_protocol_text(&request_message.request_line.protocol)
+                    .to_string(),
+                "200 OK".to_string(),
+                headers,
+                b"Hello\r\nworld".to_vec(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut headers: HashMap<String, String> = HashMap::new();
+        headers.insert("Content-Type".to_string(), "text/plain".to_string());
+        headers.insert("Date".to_string(), "Thu, 01 Jan 1970 00:00:00 GMT".to_string());
+        let response = response::Message::new(
+            "HTTP/1.1".to_string(),
+            "200 OK".to_string(),
+            headers,
+            b"Hello".to_vec(),
+        );
+        assert_eq!(
+            serialize(&response, &Normalization::new()),
+            "200 OK\nContent-Type: text/plain\nDate: <masked>\n\nHello".to_string()
+        );
+
+        let response = response::Message::new(
+            "HTTP/1.1".to_string(),
+            "200 OK".to_string(),
+            HashMap::new(),
+            vec![0, 159, 146, 150],
+        );
+        assert_eq!(
+            serialize(&response, &Normalization::new()),
+            "200 OK\n\n<binary body of 4 bytes>".to_string()
+        );
+    }
+
+    #[test]
+    fn test_assert_golden() {
+        let filename = env::temp_dir().join("milstian-golden-test.txt");
+        let filename = filename.to_str().unwrap();
+        let _ = fs::remove_file(filename);
+        let normalization = Normalization::new();
+
+        let response = response::Message::new(
+            "HTTP/1.1".to_string(),
+            "200 OK".to_string(),
+            HashMap::new(),
+            b"Hello".to_vec(),
+        );
+        assert!(assert_golden(filename, &response, &normalization).is_ok());
+        assert!(assert_golden(filename, &response, &normalization).is_ok());
+
+        let response = response::Message::new(
+            "HTTP/1.1".to_string(),
+            "404 Not Found".to_string(),
+            HashMap::new(),
+            b"Hello".to_vec(),
+        );
+        let error = assert_golden(filename, &response, &normalization).unwrap_err();
+        assert!(error.ends_with("1: - 200 OK\n1: + 404 Not Found\n"));
+        let _ = fs::remove_file(filename);
+    }
+
+    #[test]
+    fn test_assert_transcript() {
+        let application = Application::new(Config::for_tests());
+        let mut responder = Hello;
+        let normalization = Normalization::new();
+        let response =
+            run_transcript(&mut responder, &application, "GET / HTTP/1.1\nHost: a\n\n").unwrap();
+        assert_eq!(response.body, b"Hello\r\nworld".to_vec());
+
+        // Masked headers, wildcards and extra headers are tolerated
+        assert!(assert_transcript(
+            &mut responder,
+            &application,
+            "GET / HTTP/1.1\r\nHost: a\r\n\r\n",
+            "HTTP/1.1 200 OK\ncontent-type: *\nDate: Thu, 01 Jan 1970 00:00:00 GMT\n\nHello\nworld",
+            &normalization
+        )
+        .is_ok());
+
+        let error = assert_transcript(
+            &mut responder,
+            &application,
+            "GET / HTTP/1.0\n\n",
+            "HTTP/1.1 200 OK\nContent-Type: text/html\nX-Missing: 1\n\nHello",
+            &normalization,
+        )
+        .unwrap_err();
+        assert!(error.contains("- HTTP/1.1 200 OK\n+ HTTP/1.0 200 OK"));
+        assert!(error.contains("- Content-Type: text/html\n+ Content-Type: text/plain"));
+        assert!(error.contains("- X-Missing: 1\n+ <missing>"));
+        assert!(error.contains("Body:\n2: + world\n"));
+        assert!(run_transcript(&mut responder, &application, "\n\n").is_err());
+    }
+
+    #[test]
+    fn test_get_diff() {
+        assert_eq!(get_diff("a\nb", "a\nb"), "");
+        assert_eq!(get_diff("a\nb", "a\nc\nd"), "2: - b\n2: + c\n3: + d\n");
+    }
+}
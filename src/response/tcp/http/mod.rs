@@ -1,82 +1,645 @@
 //! # TCP HTTP Legacy responders
 //! A collection of built-in TCP HTTP responders.
 
+pub mod admin;
+pub mod assets;
+pub mod autoindex;
+pub mod canned;
+pub mod capabilities;
+pub mod context;
+pub mod download;
+pub mod echo;
 pub mod error;
+pub mod error_page;
+pub mod event_stream;
 pub mod file_not_found;
 pub mod filesystem;
+pub mod golden;
+pub mod options;
+#[macro_use]
+pub mod router;
 
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::mem;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::SystemTime;
 
-use application_layer::http::request;
+use application_layer::http::body;
+use application_layer::http::compression;
+use application_layer::http::cookie::SetCookie;
+use application_layer::http::date;
+use application_layer::http::forwarded::Cidr;
+use application_layer::http::header::parse;
+use application_layer::http::host::Host;
+use application_layer::http::http2;
+use application_layer::http::parser::{BodyFilter, Limit, Limits, ParseState, Parser};
+use application_layer::http::request::{self, ParseError};
 use application_layer::http::response;
+use application_layer::http::status::HttpStatus;
 
-use Application;
+use response::tcp::http::canned::Canned;
+use response::tcp::http::capabilities::Capabilities;
+use {Application, Config};
+
+/// # How a body streamed by the responder is written after the response head
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseStream {
+    Chunked,
+    /// The length is known, exactly that many bytes are sent as they are
+    Length(u64),
+    /// HTTP/1.0 clients can not decode chunks, the end of the body is signaled by closing
+    UntilClose,
+}
 
 pub struct Dispatcher {
+    pub error: Option<ParseError>,
+    /// Should the connection be kept open after the response?
+    pub keep_alive: bool,
+    pub limit_exceeded: Option<Limit>,
+    parser: Parser,
     pub request_message: Option<request::Message>,
+    /// Set when the response body is streamed from the context of the application
+    pub response_stream: Option<ResponseStream>,
 }
 
 impl Dispatcher {
     pub fn new() -> Dispatcher {
+        Dispatcher::with_limits(Limits::unlimited())
+    }
+
+    pub fn with_limits(limits: Limits) -> Dispatcher {
         Dispatcher {
+            error: None,
+            keep_alive: false,
+            limit_exceeded: None,
+            parser: Parser::with_limits(limits),
             request_message: None,
+            response_stream: None,
+        }
+    }
+
+    /// Push data as it arrives from the stream, returns true when no more data is needed
+    pub fn feed(&mut self, data: &[u8]) -> bool {
+        match self.parser.feed(data) {
+            ParseState::Complete(request_message) => {
+                self.request_message = Some(request_message);
+                true
+            }
+            ParseState::Incomplete => false,
+            ParseState::Invalid(error) => {
+                self.error = Some(error);
+                true
+            }
+            ParseState::TooLarge(limit) => {
+                self.error = Some(ParseError::TooLarge(limit.clone()));
+                self.limit_exceeded = Some(limit);
+                true
+            }
+        }
+    }
+
+    /// Discard bodies of requests that no responder reading bodies would answer
+    pub fn set_capabilities(&mut self, capabilities: Vec<Capabilities>) {
+        self.parser
+            .set_body_filter(BodyFilter::new(Box::new(move |request_line: &request::Line| {
+                capabilities.iter().any(|capabilities| {
+                    capabilities.needs_body
+                        && capabilities.accepts(request_line.method, &request_line.uri.path)
+                })
+            })));
+    }
+
+    /// Bytes received after the end of the request
+    pub fn get_buffer(&self) -> &[u8] {
+        self.parser.get_buffer()
+    }
+
+    /// Was the body of the request left on the connection?
+    pub fn is_streamed(&self) -> bool {
+        self.parser.is_streamed()
+    }
+
+    /// Let responders read a body that was left on the connection from `stream`
+    pub fn set_body_stream(&mut self, stream: Box<Read + Send>) {
+        if let (Some(request_message), Some((framing, received))) =
+            (&mut self.request_message, self.parser.take_streamed())
+        {
+            request_message.body_stream =
+                body::Stream::new(body::Reader::new(framing, received, stream));
+        }
+    }
+
+    /// HTTP/1.1 connections persist unless closed by the client, HTTP/1.0 connections only
+    /// persist when asked to, see RFC 7230 section 6.3. Connections are closed after
+    /// streamed bodies since responders may leave parts of them unread.
+    pub fn is_persistent(&self) -> bool {
+        if self.is_streamed() {
+            return false;
+        }
+        let request_message = match &self.request_message {
+            Some(request_message) => request_message,
+            None => return false,
+        };
+        let options: Vec<String> = request_message
+            .headers
+            .get_all("Connection")
+            .iter()
+            .flat_map(|value| parse::split_list(value))
+            .map(|option| option.to_lowercase())
+            .collect();
+        if options.iter().any(|option| option == "close") {
+            return false;
+        }
+        match request_message.request_line.protocol {
+            request::Protocol::V1_1 => true,
+            request::Protocol::V1_0 => options.iter().any(|option| option == "keep-alive"),
+            _ => false,
         }
     }
+
+    /// Client for access logs, the socket when the request was not forwarded by a trusted proxy
+    pub fn get_log_client(
+        request_message: &request::Message,
+        socket: &SocketAddr,
+        trusted_proxies: &[Cidr],
+    ) -> String {
+        let client = request_message.get_client_addr(socket, trusted_proxies);
+        if client == socket.ip() {
+            socket.to_string()
+        } else {
+            client.to_string()
+        }
+    }
+
+    /// Status and reason when the request should be rejected before reaching responders
+    fn get_error(&self) -> Option<(HttpStatus, String)> {
+        match &self.error {
+            Some(error) => Some((error.get_status(), error.to_string())),
+            None => match &self.request_message {
+                Some(request_message) => match Host::validate(request_message) {
+                    Ok(()) => None,
+                    Err(error) => Some((HttpStatus::BadRequest, error)),
+                },
+                None => None,
+            },
+        }
+    }
+
+    fn get_error_log(socket: &SocketAddr, status: HttpStatus, reason: &str) -> String {
+        format!(
+            "HTTP access - \"{}\",\"\",\"\",\"\",\"{}\",\"0\" - {}",
+            socket, status, reason
+        )
+    }
+
+    /// Response and log line for a request that should be rejected before reaching responders
+    pub fn get_error_response(
+        &self,
+        socket: &SocketAddr,
+        config: &Config,
+    ) -> Option<(Vec<u8>, String)> {
+        // HTTP/2 clients can not read a HTTP/1 response, this is where a HTTP/2 handler goes
+        if self.error == Some(ParseError::Http2Preface) {
+            let error = ParseError::Http2Preface;
+            let log = format!(
+                "HTTP access - \"{}\",\"PRI * HTTP/2.0\",\"\",\"\",\"{}\",\"0\" - {}",
+                socket,
+                error.get_status(),
+                error
+            );
+            return Some((http2::get_rejection("HTTP/2 is not supported, use HTTP/1.1"), log));
+        }
+        let (status, reason) = self.get_error()?;
+        let mut headers = HashMap::new();
+        headers.insert("Connection".to_string(), "close".to_string());
+        let mut response = response::Message::new(
+            "HTTP/1.1".to_string(),
+            status.to_string(),
+            headers,
+            Vec::new(),
+        );
+        let mut reason = reason;
+        if let Err(error) = error_page::set_error_page(&mut response, config) {
+            reason = format!("{}, {}", reason, error);
+        }
+        Dispatcher::set_default_headers(&mut response, true, &config.server_name);
+        let response = response::to_ordered_bytes(&response, &config.header_order);
+        let log = Dispatcher::get_error_log(socket, status, &reason);
+        Some((response, log))
+    }
+
+    /// Like `get_error_response` but with the canned response of the status, when it has one
+    pub fn get_canned_error_response(
+        &self,
+        socket: &SocketAddr,
+        canned: &Canned,
+    ) -> Option<(Arc<Vec<u8>>, String)> {
+        if self.error == Some(ParseError::Http2Preface) {
+            return None;
+        }
+        let (status, reason) = self.get_error()?;
+        let response = canned.get(status)?;
+        Some((response, Dispatcher::get_error_log(socket, status, &reason)))
+    }
 }
 
 impl Dispatcher {
     pub fn matches(
         &mut self,
         request: &[u8],
-        _application: &Application,
+        application: &Application,
         _socket: &SocketAddr,
         _overflow_bytes: &u64,
     ) -> bool {
-        if let Some(request_message) = request::Message::from_tcp_stream(request) {
-            self.request_message = Some(request_message);
+        if self.request_message.is_some() {
             return true;
         }
-        false
+
+        // Stream ended before the parser completed, decode what was received
+        match request::Message::from_tcp_stream(request) {
+            Ok(request_message) => {
+                self.request_message = Some(request_message);
+                true
+            }
+            Err(error) => {
+                application.info(|| format!("Failed to decode HTTP request, error: {}", error));
+                false
+            }
+        }
+    }
+
+    /// Prepare a response when the responder streams it's body, the body of the message is
+    /// sent first. Bodies of known length are sent as they are with a Content-Length, others
+    /// are chunked. Responses to HEAD requests leave the stream unread.
+    fn set_response_stream(
+        response: &mut response::Message,
+        application: &Application,
+        protocol: request::Protocol,
+        is_head: bool,
+    ) -> Option<ResponseStream> {
+        let length = application.get_context().get_response_length();
+        let reader = application.get_context().take_response_body()?;
+        let content_length = length.map(|length| response.body.len() as u64 + length);
+        if let Some(content_length) = content_length {
+            response
+                .headers
+                .retain(|name, _| !name.eq_ignore_ascii_case("Content-Length"));
+            response
+                .headers
+                .insert("Content-Length".to_string(), content_length.to_string());
+        }
+        let is_chunked = content_length.is_none() && protocol == request::Protocol::V1_1;
+        if is_chunked {
+            response
+                .headers
+                .insert("Transfer-Encoding".to_string(), "chunked".to_string());
+        }
+        if is_head {
+            return None;
+        }
+        let initial = mem::replace(&mut response.body, Vec::new());
+        application
+            .get_context()
+            .set_response_body(Box::new(io::Cursor::new(initial).chain(reader)));
+        if let Some(content_length) = content_length {
+            Some(ResponseStream::Length(content_length))
+        } else if is_chunked {
+            Some(ResponseStream::Chunked)
+        } else {
+            Some(ResponseStream::UntilClose)
+        }
+    }
+
+    /// Add a Date, the Server name and, when the body is sent in full, a Content-Length unless
+    /// the responder already did, see RFC 7231 section 7.1.1.2 and RFC 7230 section 3.3.2
+    fn set_default_headers(
+        response: &mut response::Message,
+        has_full_body: bool,
+        server_name: &Option<String>,
+    ) {
+        let has_header = |response: &response::Message, name: &str| {
+            response
+                .headers
+                .keys()
+                .any(|existing| existing.eq_ignore_ascii_case(name))
+        };
+        if !has_header(response, "Date") {
+            response
+                .headers
+                .insert("Date".to_string(), date::format(SystemTime::now()));
+        }
+        if let Some(server_name) = server_name {
+            if !has_header(response, "Server") {
+                response
+                    .headers
+                    .insert("Server".to_string(), server_name.to_string());
+            }
+        }
+
+        // Responses to HEAD describe a body that is not sent, some statuses never have one
+        if Dispatcher::has_body(response)
+            && has_full_body
+            && !has_header(response, "Content-Length")
+            && !has_header(response, "Transfer-Encoding")
+        {
+            let length = response.body.len().to_string();
+            response.headers.insert("Content-Length".to_string(), length);
+        }
+    }
+
+    /// Informational, 204 and 304 responses never have a body, see RFC 7230 section 3.3.3
+    fn has_body(response: &response::Message) -> bool {
+        match response.status.get(..3).and_then(|code| code.parse::<u16>().ok()) {
+            Some(code) => code >= 200 && code != 204 && code != 304,
+            None => true,
+        }
+    }
+
+    /// Why the body of a response sent in full doesn't match it's framing headers, the client
+    /// would read too few or too many bytes and misread the next response on the connection
+    fn get_framing_error(response: &response::Message) -> Option<String> {
+        if !Dispatcher::has_body(response) {
+            return None;
+        }
+        let mut lengths = Vec::new();
+        let mut is_chunked = false;
+        for (name, value) in response.headers.iter() {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                lengths.push(value);
+            } else if name.eq_ignore_ascii_case("Transfer-Encoding") {
+                is_chunked = parse::split_list(value)
+                    .last()
+                    .map_or(false, |coding| coding.eq_ignore_ascii_case("chunked"));
+            }
+        }
+        if is_chunked {
+            if !lengths.is_empty() {
+                return Some("Response has both Content-Length and chunked body".to_string());
+            }
+            let mut reader = body::Reader::new(
+                body::Framing::Chunked,
+                response.body.clone(),
+                Box::new(io::empty()),
+            );
+            let mut decoded = Vec::new();
+            if let Err(error) = reader.read_to_end(&mut decoded) {
+                return Some(format!("Chunked body of response is invalid, error: {}", error));
+            }
+            return None;
+        }
+        for length in lengths {
+            match length.trim().parse::<usize>() {
+                Ok(length) if length == response.body.len() => {}
+                Ok(length) => {
+                    return Some(format!(
+                        "Content-Length {} of response differs from it's body of {} bytes",
+                        length,
+                        response.body.len()
+                    ))
+                }
+                Err(_) => return Some(format!("Invalid Content-Length {:?} of response", length)),
+            }
+        }
+        None
+    }
+
+    /// Add a `Set-Cookie` header field for each cookie at the end of a serialized head, they
+    /// can't share a field like other headers, see RFC 6265 section 3
+    fn insert_cookies(response: &mut Vec<u8>, cookies: Vec<SetCookie>) {
+        if cookies.is_empty() {
+            return;
+        }
+        let end = match response.windows(4).position(|window| window == b"\r\n\r\n") {
+            Some(end) => end + 2,
+            None => return,
+        };
+        let mut fields = Vec::new();
+        for cookie in cookies {
+            fields.extend_from_slice(format!("Set-Cookie: {}\r\n", cookie).as_bytes());
+        }
+        let body = response.split_off(end);
+        response.extend_from_slice(&fields);
+        response.extend_from_slice(&body);
+    }
+
+    /// Answer a request no responder matched when the responders for it's path declared their
+    /// methods, with the methods for `OPTIONS` and otherwise a 405, see RFC 7231 section 6.5.5
+    fn get_allow_response(
+        request_message: &request::Message,
+        capabilities: &[Capabilities],
+    ) -> Option<response::Message> {
+        // The options responder comes first and only answers for the whole server
+        let path = &request_message.request_line.uri.path;
+        let allow = Capabilities::get_allow(&capabilities[1..], Some(path))?;
+        let response = if request_message.request_line.method == request::Method::Options {
+            response::Response::ok()
+        } else {
+            response::Response::new(HttpStatus::MethodNotAllowed)
+        };
+        Some(
+            response
+                .protocol(&request_message.request_line.protocol)
+                .header("Allow", &allow)
+                .body("")
+                .build(),
+        )
     }
 
     /// Make the first http response that matches respond
     pub fn respond(
-        &self,
+        &mut self,
         _request: &[u8],
         application: &Application,
         socket: &SocketAddr,
-        responders: Vec<Box<ResponderInterface + Send>>,
+        mut responders: Vec<Box<ResponderInterface + Send>>,
         overflow_bytes: &u64,
     ) -> Result<(Vec<u8>, String), String> {
+        // Questions about the whole server are answered before any resource is looked up
+        let mut capabilities: Vec<Capabilities> = responders
+            .iter()
+            .map(|responder| responder.get_capabilities())
+            .collect();
+        let options = options::Responder::with_capabilities(&capabilities);
+        capabilities.insert(0, options.get_capabilities());
+        responders.insert(0, Box::new(options));
         if let Some(request_message) = &self.request_message {
-            for mut responder in responders.into_iter() {
+            let method = request_message.request_line.method;
+            let path = &request_message.request_line.uri.path;
+            let mut found = None;
+            let mut is_failed = false;
+            for (mut responder, capabilities) in responders.into_iter().zip(capabilities.iter()) {
+                if !capabilities.accepts(method, path) {
+                    continue;
+                }
                 if responder.matches(&request_message, &application, &socket, &overflow_bytes) {
-                    if let Ok(mut response) =
-                        responder.respond(&request_message, &application, &socket, &overflow_bytes)
-                    {
-                        let mut log = String::new();
-                        if let Some(request_message) = &self.request_message {
-                            let mut agent = String::new();
-                            let mut referer = String::new();
-                            if let Some(http_agent) = request_message.headers.get("User-Agent") {
-                                agent = http_agent.to_string();
-                            }
-                            if let Some(http_referer) = request_message.headers.get("Referer") {
-                                referer = http_referer.to_string();
-                            }
-                            log = format!(
-                                "HTTP access - \"{}\",\"{}\",\"{}\",\"{}\",\"{}\",\"{}\"",
-                                socket,
-                                &request_message.request_line.raw,
-                                agent,
-                                referer,
-                                &response.status,
-                                &response.body.len()
-                            );
+                    let response =
+                        responder.respond(&request_message, &application, &socket, &overflow_bytes);
+                    if let Err(error) = &response {
+                        application.error(|| format!("HTTP responder failed, error: {}", error));
+                        is_failed = true;
+                    }
+                    if let Ok(mut response) = response {
+                        if !capabilities.is_cacheable
+                            && !response
+                                .headers
+                                .keys()
+                                .any(|name| name.eq_ignore_ascii_case("Cache-Control"))
+                        {
+                            response
+                                .headers
+                                .insert("Cache-Control".to_string(), "no-store".to_string());
+                        }
+                        found = Some(response);
+                        break;
+                    }
+                }
+            }
+            if found.is_none() {
+                found = Dispatcher::get_allow_response(request_message, &capabilities);
+            }
+
+            // Clients are told nothing could answer instead of getting no response at all
+            if found.is_none() {
+                let status = if is_failed {
+                    HttpStatus::InternalServerError
+                } else {
+                    HttpStatus::NotFound
+                };
+                found = Some(
+                    response::Response::new(status)
+                        .protocol(&request_message.request_line.protocol)
+                        .build(),
+                );
+            }
+
+            if let Some(mut response) = found {
+                if let Some(policy) = &application.get_config().content_security_policy {
+                    if !response.headers.contains_key("Content-Security-Policy") {
+                        response.headers.insert(
+                            "Content-Security-Policy".to_string(),
+                            application
+                                .get_context()
+                                .get_content_security_policy(&policy),
+                        );
+                    }
+                }
+
+                if !response.headers.contains_key("X-Request-Id") {
+                    response.headers.insert(
+                        "X-Request-Id".to_string(),
+                        request_message.request_id.clone(),
+                    );
+                }
+
+                let config = application.get_config();
+                if let Err(error) = error_page::set_error_page(&mut response, config) {
+                    application.error(|| error);
+                }
+
+                // Streamed bodies are not known in full and are sent as they are
+                if !application.get_context().has_response_body() {
+                    compression::compress(
+                        &mut response,
+                        request_message.headers.get("Accept-Encoding"),
+                        config.compression_level,
+                        config.compression_min_bytes,
+                        &config.compression_types,
+                    );
+                }
+
+                let is_head = request_message.request_line.method == request::Method::Head;
+                let has_full_body = !application.get_context().has_response_body();
+                self.response_stream = Dispatcher::set_response_stream(
+                    &mut response,
+                    &application,
+                    request_message.request_line.protocol,
+                    is_head,
+                );
+                Dispatcher::set_default_headers(
+                    &mut response,
+                    has_full_body,
+                    &application.get_config().server_name,
+                );
+
+                // A responder bug here would corrupt the responses after it, the connection is
+                // closed instead of being kept alive
+                if has_full_body && !is_head {
+                    if let Some(error) = Dispatcher::get_framing_error(&response) {
+                        if application.get_config().strict_framing {
+                            panic!("{}", error);
                         }
-                        return Ok((response.to_bytes(), log));
+                        application.error(|| format!("{}, closing the connection", error));
+                        self.keep_alive = false;
                     }
                 }
+
+                // HEAD is answered like GET but the body is left out, see RFC 7231 section 4.3.2
+                if is_head {
+                    response.body.clear();
+                }
+
+                // Without a length the end of the body is signaled by closing
+                // Header names are case-insensitive, responders may set them in any case
+                let is_delimited = is_head
+                    || response
+                        .headers
+                        .keys()
+                        .any(|name| name.eq_ignore_ascii_case("Content-Length"))
+                    || self.response_stream == Some(ResponseStream::Chunked)
+                    || response.status.starts_with("204")
+                    || response.status.starts_with("304");
+                if !is_delimited {
+                    self.keep_alive = false;
+                }
+                let connection = if !self.keep_alive {
+                    Some("close")
+                } else if request_message.request_line.protocol == request::Protocol::V1_0 {
+                    Some("keep-alive")
+                } else {
+                    None
+                };
+                if let Some(connection) = connection {
+                    response
+                        .headers
+                        .retain(|name, _| !name.eq_ignore_ascii_case("Connection"));
+                    response
+                        .headers
+                        .insert("Connection".to_string(), connection.to_string());
+                }
+
+                let mut log = String::new();
+                if let Some(request_message) = &self.request_message {
+                    let mut agent = String::new();
+                    let mut referer = String::new();
+                    if let Some(http_agent) = request_message.headers.get("User-Agent") {
+                        agent = http_agent.to_string();
+                    }
+                    if let Some(http_referer) = request_message.headers.get("Referer") {
+                        referer = http_referer.to_string();
+                    }
+                    log = format!(
+                        "HTTP access - \"{}\",\"{}\",\"{}\",\"{}\",\"{}\",\"{}\",\"{}\"",
+                        Dispatcher::get_log_client(
+                            request_message,
+                            socket,
+                            &application.get_config().trusted_proxies
+                        ),
+                        &request_message.request_line.raw,
+                        agent,
+                        referer,
+                        &response.status,
+                        &response.body.len(),
+                        &request_message.request_id
+                    );
+                }
+                let mut response = response::to_ordered_bytes(
+                    &response,
+                    &application.get_config().header_order,
+                );
+                Dispatcher::insert_cookies(&mut response, application.get_context().take_cookies());
+                return Ok((response, log));
             }
         }
 
@@ -85,6 +648,12 @@ impl Dispatcher {
 }
 
 pub trait ResponderInterface: ResponderInterfaceCopy {
+    /// Methods and paths answered and how, responders that don't declare any may answer
+    /// anything and read the body
+    fn get_capabilities(&self) -> Capabilities {
+        Capabilities::new()
+    }
+
     fn matches(&mut self, &request::Message, &Application, &SocketAddr, &u64) -> bool;
     fn respond(
         &self,
@@ -113,3 +682,301 @@ impl Clone for Box<ResponderInterface + Send> {
         self.clone_box()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_is_persistent() {
+        let requests: [(&[u8], bool); 6] = [
+            (b"GET / HTTP/1.1\r\nHost: a\r\n\r\n", true),
+            (b"GET / HTTP/1.1\r\nHost: a\r\nConnection: Upgrade, Close\r\n\r\n", false),
+            (b"GET / HTTP/1.0\r\n\r\n", false),
+            (b"GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n", true),
+            (b"/index.htm\r\n", false),
+            (b"GET / HTTP/1.1\r\n", false),
+        ];
+        for (request, expected) in requests.iter() {
+            let mut dispatcher = Dispatcher::new();
+            dispatcher.feed(request);
+            assert_eq!(dispatcher.is_persistent(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_get_log_client() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 8080);
+        let request_message = request::Message::from_tcp_stream(
+            b"GET / HTTP/1.1\r\nHost: a\r\nX-Forwarded-For: 203.0.113.7\r\n\r\n",
+        ).unwrap();
+        assert_eq!(
+            Dispatcher::get_log_client(&request_message, &socket, &[]),
+            "10.0.0.1:8080"
+        );
+        let trusted = Cidr::from_list("10.0.0.0/8").unwrap();
+        assert_eq!(
+            Dispatcher::get_log_client(&request_message, &socket, &trusted),
+            "203.0.113.7"
+        );
+    }
+
+    fn get_config() -> Config {
+        Config::for_tests()
+    }
+
+    #[test]
+    fn test_get_error_response() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let config = get_config();
+
+        let mut dispatcher = Dispatcher::new();
+        assert!(dispatcher.feed(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"));
+        assert_eq!(dispatcher.get_error_response(&socket, &config), None);
+
+        let mut dispatcher = Dispatcher::new();
+        assert!(dispatcher.feed(http2::PREFACE));
+        let (response, log) = dispatcher.get_error_response(&socket, &config).unwrap();
+        assert_eq!(response, http2::get_rejection("HTTP/2 is not supported, use HTTP/1.1"));
+        assert!(log.contains("\"PRI * HTTP/2.0\",\"\",\"\",\"505 HTTP Version Not Supported\""));
+
+        let mut dispatcher = Dispatcher::new();
+        assert!(!dispatcher.feed(b"GET / HTTP/1.1\r\n"));
+        assert!(dispatcher.feed(b"\r\n"));
+        let (response, _) = dispatcher.get_error_response(&socket, &config).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+
+        let mut dispatcher = Dispatcher::new();
+        assert!(dispatcher.feed(b"GET / HTTP/9.9\r\n\r\n"));
+        let (response, _) = dispatcher.get_error_response(&socket, &config).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 505 HTTP Version Not Supported\r\n"));
+
+        let mut dispatcher = Dispatcher::new();
+        assert!(dispatcher.feed(b"FETCH / HTTP/1.1\r\n\r\n"));
+        let (response, _) = dispatcher.get_error_response(&socket, &config).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 501 Not Implemented\r\n"));
+
+        let mut dispatcher = Dispatcher::new();
+        assert!(dispatcher.feed(b"GET / HTTP/1.0\r\nHost : localhost\r\n\r\n"));
+        let (response, log) = dispatcher.get_error_response(&socket, &config).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+        assert!(log.ends_with("Invalid header field \"Host : localhost\""));
+
+        let mut dispatcher = Dispatcher::with_limits(Limits {
+            allow_http_0_9: false,
+            max_body_bytes: 1,
+            max_header_bytes: 1024,
+            max_request_line: 1024,
+            stream_body_bytes: None,
+        });
+        assert!(dispatcher.feed(b"POST / HTTP/1.0\r\nContent-Length: 2\r\n\r\n"));
+        let (response, _) = dispatcher.get_error_response(&socket, &config).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 413 Payload Too Large\r\n"));
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.contains("Content-Length: 0\r\n"));
+        assert!(response.contains("Date: "));
+
+        // Registered pages are sent as body
+        let config = get_config().error_page(400, "404.htm");
+        let mut dispatcher = Dispatcher::new();
+        assert!(dispatcher.feed(b"GET / HTTP/1.0\r\nHost : localhost\r\n\r\n"));
+        let (response, _) = dispatcher.get_error_response(&socket, &config).unwrap();
+        assert!(response.ends_with(&fs::read("html/404.htm").unwrap()));
+    }
+
+    #[derive(Clone)]
+    struct FailingResponder {}
+
+    impl ResponderInterface for FailingResponder {
+        fn matches(
+            &mut self,
+            _request_message: &request::Message,
+            _application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> bool {
+            true
+        }
+
+        fn respond(
+            &self,
+            _request_message: &request::Message,
+            _application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> Result<response::Message, String> {
+            Err("Failed".to_string())
+        }
+    }
+
+    #[test]
+    fn test_insert_cookies() {
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nBody".to_vec();
+        Dispatcher::insert_cookies(
+            &mut response,
+            vec![SetCookie::new("a", "1"), SetCookie::new("b", "2").http_only()],
+        );
+        assert_eq!(
+            String::from_utf8(response).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nSet-Cookie: a=1\r\n\
+             Set-Cookie: b=2; HttpOnly\r\n\r\nBody"
+        );
+    }
+
+    #[test]
+    fn test_respond_error_page() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let application = Application::new(get_config().error_page(404, "404.htm"));
+        let respond = |responders: Vec<Box<ResponderInterface + Send>>| {
+            let request = b"GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n";
+            let mut dispatcher = Dispatcher::new();
+            assert!(dispatcher.feed(request));
+            let (response, _) = dispatcher
+                .respond(request, &application, &socket, responders, &0)
+                .unwrap();
+            String::from_utf8(response).unwrap()
+        };
+
+        // Nothing answers, a responder that fails makes it a server error
+        let response = respond(Vec::new());
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(response.ends_with(&String::from_utf8(fs::read("html/404.htm").unwrap()).unwrap()));
+        let response = respond(vec![Box::new(FailingResponder {})]);
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert!(response.contains("Content-Length: 0\r\n"));
+    }
+
+    #[derive(Clone)]
+    struct LowerCaseResponder {}
+
+    impl ResponderInterface for LowerCaseResponder {
+        fn matches(
+            &mut self,
+            _request_message: &request::Message,
+            _application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> bool {
+            true
+        }
+
+        fn respond(
+            &self,
+            _request_message: &request::Message,
+            _application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> Result<response::Message, String> {
+            Ok(response::Response::ok()
+                .body("Body")
+                .header("content-length", "4")
+                .header("connection", "upgrade")
+                .build())
+        }
+    }
+
+    #[test]
+    fn test_respond_header_case() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let application = Application::new(get_config());
+        let request = b"GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n";
+        let mut dispatcher = Dispatcher::new();
+        assert!(dispatcher.feed(request));
+        dispatcher.keep_alive = dispatcher.is_persistent();
+        let responders: Vec<Box<ResponderInterface + Send>> = vec![Box::new(LowerCaseResponder {})];
+        let (response, _) = dispatcher
+            .respond(request, &application, &socket, responders, &0)
+            .unwrap();
+        let response = String::from_utf8(response).unwrap();
+
+        // A length in any case delimits the body and the connection header is replaced
+        assert!(dispatcher.keep_alive);
+        assert!(response.contains("content-length: 4\r\n"));
+        assert!(response.contains("Connection: keep-alive\r\n"));
+        assert!(!response.to_lowercase().contains("connection: upgrade"));
+    }
+
+    #[test]
+    fn test_get_framing_error() {
+        let get_error = |status: HttpStatus, headers: &[(&str, &str)], body: &[u8]| {
+            let headers = headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            Dispatcher::get_framing_error(&response::Message::new(
+                "HTTP/1.1".to_string(),
+                status.to_string(),
+                headers,
+                body.to_vec(),
+            ))
+        };
+        assert_eq!(get_error(HttpStatus::Ok, &[("Content-Length", "5")], b"Hello"), None);
+        assert_eq!(get_error(HttpStatus::Ok, &[], b"Hello"), None);
+        assert_eq!(
+            get_error(HttpStatus::Ok, &[("content-length", "4")], b"Hello"),
+            Some("Content-Length 4 of response differs from it's body of 5 bytes".to_string())
+        );
+        assert!(get_error(HttpStatus::Ok, &[("Content-Length", "five")], b"Hello").is_some());
+        assert_eq!(get_error(HttpStatus::NotModified, &[("Content-Length", "5")], b""), None);
+
+        let chunked = [("Transfer-Encoding", "chunked")];
+        assert_eq!(get_error(HttpStatus::Ok, &chunked, b"5\r\nHello\r\n0\r\n\r\n"), None);
+        assert!(get_error(HttpStatus::Ok, &chunked, b"5\r\nHello\r\n").is_some());
+        assert!(get_error(HttpStatus::Ok, &chunked, b"4\r\nHello\r\n0\r\n\r\n").is_some());
+        let both = [("Transfer-Encoding", "chunked"), ("Content-Length", "15")];
+        assert!(get_error(HttpStatus::Ok, &both, b"5\r\nHello\r\n0\r\n\r\n").is_some());
+    }
+
+    #[test]
+    fn test_set_default_headers() {
+        let get_response = |status: HttpStatus, headers: &[(&str, &str)]| {
+            let headers = headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            response::Message::new(
+                "HTTP/1.1".to_string(),
+                status.to_string(),
+                headers,
+                b"Hello".to_vec(),
+            )
+        };
+
+        let mut response = get_response(HttpStatus::Ok, &[]);
+        Dispatcher::set_default_headers(&mut response, true, &None);
+        assert_eq!(response.headers.get("Content-Length"), Some(&"5".to_string()));
+        assert!(date::parse(&response.headers["Date"]).is_some());
+
+        let mut response = get_response(HttpStatus::Ok, &[("content-length", "3"), ("date", "x")]);
+        Dispatcher::set_default_headers(&mut response, true, &None);
+        assert_eq!(response.headers.len(), 2);
+
+        let server_name = Some("Milstian".to_string());
+        let mut response = get_response(HttpStatus::Ok, &[]);
+        Dispatcher::set_default_headers(&mut response, true, &server_name);
+        assert_eq!(response.headers.get("Server"), server_name.as_ref());
+        let mut response = get_response(HttpStatus::Ok, &[("server", "Custom")]);
+        Dispatcher::set_default_headers(&mut response, true, &server_name);
+        assert_eq!(response.headers.get("server"), Some(&"Custom".to_string()));
+        assert!(!response.headers.contains_key("Server"));
+
+        let mut response = get_response(HttpStatus::Ok, &[("Transfer-Encoding", "chunked")]);
+        Dispatcher::set_default_headers(&mut response, true, &None);
+        assert!(!response.headers.contains_key("Content-Length"));
+
+        for (status, has_full_body) in [
+            (HttpStatus::Ok, false),
+            (HttpStatus::NoContent, true),
+            (HttpStatus::NotModified, true),
+            (HttpStatus::SwitchingProtocols, true),
+        ].iter()
+        {
+            let mut response = get_response(*status, &[]);
+            Dispatcher::set_default_headers(&mut response, *has_full_body, &None);
+            assert!(!response.headers.contains_key("Content-Length"));
+            assert!(response.headers.contains_key("Date"));
+        }
+    }
+}
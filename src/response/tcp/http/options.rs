@@ -0,0 +1,104 @@
+//! # TCP HTTP Server options response
+//! Answers `OPTIONS *` requests, which ask about the server rather than a resource, with the
+//! methods the server supports, see RFC 7231 section 4.3.7. Always tried before other responders,
+//! the methods come from the capabilities of the responders when they all declare theirs.
+
+use std::net::SocketAddr;
+
+use application_layer::http::request::{self, Method};
+use application_layer::http::response;
+
+use response::tcp::http::capabilities::Capabilities;
+use response::tcp::http::ResponderInterface;
+use Application;
+
+/// Methods advertised for the whole server when a responder accepts any method, TRACE is left
+/// out since it's opt-in
+pub const ALLOW: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+
+#[derive(Clone)]
+pub struct Responder {
+    allow: String,
+}
+
+impl Responder {
+    pub fn new() -> Responder {
+        Responder {
+            allow: ALLOW.to_string(),
+        }
+    }
+
+    /// Advertise the methods of the responders with these capabilities
+    pub fn with_capabilities(capabilities: &[Capabilities]) -> Responder {
+        Responder {
+            allow: Capabilities::get_allow(capabilities, None).unwrap_or_else(|| ALLOW.to_string()),
+        }
+    }
+}
+
+impl ResponderInterface for Responder {
+    fn get_capabilities(&self) -> Capabilities {
+        Capabilities::new()
+            .methods(&[Method::Options])
+            .needs_body(false)
+    }
+
+    fn matches(
+        &mut self,
+        request_message: &request::Message,
+        _application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> bool {
+        request_message.request_line.method == Method::Options
+            && request_message.request_line.request_uri == "*"
+    }
+
+    fn respond(
+        &self,
+        request_message: &request::Message,
+        _application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> Result<response::Message, String> {
+        Ok(response::Response::ok()
+            .protocol(&request_message.request_line.protocol)
+            .header("Allow", &self.allow)
+            .body("")
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use response::tcp::http::golden::{self, Normalization};
+    use Config;
+
+    #[test]
+    fn test_respond() {
+        let application = Application::new(Config::for_tests());
+        let mut responder = Responder::new();
+        golden::assert_transcript(
+            &mut responder,
+            &application,
+            "OPTIONS * HTTP/1.1\nHost: localhost\n\n",
+            "HTTP/1.1 200 OK\nAllow: GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS\n\
+             Content-Length: 0\n\n",
+            &Normalization::new(),
+        )
+        .unwrap();
+        assert!(golden::run_transcript(
+            &mut responder,
+            &application,
+            "OPTIONS /index.htm HTTP/1.1\nHost: localhost\n\n"
+        )
+        .is_err());
+        assert!(golden::run_transcript(
+            &mut responder,
+            &application,
+            "GET * HTTP/1.1\nHost: localhost\n\n"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,854 @@
+//! # TCP HTTP Router response
+//! Dispatches requests to handler functions by method and path pattern. Patterns are made of
+//! literal segments, `:name` segments that capture one segment and a trailing `*name` that
+//! captures the rest of the path. Routes are compiled into a segment trie as they are added so
+//! finding a route takes time by path depth instead of by number of routes. Routes may require
+//! a access rule, requests the rule denies are answered with 403 Forbidden. WebSocket upgrades
+//! are also refused with 403 Forbidden when they come from a page of another origin or fail
+//! authentication, before the handler gets to upgrade the connection. Groups of routes under a
+//! path prefix may only accept state-changing requests from pages of allowed sites.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use access::{Denial, Identity, Rule};
+use audit::{Event, Kind};
+use application_layer::http::fetch_metadata::Policy;
+use application_layer::http::request::{self, Method};
+use application_layer::http::response;
+use application_layer::http::uri;
+use application_layer::http::websocket;
+
+use response::tcp::http::capabilities::Capabilities;
+use response::tcp::http::ResponderInterface;
+use response::tcp::jobs::{self, Class};
+use Application;
+
+/// Signature of route handlers, functions with another signature fail to compile when added
+pub type Handler =
+    fn(&request::Message, &Application, &Parameters) -> Result<response::Message, String>;
+
+/// Finds the identity of a request, for example from a session cookie or a bearer token. Gives
+/// none when the request has no credentials and the reason when they are not valid.
+pub type Authenticator =
+    fn(&request::Message, &Application) -> Result<Option<Identity>, String>;
+
+/// # Path parameters captured by a route, percent-decoded
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Parameters {
+    pairs: Vec<(String, String)>,
+}
+
+impl Parameters {
+    pub fn new() -> Parameters {
+        Parameters::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.pairs
+            .iter()
+            .find(|(pair_name, _)| pair_name == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+#[derive(Clone)]
+struct Route {
+    handler: Handler,
+    is_absolute: bool,
+    method: Method,
+    rule: Option<Rule>,
+    segments: Vec<String>,
+}
+
+/// # Segment trie of route indexes
+/// Every pattern segment is a edge, `:name` segments share one edge and a trailing `*name`
+/// ends at the node it starts from.
+#[derive(Clone, Default)]
+struct Node {
+    ending: Vec<usize>,
+    literals: HashMap<String, Node>,
+    parameter: Option<Box<Node>>,
+    wildcards: Vec<usize>,
+}
+
+impl Node {
+    fn insert(&mut self, segments: &[String], index: usize) {
+        let segment = match segments.first() {
+            Some(segment) => segment,
+            None => {
+                self.ending.push(index);
+                return;
+            }
+        };
+        if segment.starts_with('*') && segments.len() == 1 {
+            self.wildcards.push(index);
+        } else if segment.starts_with(':') {
+            self.parameter
+                .get_or_insert_with(|| Box::new(Node::default()))
+                .insert(&segments[1..], index);
+        } else {
+            self.literals
+                .entry(segment.clone())
+                .or_default()
+                .insert(&segments[1..], index);
+        }
+    }
+
+    /// Lowest index of a route accepted by `is_accepted` matching the path
+    fn find<F: Fn(usize) -> bool>(&self, path: &[&str], is_accepted: &F) -> Option<usize> {
+        let mut found = self.wildcards.iter().cloned().find(|index| is_accepted(*index));
+        let mut keep_lowest = |index: Option<usize>| {
+            found = match (found, index) {
+                (Some(found), Some(index)) => Some(found.min(index)),
+                (found, index) => found.or(index),
+            };
+        };
+        match path.first() {
+            None => keep_lowest(self.ending.iter().cloned().find(|index| is_accepted(*index))),
+            Some(segment) => {
+                if let Some(node) = self.literals.get(*segment) {
+                    keep_lowest(node.find(&path[1..], is_accepted));
+                }
+                if let Some(node) = &self.parameter {
+                    keep_lowest(node.find(&path[1..], is_accepted));
+                }
+            }
+        }
+        found
+    }
+}
+
+/// # Routes in the order they were added, the first matching route responds
+/// Usually built with the `routes!` macro.
+/// ```rust
+/// #[macro_use]
+/// extern crate milstian_internet_framework;
+/// use milstian_internet_framework::application_layer::http::{request, response};
+/// use milstian_internet_framework::response::tcp::http::router::Parameters;
+/// use milstian_internet_framework::Application;
+/// fn show_user(
+///     _request: &request::Message,
+///     _application: &Application,
+///     parameters: &Parameters,
+/// ) -> Result<response::Message, String> {
+///     Err(format!("User {:?} is not implemented", parameters.get("id")))
+/// }
+/// fn main() {
+///     let router = routes! { GET "/users/:id" => show_user };
+///     let parameters = router.get_parameters(request::Method::Get, "/users/7").unwrap();
+///     assert_eq!(parameters.get("id"), Some(&"7".to_string()));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Router {
+    authenticator: Option<Authenticator>,
+    fetch_policies: Vec<(String, Policy)>,
+    matched: Option<(usize, Parameters)>,
+    routes: Vec<Route>,
+    trie: Node,
+    websocket_origins: Vec<String>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    /// Add a route, patterns that do not start with a slash never match
+    pub fn add(&mut self, method: Method, pattern: &str, handler: Handler) {
+        self.add_route(method, pattern, None, handler);
+    }
+
+    /// Add a route that only responds to requests of identities fulfilling `rule`
+    pub fn add_guarded(&mut self, method: Method, pattern: &str, rule: Rule, handler: Handler) {
+        self.add_route(method, pattern, Some(rule), handler);
+    }
+
+    fn add_route(&mut self, method: Method, pattern: &str, rule: Option<Rule>, handler: Handler) {
+        let route = Route {
+            handler,
+            is_absolute: pattern.starts_with('/'),
+            method,
+            rule,
+            segments: Router::get_segments(pattern)
+                .into_iter()
+                .map(|segment| segment.to_string())
+                .collect(),
+        };
+        if route.is_absolute {
+            self.trie.insert(&route.segments, self.routes.len());
+        }
+        self.routes.push(route);
+    }
+
+    /// Same as add but chainable
+    pub fn with_route(mut self, method: Method, pattern: &str, handler: Handler) -> Router {
+        self.add(method, pattern, handler);
+        self
+    }
+
+    /// Same as add_guarded but chainable
+    pub fn with_guarded_route(
+        mut self,
+        method: Method,
+        pattern: &str,
+        rule: Rule,
+        handler: Handler,
+    ) -> Router {
+        self.add_guarded(method, pattern, rule, handler);
+        self
+    }
+
+    /// Find identities of requests with `authenticator` unless the context already has one
+    pub fn with_authenticator(mut self, authenticator: Authenticator) -> Router {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// Refuse state-changing requests to paths under `prefix` from sites `policy` doesn't
+    /// allow, the policy of the longest matching prefix applies
+    pub fn with_fetch_policy(mut self, prefix: &str, policy: Policy) -> Router {
+        self.fetch_policies.push((prefix.to_string(), policy));
+        self
+    }
+
+    /// Only accept WebSocket upgrades from pages of `origins`, like `https://example.com`,
+    /// instead of from pages of the same origin as the `Host`
+    pub fn with_websocket_origins(mut self, origins: &[&str]) -> Router {
+        self.websocket_origins = origins.iter().map(|origin| origin.to_string()).collect();
+        self
+    }
+
+    /// Parameters of the first route matching method and path
+    pub fn get_parameters(&self, method: Method, path: &str) -> Option<Parameters> {
+        self.find(method, path).map(|(_, parameters)| parameters)
+    }
+
+    /// HEAD requests fall back to the GET route when there is no HEAD route
+    fn find(&self, method: Method, path: &str) -> Option<(usize, Parameters)> {
+        if !path.starts_with('/') {
+            return None;
+        }
+        let path_segments = Router::get_segments(path);
+        let index = match self
+            .trie
+            .find(&path_segments, &|index| self.routes[index].method == method)
+        {
+            Some(index) => index,
+            None if method == Method::Head => self
+                .trie
+                .find(&path_segments, &|index| self.routes[index].method == Method::Get)?,
+            None => return None,
+        };
+        Router::match_segments(&self.routes[index].segments, &path_segments)
+            .map(|parameters| (index, parameters))
+    }
+
+    /// Identity of the context or else of the authenticator, which is kept in the context so
+    /// handlers can get it, or why authentication failed. Authentications are audited.
+    fn get_identity(
+        &self,
+        request_message: &request::Message,
+        application: &Application,
+        socket: &SocketAddr,
+    ) -> Result<Option<Identity>, String> {
+        if let Some(identity) = application.get_context().get_identity() {
+            return Ok(Some(identity));
+        }
+        let result = match self.authenticator {
+            Some(authenticator) => authenticator(request_message, application),
+            None => return Ok(None),
+        };
+        let trusted_proxies = &application.get_config().trusted_proxies;
+        match result {
+            Ok(Some(identity)) => {
+                application.audit(
+                    Event::from_request(
+                        Kind::AuthenticationSucceeded,
+                        request_message,
+                        socket,
+                        trusted_proxies,
+                    ).identity(&identity.id),
+                );
+                application.get_context().set_identity(identity.clone());
+                Ok(Some(identity))
+            }
+            Ok(None) => Ok(None),
+            Err(reason) => {
+                application.audit(
+                    Event::from_request(
+                        Kind::AuthenticationFailed,
+                        request_message,
+                        socket,
+                        trusted_proxies,
+                    ).reason(&reason),
+                );
+                Err(reason)
+            }
+        }
+    }
+
+    /// Audit the denial of a request and answer it with 403 Forbidden
+    fn deny(
+        request_message: &request::Message,
+        application: &Application,
+        socket: &SocketAddr,
+        denial: &Denial,
+    ) -> response::Message {
+        let mut event = Event::from_request(
+            Kind::AccessDenied,
+            request_message,
+            socket,
+            &application.get_config().trusted_proxies,
+        ).reason(&denial.reason);
+        event.identity = denial.identity.clone();
+        application.audit(event);
+        denial.to_response(&request_message.request_line.protocol)
+    }
+
+    /// Fetch policy of the longest prefix of the path, prefixes match whole segments
+    fn get_fetch_policy(&self, path: &str) -> Option<&Policy> {
+        self.fetch_policies
+            .iter()
+            .filter(|(prefix, _)| {
+                path.starts_with(prefix.as_str())
+                    && (prefix.ends_with('/')
+                        || path.len() == prefix.len()
+                        || path[prefix.len()..].starts_with('/'))
+            }).max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, policy)| policy)
+    }
+
+    fn get_segments(path: &str) -> Vec<&str> {
+        path.split('/').filter(|segment| !segment.is_empty()).collect()
+    }
+
+    fn match_segments(pattern: &[String], path: &[&str]) -> Option<Parameters> {
+        let mut parameters = Parameters::new();
+        for (index, segment) in pattern.iter().enumerate() {
+            if let Some(name) = segment.strip_prefix('*') {
+                if index + 1 == pattern.len() {
+                    let rest: Vec<String> = path[index.min(path.len())..]
+                        .iter()
+                        .map(|segment| uri::decode(segment))
+                        .collect();
+                    parameters.pairs.push((name.to_string(), rest.join("/")));
+                    return Some(parameters);
+                }
+            }
+            let path_segment = path.get(index)?;
+            if let Some(name) = segment.strip_prefix(':') {
+                parameters
+                    .pairs
+                    .push((name.to_string(), uri::decode(path_segment)));
+            } else if segment != path_segment {
+                return None;
+            }
+        }
+        if pattern.len() == path.len() {
+            Some(parameters)
+        } else {
+            None
+        }
+    }
+}
+
+impl ResponderInterface for Router {
+    fn get_capabilities(&self) -> Capabilities {
+        let mut methods: Vec<Method> = Vec::new();
+        for route in &self.routes {
+            if !methods.contains(&route.method) {
+                methods.push(route.method);
+            }
+        }
+        Capabilities::new().methods(&methods)
+    }
+
+    fn matches(
+        &mut self,
+        request_message: &request::Message,
+        _application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> bool {
+        self.matched = self.find(
+            request_message.request_line.method,
+            &request_message.request_line.uri.path,
+        );
+        self.matched.is_some()
+    }
+
+    fn respond(
+        &self,
+        request_message: &request::Message,
+        application: &Application,
+        socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> Result<response::Message, String> {
+        match &self.matched {
+            Some((index, parameters)) => {
+                let route = &self.routes[*index];
+                let is_upgrade = request_message.is_websocket_upgrade();
+                if is_upgrade {
+                    if let Err(reason) =
+                        websocket::check_origin(request_message, &self.websocket_origins)
+                    {
+                        let denial = Denial {
+                            identity: None,
+                            reason,
+                            required: "origin".to_string(),
+                        };
+                        return Ok(Router::deny(request_message, application, socket, &denial));
+                    }
+                }
+                if let Some(policy) = self.get_fetch_policy(&request_message.request_line.uri.path)
+                {
+                    if let Err(reason) = policy.check(request_message) {
+                        let denial = Denial {
+                            identity: None,
+                            reason,
+                            required: policy.to_string(),
+                        };
+                        return Ok(Router::deny(request_message, application, socket, &denial));
+                    }
+                }
+                let identity = match self.get_identity(request_message, application, socket) {
+                    Ok(identity) => identity,
+                    Err(reason) if is_upgrade => {
+                        let denial = Denial {
+                            identity: None,
+                            reason,
+                            required: "authenticated".to_string(),
+                        };
+                        return Ok(Router::deny(request_message, application, socket, &denial));
+                    }
+                    Err(_) => None,
+                };
+                if let Some(rule) = &route.rule {
+                    if let Err(denial) = rule.evaluate(identity.as_ref()) {
+                        return Ok(Router::deny(request_message, application, socket, &denial));
+                    }
+                }
+                if is_upgrade {
+                    jobs::set_class(Class::WebSocket);
+                }
+                (route.handler)(request_message, application, parameters)
+            }
+            None => Err("Error: No route matched".to_string()),
+        }
+    }
+}
+
+/// Build a `Router` from routes like `GET "/users/:id" => show_user`, methods are checked
+/// when compiling and handlers must have the `Handler` signature. Access rules follow the
+/// handler like `DELETE "/users/:id" => delete_user; requires "role:admin"`, invalid rules
+/// panic when the router is built.
+#[macro_export]
+macro_rules! routes {
+    (@add $router:ident, $method:ident, $pattern:expr, $handler:expr) => {
+        $router.add(
+            routes!(@method $method),
+            $pattern,
+            $handler as $crate::response::tcp::http::router::Handler,
+        );
+    };
+    (@add $router:ident, $method:ident, $pattern:expr, $handler:expr, $rule:expr) => {
+        $router.add_guarded(
+            routes!(@method $method),
+            $pattern,
+            $crate::access::Rule::from_str($rule)
+                .unwrap_or_else(|error| panic!("Invalid rule of route {}: {}", $pattern, error)),
+            $handler as $crate::response::tcp::http::router::Handler,
+        );
+    };
+    (@method CONNECT) => { $crate::application_layer::http::request::Method::Connect };
+    (@method DELETE) => { $crate::application_layer::http::request::Method::Delete };
+    (@method GET) => { $crate::application_layer::http::request::Method::Get };
+    (@method HEAD) => { $crate::application_layer::http::request::Method::Head };
+    (@method OPTIONS) => { $crate::application_layer::http::request::Method::Options };
+    (@method PATCH) => { $crate::application_layer::http::request::Method::Patch };
+    (@method POST) => { $crate::application_layer::http::request::Method::Post };
+    (@method PUT) => { $crate::application_layer::http::request::Method::Put };
+    (@method TRACE) => { $crate::application_layer::http::request::Method::Trace };
+    ($($method:ident $pattern:expr => $handler:expr $(; requires $rule:expr)*),* $(,)*) => {{
+        let mut router = $crate::response::tcp::http::router::Router::new();
+        $(
+            routes!(@add router, $method, $pattern, $handler $(, $rule)*);
+        )*
+        router
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::net::{IpAddr, Ipv4Addr};
+    use application_layer::http::arguments::Arguments;
+    use application_layer::http::status::HttpStatus;
+    use response::tcp::http::context::Context;
+    use response::tcp::http::Dispatcher;
+    use Config;
+
+    fn show_user(
+        request_message: &request::Message,
+        _application: &Application,
+        parameters: &Parameters,
+    ) -> Result<response::Message, String> {
+        let body = format!("user {}", parameters.get("id").unwrap()).into_bytes();
+        Ok(response::Message::new(
+            request_message.request_line.protocol.to_string(),
+            HttpStatus::Ok.to_string(),
+            HashMap::new(),
+            body,
+        ))
+    }
+
+    fn create_user(
+        _request_message: &request::Message,
+        _application: &Application,
+        _parameters: &Parameters,
+    ) -> Result<response::Message, String> {
+        Err("Not implemented".to_string())
+    }
+
+    #[test]
+    fn test_get_parameters() {
+        let router = routes! {
+            GET "/users/:id" => show_user,
+            POST "/users" => create_user,
+            GET "/files/*path" => show_user,
+            GET "/" => show_user,
+        };
+        let parameters = router.get_parameters(Method::Get, "/users/a%20b").unwrap();
+        assert_eq!(parameters.get("id"), Some(&"a b".to_string()));
+        assert!(router.get_parameters(Method::Post, "/users").unwrap().is_empty());
+        assert!(router.get_parameters(Method::Post, "/users/7").is_none());
+        assert!(router.get_parameters(Method::Get, "/users").is_none());
+        assert!(router.get_parameters(Method::Get, "/users/7/posts").is_none());
+        assert_eq!(
+            router
+                .get_parameters(Method::Get, "/files/css/style.css")
+                .unwrap()
+                .get("path"),
+            Some(&"css/style.css".to_string())
+        );
+        assert_eq!(
+            router.get_parameters(Method::Get, "/files").unwrap().get("path"),
+            Some(&"".to_string())
+        );
+        assert!(router.get_parameters(Method::Get, "/").is_some());
+        assert!(router.get_parameters(Method::Get, "users/7").is_none());
+
+        let router = Router::new().with_route(Method::Get, "relative", show_user);
+        assert!(router.get_parameters(Method::Get, "/relative").is_none());
+    }
+
+    #[test]
+    fn test_route_order() {
+        // Overlapping routes are tried in the order they were added
+        let router = routes! {
+            GET "/users/me" => create_user,
+            GET "/users/:id" => show_user,
+            GET "/users/:name/*rest" => create_user,
+            GET "/*path" => create_user,
+            GET "/users/:id/posts" => show_user,
+            POST "/users/me" => create_user,
+        };
+        assert_eq!(router.find(Method::Get, "/users/me").unwrap().0, 0);
+        assert_eq!(router.find(Method::Get, "/users/7").unwrap().0, 1);
+        assert_eq!(router.find(Method::Get, "/users/7/posts").unwrap().0, 2);
+        assert_eq!(router.find(Method::Get, "/users").unwrap().0, 3);
+        assert_eq!(router.find(Method::Get, "/other/page").unwrap().0, 3);
+        assert_eq!(router.find(Method::Post, "/users/me").unwrap().0, 5);
+        assert!(router.find(Method::Post, "/users/7").is_none());
+
+        // Parameter names may differ between routes sharing a segment
+        let parameters = router.get_parameters(Method::Get, "/users/7/a/b").unwrap();
+        assert_eq!(parameters.get("name"), Some(&"7".to_string()));
+        assert_eq!(parameters.get("rest"), Some(&"a/b".to_string()));
+
+        let mut router = Router::new();
+        for index in 0..1000 {
+            router.add(Method::Get, &format!("/section{}/:id", index), show_user);
+        }
+        assert_eq!(router.find(Method::Get, "/section999/1").unwrap().0, 999);
+    }
+
+    fn get_application() -> Application {
+        Application::new(Config::for_tests())
+    }
+
+    #[test]
+    fn test_respond() {
+        let application = get_application();
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let mut router = routes! { GET "/users/:id" => show_user };
+
+        let request_message =
+            request::Message::from_tcp_stream(b"GET /users/42 HTTP/1.1\r\nHost: a\r\n\r\n")
+                .unwrap();
+        assert!(router.matches(&request_message, &application, &socket, &0));
+        let response = router
+            .respond(&request_message, &application, &socket, &0)
+            .unwrap();
+        assert_eq!(response.body, b"user 42".to_vec());
+
+        let request_message =
+            request::Message::from_tcp_stream(b"GET /users HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        assert!(!router.matches(&request_message, &application, &socket, &0));
+    }
+
+    fn authenticate(
+        request_message: &request::Message,
+        _application: &Application,
+    ) -> Result<Option<Identity>, String> {
+        let authorization = match request_message.headers.get("Authorization") {
+            Some(authorization) => authorization,
+            None => return Ok(None),
+        };
+        match authorization.as_str() {
+            "Bearer admin" => Ok(Some(Identity::new("alice").role("admin"))),
+            "Bearer user" => Ok(Some(Identity::new("bob").permission("users.read"))),
+            _ => Err("Unknown token".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let router = routes! {
+            GET "/users/:id" => show_user; requires "role:admin or permission:users.read",
+            DELETE "/users/:id" => show_user; requires "role:admin",
+            GET "/public/:id" => show_user,
+        }.with_authenticator(authenticate);
+        let respond = |request: &[u8]| {
+            let application = get_application();
+            let mut router = router.clone();
+            let request_message = request::Message::from_tcp_stream(request).unwrap();
+            assert!(router.matches(&request_message, &application, &socket, &0));
+            let response = router
+                .respond(&request_message, &application, &socket, &0)
+                .unwrap();
+            (response, application.get_context().get_identity())
+        };
+
+        let (response, identity) =
+            respond(b"GET /users/7 HTTP/1.1\r\nHost: a\r\nAuthorization: Bearer user\r\n\r\n");
+        assert_eq!(response.body, b"user 7".to_vec());
+        assert_eq!(identity.unwrap().id, "bob");
+
+        let (response, _) =
+            respond(b"DELETE /users/7 HTTP/1.1\r\nHost: a\r\nAuthorization: Bearer user\r\n\r\n");
+        assert_eq!(response.status, "403 Forbidden");
+        assert_eq!(
+            String::from_utf8(response.body).unwrap(),
+            "{\"status\":403,\"reason\":\"Missing role admin\",\"required\":\"role:admin\"}"
+        );
+        let (response, _) =
+            respond(b"DELETE /users/7 HTTP/1.1\r\nHost: a\r\nAuthorization: Bearer admin\r\n\r\n");
+        assert_eq!(response.status, "200 OK");
+
+        // Requests without a identity only reach routes without rules
+        let (response, identity) = respond(b"GET /users/7 HTTP/1.1\r\nHost: a\r\n\r\n");
+        assert_eq!(response.status, "403 Forbidden");
+        assert!(identity.is_none());
+        let (response, _) = respond(b"GET /public/7 HTTP/1.1\r\nHost: a\r\n\r\n");
+        assert_eq!(response.status, "200 OK");
+    }
+
+    #[test]
+    fn test_websocket() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let router = routes! { GET "/chat/:id" => show_user }.with_authenticator(authenticate);
+        let respond = |router: &Router, headers: &str| {
+            let application = get_application();
+            let mut router = router.clone();
+            let request = format!(
+                "GET /chat/7 HTTP/1.1\r\nHost: example.com\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\n{}\r\n",
+                headers
+            );
+            let request_message = request::Message::from_tcp_stream(request.as_bytes()).unwrap();
+            assert!(router.matches(&request_message, &application, &socket, &0));
+            let response = router
+                .respond(&request_message, &application, &socket, &0)
+                .unwrap();
+            String::from_utf8(response.body).unwrap()
+        };
+
+        assert_eq!(respond(&router, "Origin: https://example.com\r\n"), "user 7");
+        let denied = respond(&router, "Origin: https://evil.com\r\n");
+        assert!(denied.contains("\"required\":\"origin\""));
+        assert!(respond(&router, "").contains("Missing Origin header"));
+        assert!(
+            respond(
+                &router,
+                "Origin: https://example.com\r\nAuthorization: Bearer stolen\r\n"
+            ).contains("Unknown token")
+        );
+
+        let router = router.with_websocket_origins(&["https://app.example.com"]);
+        assert_eq!(respond(&router, "Origin: https://app.example.com\r\n"), "user 7");
+        assert!(respond(&router, "Origin: https://example.com\r\n").contains("403"));
+    }
+
+    #[test]
+    fn test_fetch_policies() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let router = routes! {
+            POST "/users/:id" => show_user,
+            POST "/api/users/:id" => show_user,
+            POST "/apiv2/users/:id" => show_user,
+        }.with_fetch_policy("/", Policy::SameOrigin)
+            .with_fetch_policy("/api", Policy::Origins(vec!["https://app.example.com".into()]));
+        let respond = |path: &str, origin: &str| {
+            let application = get_application();
+            let mut router = router.clone();
+            let request = format!(
+                "POST {} HTTP/1.1\r\nHost: example.com\r\nSec-Fetch-Site: cross-site\r\n\
+                 Origin: {}\r\nContent-Length: 0\r\n\r\n",
+                path, origin
+            );
+            let request_message = request::Message::from_tcp_stream(request.as_bytes()).unwrap();
+            assert!(router.matches(&request_message, &application, &socket, &0));
+            router
+                .respond(&request_message, &application, &socket, &0)
+                .unwrap()
+                .status
+        };
+        assert_eq!(respond("/users/7", "https://app.example.com"), "403 Forbidden");
+        assert_eq!(respond("/api/users/7", "https://app.example.com"), "200 OK");
+        assert_eq!(respond("/api/users/7", "https://evil.com"), "403 Forbidden");
+        assert_eq!(respond("/apiv2/users/7", "https://app.example.com"), "403 Forbidden");
+    }
+
+    #[test]
+    fn test_audit() {
+        let filename = env::temp_dir().join("milstian-audit-test.log");
+        let _ = fs::remove_file(&filename);
+        let mut config = get_application().get_config().clone();
+        config.audit_log_file = Some(filename.to_str().unwrap().to_string());
+        let application = Application::new(config);
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let router = routes! {
+            DELETE "/users/:id" => show_user; requires "role:admin",
+        }.with_authenticator(authenticate);
+        for request in [
+            &b"DELETE /users/7 HTTP/1.1\r\nHost: a\r\nAuthorization: Bearer user\r\n\r\n"[..],
+            &b"DELETE /users/7 HTTP/1.1\r\nHost: a\r\nAuthorization: Bearer stolen\r\n\r\n"[..],
+        ].iter()
+        {
+            let mut application = application.clone();
+            application.set_context(Context::new());
+            let mut router = router.clone();
+            let request_message = request::Message::from_tcp_stream(request).unwrap();
+            assert!(router.matches(&request_message, &application, &socket, &0));
+            router
+                .respond(&request_message, &application, &socket, &0)
+                .unwrap();
+        }
+        application.get_audit_log().unwrap().flush();
+
+        let events: Vec<String> = fs::read_to_string(&filename)
+            .unwrap()
+            .lines()
+            .map(|line| line[line.find(',').unwrap()..].to_string())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ",\"event\":\"authentication_succeeded\",\"identity\":\"bob\",\
+                 \"route\":\"DELETE /users/7\",\"reason\":\"\",\"client\":\"127.0.0.1\"}",
+                ",\"event\":\"access_denied\",\"identity\":\"bob\",\
+                 \"route\":\"DELETE /users/7\",\"reason\":\"Missing role admin\",\
+                 \"client\":\"127.0.0.1\"}",
+                ",\"event\":\"authentication_failed\",\"identity\":null,\
+                 \"route\":\"DELETE /users/7\",\"reason\":\"Unknown token\",\
+                 \"client\":\"127.0.0.1\"}",
+                ",\"event\":\"access_denied\",\"identity\":null,\
+                 \"route\":\"DELETE /users/7\",\"reason\":\"Authentication required\",\
+                 \"client\":\"127.0.0.1\"}",
+            ]
+        );
+        let _ = fs::remove_file(&filename);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid rule of route /admin")]
+    fn test_invalid_rule() {
+        routes! { GET "/admin" => show_user; requires "admin" };
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let application = get_application();
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let router = routes! {
+            GET "/users/:id" => show_user,
+            HEAD "/users/:id" => show_user,
+        };
+        let respond = |request: &[u8]| {
+            let mut dispatcher = Dispatcher::new();
+            dispatcher.set_capabilities(vec![router.get_capabilities()]);
+            assert!(dispatcher.feed(request));
+            let responders: Vec<Box<ResponderInterface + Send>> = vec![Box::new(router.clone())];
+            let (response, _) = dispatcher
+                .respond(request, &application, &socket, responders, &0)
+                .unwrap();
+            (String::from_utf8(response).unwrap(), dispatcher)
+        };
+
+        // Bodies only a router without body methods could answer are not kept
+        let (response, dispatcher) =
+            respond(b"POST /users/42 HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\n\r\nabc");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("Allow: GET, HEAD, OPTIONS\r\n"));
+        assert_eq!(
+            dispatcher.request_message.unwrap().body,
+            request::BodyContentType::SinglePart(Arguments::new())
+        );
+
+        let (response, _) = respond(b"OPTIONS /users/42 HTTP/1.1\r\nHost: a\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Allow: GET, HEAD, OPTIONS\r\n"));
+        let (response, _) = respond(b"OPTIONS * HTTP/1.1\r\nHost: a\r\n\r\n");
+        assert!(response.contains("Allow: GET, HEAD, OPTIONS\r\n"));
+        let (response, _) = respond(b"GET /users/42 HTTP/1.1\r\nHost: a\r\n\r\n");
+        assert!(response.ends_with("user 42"));
+    }
+
+    #[test]
+    fn test_head() {
+        let application = get_application();
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8888);
+        let router = routes! { GET "/users/:id" => show_user };
+        let request = b"HEAD /users/42 HTTP/1.1\r\nHost: a\r\n\r\n";
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.set_capabilities(vec![router.get_capabilities()]);
+        assert!(dispatcher.feed(request));
+        let responders: Vec<Box<ResponderInterface + Send>> = vec![Box::new(router)];
+        let (response, _) = dispatcher
+            .respond(request, &application, &socket, responders, &0)
+            .unwrap();
+        let response = String::from_utf8(response).unwrap();
+
+        // The GET route answers, without the body
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Length: 7\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+        assert!(!response.contains("user 42"));
+    }
+}
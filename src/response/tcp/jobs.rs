@@ -0,0 +1,150 @@
+//! # Worker jobs
+//! Statistics of the jobs run by the workers by class of work, so the pool can be sized for
+//! the workload, like how long static files wait for a worker behind long-lived proxies.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use response::tcp::latency::{self, Histogram};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Class {
+    /// Work sent to the pool that serves no connection
+    Background,
+    /// Connection that served no more specific work, like dynamic responses
+    Connection,
+    /// Connection tunneled to another server
+    Proxy,
+    StaticFile,
+    WebSocket,
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let class = match self {
+            Class::Background => "background",
+            Class::Connection => "connection",
+            Class::Proxy => "proxy",
+            Class::StaticFile => "static_file",
+            Class::WebSocket => "websocket",
+        };
+        write!(formatter, "{}", class)
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<Class>> = Cell::new(None);
+}
+
+/// Count the job running on this thread as `class` instead of the class it was sent with,
+/// for code that finds out what kind of work it's doing, like a responder serving a file
+pub fn set_class(class: Class) {
+    CURRENT.with(|current| current.set(Some(class)));
+}
+
+/// Class set for the job running on this thread since the last call, if any
+pub fn take_class() -> Option<Class> {
+    CURRENT.with(|current| current.replace(None))
+}
+
+/// # Statistics of a class of jobs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub panics: u64,
+    /// Time from being sent to the pool until a worker started the job
+    pub queue_wait: latency::Summary,
+    pub run_time: latency::Summary,
+}
+
+#[derive(Debug)]
+struct Recorded {
+    panics: u64,
+    queue_wait: Histogram,
+    run_time: Histogram,
+}
+
+/// # Job statistics shared by all workers
+/// ```rust
+/// use milstian_internet_framework::response::tcp::jobs::{Class, Jobs};
+/// use std::time::Duration;
+/// let jobs = Jobs::new();
+/// jobs.record(Class::StaticFile, Duration::from_millis(1), Duration::from_millis(3), false);
+/// jobs.record(Class::Proxy, Duration::from_millis(20), Duration::from_secs(60), true);
+/// let snapshot = jobs.get_snapshot();
+/// assert_eq!(snapshot[0].0, Class::Proxy);
+/// assert_eq!(snapshot[0].1.panics, 1);
+/// assert_eq!(snapshot[1].1.run_time.count, 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Jobs {
+    inner: Arc<Mutex<HashMap<Class, Recorded>>>,
+}
+
+impl Jobs {
+    pub fn new() -> Jobs {
+        Jobs {
+            inner: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn record(&self, class: Class, queue_wait: Duration, run_time: Duration, panicked: bool) {
+        let mut classes = match self.inner.lock() {
+            Ok(classes) => classes,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let recorded = classes.entry(class).or_insert_with(|| Recorded {
+            panics: 0,
+            queue_wait: Histogram::new(),
+            run_time: Histogram::new(),
+        });
+        recorded.queue_wait.record(queue_wait);
+        recorded.run_time.record(run_time);
+        if panicked {
+            recorded.panics += 1;
+        }
+    }
+
+    /// Summaries of the classes that ran jobs, sorted by class
+    pub fn get_snapshot(&self) -> Vec<(Class, Summary)> {
+        let classes = match self.inner.lock() {
+            Ok(classes) => classes,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut snapshot: Vec<(Class, Summary)> = classes
+            .iter()
+            .map(|(class, recorded)| {
+                (
+                    *class,
+                    Summary {
+                        panics: recorded.panics,
+                        queue_wait: recorded.queue_wait.get_summary(),
+                        run_time: recorded.run_time.get_summary(),
+                    },
+                )
+            })
+            .collect();
+        snapshot.sort_by_key(|(class, _)| *class);
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_class() {
+        assert_eq!(take_class(), None);
+        set_class(Class::Proxy);
+        set_class(Class::WebSocket);
+        thread::spawn(|| assert_eq!(take_class(), None))
+            .join()
+            .unwrap();
+        assert_eq!(take_class(), Some(Class::WebSocket));
+        assert_eq!(take_class(), None);
+    }
+}
@@ -0,0 +1,278 @@
+//! # Request latency
+//! Histograms of request latency for all requests and per route, so percentiles can be read
+//! from the admin API without a external monitoring system.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Values below this are counted exactly, in microseconds
+const LINEAR_LIMIT: u64 = 128;
+
+/// Buckets for every power of two above the linear limit, gives at most 1.6% error
+const SUB_BUCKETS: usize = 64;
+
+/// Highest power of two with own buckets, about 19 hours in microseconds
+const MAX_MAGNITUDE: usize = 35;
+
+const BUCKETS: usize = LINEAR_LIMIT as usize + (MAX_MAGNITUDE - 6) * SUB_BUCKETS;
+
+/// Routes with own histograms, requests to further routes are only counted for all requests
+pub const MAX_ROUTES: usize = 100;
+
+/// # Counts of latencies in log-linear buckets
+/// Like a HDR histogram it keeps a fixed relative precision over the whole range using
+/// constant memory, recording is a index calculation and a increment.
+/// ```rust
+/// use milstian_internet_framework::response::tcp::latency::Histogram;
+/// use std::time::Duration;
+/// let mut histogram = Histogram::new();
+/// for milliseconds in 1..101 {
+///     histogram.record(Duration::from_millis(milliseconds));
+/// }
+/// let p50 = histogram.get_percentile(50.0);
+/// assert!(p50 >= Duration::from_millis(50) && p50 < Duration::from_millis(51));
+/// assert_eq!(histogram.get_percentile(100.0), Duration::from_millis(100));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    count: u64,
+    counts: Vec<u64>,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Histogram {
+        Histogram {
+            count: 0,
+            counts: vec![0; BUCKETS],
+            max: 0,
+        }
+    }
+
+    pub fn record(&mut self, duration: Duration) {
+        let microseconds = Histogram::get_microseconds(duration);
+        self.counts[Histogram::get_index(microseconds)] += 1;
+        self.count += 1;
+        if microseconds > self.max {
+            self.max = microseconds;
+        }
+    }
+
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn get_max(&self) -> Duration {
+        Duration::from_micros(self.max)
+    }
+
+    /// Latency that `percentile` percent of the recorded requests were at or below,
+    /// zero when nothing is recorded
+    pub fn get_percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::new(0, 0);
+        }
+        // Rounding errors must not move the rank up, 99.9% of 1000 is 999.0000000000001
+        let rank = (percentile / 100.0 * self.count as f64 - 1e-9).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(Histogram::get_highest(index).min(self.max));
+            }
+        }
+        Duration::from_micros(self.max)
+    }
+
+    pub fn get_summary(&self) -> Summary {
+        Summary {
+            count: self.count,
+            max: self.get_max(),
+            p50: self.get_percentile(50.0),
+            p95: self.get_percentile(95.0),
+            p99: self.get_percentile(99.0),
+            p999: self.get_percentile(99.9),
+        }
+    }
+
+    fn get_microseconds(duration: Duration) -> u64 {
+        duration
+            .as_secs()
+            .saturating_mul(1_000_000)
+            .saturating_add(u64::from(duration.subsec_micros()))
+    }
+
+    fn get_index(microseconds: u64) -> usize {
+        if microseconds < LINEAR_LIMIT {
+            return microseconds as usize;
+        }
+        let magnitude = 63 - microseconds.leading_zeros() as usize;
+        if magnitude > MAX_MAGNITUDE {
+            return BUCKETS - 1;
+        }
+        let shift = magnitude - 6;
+        LINEAR_LIMIT as usize
+            + (magnitude - 7) * SUB_BUCKETS
+            + ((microseconds >> shift) as usize - SUB_BUCKETS)
+    }
+
+    /// Highest value counted in a bucket
+    fn get_highest(index: usize) -> u64 {
+        if index < LINEAR_LIMIT as usize {
+            return index as u64;
+        }
+        let index = index - LINEAR_LIMIT as usize;
+        let shift = index / SUB_BUCKETS + 1;
+        let sub_bucket = (index % SUB_BUCKETS + SUB_BUCKETS) as u64;
+        ((sub_bucket + 1) << shift) - 1
+    }
+}
+
+/// # Percentiles of a histogram
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub count: u64,
+    pub max: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+}
+
+/// # Summaries of all requests and of every route, routes sorted by path
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub all: Summary,
+    pub routes: Vec<(String, Summary)>,
+}
+
+#[derive(Debug)]
+struct Recorded {
+    all: Histogram,
+    routes: HashMap<String, Histogram>,
+}
+
+/// # Latency histograms shared by all workers
+/// ```rust
+/// use milstian_internet_framework::response::tcp::latency::Latencies;
+/// use std::time::Duration;
+/// let latencies = Latencies::new();
+/// latencies.record("/", Duration::from_millis(3));
+/// latencies.record("/slow", Duration::from_millis(300));
+/// let snapshot = latencies.get_snapshot();
+/// assert_eq!(snapshot.all.count, 2);
+/// assert_eq!(snapshot.routes[1].0, "/slow");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Latencies {
+    inner: Arc<Mutex<Recorded>>,
+}
+
+impl Latencies {
+    pub fn new() -> Latencies {
+        Latencies {
+            inner: Arc::new(Mutex::new(Recorded {
+                all: Histogram::new(),
+                routes: HashMap::new(),
+            })),
+        }
+    }
+
+    pub fn record(&self, route: &str, duration: Duration) {
+        let mut recorded = match self.inner.lock() {
+            Ok(recorded) => recorded,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        recorded.all.record(duration);
+        if let Some(histogram) = recorded.routes.get_mut(route) {
+            histogram.record(duration);
+            return;
+        }
+        if recorded.routes.len() < MAX_ROUTES {
+            let mut histogram = Histogram::new();
+            histogram.record(duration);
+            recorded.routes.insert(route.to_string(), histogram);
+        }
+    }
+
+    pub fn get_snapshot(&self) -> Snapshot {
+        let recorded = match self.inner.lock() {
+            Ok(recorded) => recorded,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut routes: Vec<(String, Summary)> = recorded
+            .routes
+            .iter()
+            .map(|(route, histogram)| (route.clone(), histogram.get_summary()))
+            .collect();
+        routes.sort_by(|a, b| a.0.cmp(&b.0));
+        Snapshot {
+            all: recorded.all.get_summary(),
+            routes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        // Every value is within it's bucket and buckets are contiguous
+        let mut previous_highest = None;
+        for index in 0..BUCKETS {
+            let highest = Histogram::get_highest(index);
+            assert_eq!(Histogram::get_index(highest), index);
+            if let Some(previous_highest) = previous_highest {
+                assert_eq!(Histogram::get_index(previous_highest + 1), index);
+            }
+            previous_highest = Some(highest);
+        }
+        assert_eq!(Histogram::get_index(1 << 40), BUCKETS - 1);
+
+        // Relative error stays small
+        for microseconds in &[200, 1_500, 45_000, 2_000_000, 600_000_000] {
+            let highest = Histogram::get_highest(Histogram::get_index(*microseconds));
+            assert!((highest - microseconds) as f64 / *microseconds as f64 <= 0.016);
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = Histogram::new();
+        assert_eq!(histogram.get_summary(), Summary::default());
+        for _ in 0..990 {
+            histogram.record(Duration::from_micros(100));
+        }
+        for _ in 0..9 {
+            histogram.record(Duration::from_millis(20));
+        }
+        histogram.record(Duration::from_secs(2));
+
+        let summary = histogram.get_summary();
+        assert_eq!(summary.count, 1000);
+        assert_eq!(summary.p50, Duration::from_micros(100));
+        assert_eq!(summary.p95, Duration::from_micros(100));
+        assert_eq!(summary.p99, Duration::from_micros(100));
+        assert!(summary.p999 >= Duration::from_millis(20));
+        assert!(summary.p999 < Duration::from_micros(20_400));
+        assert_eq!(summary.max, Duration::from_secs(2));
+        assert_eq!(histogram.get_percentile(100.0), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_route_limit() {
+        let latencies = Latencies::new();
+        for index in 0..MAX_ROUTES + 5 {
+            latencies.record(&format!("/{}", index), Duration::from_millis(1));
+        }
+        latencies.record("/0", Duration::from_millis(1));
+        let snapshot = latencies.get_snapshot();
+        assert_eq!(snapshot.all.count, MAX_ROUTES as u64 + 6);
+        assert_eq!(snapshot.routes.len(), MAX_ROUTES);
+        assert_eq!(snapshot.routes[0].0, "/0");
+        assert_eq!(snapshot.routes[0].1.count, 2);
+    }
+}
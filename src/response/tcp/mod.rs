@@ -1,125 +1,910 @@
 //! # Namespace for TCP responses
 
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod completion;
+pub mod connections;
 pub mod http;
+pub mod jobs;
+pub mod latency;
+pub mod raw;
+pub mod reset;
+pub mod splice;
+pub mod trace;
 
+use std::borrow::Cow;
 use std::io::prelude::*;
+use std::io::{self, ErrorKind};
+#[cfg(feature = "chaos")]
+use std::net::Shutdown;
 use std::net::{SocketAddr, TcpStream};
 use std::str;
+use std::time::{Duration, Instant};
 
-use response::tcp::http::ResponderInterface;
+use application_layer::http::chunked;
+use application_layer::http::parser::Limits;
+use application_layer::http::request;
+use response::tcp::completion::{Completion, Outcome};
+use response::tcp::connections::{Registration, State};
+use response::tcp::http::context::Context;
+use response::tcp::http::{ResponderInterface, ResponseStream};
+use response::tcp::raw::{HandlerInterface, PEEK_BYTES};
 
 use Application;
 
+/// What a connection carries over from one request to the next
+#[derive(Default)]
+struct Carried {
+    /// Client has closed it's side of the connection, it may still wait for responses
+    is_half_closed: bool,
+    /// Bytes received after the end of the previous request
+    pipelined: Vec<u8>,
+}
+
 /// This struct should handle the dispatching of requests to a specific response type
 pub struct Dispatcher {}
 
 impl Dispatcher {
-    /// This method takes a TcpStream and tries to find a appropriate response handler
+    /// Hand the connection to the first raw handler that serves it, otherwise answer HTTP
+    pub fn serve(
+        stream: TcpStream,
+        socket: SocketAddr,
+        application: Application,
+        handlers: Vec<Box<HandlerInterface + Send>>,
+        responders: Vec<Box<ResponderInterface + Send>>,
+    ) {
+        match Dispatcher::get_raw_handler(&stream, &application, handlers) {
+            Some(mut handler) => handler.handle(stream, &socket, &application),
+            None => Dispatcher::http(stream, socket, application, responders),
+        }
+    }
+
+    /// Handler owning the port of the stream or matching it's first bytes
+    fn get_raw_handler(
+        stream: &TcpStream,
+        application: &Application,
+        handlers: Vec<Box<HandlerInterface + Send>>,
+    ) -> Option<Box<HandlerInterface + Send>> {
+        if handlers.is_empty() {
+            return None;
+        }
+        let port = match stream.local_addr() {
+            Ok(address) => address.port(),
+            Err(error) => {
+                application.error(|| format!("Failed to get local TCP address, error: {}", error));
+                return None;
+            }
+        };
+        let mut initial: Option<Vec<u8>> = None;
+        for mut handler in handlers.into_iter() {
+            match handler.get_port() {
+                Some(handler_port) if handler_port == port => return Some(handler),
+                Some(_) => {}
+                None => {
+                    // Only peek when needed since clients of port handlers may wait for a greeting
+                    if initial.is_none() {
+                        initial = Some(Dispatcher::peek(stream, application));
+                    }
+                    if handler.matches(initial.as_ref().map_or(&[], |initial| initial), application)
+                    {
+                        return Some(handler);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Bytes that have arrived on the stream without consuming them
+    fn peek(stream: &TcpStream, application: &Application) -> Vec<u8> {
+        let timeout = application.get_config().keep_alive_timeout;
+        if timeout > 0 {
+            if let Err(error) = stream.set_read_timeout(Some(Duration::from_secs(timeout))) {
+                application.error(|| format!("Failed to set TCP read time-out, error: {}", error));
+            }
+        }
+        let mut initial = [0; PEEK_BYTES];
+        match stream.peek(&mut initial) {
+            Ok(peek_size) => initial[..peek_size].to_vec(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// This method takes a TcpStream and answers requests on it until the connection should close
     pub fn http(
         mut stream: TcpStream,
         socket: SocketAddr,
-        application: Application,
+        mut application: Application,
         responders: Vec<Box<ResponderInterface + Send>>,
     ) {
+        let timeout = application.get_config().keep_alive_timeout;
+        if timeout > 0 {
+            if let Err(error) = stream.set_read_timeout(Some(Duration::from_secs(timeout))) {
+                application.error(|| format!("Failed to set TCP read time-out, error: {}", error));
+            }
+        }
+
+        let registration = application.get_connections().open(socket);
+        let mut carried = Carried::default();
+        let mut request_number = 0;
+        loop {
+            request_number = request_number + 1;
+            if !Dispatcher::http_request(
+                &mut stream,
+                &socket,
+                &mut application,
+                &registration,
+                responders.clone(),
+                request_number,
+                &mut carried,
+            ) {
+                break;
+            }
+        }
+    }
+
+    /// Answer one request, returns true if the connection should be kept open for another.
+    /// Bytes received after the end of the request are carried over to the next one.
+    fn http_request(
+        stream: &mut TcpStream,
+        socket: &SocketAddr,
+        application: &mut Application,
+        registration: &Registration,
+        responders: Vec<Box<ResponderInterface + Send>>,
+        request_number: usize,
+        carried: &mut Carried,
+    ) -> bool {
+        // Every request gets a new context
+        application.set_context(Context::new());
+
         // Create a array with 512 elements containing the value 0
         let mut temp_buffer = [0; 512];
         let mut buffer: Vec<u8> = Vec::new();
         let config = application.get_config();
         let mut acc_read_size: u64 = 0;
         let mut overflow_bytes: u64 = 0;
+        let mut http_dispatcher = http::Dispatcher::with_limits(Limits {
+            allow_http_0_9: config.allow_http_0_9,
+            max_body_bytes: config.max_body_bytes,
+            max_header_bytes: config.max_header_bytes,
+            max_request_line: config.max_request_line,
+            stream_body_bytes: config.stream_body_bytes,
+        });
+        http_dispatcher.set_capabilities(
+            responders
+                .iter()
+                .map(|responder| responder.get_capabilities())
+                .collect(),
+        );
+        let mut completion = Completion::new(*socket, request_number);
+        let start = Instant::now();
+        let mut first_byte: Option<Instant> = None;
 
-        loop {
+        // Requests pipelined after the previous request are answered before reading more
+        let mut is_complete = false;
+        if !carried.pipelined.is_empty() {
+            first_byte = Some(start);
+            registration.set_state(State::Reading);
+            overflow_bytes = Dispatcher::push(&mut buffer, &carried.pipelined, config.tcp_limit);
+            is_complete = http_dispatcher.feed(&carried.pipelined);
+            carried.pipelined.clear();
+        }
+
+        while !is_complete {
             match stream.read(&mut temp_buffer) {
+                Ok(0) => {
+                    // Client closed it's side of the stream, it may still wait for a response
+                    carried.is_half_closed = true;
+                    break;
+                }
                 Ok(read_size) => {
-                    // Move all non-empty values to new buffer
-                    for value in temp_buffer.iter() {
-                        acc_read_size = acc_read_size + 1;
-                        if value != &0 {
-                            if buffer.len() < config.tcp_limit {
-                                buffer.push(*value);
-                            } else {
-                                overflow_bytes = overflow_bytes + 1;
-                            }
-                        }
+                    acc_read_size = acc_read_size + read_size as u64;
+                    registration.add_received(read_size as u64);
+                    if first_byte.is_none() {
+                        first_byte = Some(Instant::now());
+                        registration.set_state(State::Reading);
                     }
 
-                    // Did we reach end of stream?
-                    if read_size < 512 {
-                        break;
-                    }
+                    let data = &temp_buffer[..read_size];
+                    let overflow = Dispatcher::push(&mut buffer, data, config.tcp_limit);
+                    overflow_bytes = overflow_bytes + overflow;
+                    is_complete = http_dispatcher.feed(data);
+                }
+                Err(ref error)
+                    if error.kind() == ErrorKind::WouldBlock
+                        || error.kind() == ErrorKind::TimedOut =>
+                {
+                    application
+                        .info(|| format!("TCP stream was idle for {}s", config.keep_alive_timeout));
+                    break;
+                }
+                Err(ref error) if Dispatcher::is_gone(error) => {
+                    application.info(|| format!("Client closed TCP stream, error: {}", error));
+                    carried.is_half_closed = true;
+                    break;
                 }
                 Err(error) => {
                     application
-                        .get_feedback()
-                        .error(format!("Failed to read from TCP stream, error: {}", error));
+                        .error(|| format!("Failed to read from TCP stream, error: {}", error));
                     break;
                 }
             }
         }
 
-        if buffer.len() > 0 {
-            // println!("Found non-empty TCP blog {:?} b= {:?}", str::from_utf8(&buffer), buffer);
-            let mut response = Vec::new();
-            let mut log = String::new();
-            let mut http_dispatcher = http::Dispatcher::new();
+        // Large bodies are read from the connection by responders
+        if http_dispatcher.is_streamed() {
+            match stream.try_clone() {
+                Ok(body_stream) => http_dispatcher.set_body_stream(Box::new(body_stream)),
+                Err(error) => {
+                    application.error(|| format!("Failed to clone TCP stream, error: {}", error))
+                }
+            }
+        }
 
-            if http_dispatcher.matches(&buffer, &application, &socket, &overflow_bytes) {
-                application
-                    .get_feedback()
-                    .info(format!("Request was successfully decoded as HTTP"));
-                match http_dispatcher.respond(
-                    &buffer,
-                    &application,
-                    &socket,
-                    responders,
-                    &overflow_bytes,
-                ) {
-                    Ok((http_response, http_log)) => {
-                        response = http_response;
-                        log = http_log;
-                        application
-                            .get_feedback()
-                            .info(format!("Found non-empty HTTP response to TCP stream"));
+        if buffer.is_empty() {
+            application.info(|| {
+                format!(
+                    "TCP stream was empty, accumulated read size: {}",
+                    acc_read_size
+                )
+            });
+            return false;
+        }
+
+        let read_end = Instant::now();
+        let first_byte = first_byte.unwrap_or(start);
+        completion.wait_time = first_byte.duration_since(start);
+        completion.read_time = read_end.duration_since(first_byte);
+        completion.received_bytes = acc_read_size;
+        completion.overflow_bytes = overflow_bytes;
+
+        // Canned responses are written as they are, without copying them
+        let canned_response;
+        let mut response: Cow<[u8]> = Cow::Owned(Vec::new());
+        let mut log = String::new();
+        registration.set_state(State::Responding);
+        if let Some((error_response, error_log)) =
+            http_dispatcher.get_canned_error_response(socket, application.get_canned())
+        {
+            canned_response = error_response;
+            response = Cow::Borrowed(&canned_response[..]);
+            log = error_log;
+        } else if let Some((error_response, error_log)) =
+            http_dispatcher.get_error_response(socket, config)
+        {
+            response = Cow::Owned(error_response);
+            log = error_log;
+        } else if http_dispatcher.matches(&buffer, application, socket, &overflow_bytes) {
+            if let Some(request_message) = &http_dispatcher.request_message {
+                registration.set_request(
+                    &request_message.request_line.request_uri,
+                    &request_message.request_id,
+                );
+
+                // Only HTTP/1.1 clients expect informational responses before the final one
+                if request_message.request_line.protocol == request::Protocol::V1_1 {
+                    match stream.try_clone() {
+                        Ok(interim_stream) => application
+                            .get_context()
+                            .set_interim_writer(Box::new(interim_stream)),
+                        Err(error) => application.error(|| {
+                            format!("Failed to clone TCP stream, error: {}", error)
+                        }),
                     }
-                    Err(error) => {
-                        application
-                            .get_feedback()
-                            .error(format!("Got empty HTTP response! Error: {}", error));
+                }
+            }
+
+            http_dispatcher.keep_alive =
+                request_number < config.keep_alive_max_requests && http_dispatcher.is_persistent();
+            match http_dispatcher.respond(
+                &buffer,
+                application,
+                socket,
+                responders,
+                &overflow_bytes,
+            ) {
+                Ok((http_response, http_log)) => {
+                    response = Cow::Owned(http_response);
+                    log = http_log;
+                    completion.keep_alive = http_dispatcher.keep_alive;
+                }
+                Err(error) => {
+                    application.error(|| format!("Got empty HTTP response! Error: {}", error));
+                }
+            }
+        }
+        if let Some(request_message) = &http_dispatcher.request_message {
+            completion.request_id = request_message.request_id.clone();
+            completion.request_line = request_message.request_line.raw.clone();
+            completion.route = request_message.request_line.uri.path.clone();
+            completion.outcome = Outcome::Unanswered;
+        }
+        completion.status = Completion::get_status(&response);
+        let respond_end = Instant::now();
+        completion.respond_time = respond_end.duration_since(read_end);
+
+        if !response.is_empty() {
+            #[cfg(feature = "chaos")]
+            if let Some(chaos) = &config.chaos {
+                let faults = chaos.get_faults();
+                if !faults.is_empty() {
+                    application.info(|| format!("Injecting faults {:?} into response", &faults));
+                }
+                match chaos::Chaos::apply(&faults, response.into_owned()) {
+                    Some(faulty_response) => response = Cow::Owned(faulty_response),
+                    None => {
+                        if config.reset_on_force_close {
+                            Dispatcher::set_reset(stream, application);
+                        } else if let Err(error) = stream.shutdown(Shutdown::Both) {
+                            application.error(|| {
+                                format!("Failed to shutdown TCP stream, error: {}", error)
+                            });
+                        }
+                        completion.outcome = Outcome::Dropped;
+                        completion.keep_alive = false;
+                        response = Cow::Owned(Vec::new());
                     }
                 }
-            } else {
-                application
-                    .get_feedback()
-                    .info(format!("Request could not be decoded as HTTP"));
             }
 
             if !response.is_empty() {
-                application.get_feedback().info(log);
-                match stream.write(&response) {
-                    Ok(_) => {
-                        if let Err(error) = stream.flush() {
+                registration.set_state(State::Writing);
+                application.log_access(log);
+                completion.outcome = Outcome::WriteFailed;
+                match stream.write_all(&response) {
+                    Ok(_) => match stream.flush() {
+                        Ok(_) => {
+                            completion.outcome = Outcome::Answered;
+                            completion.sent_bytes = response.len();
+                            registration.add_sent(response.len() as u64);
+                        }
+                        Err(error) => {
                             application
-                                .get_feedback()
-                                .info(format!("Failed to flush TCP stream, error: {}", error));
+                                .info(|| format!("Failed to flush TCP stream, error: {}", error));
                         }
+                    },
+                    Err(ref error) if Dispatcher::is_gone(error) => {
+                        application.info(|| {
+                            format!(
+                                "Client closed TCP stream before the response, error: {}",
+                                error
+                            )
+                        });
                     }
                     Err(error) => {
                         application
-                            .get_feedback()
-                            .error(format!("Failed to write to TCP stream, error: {}", error));
+                            .error(|| format!("Failed to write to TCP stream, error: {}", error));
                     }
                 }
-            } else {
-                application.get_feedback().error(format!(
+
+                // The body streamed by the responder follows the head
+                if let (Outcome::Answered, Some(response_stream)) =
+                    (&completion.outcome, http_dispatcher.response_stream)
+                {
+                    match Dispatcher::write_response_stream(stream, application, response_stream)
+                    {
+                        Ok(sent_bytes) => {
+                            completion.sent_bytes += sent_bytes;
+                            registration.add_sent(sent_bytes as u64);
+                        }
+                        Err(error) => {
+                            application.info(|| {
+                                format!(
+                                    "Failed to write streamed body to TCP stream, error: {}",
+                                    error
+                                )
+                            });
+                            completion.outcome = Outcome::WriteFailed;
+                        }
+                    }
+                }
+                if completion.outcome != Outcome::Answered {
+                    completion.keep_alive = false;
+                }
+            }
+        } else if carried.is_half_closed {
+            application.info(|| {
+                format!(
+                    "Client closed TCP stream before completing request {:?}",
+                    str::from_utf8(&buffer)
+                )
+            });
+        } else {
+            application.error(|| {
+                format!(
                     "Found no response for TCP stream {:?}",
                     str::from_utf8(&buffer)
-                ));
+                )
+            });
+        }
+        completion.write_time = respond_end.elapsed();
+        registration.set_state(State::Idle);
+
+        // Clients over a limit are closed after the error response
+        if config.reset_on_force_close && http_dispatcher.limit_exceeded.is_some() {
+            Dispatcher::set_reset(stream, application);
+        }
+        if completion.keep_alive {
+            carried
+                .pipelined
+                .extend_from_slice(http_dispatcher.get_buffer());
+
+            // No more requests can arrive, only those already received are answered
+            if carried.is_half_closed && carried.pipelined.is_empty() {
+                completion.keep_alive = false;
             }
+        }
+
+        if !completion.route.is_empty() {
+            application
+                .get_latencies()
+                .record(&completion.route, completion.get_total_time());
+        }
+        // The summary is only formatted when it's traced or written
+        if config.trace_capacity > 0 {
+            let summary = completion.to_string();
+            application.info(|| summary.clone());
+            application.get_trace().record(summary);
         } else {
-            application.get_feedback().info(format!(
-                "TCP stream was empty, accumulated read size: {}",
-                acc_read_size
-            ));
+            application.info(|| completion.to_string());
+        }
+        application.get_context().run_after_write(&completion);
+        completion.keep_alive
+    }
+
+    /// Copy the body streamed by the responder to the stream, returns the bytes of body sent
+    fn write_response_stream(
+        stream: &mut TcpStream,
+        application: &Application,
+        response_stream: ResponseStream,
+    ) -> io::Result<usize> {
+        let mut reader = match application.get_context().take_response_body() {
+            Some(reader) => reader,
+            None => return Ok(0),
+        };
+        let sent_bytes = match response_stream {
+            ResponseStream::Chunked => {
+                let mut encoder = chunked::Encoder::new(&mut *stream);
+                let sent_bytes = io::copy(&mut reader, &mut encoder)?;
+                encoder.finish()?;
+                sent_bytes
+            }
+            ResponseStream::Length(length) => {
+                let mut body = reader.take(length);
+                let sent_bytes = io::copy(&mut body, stream)?;
+                stream.flush()?;
+                let is_strict = application.get_config().strict_framing;
+
+                // The client waits for the rest, the connection can't be reused
+                if sent_bytes < length {
+                    let error = format!(
+                        "Streamed body ended after {} of {} bytes",
+                        sent_bytes, length
+                    );
+                    if is_strict {
+                        panic!("{}", error);
+                    }
+                    return Err(io::Error::new(ErrorKind::UnexpectedEof, error));
+                }
+
+                // Bytes past the declared length are not sent, the responder has a bug
+                let mut extra = [0; 1];
+                if is_strict && body.into_inner().read(&mut extra)? > 0 {
+                    panic!("Streamed body is longer than it's length of {} bytes", length);
+                }
+                sent_bytes
+            }
+            ResponseStream::UntilClose => {
+                let sent_bytes = io::copy(&mut reader, stream)?;
+                stream.flush()?;
+                sent_bytes
+            }
+        };
+        Ok(sent_bytes as usize)
+    }
+
+    /// Make closing the stream reset the connection, see `reset`
+    fn set_reset(stream: &TcpStream, application: &Application) {
+        if let Err(error) = reset::set_reset_on_close(stream) {
+            application.error(|| {
+                format!(
+                    "Failed to set TCP stream to reset on close, error: {}",
+                    error
+                )
+            });
         }
     }
+
+    /// Did the error happen because the client reset or abandoned the connection?
+    fn is_gone(error: &io::Error) -> bool {
+        let kind = error.kind();
+        kind == ErrorKind::BrokenPipe
+            || kind == ErrorKind::ConnectionAborted
+            || kind == ErrorKind::ConnectionReset
+            || kind == ErrorKind::NotConnected
+    }
+
+    /// Only the first bytes are kept, the parser enforces the request size limits.
+    /// Returns the number of bytes that did not fit.
+    fn push(buffer: &mut Vec<u8>, data: &[u8], limit: usize) -> u64 {
+        let available = limit.saturating_sub(buffer.len());
+        if data.len() > available {
+            buffer.extend_from_slice(&data[..available]);
+            (data.len() - available) as u64
+        } else {
+            buffer.extend_from_slice(data);
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::net::{Shutdown, TcpListener};
+    use std::thread;
+
+    use application_layer::http::response;
+    use application_layer::http::status::HttpStatus;
+    use Config;
+
+    /// Answers with the request target as body, followed by the body when it was streamed.
+    /// Targets under `/stream` get a body streamed from the context after the target, of
+    /// known length under `/stream/length` and cut short under `/stream/short`. Targets under
+    /// `/hints` are preceded by 103 Early Hints.
+    #[derive(Clone)]
+    struct EchoResponder {}
+
+    impl ResponderInterface for EchoResponder {
+        fn matches(
+            &mut self,
+            _request_message: &request::Message,
+            _application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> bool {
+            true
+        }
+
+        fn respond(
+            &self,
+            request_message: &request::Message,
+            application: &Application,
+            _socket: &SocketAddr,
+            _overflow_bytes: &u64,
+        ) -> Result<response::Message, String> {
+            let mut body = request_message
+                .request_line
+                .request_uri
+                .clone()
+                .into_bytes();
+            if body.starts_with(b"/hints") {
+                application.get_context().send_interim(
+                    HttpStatus::EarlyHints,
+                    &[("Link", "</style.css>; rel=preload; as=style")],
+                )?;
+            }
+            if body.starts_with(b"/stream/length") {
+                application
+                    .get_context()
+                    .set_response_body_with_length(Box::new(&b"-streamed"[..]), 9);
+            } else if body.starts_with(b"/stream/short") {
+                application
+                    .get_context()
+                    .set_response_body_with_length(Box::new(&b"-streamed"[..]), 20);
+            } else if body.starts_with(b"/stream") {
+                application
+                    .get_context()
+                    .set_response_body(Box::new(&b"-streamed"[..]));
+            }
+            if body.starts_with(b"/stream") {
+                return Ok(response::Message::new(
+                    "HTTP/1.1".to_string(),
+                    "200 OK".to_string(),
+                    HashMap::new(),
+                    body,
+                ));
+            }
+            if let Some(mut reader) = request_message.body_reader() {
+                if let Err(error) = reader.read_to_end(&mut body) {
+                    return Err(format!("Failed to read body, error: {}", error));
+                }
+            }
+            let mut headers = HashMap::new();
+            headers.insert("Content-Length".to_string(), body.len().to_string());
+            Ok(response::Message::new(
+                "HTTP/1.1".to_string(),
+                "200 OK".to_string(),
+                headers,
+                body,
+            ))
+        }
+    }
+
+    /// Answers connections starting with `PING` or any connection to it's port
+    #[derive(Clone)]
+    struct PingHandler {
+        port: Option<u16>,
+    }
+
+    impl HandlerInterface for PingHandler {
+        fn get_port(&self) -> Option<u16> {
+            self.port
+        }
+
+        fn matches(&mut self, initial: &[u8], _application: &Application) -> bool {
+            initial.starts_with(b"PING")
+        }
+
+        fn handle(
+            &mut self,
+            mut stream: TcpStream,
+            _socket: &SocketAddr,
+            _application: &Application,
+        ) {
+            if self.port.is_none() {
+                let mut line = [0; 5];
+                stream.read_exact(&mut line).unwrap();
+            }
+            stream.write_all(b"PONG\n").unwrap();
+        }
+    }
+
+    /// Send data on a new connection and read everything until the server closes it
+    fn get_transcript(
+        data: &[u8],
+        keep_alive_max_requests: usize,
+        stream_body_bytes: Option<usize>,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        get_served_transcript(
+            listener,
+            data,
+            keep_alive_max_requests,
+            stream_body_bytes,
+            Vec::new(),
+            false,
+        )
+    }
+
+    /// Closing the write side right after sending data, the client still reads the responses
+    fn get_served_transcript(
+        listener: TcpListener,
+        data: &[u8],
+        keep_alive_max_requests: usize,
+        stream_body_bytes: Option<usize>,
+        handlers: Vec<Box<HandlerInterface + Send>>,
+        is_half_closed: bool,
+    ) -> String {
+        let address = listener.local_addr().unwrap();
+        let application = Application::new(Config {
+            keep_alive_max_requests,
+            server_host: "127.0.0.1".to_string(),
+            server_limit: 1,
+            server_port: address.port(),
+            stream_body_bytes,
+            ..Config::for_tests()
+        });
+        let server = thread::spawn(move || {
+            let (stream, socket) = listener.accept().unwrap();
+            let responders: Vec<Box<ResponderInterface + Send>> = vec![Box::new(EchoResponder {})];
+            Dispatcher::serve(stream, socket, application, handlers, responders);
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+        client.write_all(data).unwrap();
+        if is_half_closed {
+            client.shutdown(Shutdown::Write).unwrap();
+        }
+        let mut transcript = String::new();
+        client.read_to_string(&mut transcript).unwrap();
+        server.join().unwrap();
+        transcript
+    }
+
+    #[test]
+    fn test_http_pipelining() {
+        let transcript = get_transcript(
+            b"GET /a HTTP/1.1\r\nHost: localhost\r\n\r\n\
+              POST /b HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nx=1\
+              GET /c HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            100,
+            None,
+        );
+        let bodies: Vec<&str> = transcript
+            .split("HTTP/1.1 200 OK\r\n")
+            .skip(1)
+            .map(|response| response.rsplit("\r\n\r\n").next().unwrap())
+            .collect();
+        assert_eq!(bodies, vec!["/a", "/b", "/c"]);
+        let last_response = transcript.rsplit("HTTP/1.1 200 OK\r\n").next().unwrap();
+        assert!(last_response.contains("Connection: close\r\n"));
+
+        // Requests after the limit are not answered
+        let transcript = get_transcript(
+            b"GET /a HTTP/1.1\r\nHost: localhost\r\n\r\nGET /b HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            1,
+            None,
+        );
+        assert_eq!(transcript.matches("200 OK").count(), 1);
+        assert!(transcript.ends_with("/a"));
+    }
+
+    #[test]
+    fn test_http_interim_response() {
+        let transcript = get_transcript(
+            b"GET /hints HTTP/1.1\r\nHost: localhost\r\n\r\n\
+              GET /hints HTTP/1.0\r\nHost: localhost\r\n\r\n",
+            100,
+            None,
+        );
+        assert!(transcript.starts_with(
+            "HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n\
+             HTTP/1.1 200 OK\r\n"
+        ));
+        // Not sent to the HTTP/1.0 client
+        assert_eq!(transcript.matches("103 Early Hints").count(), 1);
+        assert_eq!(transcript.matches("200 OK").count(), 2);
+    }
+
+    #[test]
+    fn test_serve_raw() {
+        let handlers: Vec<Box<HandlerInterface + Send>> =
+            vec![Box::new(PingHandler { port: None })];
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let transcript =
+            get_served_transcript(listener, b"PING\n", 100, None, handlers.clone(), false);
+        assert_eq!(transcript, "PONG\n");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let transcript = get_served_transcript(
+            listener,
+            b"GET /a HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            100,
+            None,
+            handlers,
+            false,
+        );
+        assert!(transcript.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(transcript.ends_with("/a"));
+
+        // Connections to the port of a handler are handed over before the client sends anything
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handlers: Vec<Box<HandlerInterface + Send>> =
+            vec![Box::new(PingHandler { port: Some(port) })];
+        assert_eq!(
+            get_served_transcript(listener, b"", 100, None, handlers, false),
+            "PONG\n"
+        );
+    }
+
+    #[test]
+    fn test_http_half_closed() {
+        // Keep-alive would otherwise hold the connection until the 5 second idle time-out
+        let get_half_closed_transcript = |data: &[u8]| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let started = Instant::now();
+            let transcript = get_served_transcript(listener, data, 100, None, Vec::new(), true);
+            assert!(started.elapsed() < Duration::from_secs(2));
+            transcript
+        };
+
+        // Connecting and closing right away gets nothing
+        assert_eq!(get_half_closed_transcript(b""), "");
+
+        // Requests received before the close are answered
+        let transcript = get_half_closed_transcript(b"GET /a HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(transcript.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(transcript.ends_with("/a"));
+        let transcript = get_half_closed_transcript(
+            b"GET /a HTTP/1.1\r\nHost: localhost\r\n\r\nGET /b HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert_eq!(transcript.matches("200 OK").count(), 2);
+        assert!(transcript.ends_with("/b"));
+
+        // A request cut short by the close can not be answered
+        let transcript = get_half_closed_transcript(b"GET /a HTTP/1.1\r\nHost: localhost\r\n");
+        assert_eq!(transcript, "");
+    }
+
+    #[test]
+    fn test_http_response_stream() {
+        let transcript = get_transcript(
+            b"GET /stream HTTP/1.1\r\nHost: localhost\r\n\r\n\
+              GET /a HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            100,
+            None,
+        );
+        let responses: Vec<&str> = transcript.split("HTTP/1.1 200 OK\r\n").skip(1).collect();
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].contains("Transfer-Encoding: chunked\r\n"));
+        assert!(!responses[0].contains("Content-Length"));
+        assert!(responses[0].ends_with("\r\n\r\n7\r\n/stream\r\n9\r\n-streamed\r\n0\r\n\r\n"));
+        assert!(responses[1].ends_with("\r\n\r\n/a"));
+
+        // HTTP/1.0 clients read until the connection is closed
+        let transcript = get_transcript(b"GET /stream HTTP/1.0\r\n\r\n", 100, None);
+        assert!(transcript.contains("Connection: close\r\n"));
+        assert!(!transcript.contains("Transfer-Encoding"));
+        assert!(transcript.ends_with("\r\n\r\n/stream-streamed"));
+
+        // HEAD responses describe the body without sending it
+        let transcript = get_transcript(
+            b"HEAD /stream HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            100,
+            None,
+        );
+        assert!(transcript.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(transcript.ends_with("\r\n\r\n"));
+        assert!(!transcript.contains("/stream"));
+
+        // Bodies of known length are sent as they are and the connection is kept open
+        let transcript = get_transcript(
+            b"GET /stream/length HTTP/1.1\r\nHost: localhost\r\n\r\n\
+              GET /a HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            100,
+            None,
+        );
+        let responses: Vec<&str> = transcript.split("HTTP/1.1 200 OK\r\n").skip(1).collect();
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].contains("Content-Length: 23\r\n"));
+        assert!(!responses[0].contains("Transfer-Encoding"));
+        assert!(responses[0].ends_with("\r\n\r\n/stream/length-streamed"));
+
+        // A body shorter than announced ends the connection
+        let transcript = get_transcript(
+            b"GET /stream/short HTTP/1.1\r\nHost: localhost\r\n\r\n\
+              GET /a HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            100,
+            None,
+        );
+        assert!(transcript.contains("Content-Length: 33\r\n"));
+        assert!(transcript.ends_with("\r\n\r\n/stream/short-streamed"));
+    }
+
+    #[test]
+    fn test_http_server_options() {
+        let transcript = get_transcript(
+            b"OPTIONS * HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            100,
+            None,
+        );
+        assert!(transcript.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(transcript.contains("Allow: GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS\r\n"));
+        assert!(transcript.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_http_streamed_body() {
+        // Larger than both the TCP and the body limit
+        let body = "x".repeat(4000);
+        let request = format!(
+            "POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4000\r\n\r\n{}",
+            body
+        );
+        let transcript = get_transcript(request.as_bytes(), 100, Some(16));
+        assert!(transcript.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(transcript.contains("Connection: close\r\n"));
+        assert!(transcript.ends_with(&format!("\r\n\r\n/upload{}", body)));
+
+        let transcript = get_transcript(
+            b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+              3\r\nabc\r\n0\r\n\r\n",
+            100,
+            Some(16),
+        );
+        assert!(transcript.ends_with("\r\n\r\n/uploadabc"));
+    }
+
+    #[test]
+    fn test_push() {
+        let mut buffer = Vec::new();
+        assert_eq!(Dispatcher::push(&mut buffer, b"abc", 4), 0);
+        assert_eq!(Dispatcher::push(&mut buffer, b"def", 4), 2);
+        assert_eq!(Dispatcher::push(&mut buffer, b"g", 4), 1);
+        assert_eq!(buffer, b"abcd");
+    }
 }
@@ -0,0 +1,47 @@
+//! # TCP raw responses
+//! Handlers for simple protocols other than HTTP served by the same application, like a
+//! metrics text socket. A handler is picked by the port the connection arrived on or by the
+//! first bytes the client sent.
+
+use std::net::{SocketAddr, TcpStream};
+
+use Application;
+
+/// Most bytes peeked from a new connection to pick a handler
+pub const PEEK_BYTES: usize = 64;
+
+pub trait HandlerInterface: HandlerInterfaceCopy {
+    /// Port the handler owns, connections to it are handed over without reading anything.
+    /// Handlers without a port share the HTTP port and are picked by `matches`.
+    fn get_port(&self) -> Option<u16> {
+        None
+    }
+
+    /// Does the handler serve a connection on the HTTP port that started with these bytes?
+    /// At most `PEEK_BYTES` that arrived when the connection was accepted are given.
+    fn matches(&mut self, _initial: &[u8], _application: &Application) -> bool {
+        false
+    }
+
+    /// Serve the connection until it should be closed, nothing has been read from it
+    fn handle(&mut self, stream: TcpStream, socket: &SocketAddr, application: &Application);
+}
+
+pub trait HandlerInterfaceCopy {
+    fn clone_box(&self) -> Box<HandlerInterface + Send>;
+}
+
+impl<T> HandlerInterfaceCopy for T
+where
+    T: 'static + HandlerInterface + Clone + Send,
+{
+    fn clone_box(&self) -> Box<HandlerInterface + Send> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<HandlerInterface + Send> {
+    fn clone(&self) -> Box<HandlerInterface + Send> {
+        self.clone_box()
+    }
+}
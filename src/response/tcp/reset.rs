@@ -0,0 +1,98 @@
+//! # TCP connection reset
+//! Makes closing a connection send a RST instead of a FIN by setting `SO_LINGER` to zero, the
+//! server side then skips `TIME_WAIT` which saves ports and connection tracking entries when
+//! many abusive clients are closed. Data not yet sent when the stream is dropped is discarded.
+
+use std::io;
+use std::net::TcpStream;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SOL_SOCKET: i32 = 1;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SO_LINGER: i32 = 13;
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+const SOL_SOCKET: i32 = 0xffff;
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+const SO_LINGER: i32 = 0x0080;
+
+/// Same layout as `struct linger` in `sys/socket.h`
+#[cfg(unix)]
+#[repr(C)]
+struct Linger {
+    l_onoff: i32,
+    l_linger: i32,
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn setsockopt(
+        socket: i32,
+        level: i32,
+        name: i32,
+        value: *const ::std::os::raw::c_void,
+        length: u32,
+    ) -> i32;
+}
+
+/// Reset the connection instead of closing it gracefully when the stream is dropped
+/// # Example
+/// ```rust,no_run
+/// use milstian_internet_framework::response::tcp::reset;
+/// use std::net::TcpStream;
+/// let stream = TcpStream::connect("127.0.0.1:8080").unwrap();
+/// reset::set_reset_on_close(&stream).unwrap();
+/// drop(stream);
+/// ```
+#[cfg(unix)]
+pub fn set_reset_on_close(stream: &TcpStream) -> io::Result<()> {
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    let linger = Linger {
+        l_onoff: 1,
+        l_linger: 0,
+    };
+    let result = unsafe {
+        setsockopt(
+            stream.as_raw_fd(),
+            SOL_SOCKET,
+            SO_LINGER,
+            &linger as *const Linger as *const ::std::os::raw::c_void,
+            mem::size_of::<Linger>() as u32,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Other platforms close connections gracefully
+#[cfg(not(unix))]
+pub fn set_reset_on_close(_stream: &TcpStream) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Resetting connections on close is only supported on unix",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::prelude::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_set_reset_on_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        set_reset_on_close(&accepted).unwrap();
+        drop(accepted);
+
+        let mut received = Vec::new();
+        let error = client.read_to_end(&mut received).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+    }
+}
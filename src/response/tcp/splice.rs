@@ -0,0 +1,182 @@
+//! # TCP stream splice
+//! Copies bytes both ways between a client connection and a backend connection, for raw
+//! handlers that tunnel a protocol to another server instead of answering it themselves.
+
+use std::io::prelude::*;
+use std::io::ErrorKind;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use response::tcp::jobs::{self, Class};
+
+/// # Bytes copied in each direction and why the splice ended
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Totals {
+    /// Bytes copied from the backend to the client
+    pub downstream_bytes: u64,
+    /// Neither side sent anything for the idle timeout, both connections were shut down
+    pub is_idle_timeout: bool,
+    /// Bytes copied from the client to the backend
+    pub upstream_bytes: u64,
+}
+
+/// Copy bytes between the connections until both sides closed their half or neither sent
+/// anything for `idle_timeout`. A side that closes it's half is passed on to the other side.
+/// # Example
+/// ```rust,no_run
+/// use milstian_internet_framework::response::tcp::splice;
+/// use std::net::{TcpListener, TcpStream};
+/// use std::time::Duration;
+/// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+/// let (client, _) = listener.accept().unwrap();
+/// let backend = TcpStream::connect("127.0.0.1:6379").unwrap();
+/// let totals = splice::splice(client, backend, Duration::from_secs(60)).unwrap();
+/// println!("Sent {} bytes to the backend", totals.upstream_bytes);
+/// ```
+pub fn splice(
+    client: TcpStream,
+    backend: TcpStream,
+    idle_timeout: Duration,
+) -> Result<Totals, String> {
+    jobs::set_class(Class::Proxy);
+    let activity = Arc::new(Mutex::new(Instant::now()));
+    let (client_reader, backend_writer) = match (client.try_clone(), backend.try_clone()) {
+        (Ok(client_reader), Ok(backend_writer)) => (client_reader, backend_writer),
+        (Err(error), _) | (_, Err(error)) => {
+            return Err(format!("Failed to clone stream for splice, error: {}", error))
+        }
+    };
+
+    let downstream_activity = activity.clone();
+    let downstream =
+        thread::spawn(move || pipe(backend, client, &downstream_activity, idle_timeout));
+    let upstream = pipe(client_reader, backend_writer, &activity, idle_timeout);
+    let downstream = match downstream.join() {
+        Ok(downstream) => downstream,
+        Err(_) => return Err("Splice thread panicked".to_string()),
+    };
+
+    match (upstream, downstream) {
+        (Ok((upstream_bytes, upstream_idle)), Ok((downstream_bytes, downstream_idle))) => {
+            Ok(Totals {
+                downstream_bytes,
+                is_idle_timeout: upstream_idle || downstream_idle,
+                upstream_bytes,
+            })
+        }
+        (Err(error), _) => Err(format!("Failed to copy to backend, error: {}", error)),
+        (_, Err(error)) => Err(format!("Failed to copy to client, error: {}", error)),
+    }
+}
+
+/// Copy one direction until end of stream, returns bytes copied and if the idle timeout ended it
+fn pipe(
+    mut from: TcpStream,
+    mut to: TcpStream,
+    activity: &Mutex<Instant>,
+    idle_timeout: Duration,
+) -> Result<(u64, bool), String> {
+    let mut total = 0;
+    let mut buffer = [0; 8192];
+    loop {
+        let last_activity = match activity.lock() {
+            Ok(last_activity) => *last_activity,
+            Err(_) => return Err("Splice activity lock was poisoned".to_string()),
+        };
+        let remaining = match idle_timeout.checked_sub(last_activity.elapsed()) {
+            Some(remaining) if remaining > Duration::new(0, 0) => remaining,
+            _ => {
+                let _ = from.shutdown(Shutdown::Both);
+                let _ = to.shutdown(Shutdown::Both);
+                return Ok((total, true));
+            }
+        };
+        if let Err(error) = from.set_read_timeout(Some(remaining)) {
+            return Err(error.to_string());
+        }
+        match from.read(&mut buffer) {
+            Ok(0) => {
+                // Pass on the end of stream so the other side can finish
+                let _ = to.shutdown(Shutdown::Write);
+                return Ok((total, false));
+            }
+            Ok(read_size) => {
+                if let Err(error) = to.write_all(&buffer[..read_size]) {
+                    let _ = from.shutdown(Shutdown::Both);
+                    return Err(error.to_string());
+                }
+                total += read_size as u64;
+                if let Ok(mut last_activity) = activity.lock() {
+                    *last_activity = Instant::now();
+                }
+            }
+            Err(ref error)
+                if error.kind() == ErrorKind::WouldBlock
+                    || error.kind() == ErrorKind::TimedOut
+                    || error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => {
+                let _ = to.shutdown(Shutdown::Both);
+                return Err(error.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Connected client side and accepted server side
+    fn get_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        (client, accepted)
+    }
+
+    #[test]
+    fn test_splice() {
+        let (mut client, accepted) = get_pair();
+        let (backend, mut backend_server) = get_pair();
+        let splicer =
+            thread::spawn(move || splice(accepted, backend, Duration::from_secs(5)).unwrap());
+
+        // The backend answers in upper case and closes after the client is done
+        let echo = thread::spawn(move || {
+            let mut request = String::new();
+            backend_server.read_to_string(&mut request).unwrap();
+            backend_server.write_all(request.to_uppercase().as_bytes()).unwrap();
+        });
+        client.write_all(b"hello").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        echo.join().unwrap();
+
+        assert_eq!(response, "HELLO");
+        assert_eq!(
+            splicer.join().unwrap(),
+            Totals {
+                downstream_bytes: 5,
+                is_idle_timeout: false,
+                upstream_bytes: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_splice_idle_timeout() {
+        let (mut client, accepted) = get_pair();
+        let (backend, _backend_server) = get_pair();
+        let started = Instant::now();
+        let totals = splice(accepted, backend, Duration::from_millis(50)).unwrap();
+        assert!(totals.is_idle_timeout);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let mut response = Vec::new();
+        assert_eq!(client.read_to_end(&mut response).unwrap(), 0);
+    }
+}
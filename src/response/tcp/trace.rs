@@ -0,0 +1,139 @@
+//! # Request trace
+//! Ring buffer keeping the summaries of the last requests for post-mortem debugging.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Order the entry was recorded in, starting at 1
+    pub sequence: usize,
+    pub summary: String,
+}
+
+#[derive(Debug)]
+struct Slots {
+    next: AtomicUsize,
+    slots: Vec<Mutex<Option<Entry>>>,
+}
+
+/// # Keeps the last entries, older entries are overwritten
+/// Every slot has it's own lock so concurrent workers rarely wait for each other.
+/// ```rust
+/// use milstian_internet_framework::response::tcp::trace::Ring;
+/// let ring = Ring::new(2);
+/// ring.record("first".to_string());
+/// ring.record("second".to_string());
+/// ring.record("third".to_string());
+/// assert_eq!(ring.dump(), "2 second\n3 third\n");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Ring {
+    inner: Arc<Slots>,
+}
+
+impl Ring {
+    /// A ring with zero capacity records nothing
+    pub fn new(capacity: usize) -> Ring {
+        Ring {
+            inner: Arc::new(Slots {
+                next: AtomicUsize::new(0),
+                slots: (0..capacity).map(|_| Mutex::new(None)).collect(),
+            }),
+        }
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.inner.slots.len()
+    }
+
+    pub fn record(&self, summary: String) {
+        if self.inner.slots.is_empty() {
+            return;
+        }
+        let index = self.inner.next.fetch_add(1, Ordering::SeqCst);
+        let slot = &self.inner.slots[index % self.inner.slots.len()];
+        let mut slot = match slot.lock() {
+            Ok(slot) => slot,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        // A slower writer must not overwrite a newer entry in the same slot
+        let is_newer = match &*slot {
+            Some(entry) => entry.sequence <= index,
+            None => true,
+        };
+        if is_newer {
+            *slot = Some(Entry {
+                sequence: index + 1,
+                summary,
+            });
+        }
+    }
+
+    /// Copy of recorded entries, oldest first
+    pub fn get_entries(&self) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = self
+            .inner
+            .slots
+            .iter()
+            .filter_map(|slot| match slot.lock() {
+                Ok(slot) => slot.clone(),
+                Err(poisoned) => poisoned.into_inner().clone(),
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.sequence);
+        entries
+    }
+
+    /// Entries as text with one entry per line, oldest first
+    pub fn dump(&self) -> String {
+        self.get_entries()
+            .iter()
+            .map(|entry| format!("{} {}\n", entry.sequence, entry.summary))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_record() {
+        let ring = Ring::new(3);
+        assert!(ring.get_entries().is_empty());
+        for index in 1..6 {
+            ring.record(format!("request {}", index));
+        }
+        let entries = ring.get_entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].sequence, 3);
+        assert_eq!(entries[2].summary, "request 5");
+
+        let ring = Ring::new(0);
+        ring.record("ignored".to_string());
+        assert_eq!(ring.dump(), "");
+    }
+
+    #[test]
+    fn test_concurrent_record() {
+        let ring = Ring::new(16);
+        let threads: Vec<thread::JoinHandle<()>> = (0..4)
+            .map(|thread_index| {
+                let ring = ring.clone();
+                thread::spawn(move || {
+                    for index in 0..100 {
+                        ring.record(format!("{}-{}", thread_index, index));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let sequences: Vec<usize> = ring.get_entries().iter().map(|entry| entry.sequence).collect();
+        assert_eq!(sequences, (385..401).collect::<Vec<usize>>());
+    }
+}
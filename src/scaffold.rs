@@ -0,0 +1,212 @@
+//! # Project scaffold
+//! Writes a runnable starter project using the framework, only built with the `scaffold`
+//! feature. The project has a configuration file, a HTML root, a custom responder next to
+//! the filesystem responders and a systemd unit.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const CARGO_TOML: &str = r#"[package]
+name = "{name}"
+version = "0.1.0"
+
+[dependencies]
+milstian-internet-framework = "0.3"
+"#;
+
+const CONFIG: &str = "# Arguments of Config::from_env_args, one per line in this order
+# TCP hostname
+localhost
+# TCP port
+8888
+# Limit of workers
+4
+# HTTP directory index files, the first that exists is sent, separated by commas
+index.htm
+# HTTP web-server file-system root
+./html/
+# HTTP file not found file
+404.htm
+# Maximum TCP request size
+1048576
+";
+
+const MAIN: &str = r#"extern crate milstian_internet_framework;
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::SocketAddr;
+
+use milstian_internet_framework::application_layer::http::status::HttpStatus;
+use milstian_internet_framework::application_layer::http::{request, response};
+use milstian_internet_framework::response::tcp::http::{
+    error, file_not_found, filesystem, ResponderInterface,
+};
+use milstian_internet_framework::{Application, Config};
+
+/// Answers `/hello` with a plain text greeting
+#[derive(Clone)]
+struct Hello;
+
+impl ResponderInterface for Hello {
+    fn matches(
+        &mut self,
+        request_message: &request::Message,
+        _application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> bool {
+        request_message.request_line.uri.path == "/hello"
+    }
+
+    fn respond(
+        &self,
+        request_message: &request::Message,
+        _application: &Application,
+        _socket: &SocketAddr,
+        _overflow_bytes: &u64,
+    ) -> Result<response::Message, String> {
+        let body = b"Hello from {name}\n".to_vec();
+        let mut headers = HashMap::new();
+        headers.insert("Content-Length".to_string(), body.len().to_string());
+        headers.insert("Content-Type".to_string(), "text/plain".to_string());
+        Ok(response::Message::new(
+            request_message.request_line.protocol.to_string(),
+            HttpStatus::Ok.to_string(),
+            headers,
+            body,
+        ))
+    }
+}
+
+fn main() {
+    let config = fs::read_to_string("milstian.conf").expect("Failed to read milstian.conf");
+    let mut args = vec![String::new()];
+    for line in config.lines().map(|line| line.trim()) {
+        if !line.is_empty() && !line.starts_with('#') {
+            args.push(line.to_string());
+        }
+    }
+    let config = Config::from_env_args(args).expect("Failed to get configuration from file");
+    let responders: Vec<Box<ResponderInterface + Send>> = vec![
+        Box::new(Hello),
+        Box::new(filesystem::Responder::new()),
+        Box::new(file_not_found::Responder::new()),
+        Box::new(error::Responder::new()),
+    ];
+    Application::new(config).tcp_http(responders);
+}
+"#;
+
+const INDEX: &str = "<!DOCTYPE html>
+<html><head><title>{name}</title></head>
+<body><h1>{name}</h1>
+<p>Served by the Milstian Internet Framework, try <a href=\"/hello\">hello</a>.</p>
+</body></html>
+";
+
+const NOT_FOUND: &str = "<!DOCTYPE html>
+<html><head><title>Not found</title></head>
+<body><h1>Not found</h1><p>The page does not exist.</p></body></html>
+";
+
+const SERVICE: &str = "[Unit]
+Description={name}
+After=network.target
+
+[Service]
+WorkingDirectory=/opt/{name}
+ExecStart=/opt/{name}/target/release/{name}
+Restart=on-failure
+
+[Install]
+WantedBy=multi-user.target
+";
+
+/// Relative path and content of every file in a project
+pub fn get_files(name: &str) -> Vec<(String, String)> {
+    let service = format!("{}.service", name);
+    let files: Vec<(&str, &str)> = vec![
+        ("Cargo.toml", CARGO_TOML),
+        ("milstian.conf", CONFIG),
+        ("src/main.rs", MAIN),
+        ("html/index.htm", INDEX),
+        ("html/404.htm", NOT_FOUND),
+        (&service, SERVICE),
+    ];
+    files
+        .into_iter()
+        .map(|(path, template)| (path.to_string(), template.replace("{name}", name)))
+        .collect()
+}
+
+/// Write a project to a directory, existing files are never overwritten
+/// # Example
+/// ```rust,no_run
+/// use milstian_internet_framework::scaffold;
+/// let written = scaffold::write(std::path::Path::new("./my_site"), "my_site").unwrap();
+/// assert_eq!(written.len(), 6);
+/// ```
+pub fn write(directory: &Path, name: &str) -> Result<Vec<PathBuf>, String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "-_".contains(character))
+    {
+        return Err(format!(
+            "Invalid project name '{}', expected letters, digits, dashes and underscores",
+            name
+        ));
+    }
+    let files = get_files(name);
+    for (path, _) in files.iter() {
+        let path = directory.join(path);
+        if path.exists() {
+            return Err(format!("Refusing to overwrite existing file {:?}", path));
+        }
+    }
+
+    let mut written = Vec::with_capacity(files.len());
+    for (path, content) in files {
+        let path = directory.join(path);
+        if let Some(parent) = path.parent() {
+            if let Err(error) = fs::create_dir_all(parent) {
+                return Err(format!(
+                    "Failed to create directory {:?}, error: {}",
+                    parent, error
+                ));
+            }
+        }
+        match fs::File::create(&path).and_then(|mut file| file.write_all(content.as_bytes())) {
+            Ok(()) => written.push(path),
+            Err(error) => {
+                return Err(format!("Failed to write file {:?}, error: {}", path, error))
+            }
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_write() {
+        let directory = env::temp_dir().join(format!("milstian-scaffold-{}", process::id()));
+        let written = write(&directory, "my_site").unwrap();
+        assert_eq!(written.len(), 6);
+        let main = fs::read_to_string(directory.join("src/main.rs")).unwrap();
+        assert!(main.contains("Hello from my_site"));
+        let cargo = fs::read_to_string(directory.join("Cargo.toml")).unwrap();
+        assert!(cargo.starts_with("[package]\nname = \"my_site\"\n"));
+        assert!(directory.join("my_site.service").is_file());
+
+        assert!(write(&directory, "my_site").is_err());
+        assert!(write(&directory, "../site").is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
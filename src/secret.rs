@@ -0,0 +1,138 @@
+//! # Secrets
+//! Configuration values like signing keys and tokens that never show up in debug output, config
+//! dumps or crash reports. They can be read from the environment or a file only the owner can read.
+
+use std::env;
+use std::fmt;
+use std::fs;
+
+#[derive(Clone, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: &str) -> Secret {
+        Secret(value.to_string())
+    }
+
+    /// Resolve a configuration value, `${env:NAME}` reads environment variable `NAME` and
+    /// `${file:path}` reads a file that group and others can't access, other values are used as
+    /// they are
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::secret::Secret;
+    /// use std::env;
+    /// env::set_var("MILSTIAN_EXAMPLE_TOKEN", "abc");
+    /// let secret = Secret::from_config("${env:MILSTIAN_EXAMPLE_TOKEN}").unwrap();
+    /// assert_eq!(secret.get_value(), "abc");
+    /// assert_eq!(format!("{:?}", secret), "<redacted>");
+    /// ```
+    pub fn from_config(value: &str) -> Result<Secret, String> {
+        if !value.starts_with("${") || !value.ends_with('}') {
+            return Ok(Secret::new(value));
+        }
+        let reference = &value[2..value.len() - 1];
+        if let Some(name) = reference.strip_prefix("env:") {
+            match env::var(name) {
+                Ok(value) => Ok(Secret(value)),
+                Err(error) => Err(format!(
+                    "Failed to read secret from environment variable {}, error: {}",
+                    name, error
+                )),
+            }
+        } else if let Some(filename) = reference.strip_prefix("file:") {
+            Secret::from_file(filename)
+        } else {
+            Err(format!("Unknown secret reference {}", value))
+        }
+    }
+
+    /// Read a secret from a file, a trailing line-break is removed
+    pub fn from_file(filename: &str) -> Result<Secret, String> {
+        Secret::check_permissions(filename)?;
+        match fs::read_to_string(filename) {
+            Ok(value) => Ok(Secret(value.trim_end_matches(&['\r', '\n'][..]).to_string())),
+            Err(error) => Err(format!(
+                "Failed to read secret file {}, error: {}",
+                filename, error
+            )),
+        }
+    }
+
+    #[cfg(unix)]
+    fn check_permissions(filename: &str) -> Result<(), String> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = match fs::metadata(filename) {
+            Ok(metadata) => metadata.permissions().mode(),
+            Err(error) => {
+                return Err(format!(
+                    "Failed to read secret file {}, error: {}",
+                    filename, error
+                ))
+            }
+        };
+        if mode & 0o077 != 0 {
+            return Err(format!(
+                "Secret file {} can be accessed by group or others, permissions: {:o}",
+                filename,
+                mode & 0o777
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn check_permissions(_filename: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn get_value(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "<redacted>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(Secret::from_config("abc").unwrap().get_value(), "abc");
+        env::set_var("MILSTIAN_TEST_SECRET", "def");
+        assert_eq!(
+            Secret::from_config("${env:MILSTIAN_TEST_SECRET}")
+                .unwrap()
+                .get_value(),
+            "def"
+        );
+        assert!(Secret::from_config("${env:MILSTIAN_TEST_MISSING}").is_err());
+        assert!(Secret::from_config("${vault:key}").is_err());
+        assert_eq!(format!("{:?}", Secret::new("abc")), "<redacted>");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_from_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let filename = env::temp_dir().join("milstian-secret-test.txt");
+        fs::write(&filename, "ghi\n").unwrap();
+        let filename_string = filename.to_str().unwrap().to_string();
+        fs::set_permissions(&filename, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(Secret::from_file(&filename_string).is_err());
+        fs::set_permissions(&filename, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(
+            Secret::from_config(&format!("${{file:{}}}", filename_string))
+                .unwrap()
+                .get_value(),
+            "ghi"
+        );
+        fs::remove_file(&filename).unwrap();
+    }
+}
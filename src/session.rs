@@ -0,0 +1,173 @@
+//! # Sessions
+//! Sessions per identity with their client, user agent and when they were last seen, shared by
+//! all clones of the application so users can see and end their sessions on other devices.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use digest;
+use random;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub client: String,
+    pub created: SystemTime,
+    pub id: String,
+    pub identity: String,
+    pub last_seen: SystemTime,
+    pub user_agent: String,
+}
+
+/// # Open sessions by id
+#[derive(Clone, Debug)]
+pub struct Sessions {
+    limit: Option<usize>,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl Sessions {
+    /// Identities get at most `limit` sessions at the same time when set
+    pub fn new(limit: Option<usize>) -> Sessions {
+        Sessions {
+            limit,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Start a session for identity and return it, the least recently seen sessions of the
+    /// identity are ended when it has more than the limit. Ids are read from the random source
+    /// of the operating system, no session is started when it's not available.
+    /// # Example
+    /// ```rust
+    /// use milstian_internet_framework::session::Sessions;
+    /// let sessions = Sessions::new(Some(2));
+    /// let laptop = sessions.create("alice", "10.0.0.1", "Firefox").unwrap();
+    /// let phone = sessions.create("alice", "10.0.0.2", "Safari").unwrap();
+    /// assert_eq!(sessions.remove_others(&phone.id), 1);
+    /// assert!(sessions.get(&laptop.id).is_none());
+    /// ```
+    pub fn create(
+        &self,
+        identity: &str,
+        client: &str,
+        user_agent: &str,
+    ) -> Result<Session, String> {
+        let now = SystemTime::now();
+        let session = Session {
+            client: client.to_string(),
+            created: now,
+            id: digest::to_hex(&random::secure_bytes(16)?),
+            identity: identity.to_string(),
+            last_seen: now,
+            user_agent: user_agent.to_string(),
+        };
+        let mut sessions = self.lock();
+        sessions.insert(session.id.clone(), session.clone());
+        if let Some(limit) = self.limit {
+            let mut existing = Sessions::get_sorted(&sessions, identity);
+            if existing.len() > limit {
+                for ended in existing.split_off(limit) {
+                    sessions.remove(&ended.id);
+                }
+            }
+        }
+        Ok(session)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Session> {
+        self.lock().get(id).cloned()
+    }
+
+    /// Sessions of identity, the most recently seen first
+    pub fn get_by_identity(&self, identity: &str) -> Vec<Session> {
+        Sessions::get_sorted(&self.lock(), identity)
+    }
+
+    /// Mark a session as seen now from client, returns it when it's open
+    pub fn touch(&self, id: &str, client: &str) -> Option<Session> {
+        let mut sessions = self.lock();
+        let session = sessions.get_mut(id)?;
+        session.client = client.to_string();
+        session.last_seen = SystemTime::now();
+        Some(session.clone())
+    }
+
+    /// End a session, returns whether it was open
+    pub fn remove(&self, id: &str) -> bool {
+        self.lock().remove(id).is_some()
+    }
+
+    /// End all other sessions of the identity of a session, like logging out other devices,
+    /// returns the number of ended sessions
+    pub fn remove_others(&self, id: &str) -> usize {
+        let mut sessions = self.lock();
+        let identity = match sessions.get(id) {
+            Some(session) => session.identity.clone(),
+            None => return 0,
+        };
+        let before = sessions.len();
+        sessions.retain(|existing, session| existing == id || session.identity != identity);
+        before - sessions.len()
+    }
+
+    fn get_sorted(sessions: &HashMap<String, Session>, identity: &str) -> Vec<Session> {
+        let mut sorted: Vec<Session> = sessions
+            .values()
+            .filter(|session| session.identity == identity)
+            .cloned()
+            .collect();
+        sorted.sort_by_key(|session| Reverse(session.last_seen));
+        sorted
+    }
+
+    fn lock(&self) -> MutexGuard<HashMap<String, Session>> {
+        match self.sessions.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sessions() {
+        let sessions = Sessions::new(Some(2));
+        let first = sessions.create("alice", "10.0.0.1", "Firefox").unwrap();
+        thread::sleep(Duration::from_millis(2));
+        let second = sessions.create("alice", "10.0.0.2", "Safari").unwrap();
+        let other = sessions.create("bob", "10.0.0.3", "Curl").unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(first.id.len(), 32);
+
+        // Seeing a session moves it first
+        thread::sleep(Duration::from_millis(2));
+        let touched = sessions.touch(&first.id, "10.0.0.4").unwrap();
+        assert_eq!(touched.client, "10.0.0.4");
+        let ids: Vec<String> = sessions
+            .get_by_identity("alice")
+            .into_iter()
+            .map(|session| session.id)
+            .collect();
+        assert_eq!(ids, vec![first.id.clone(), second.id.clone()]);
+
+        // The least recently seen session ends when the limit is exceeded
+        thread::sleep(Duration::from_millis(2));
+        let third = sessions.create("alice", "10.0.0.5", "Chrome").unwrap();
+        assert!(sessions.get(&second.id).is_none());
+        assert_eq!(sessions.get_by_identity("alice").len(), 2);
+
+        assert_eq!(sessions.remove_others(&third.id), 1);
+        assert!(sessions.get(&first.id).is_none());
+        assert!(sessions.get(&other.id).is_some());
+        assert!(sessions.remove(&third.id));
+        assert!(!sessions.remove(&third.id));
+        assert!(sessions.touch(&third.id, "10.0.0.5").is_none());
+    }
+}
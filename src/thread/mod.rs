@@ -1,44 +1,31 @@
 //! # Handles the workers
 
-// TODO Add tests to this file
-
-use std::time::SystemTime;
+use std::time::{Duration, Instant};
 
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
 
+use response::tcp::connections::{Connection, State};
+use response::tcp::jobs::{self, Class};
 use Application;
 
 pub struct Pool<'a> {
     application: &'a Application,
+    /// Ids of workers whose threads have stopped
+    finished: mpsc::Receiver<usize>,
     workers: Vec<Worker>,
     sender: mpsc::Sender<Message>,
 }
 
 impl<'a> Pool<'a> {
-    /// Create a job from a closure and send for execution
-    pub fn execute<F>(&self, f: F)
-    where
-        F: FnOnce() + Send + 'static,
-    {
-        // Place job inside a Box inside a message
-        let message = Message::NewJob(Box::new(f));
-
-        &self
-            .application
-            .get_feedback()
-            .info("Sending job down the channel".to_string());
-
-        // Send a NewJob Message down the channel
-        // If it fails thread? will crash deliberately
-        let sender_clone = self.sender.clone();
-        thread::spawn(move || {
-            sender_clone
-                .send(message)
-                .expect("Failed to send job down the channel");
-        });
+    /// Sender of jobs that can be moved to other threads
+    pub fn get_executor(&self) -> Executor {
+        Executor {
+            sender: self.sender.clone(),
+        }
     }
 
     /// Create a new mutex channel with specified number of receivers
@@ -51,19 +38,79 @@ impl<'a> Pool<'a> {
         let (sender, receiver) = mpsc::channel();
 
         let receiver = Arc::new(Mutex::new(receiver));
+        let (finished_sender, finished) = mpsc::channel();
 
         let mut workers = Vec::with_capacity(size);
 
         for id in 0..size {
-            workers.push(Worker::new(&application, id, Arc::clone(&receiver)));
+            workers.push(Worker::new(
+                &application,
+                id,
+                Arc::clone(&receiver),
+                finished_sender.clone(),
+            ));
         }
 
         Pool {
             application,
+            finished,
             sender,
             workers,
         }
     }
+
+    /// Wait at most `timeout` for worker `id` to stop, workers that stopped while waiting are
+    /// remembered in `stopped`
+    fn wait(
+        finished: &mpsc::Receiver<usize>,
+        stopped: &mut Vec<usize>,
+        id: usize,
+        timeout: Duration,
+    ) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !stopped.contains(&id) {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            match finished.recv_timeout(deadline - now) {
+                Ok(stopped_id) => stopped.push(stopped_id),
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Name of the thread of worker `id`, connections record it so running requests can be traced
+/// to their worker
+pub fn get_thread_name(id: usize) -> String {
+    format!("worker-{}", id)
+}
+
+/// Requests the connections of worker `id` are busy with
+fn get_running_requests(connections: &[Connection], id: usize) -> String {
+    let name = get_thread_name(id);
+    let requests: Vec<String> = connections
+        .iter()
+        .filter(|connection| {
+            connection.worker.as_ref() == Some(&name) && connection.state != State::Idle
+        })
+        .map(|connection| {
+            format!(
+                "{} {} ({}, {}s)",
+                connection.request_id,
+                connection.request_uri,
+                connection.state,
+                connection.get_duration().as_secs()
+            )
+        })
+        .collect();
+    if requests.is_empty() {
+        "no known request".to_string()
+    } else {
+        requests.join(", ")
+    }
 }
 
 impl<'a> Drop for Pool<'a> {
@@ -87,12 +134,34 @@ impl<'a> Drop for Pool<'a> {
             .get_feedback()
             .info("Shutting down all workers.".to_string());
 
-        // Join every workers thread
+        // Join every workers thread, workers that don't finish their job in time are left running
+        let timeout = self.application.get_config().worker_shutdown_timeout;
+        let mut stopped = Vec::new();
         for worker in &mut self.workers {
             self.application
                 .get_feedback()
                 .info(format!("Shutting down worker {}", worker.id));
 
+            if timeout > 0
+                && !Pool::wait(
+                    &self.finished,
+                    &mut stopped,
+                    worker.id,
+                    Duration::from_secs(timeout),
+                )
+            {
+                let connections = self.application.get_connections().get_snapshot();
+                self.application.error(|| {
+                    format!(
+                        "Worker {} did not finish within {}s, left it running: {}",
+                        worker.id,
+                        timeout,
+                        get_running_requests(&connections, worker.id)
+                    )
+                });
+                continue;
+            }
+
             if let Some(thread) = worker.thread.take() {
                 if let Err(error) = thread.join() {
                     self.application.get_feedback().info(format!(
@@ -107,6 +176,35 @@ impl<'a> Drop for Pool<'a> {
                 ));
             }
         }
+
+        // All jobs that were joined are finished so the access log contains every answered request
+        if let Some(access_log) = self.application.get_access_log() {
+            self.application
+                .get_feedback()
+                .info("Flushing access log.".to_string());
+            access_log.shutdown();
+        }
+    }
+}
+
+/// # Sends jobs to the workers of a pool from threads that can not borrow it
+#[derive(Clone)]
+pub struct Executor {
+    sender: mpsc::Sender<Message>,
+}
+
+impl Executor {
+    pub fn execute<F>(&self, class: Class, f: F) -> Result<(), String>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        match self
+            .sender
+            .send(Message::NewJob(Box::new(f), class, Instant::now()))
+        {
+            Ok(()) => Ok(()),
+            Err(_) => Err("Failed to send job, the workers have terminated".to_string()),
+        }
     }
 }
 
@@ -121,42 +219,67 @@ impl<'a> Worker {
         application: &'a Application,
         id: usize,
         receiver: Arc<Mutex<mpsc::Receiver<Message>>>,
+        finished: mpsc::Sender<usize>,
     ) -> Worker {
         let application_clone = application.clone();
 
-        let thread = thread::spawn(move || {
-            loop {
-                let message = receiver.lock().unwrap().recv().unwrap();
-                match message {
-                    Message::NewJob(job) => {
-                        let start = SystemTime::now();
-                        application_clone
-                            .get_feedback()
-                            .info(format!("Worker {} started executing job from channel", id));
-                        job.call_box();
-
-                        // TODO Add time-out for process?
-
-                        let mut elapsed_secs = 0;
-                        let mut elapsed_millis = 0;
-                        if let Ok(time_elapsed) = start.elapsed() {
-                            elapsed_secs = time_elapsed.as_secs();
-                            elapsed_millis = time_elapsed.subsec_millis();
+        let thread = thread::Builder::new()
+            .name(get_thread_name(id))
+            .spawn(move || {
+                let _finished = Finished {
+                    id,
+                    sender: finished,
+                };
+                loop {
+                    let message = receiver.lock().unwrap().recv().unwrap();
+                    match message {
+                        Message::NewJob(job, class, sent) => {
+                            let queue_wait = sent.elapsed();
+                            let start = Instant::now();
+                            application_clone
+                                .get_feedback()
+                                .info(format!("Worker {} started executing job from channel", id));
+
+                            // A panicking job is counted and the worker goes on with the next
+                            jobs::take_class();
+                            let result = panic::catch_unwind(AssertUnwindSafe(|| job.call_box()));
+                            let run_time = start.elapsed();
+                            let class = jobs::take_class().unwrap_or(class);
+                            application_clone.get_jobs().record(
+                                class,
+                                queue_wait,
+                                run_time,
+                                result.is_err(),
+                            );
+
+                            // TODO Add time-out for process?
+
+                            if result.is_err() {
+                                application_clone.error(|| {
+                                    format!(
+                                        "Worker {} recovered from a panic in a {} job",
+                                        id, class
+                                    )
+                                });
+                            }
+                            application_clone.get_feedback().info(format!(
+                                "Worker {} finished executing {} job after {}s {}ms",
+                                id,
+                                class,
+                                run_time.as_secs(),
+                                run_time.subsec_millis()
+                            ));
+                        }
+                        Message::Terminate => {
+                            application_clone
+                                .get_feedback()
+                                .info(format!("Worker {} was told to terminate", id));
+                            break;
                         }
-                        application_clone.get_feedback().info(format!(
-                            "Worker {} finished executing after {}s {}ms",
-                            id, elapsed_secs, elapsed_millis
-                        ));
-                    }
-                    Message::Terminate => {
-                        application_clone
-                            .get_feedback()
-                            .info(format!("Worker {} was told to terminate", id));
-                        break;
                     }
                 }
-            }
-        });
+            })
+            .expect("Failed to spawn worker thread");
 
         Worker {
             id: id,
@@ -165,8 +288,21 @@ impl<'a> Worker {
     }
 }
 
+/// Tells the pool the worker stopped when dropped, also when a job panicked
+struct Finished {
+    id: usize,
+    sender: mpsc::Sender<usize>,
+}
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        let _ = self.sender.send(self.id);
+    }
+}
+
 enum Message {
-    NewJob(Job),
+    /// Job, it's class and when it was sent
+    NewJob(Job, Class, Instant),
     Terminate,
 }
 
@@ -181,3 +317,96 @@ impl<F: FnOnce()> FnBox for F {
         (*self)()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use feedback::Level;
+    use response::tcp::connections::Table;
+    use Config;
+
+    fn get_application(worker_shutdown_timeout: u64) -> Application {
+        Application::new(Config {
+            feedback_level: Level::Off,
+            server_limit: 2,
+            worker_shutdown_timeout,
+            ..Config::for_tests()
+        })
+    }
+
+    #[test]
+    fn test_get_running_requests() {
+        let table = Table::new();
+        let (sender, receiver) = mpsc::channel();
+        let worker_table = table.clone();
+        let thread = thread::Builder::new()
+            .name(get_thread_name(3))
+            .spawn(move || {
+                let registration = worker_table.open("127.0.0.1:1000".parse().unwrap());
+                registration.set_request("/slow", "7-a");
+                registration.set_state(State::Responding);
+                let idle = worker_table.open("127.0.0.1:2000".parse().unwrap());
+                sender.send(()).unwrap();
+                thread::park();
+                drop(idle);
+            })
+            .unwrap();
+        receiver.recv().unwrap();
+        let connections = table.get_snapshot();
+        assert_eq!(
+            get_running_requests(&connections, 3),
+            "7-a /slow (responding, 0s)"
+        );
+        assert_eq!(get_running_requests(&connections, 2), "no known request");
+        thread.thread().unpark();
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_execute() {
+        let application = get_application(0);
+        {
+            let pool = Pool::new(&application, 1);
+            let executor = pool.get_executor();
+            executor
+                .execute(Class::Connection, || panic!("Job panicked on purpose"))
+                .unwrap();
+            executor
+                .execute(Class::Connection, || jobs::set_class(Class::StaticFile))
+                .unwrap();
+            thread::sleep(Duration::from_millis(200));
+        }
+        // The only worker survived the panic to run the second job
+        let snapshot = application.get_jobs().get_snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].0, Class::Connection);
+        assert_eq!(snapshot[0].1.panics, 1);
+        assert_eq!(snapshot[1].0, Class::StaticFile);
+        assert_eq!(snapshot[1].1.run_time.count, 1);
+    }
+
+    #[test]
+    fn test_drop() {
+        let application = get_application(1);
+        let (sender, receiver) = mpsc::channel::<()>();
+        let start = Instant::now();
+        {
+            let pool = Pool::new(&application, 2);
+            pool.get_executor()
+                .execute(Class::Connection, move || {
+                    // Ignores every deadline until the test is done
+                    let _ = receiver.recv();
+                })
+                .unwrap();
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        drop(sender);
+
+        // Workers that finish in time are joined
+        let application = get_application(0);
+        let start = Instant::now();
+        drop(Pool::new(&application, 2));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -1,13 +1,22 @@
 //! # Supported transport layers
 //! Binds to the transport layer socket and spawns new threads for dispatching responses.
 
-use std::net::TcpListener;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
 
+use check::Report;
+use crash;
 use response::tcp::http::ResponderInterface;
+use response::tcp::jobs::Class;
+use response::tcp::raw::HandlerInterface;
 use response::tcp::Dispatcher;
-use thread::Pool;
+use thread::{Executor, Pool};
 use Application;
 
+/// Most accepted connections handed to the pool at once
+const HANDOFF_BATCH: usize = 64;
+
 pub struct TCP {}
 
 impl TCP {
@@ -27,6 +36,16 @@ impl TCP {
     /// ```
     // TODO Add example here that does not panic
     pub fn http(application: &Application, responders: Vec<Box<ResponderInterface + Send>>) {
+        TCP::http_and_raw(application, responders, Vec::new())
+    }
+
+    /// Serve HTTP together with raw protocol handlers, handlers owning a port other than the
+    /// server port get a listener of their own and share the workers with HTTP
+    pub fn http_and_raw(
+        application: &Application,
+        responders: Vec<Box<ResponderInterface + Send>>,
+        handlers: Vec<Box<HandlerInterface + Send>>,
+    ) {
         let config = application.get_config();
         let path = format!("{}:{}", &config.server_host, &config.server_port);
         let listener = TcpListener::bind(&path);
@@ -36,36 +55,176 @@ impl TCP {
 
         match listener {
             Ok(listener) => {
+                crash::install(&application);
+                TCP::warn_missing_files(application);
                 let pool = Pool::new(&application, config.server_limit);
-                loop {
-                    match listener.accept() {
-                        Ok((stream, socket)) => {
-                            application
-                                .get_feedback()
-                                .info(format!("Received new TCP stream from {}", socket));
-                            let application = application.clone();
-                            let responders = responders.clone();
-                            application
-                                .get_feedback()
-                                .info("Sending stream as HTTP job to pool".to_string());
-                            pool.execute(move || {
-                                Dispatcher::http(stream, socket, application, responders);
-                            });
-                        }
-                        Err(e) => {
-                            application
-                                .get_feedback()
-                                .error(format!("Failed to accept a incoming stream, error: {}", e));
+                let executor = pool.get_executor();
+                let mut ports: Vec<u16> = handlers
+                    .iter()
+                    .filter_map(|handler| handler.get_port())
+                    .filter(|port| *port != config.server_port)
+                    .collect();
+                ports.sort();
+                ports.dedup();
+                for port in ports {
+                    TCP::raw(application, port, handlers.clone(), pool.get_executor());
+                }
+
+                // Accepting goes on while connections are handed to the pool
+                let (handoff, accepted) = mpsc::channel();
+                TCP::accept(application, listener, handoff);
+                while let Some(batch) = TCP::get_batch(&accepted) {
+                    application
+                        .info(|| format!("Sending {} streams as HTTP jobs to pool", batch.len()));
+                    for (stream, socket) in batch {
+                        application
+                            .get_feedback()
+                            .info(format!("Received new TCP stream from {}", socket));
+                        let application = application.clone();
+                        let feedback = application.get_feedback().clone();
+                        let responders = responders.clone();
+                        let handlers = handlers.clone();
+                        let job = move || {
+                            Dispatcher::serve(stream, socket, application, handlers, responders);
+                        };
+                        if let Err(error) = executor.execute(Class::Connection, job) {
+                            feedback.error(error);
                         }
                     }
                 }
             }
             Err(e) => {
-                panic!(format!(
-                    "Failed to bind to server and port: {}, error: {}",
-                    &path, e
-                ));
+                panic!("Failed to bind to server and port: {}, error: {}", &path, e);
+            }
+        }
+    }
+
+    /// Accept connections on a thread of it's own and hand them off through `handoff`, so a
+    /// slow handoff doesn't delay accepting
+    fn accept(
+        application: &Application,
+        listener: TcpListener,
+        handoff: mpsc::Sender<(TcpStream, SocketAddr)>,
+    ) {
+        let application = application.clone();
+        let name = match listener.local_addr() {
+            Ok(address) => format!("accept-{}", address.port()),
+            Err(_) => "accept".to_string(),
+        };
+        thread::Builder::new()
+            .name(name)
+            .spawn(move || loop {
+                match listener.accept() {
+                    Ok(accepted) => {
+                        if handoff.send(accepted).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        application
+                            .get_feedback()
+                            .error(format!("Failed to accept a incoming stream, error: {}", e));
+                    }
+                }
+            })
+            .expect("Failed to spawn accept thread");
+    }
+
+    /// Wait for a accepted connection and take the others already waiting with it, none when
+    /// every accept thread has stopped
+    fn get_batch(
+        accepted: &mpsc::Receiver<(TcpStream, SocketAddr)>,
+    ) -> Option<Vec<(TcpStream, SocketAddr)>> {
+        let mut batch = vec![accepted.recv().ok()?];
+        while batch.len() < HANDOFF_BATCH {
+            match accepted.try_recv() {
+                Ok(connection) => batch.push(connection),
+                Err(_) => break,
             }
         }
+        Some(batch)
+    }
+
+    /// Requests for missing files and pages get built-in pages, which is easy to miss
+    fn warn_missing_files(application: &Application) {
+        for check in Report::from_files(application.get_config()).get_problems() {
+            application.get_feedback().error(format!(
+                "WARNING {}: {}, built-in pages are served instead",
+                check.name, check.message
+            ));
+        }
+    }
+
+    /// Accept connections to a raw handler port like the server port and hand them to the pool
+    /// from a thread of it's own
+    fn raw(
+        application: &Application,
+        port: u16,
+        handlers: Vec<Box<HandlerInterface + Send>>,
+        executor: Executor,
+    ) {
+        let path = format!("{}:{}", &application.get_config().server_host, port);
+        let listener = match TcpListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => panic!("Failed to bind to raw handler port: {}, error: {}", &path, e),
+        };
+        application
+            .get_feedback()
+            .info(format!("Listening on raw requests via TCP to {}", &path));
+        let (handoff, accepted) = mpsc::channel();
+        TCP::accept(application, listener, handoff);
+        let application = application.clone();
+        thread::spawn(move || {
+            while let Some(batch) = TCP::get_batch(&accepted) {
+                for (stream, socket) in batch {
+                    let application = application.clone();
+                    let feedback = application.get_feedback().clone();
+                    let handlers = handlers.clone();
+                    let job = move || {
+                        Dispatcher::serve(stream, socket, application, handlers, Vec::new());
+                    };
+                    if let Err(error) = executor.execute(Class::Connection, job) {
+                        feedback.error(error);
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use feedback::Level;
+    use std::time::Duration;
+    use Config;
+
+    #[test]
+    fn test_accept() {
+        let application = Application::new(Config {
+            feedback_level: Level::Off,
+            ..Config::for_tests()
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (handoff, accepted) = mpsc::channel();
+        TCP::accept(&application, listener, handoff);
+
+        let clients: Vec<TcpStream> = (0..3)
+            .map(|_| TcpStream::connect(address).unwrap())
+            .collect();
+        thread::sleep(Duration::from_millis(200));
+
+        // Connections waiting together are handed off together
+        let batch = TCP::get_batch(&accepted).unwrap();
+        assert_eq!(batch.len(), 3);
+        let mut ports: Vec<u16> = batch.iter().map(|(_, socket)| socket.port()).collect();
+        let mut expected: Vec<u16> = clients
+            .iter()
+            .map(|client| client.local_addr().unwrap().port())
+            .collect();
+        ports.sort();
+        expected.sort();
+        assert_eq!(ports, expected);
     }
 }